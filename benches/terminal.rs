@@ -0,0 +1,191 @@
+//! 终端性能基准
+//! 测量大量日志输出经过 process_input 的解析耗时，以及渲染准备（生成界面图形）的耗时
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use eframe::egui;
+use std::hint::black_box;
+use std::io::Write;
+use termlink::terminal::buffer::Scroll;
+use termlink::terminal::scrollback::ScrollbackConfig;
+use termlink::terminal::{TerminalEmulator, TerminalRenderer, TerminalTheme};
+
+const ROWS: usize = 50;
+const COLS: usize = 200;
+
+/// 与 I/O 线程每次读取的大小一致
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// 生成带颜色的日志
+fn log_dump(lines: usize) -> Vec<u8> {
+    let levels = [
+        "\x1b[32mINFO\x1b[0m",
+        "\x1b[33mWARN\x1b[0m",
+        "\x1b[1;31mERROR\x1b[0m",
+    ];
+    let mut data = Vec::new();
+    for i in 0..lines {
+        write!(
+            data,
+            "2026-01-01T00:{:02}:{:02}.{:03}Z {} worker-{} \x1b[36mGET\x1b[0m /api/v1/items/{} status=200 latency={}ms 中文日志\r\n",
+            i / 60 % 60,
+            i % 60,
+            i % 1000,
+            levels[i % levels.len()],
+            i % 8,
+            i * 7,
+            i % 250
+        )
+        .unwrap();
+    }
+    data
+}
+
+/// 写满历史记录的仿真器
+fn filled_emulator() -> TerminalEmulator {
+    let mut emulator = TerminalEmulator::new(ROWS, COLS);
+    for chunk in log_dump(2_000).chunks(CHUNK_SIZE) {
+        emulator.process_input(chunk).unwrap();
+    }
+    emulator
+}
+
+/// 在无窗口的 egui 上下文中渲染一帧，返回生成的图形数量
+fn render_frame(
+    ctx: &egui::Context,
+    renderer: &mut TerminalRenderer,
+    emulator: &TerminalEmulator,
+) -> usize {
+    let input = egui::RawInput {
+        screen_rect: Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(1920.0, 1080.0),
+        )),
+        ..Default::default()
+    };
+    let output = ctx.run(input, |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            emulator.with_buffer(|buffer| renderer.render(ui, buffer));
+        });
+    });
+    output.shapes.len()
+}
+
+fn bench_process_input(c: &mut Criterion) {
+    let data = log_dump(20_000);
+    let mut group = c.benchmark_group("process_input");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("log_dump", |b| {
+        b.iter(|| {
+            let mut emulator = TerminalEmulator::new(ROWS, COLS);
+            for chunk in data.chunks(CHUNK_SIZE) {
+                emulator.process_input(chunk).unwrap();
+            }
+            black_box(emulator.with_buffer(|buffer| buffer.cursor_row))
+        })
+    });
+    group.finish();
+}
+
+fn bench_render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+
+    // 画面没有变化：可见行全部来自缓存
+    group.bench_function("static_screen", |b| {
+        let emulator = filled_emulator();
+        let ctx = egui::Context::default();
+        let mut renderer = TerminalRenderer::new(TerminalTheme::default());
+        for _ in 0..3 {
+            render_frame(&ctx, &mut renderer, &emulator);
+        }
+        b.iter(|| black_box(render_frame(&ctx, &mut renderer, &emulator)))
+    });
+
+    // 只改写光标所在行（如 shell 回显）
+    group.bench_function("edit_one_row", |b| {
+        let mut emulator = filled_emulator();
+        let ctx = egui::Context::default();
+        let mut renderer = TerminalRenderer::new(TerminalTheme::default());
+        for _ in 0..3 {
+            render_frame(&ctx, &mut renderer, &emulator);
+        }
+        let mut i = 0u8;
+        b.iter(|| {
+            i = i.wrapping_add(1);
+            emulator
+                .process_input(&[b'\r', b'a' + i % 26, b'b', b'c'])
+                .unwrap();
+            black_box(render_frame(&ctx, &mut renderer, &emulator))
+        })
+    });
+
+    // 每帧都有新的输出行，画面整体滚动
+    group.bench_function("scrolling_output", |b| {
+        let mut emulator = filled_emulator();
+        let line = log_dump(1);
+        let ctx = egui::Context::default();
+        let mut renderer = TerminalRenderer::new(TerminalTheme::default());
+        b.iter(|| {
+            emulator.process_input(&line).unwrap();
+            black_box(render_frame(&ctx, &mut renderer, &emulator))
+        })
+    });
+
+    // 10 万行历史，视口在中间来回翻页：每帧只生成并绘制视口中的行
+    group.bench_function("deep_history_scrolled", |b| {
+        let mut emulator = TerminalEmulator::new(ROWS, COLS);
+        emulator.set_scrollback(ScrollbackConfig {
+            max_lines: 100_000,
+            ..ScrollbackConfig::default()
+        });
+        for chunk in log_dump(100_000).chunks(CHUNK_SIZE) {
+            emulator.process_input(chunk).unwrap();
+        }
+        emulator.scroll_display(Scroll::ToRow(50_000));
+        let ctx = egui::Context::default();
+        let mut renderer = TerminalRenderer::new(TerminalTheme::default());
+        let mut up = false;
+        b.iter(|| {
+            up = !up;
+            emulator.scroll_display(if up { Scroll::PageUp } else { Scroll::PageDown });
+            black_box(render_frame(&ctx, &mut renderer, &emulator))
+        })
+    });
+
+    // egui 本身一帧的开销，作为参照
+    group.bench_function("empty_frame", |b| {
+        let ctx = egui::Context::default();
+        b.iter(|| {
+            let input = egui::RawInput {
+                screen_rect: Some(egui::Rect::from_min_size(
+                    egui::Pos2::ZERO,
+                    egui::vec2(1920.0, 1080.0),
+                )),
+                ..Default::default()
+            };
+            black_box(
+                ctx.run(input, |ctx| {
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| ui.label("x"));
+                    });
+                })
+                .shapes
+                .len(),
+            )
+        })
+    });
+
+    // 没有行缓存（每帧新建渲染器）
+    group.bench_function("uncached", |b| {
+        let emulator = filled_emulator();
+        let ctx = egui::Context::default();
+        b.iter(|| {
+            let mut renderer = TerminalRenderer::new(TerminalTheme::default());
+            black_box(render_frame(&ctx, &mut renderer, &emulator))
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_process_input, bench_render);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+//! 模糊测试：任意字节流、任意终端尺寸下 process_input 都不能 panic
+//!
+//! 运行：`cargo +nightly fuzz run process_input fuzz/corpus/process_input fuzz/seeds/process_input`
+//!（在项目根目录执行）。`fuzz/seeds/process_input` 是纳入版本库的种子，覆盖 APC 图片、sixel、OSC
+//! 与曾导致 panic 或卡住的序列；`fuzz/corpus` 为运行时生成的语料，不纳入版本库。
+//! 输入的前三个字节选择仿真引擎与初始尺寸（允许为 0），之后每段以一个控制字节开头：
+//! 低 6 位为段长，最高两位都置位时在写入该段后按段内容调整终端大小。
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use termlink::terminal::buffer::Scroll;
+use termlink::terminal::emulator::{TerminalEmulator, TerminalEmulatorTrait};
+use termlink::terminal::WezTermAdapter;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&[engine, rows, cols], mut rest)) = data.split_first_chunk::<3>() else {
+        return;
+    };
+    let (rows, cols) = (rows as usize % 64, cols as usize % 256);
+    let mut emulator: Box<dyn TerminalEmulatorTrait> = if engine & 1 == 0 {
+        Box::new(TerminalEmulator::new(rows, cols))
+    } else {
+        Box::new(WezTermAdapter::new(rows, cols))
+    };
+
+    while let Some((&control, tail)) = rest.split_first() {
+        let len = ((control & 0x3f) as usize + 1).min(tail.len());
+        let (chunk, next) = tail.split_at(len);
+        emulator.process_input(chunk).unwrap();
+        if control & 0xc0 == 0xc0 {
+            let first = chunk.first().copied().unwrap_or(0) as usize;
+            let last = chunk.last().copied().unwrap_or(0) as usize;
+            emulator.resize(first % 8, last % 32);
+            emulator.scroll_display(Scroll::Delta(first as isize % 5 - 2));
+        }
+        rest = next;
+    }
+
+    emulator.visit_buffer(&mut |buffer| {
+        let _ = buffer.get_selected_text();
+    });
+});
@@ -1,17 +1,24 @@
 //! UI组件模块
 //! 包含主应用的用户界面实现
 
-use crate::config::settings::ConnectionGroup;
-use crate::config::settings::ThemeMode;
-use crate::config::{AppSettings, ConnectionConfig};
-use crate::i18n::{I18nKey, I18nManager, Language};
-use crate::ssh::{ConnectionManager, ConnectionTestResult, SessionState, SshSession};
-use crate::terminal::{TerminalEmulator, TextSelector};
-// use crate::terminal::TerminalEmulator; // 已切换到WezTermAdapter
-// use crate::terminal::WezTermAdapter;
 use eframe::egui;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use termlink::config::settings::ConnectionGroup;
+use termlink::config::settings::TerminalProfile;
+use termlink::config::settings::ThemeMode;
+use termlink::config::{AppSettings, ConnectionConfig};
+use termlink::i18n::{I18nKey, I18nManager, Language};
+use termlink::logging::{FilteredLogs, LogBuffer};
+use termlink::ssh::{
+    AuthMethod, ConnectError, ConnectStage, ConnectionManager, ConnectionTestResult, SessionState,
+};
+use termlink::terminal::buffer::Scroll;
+use termlink::terminal::clipboard::ClipboardPolicy;
+use termlink::terminal::cursor::CursorShape;
+use termlink::terminal::emulator::{EmulatorEngine, TerminalEmulatorTrait};
+use termlink::terminal::input::KeyEncoder;
+use termlink::terminal::{TerminalEmulator, TextSelector, WezTermAdapter};
 
 // 获取构建时信息
 fn get_version() -> String {
@@ -142,6 +149,97 @@ impl AboutDialog {
     }
 }
 
+/// 日志窗口，按级别过滤显示最近的日志
+pub struct LogViewer {
+    pub show: bool,
+    logs: FilteredLogs,
+}
+
+impl LogViewer {
+    pub fn new(buffer: LogBuffer) -> Self {
+        Self {
+            show: false,
+            logs: FilteredLogs::new(buffer, tracing::Level::INFO),
+        }
+    }
+
+    pub fn show(&mut self) {
+        self.show = true;
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context, i18n: &I18nManager) {
+        if !self.show {
+            return;
+        }
+
+        let mut open = true;
+        egui::Window::new(i18n.get(I18nKey::LogViewerTitle))
+            .open(&mut open)
+            .default_size([760.0, 420.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(i18n.get(I18nKey::LogLevel));
+                    let mut selected = self.logs.level();
+                    egui::ComboBox::from_id_salt("log_viewer_level")
+                        .selected_text(selected.as_str())
+                        .show_ui(ui, |ui| {
+                            for level in [
+                                tracing::Level::ERROR,
+                                tracing::Level::WARN,
+                                tracing::Level::INFO,
+                                tracing::Level::DEBUG,
+                                tracing::Level::TRACE,
+                            ] {
+                                ui.selectable_value(&mut selected, level, level.as_str());
+                            }
+                        });
+                    self.logs.set_level(selected);
+
+                    if ui.button(i18n.get(I18nKey::ClearLogs)).clicked() {
+                        self.logs.clear();
+                    }
+                    if let Some(dir) = termlink::logging::log_dir()
+                        && ui.button(i18n.get(I18nKey::OpenLogFolder)).clicked()
+                    {
+                        termlink::utils::helpers::open_with_system(&dir.to_string_lossy());
+                    }
+                });
+                ui.separator();
+
+                // 只复制新增的记录、只布局可见的行，日志很多时也不会拖慢界面
+                self.logs.refresh();
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                egui::ScrollArea::both()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show_rows(ui, row_height, self.logs.len(), |ui, range| {
+                        for record in self.logs.range(range) {
+                            let color = match record.level {
+                                tracing::Level::ERROR => ui.visuals().error_fg_color,
+                                tracing::Level::WARN => ui.visuals().warn_fg_color,
+                                tracing::Level::INFO => ui.visuals().text_color(),
+                                _ => ui.visuals().weak_text_color(),
+                            };
+                            let line = format!(
+                                "{} {:5} {}: {}",
+                                record.time.format("%H:%M:%S%.3f"),
+                                record.level,
+                                record.target,
+                                record.message
+                            );
+                            ui.add(
+                                egui::Label::new(
+                                    egui::RichText::new(line).monospace().color(color),
+                                )
+                                .wrap_mode(egui::TextWrapMode::Extend),
+                            );
+                        }
+                    });
+            });
+        self.show = open;
+    }
+}
+
 /// 设置对话框
 #[derive(Default)]
 pub struct SettingsDialog {
@@ -270,7 +368,7 @@ impl SettingsDialog {
                                             // 初始化自定义主题
                                             if self.temp_settings.terminal.custom_theme.is_none() {
                                                 self.temp_settings.terminal.custom_theme = Some(
-                                                    crate::config::settings::CustomTheme::default(),
+                                                    termlink::config::settings::CustomTheme::default(),
                                                 );
                                             }
                                         }
@@ -327,6 +425,132 @@ impl SettingsDialog {
                                     i18n.get(I18nKey::CursorBlink),
                                 );
 
+                                egui::ComboBox::from_label(i18n.get(I18nKey::CursorShape))
+                                    .selected_text(cursor_shape_label(
+                                        i18n,
+                                        self.temp_settings.terminal.cursor_shape,
+                                    ))
+                                    .show_ui(ui, |ui| {
+                                        for shape in CURSOR_SHAPES {
+                                            ui.selectable_value(
+                                                &mut self.temp_settings.terminal.cursor_shape,
+                                                shape,
+                                                cursor_shape_label(i18n, shape),
+                                            );
+                                        }
+                                    });
+
+                                ui.horizontal(|ui| {
+                                    ui.label(i18n.get(I18nKey::CursorBlinkInterval));
+                                    ui.add(
+                                        egui::DragValue::new(
+                                            &mut self.temp_settings.terminal.cursor_blink_interval,
+                                        )
+                                        .speed(10.0)
+                                        .range(100..=2000),
+                                    );
+                                });
+
+                                ui.horizontal(|ui| {
+                                    ui.label(i18n.get(I18nKey::ScrollbackLines));
+                                    ui.add(
+                                        egui::DragValue::new(
+                                            &mut self.temp_settings.terminal.scrollback_lines,
+                                        )
+                                        .speed(100.0)
+                                        .range(0..=10_000_000),
+                                    )
+                                    .on_hover_text(i18n.get(I18nKey::ScrollbackLinesHint));
+                                });
+
+                                ui.horizontal(|ui| {
+                                    ui.label(i18n.get(I18nKey::ScrollbackMemoryLimit));
+                                    ui.add(
+                                        egui::DragValue::new(
+                                            &mut self.temp_settings.terminal.scrollback_memory_mb,
+                                        )
+                                        .range(0..=16 * 1024),
+                                    );
+                                });
+
+                                ui.checkbox(
+                                    &mut self.temp_settings.terminal.scrollback_compress,
+                                    i18n.get(I18nKey::ScrollbackCompress),
+                                );
+
+                                ui.checkbox(
+                                    &mut self.temp_settings.terminal.alt_sends_escape,
+                                    i18n.get(I18nKey::AltSendsEscape),
+                                );
+
+                                ui.checkbox(
+                                    &mut self.temp_settings.terminal.confirm_multiline_paste,
+                                    i18n.get(I18nKey::ConfirmMultilinePaste),
+                                );
+
+                                ui.horizontal(|ui| {
+                                    ui.label(i18n.get(I18nKey::LargePasteThreshold));
+                                    ui.add(
+                                        egui::DragValue::new(
+                                            &mut self.temp_settings.terminal.large_paste_threshold,
+                                        )
+                                        .speed(100.0)
+                                        .range(0..=10_000_000),
+                                    );
+                                });
+
+                                egui::ComboBox::from_label(i18n.get(I18nKey::Osc52Policy))
+                                    .selected_text(clipboard_policy_label(
+                                        i18n,
+                                        self.temp_settings.terminal.osc52_policy,
+                                    ))
+                                    .show_ui(ui, |ui| {
+                                        for policy in [
+                                            ClipboardPolicy::Allow,
+                                            ClipboardPolicy::Ask,
+                                            ClipboardPolicy::Deny,
+                                        ] {
+                                            ui.selectable_value(
+                                                &mut self.temp_settings.terminal.osc52_policy,
+                                                policy,
+                                                clipboard_policy_label(i18n, policy),
+                                            );
+                                        }
+                                    });
+
+                                ui.horizontal(|ui| {
+                                    ui.label(i18n.get(I18nKey::Osc52MaxBytes));
+                                    ui.add(
+                                        egui::DragValue::new(
+                                            &mut self.temp_settings.terminal.osc52_max_bytes,
+                                        )
+                                        .speed(1024.0)
+                                        .range(0..=64 * 1024 * 1024),
+                                    );
+                                });
+
+                                ui.checkbox(
+                                    &mut self.temp_settings.terminal.osc52_allow_read,
+                                    i18n.get(I18nKey::Osc52AllowRead),
+                                );
+
+                                ui.checkbox(
+                                    &mut self.temp_settings.terminal.bell_visual,
+                                    i18n.get(I18nKey::BellVisual),
+                                );
+                                ui.checkbox(
+                                    &mut self.temp_settings.terminal.bell_sound,
+                                    i18n.get(I18nKey::BellSound),
+                                );
+
+                                ui.horizontal(|ui| {
+                                    ui.label(i18n.get(I18nKey::TitleTemplate));
+                                    ui.text_edit_singleline(
+                                        &mut self.temp_settings.terminal.title_template,
+                                    )
+                                    .on_hover_text(i18n.get(I18nKey::TitleTemplateHint));
+                                });
+
                                 // 如果选择了自定义主题，显示颜色选择器
                                 if self.temp_settings.terminal.theme == "custom" {
                                     ui.add_space(10.0);
@@ -340,7 +564,7 @@ impl SettingsDialog {
                                             .selected_text(i18n.get(I18nKey::SelectPreset))
                                             .show_ui(ui, |ui| {
                                                 for (name, preset) in
-                                                    crate::config::settings::CustomTheme::presets()
+                                                    termlink::config::settings::CustomTheme::presets()
                                                 {
                                                     if ui.button(name).clicked() {
                                                         if let Some(ref mut custom) =
@@ -481,7 +705,7 @@ impl SettingsDialog {
                                         ui.add_space(10.0);
                                         if ui.button(i18n.get(I18nKey::ResetToDefault)).clicked() {
                                             *custom_theme =
-                                                crate::config::settings::CustomTheme::default();
+                                                termlink::config::settings::CustomTheme::default();
                                         }
                                     }
                                 }
@@ -561,7 +785,7 @@ pub struct App {
     /// 为每个会话维护的终端仿真器
     pub terminal_emulators: std::collections::HashMap<
         String,
-        Box<dyn crate::terminal::emulator::TerminalEmulatorTrait>,
+        Box<dyn termlink::terminal::emulator::TerminalEmulatorTrait>,
     >,
     /// 文本选择器
     pub text_selector: TextSelector,
@@ -569,14 +793,14 @@ pub struct App {
     pub last_read_time: Option<std::time::Instant>,
     /// 关于对话框
     pub about_dialog: AboutDialog,
+    /// 日志窗口
+    pub log_viewer: LogViewer,
     /// 设置对话框
     pub settings_dialog: SettingsDialog,
     /// 连接超时时间（秒）
     pub connection_timeout: u64,
     /// 测试连接超时时间（秒）
     pub test_connection_timeout: u64,
-    /// 会话连接开始时间（用于超时检查）
-    pub session_start_times: std::collections::HashMap<String, std::time::Instant>,
     /// 导入配置对话框状态
     pub show_import_dialog: bool,
     /// 导出配置对话框状态
@@ -587,6 +811,26 @@ pub struct App {
     pub export_file_path: String,
     /// 导入模式：覆盖(true) 或 合并(false)
     pub import_overwrite_mode: bool,
+    /// 等待用户确认的粘贴内容
+    pub pending_paste: Option<String>,
+    /// 各会话所属连接的终端配置
+    pub session_profiles: std::collections::HashMap<String, TerminalProfile>,
+    /// 终端仿真器回调中产生、等待界面线程处理的事件（会话名, 事件）
+    pub terminal_events: Arc<Mutex<Vec<(String, termlink::terminal::TerminalEvent)>>>,
+    /// 等待用户确认写入剪贴板的内容 (OSC 52)
+    pub pending_clipboard_write: Option<String>,
+    /// 各会话的响铃状态
+    pub bell_states: std::collections::HashMap<String, BellState>,
+    /// 各会话中远端程序设置的标题 (OSC 0/2)
+    pub session_titles: std::collections::HashMap<String, String>,
+    /// 当前窗口标题，变化时才通知系统窗口
+    pub window_title: String,
+    /// 上次记录的终端焦点状态（会话名, 是否有焦点），用于焦点变化报告 (DECSET 1004)
+    pub terminal_focus: Option<(String, bool)>,
+    /// 各已连接会话的后台 I/O 线程
+    pub session_workers: std::collections::HashMap<String, termlink::ssh::SessionWorker>,
+    /// 各会话的终端渲染器，跨帧保留以复用行图形缓存
+    pub terminal_renderers: std::collections::HashMap<String, termlink::terminal::TerminalRenderer>,
 }
 
 impl Default for App {
@@ -613,15 +857,25 @@ impl Default for App {
             text_selector: TextSelector::new(),
             last_read_time: None,
             about_dialog: AboutDialog::new(),
+            log_viewer: LogViewer::new(termlink::logging::buffer()),
             settings_dialog: SettingsDialog::new(),
             connection_timeout: 30,
             test_connection_timeout: 10,
-            session_start_times: std::collections::HashMap::new(),
             show_import_dialog: false,
             show_export_dialog: false,
             import_file_path: String::new(),
             export_file_path: String::new(),
             import_overwrite_mode: true,
+            pending_paste: None,
+            session_profiles: std::collections::HashMap::new(),
+            terminal_events: Arc::new(Mutex::new(Vec::new())),
+            pending_clipboard_write: None,
+            bell_states: std::collections::HashMap::new(),
+            session_titles: std::collections::HashMap::new(),
+            window_title: APP_TITLE.to_string(),
+            terminal_focus: None,
+            session_workers: std::collections::HashMap::new(),
+            terminal_renderers: std::collections::HashMap::new(),
         };
 
         // 加载保存的应用状态
@@ -634,7 +888,7 @@ impl Default for App {
         }
 
         // 检测系统主题并更新设置
-        let system_theme = crate::utils::helpers::detect_system_theme();
+        let system_theme = termlink::utils::helpers::detect_system_theme();
         app.settings.appearance.system_theme = system_theme;
 
         // 应用当前主题设置
@@ -655,6 +909,7 @@ pub struct ConnectionForm {
     pub private_key_path: String,
     pub save_to_history: bool,
     pub group: Option<String>,
+    pub terminal_profile: TerminalProfile,
 }
 
 /// 分组配置表单
@@ -664,6 +919,27 @@ pub struct GroupForm {
     pub description: String,
 }
 
+/// 会话的响铃状态
+#[derive(Default)]
+pub struct BellState {
+    /// 最近一次响铃的时间（限流后实际生效的响铃）
+    pub last_ring: Option<std::time::Instant>,
+    /// 在后台会话中响铃，标签页上显示提醒，切换到该会话后清除
+    pub attention: bool,
+}
+
+/// 应用名称，用作默认窗口标题
+const APP_TITLE: &str = "TermLink";
+
+/// 标签页标题的最大字符数，超出部分以省略号代替
+const TAB_TITLE_MAX_CHARS: usize = 40;
+
+/// 同一会话两次响铃的最小间隔，更频繁的响铃被忽略
+const BELL_MIN_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// 响铃时终端闪烁的时长
+const BELL_FLASH_DURATION: std::time::Duration = std::time::Duration::from_millis(150);
+
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // 如果终端有焦点，禁用 egui 的默认复制/粘贴/剪切快捷键
@@ -681,9 +957,6 @@ impl eframe::App for App {
         // 应用主题色，确保手动切换主题能立即生效
         self.apply_app_visuals(ctx);
 
-        // 添加调试信息
-        // println!("Update called at {:?}", std::time::Instant::now());
-
         // 检查测试连接结果
         if self.test_completed.load(Ordering::Relaxed) {
             if let Ok(mut guard) = self.shared_test_result.lock() {
@@ -695,11 +968,26 @@ impl eframe::App for App {
             }
         }
 
-        // 检查连接超时
-        self.check_connection_timeouts();
+        // 取出连接任务的阶段事件并更新会话状态，超时的连接由任务自行取消
+        self.connection_manager.lock().unwrap().poll_connections();
+
+        // 为已连接的会话启动后台 I/O 线程，终端数据由这些线程持续读取
+        self.sync_session_workers(ctx);
+
+        // 处理终端产生的事件（剪贴板请求等）
+        self.process_terminal_events();
 
-        // 读取终端数据（关键：这必须在UI构建之前执行）
-        self.read_from_terminal();
+        // 窗口标题跟随当前会话
+        let window_title = match self.current_session {
+            Some(ref session_name) => {
+                format!("{} - {}", self.session_title(session_name), APP_TITLE)
+            }
+            None => APP_TITLE.to_string(),
+        };
+        if window_title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(window_title.clone()));
+            self.window_title = window_title;
+        }
 
         // 主窗口布局
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
@@ -729,6 +1017,9 @@ impl eframe::App for App {
         // 渲染对话框
         self.about_dialog.ui(ctx, &self.i18n);
 
+        // 渲染日志窗口
+        self.log_viewer.ui(ctx, &self.i18n);
+
         // 渲染设置对话框
         let mut settings_to_apply = None;
         let mut lang_to_apply = None;
@@ -753,6 +1044,19 @@ impl eframe::App for App {
             self.render_export_dialog(ctx);
         }
 
+        // 渲染粘贴确认对话框
+        if self.pending_paste.is_some() {
+            self.render_paste_confirm_dialog(ctx);
+        }
+
+        // 渲染剪贴板写入确认对话框
+        if self.pending_clipboard_write.is_some() {
+            self.render_clipboard_confirm_dialog(ctx);
+        }
+
+        // 渲染未知主机密钥确认对话框
+        self.render_host_key_confirm_dialog(ctx);
+
         // 请求下一帧更新，但要控制频率
         ctx.request_repaint_after(std::time::Duration::from_millis(50));
     }
@@ -761,14 +1065,26 @@ impl eframe::App for App {
 impl App {
     /// 更新应用的主题视觉效果
     fn apply_app_visuals(&mut self, ctx: &egui::Context) {
-        use crate::config::settings::ThemeMode;
+        use termlink::config::settings::ThemeMode;
+
+        // 跟随系统切换深浅色：终端颜色在渲染时解析，已有内容随之变色
+        if let Some(system_theme) = ctx.system_theme() {
+            let system_theme = match system_theme {
+                egui::Theme::Dark => "dark",
+                egui::Theme::Light => "light",
+            };
+            if self.settings.appearance.system_theme != system_theme {
+                self.settings.appearance.system_theme = system_theme.to_string();
+                self.settings.terminal.theme = self.settings.get_current_theme();
+            }
+        }
 
         let theme_str = self.settings.get_current_theme();
 
         let visuals = if theme_str == "custom" {
             if let Some(ref custom) = self.settings.terminal.custom_theme {
-                let bg = crate::terminal::ThemeStyle::parse_hex(&custom.background);
-                let fg = crate::terminal::ThemeStyle::parse_hex(&custom.foreground);
+                let bg = termlink::terminal::ThemeStyle::parse_hex(&custom.background);
+                let fg = termlink::terminal::ThemeStyle::parse_hex(&custom.foreground);
 
                 // 判断背景深浅
                 let is_dark = {
@@ -805,7 +1121,7 @@ impl App {
                 v.widgets.active.bg_fill = bg;
 
                 // 重点设置：选择颜色
-                v.selection.bg_fill = crate::terminal::ThemeStyle::parse_hex(&custom.selection);
+                v.selection.bg_fill = termlink::terminal::ThemeStyle::parse_hex(&custom.selection);
 
                 v
             } else {
@@ -881,6 +1197,10 @@ impl App {
             });
 
             ui.menu_button(self.i18n.get(I18nKey::MenuHelp), |ui| {
+                if ui.button(self.i18n.get(I18nKey::MenuLogViewer)).clicked() {
+                    self.log_viewer.show();
+                    ui.close();
+                }
                 if ui.button(self.i18n.get(I18nKey::MenuAbout)).clicked() {
                     self.about_dialog.show();
                     ui.close();
@@ -1216,7 +1536,7 @@ impl App {
 
     /// 关闭会话
     fn close_session(&mut self, session_name: &str) {
-        let manager = self.connection_manager.lock().unwrap();
+        let mut manager = self.connection_manager.lock().unwrap();
         manager.disconnect(session_name);
         drop(manager);
 
@@ -1224,33 +1544,117 @@ impl App {
         if self.current_session.as_ref().map(|s| s.as_str()) == Some(session_name) {
             self.current_session = None;
         }
+        self.bell_states.remove(session_name);
+        self.session_titles.remove(session_name);
+        self.session_workers.remove(session_name);
+        self.terminal_renderers.remove(session_name);
+    }
+
+    /// 确保会话有对应的终端仿真器
+    fn ensure_terminal_emulator(&mut self, session_name: &str) {
+        if self.terminal_emulators.contains_key(session_name) {
+            return;
+        }
+
+        let profile = self
+            .session_profiles
+            .get(session_name)
+            .cloned()
+            .unwrap_or_default();
+        let emulator = self.create_terminal_emulator(session_name, &profile, 24, 80);
+        self.terminal_emulators
+            .insert(session_name.to_string(), emulator);
+    }
+
+    /// 按配置选择的仿真引擎创建终端仿真器，并设置事件回调与回滚限制
+    fn create_terminal_emulator(
+        &self,
+        session_name: &str,
+        profile: &TerminalProfile,
+        rows: usize,
+        cols: usize,
+    ) -> Box<dyn TerminalEmulatorTrait> {
+        let callback = self.terminal_event_callback(session_name);
+        let mut emulator: Box<dyn TerminalEmulatorTrait> = match profile.emulator_engine {
+            EmulatorEngine::Builtin => {
+                let mut emulator = TerminalEmulator::new(rows, cols);
+                emulator.set_event_callback(callback);
+                Box::new(emulator)
+            }
+            EmulatorEngine::WezTerm => {
+                let mut emulator = WezTermAdapter::new(rows, cols);
+                emulator.set_event_callback(callback);
+                Box::new(emulator)
+            }
+        };
+        emulator.set_scrollback(profile.scrollback(&self.settings.terminal));
+        emulator
+    }
+
+    /// 为已连接但还没有 I/O 线程的会话启动线程，并结束已关闭会话的线程
+    ///
+    /// 线程把读到的数据直接交给会话的仿真器，有新数据时唤醒界面重绘，
+    /// 因此后台标签页的输出、响铃和标题变化也能及时处理。
+    fn sync_session_workers(&mut self, ctx: &egui::Context) {
+        let sessions: Vec<_> = {
+            let manager = self.connection_manager.lock().unwrap();
+            manager
+                .get_active_sessions()
+                .into_iter()
+                .filter_map(|name| manager.get_session(&name))
+                .filter(|session| session.is_connected())
+                .collect()
+        };
+
+        self.session_workers
+            .retain(|name, _| sessions.iter().any(|session| &session.name == name));
+
+        for session in sessions {
+            if self.session_workers.contains_key(&session.name) {
+                continue;
+            }
+            self.ensure_terminal_emulator(&session.name);
+            let Some(emulator) = self.terminal_emulators.get(&session.name) else {
+                continue;
+            };
+            let Some(input) = emulator.input_handle() else {
+                continue;
+            };
+            let (rows, cols) = emulator.with_buffer(|buffer| (buffer.rows, buffer.cols));
+
+            let ctx = ctx.clone();
+            let replies = input.clone();
+            let worker = termlink::ssh::SessionWorker::spawn(
+                &session.name,
+                session.channel.clone(),
+                move |data| input.process(data),
+                // 回复远端的查询（如 kitty 键盘协议状态）
+                move || replies.take_pending_output(),
+                move || ctx.request_repaint(),
+            );
+            match worker {
+                Ok(worker) => {
+                    // 远端终端按仿真器当前大小对齐
+                    worker.resize(rows as u32, cols as u32);
+                    self.session_workers.insert(session.name, worker);
+                }
+                Err(e) => tracing::error!(session = %session.name, "启动会话 I/O 线程失败: {}", e),
+            }
+        }
+    }
+
+    /// 通过会话的 I/O 线程向远端写入数据
+    fn write_to_session(&self, session_name: &str, data: &[u8]) {
+        if let Some(worker) = self.session_workers.get(session_name) {
+            worker.write(data);
+        }
     }
 
     /// 主内容区域
     fn main_content(&mut self, ui: &mut egui::Ui) {
         // 确保当前会话有对应的终端仿真器
-        if let Some(ref session_name) = self.current_session {
-            if !self.terminal_emulators.contains_key(session_name) {
-                let mut emulator = TerminalEmulator::new(24, 80);
-                // WezTermAdapter::new(24, 80, crate::terminal::TerminalTheme::default());
-
-                // 设置终端事件回调
-                let session_name_clone = session_name.clone();
-                let manager_clone = self.connection_manager.clone();
-                emulator.set_event_callback(move |event| {
-                    if let crate::terminal::TerminalEvent::Resize { rows, cols } = event {
-                        let manager = manager_clone.lock().unwrap();
-                        if let Some(session) = manager.get_session(&session_name_clone) {
-                            if let Err(e) = session.resize_terminal(rows as u32, cols as u32) {
-                                eprintln!("调整终端大小失败: {}", e);
-                            }
-                        }
-                    }
-                });
-
-                self.terminal_emulators
-                    .insert(session_name.clone(), Box::new(emulator));
-            }
+        if let Some(session_name) = self.current_session.clone() {
+            self.ensure_terminal_emulator(&session_name);
         }
 
         // 显示活动会话标签页
@@ -1272,9 +1676,6 @@ impl App {
 
             // 显示终端界面
             self.render_terminal_session(ui, session_display_info.as_ref().unwrap());
-
-            // 从SSH会话读取输出
-            self.read_from_terminal();
         } else {
             // 显示欢迎界面
             ui.centered_and_justified(|ui| {
@@ -1307,201 +1708,289 @@ impl App {
                 self.i18n.get(I18nKey::Status),
                 self.format_session_state(state)
             ));
+            if matches!(state, SessionState::Connecting(_))
+                && ui.button(self.i18n.get(I18nKey::CancelConnect)).clicked()
+            {
+                self.connection_manager.lock().unwrap().cancel_connect(name);
+            }
         });
 
-        // 显示终端
-        egui::ScrollArea::vertical()
-            .auto_shrink([false; 2])
-            .stick_to_bottom(true)
-            .show(ui, |ui| {
-                // 获取当前会话对应的终端仿真器
-                if let Some(ref session_name) = self.current_session {
-                    if let Some(ref mut emulator) = self.terminal_emulators.get_mut(session_name) {
-                        // 更新仿真器中的主题信息
-                        let theme_style = match self.settings.get_current_theme().as_str() {
-                            "light" => crate::terminal::ThemeStyle::light(),
-                            "custom" => {
-                                if let Some(ref custom) = self.settings.terminal.custom_theme {
-                                    crate::terminal::ThemeStyle {
-                                        foreground: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.foreground,
-                                        ),
-                                        background: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.background,
-                                        ),
-                                        cursor: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.cursor,
-                                        ),
-                                        selection: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.selection,
-                                        ),
-                                        black: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.black,
-                                        ),
-                                        red: crate::terminal::ThemeStyle::parse_hex(&custom.red),
-                                        green: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.green,
-                                        ),
-                                        yellow: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.yellow,
-                                        ),
-                                        blue: crate::terminal::ThemeStyle::parse_hex(&custom.blue),
-                                        magenta: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.magenta,
-                                        ),
-                                        cyan: crate::terminal::ThemeStyle::parse_hex(&custom.cyan),
-                                        white: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.white,
-                                        ),
-                                        bright_black: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.bright_black,
-                                        ),
-                                        bright_red: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.bright_red,
-                                        ),
-                                        bright_green: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.bright_green,
-                                        ),
-                                        bright_yellow: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.bright_yellow,
-                                        ),
-                                        bright_blue: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.bright_blue,
-                                        ),
-                                        bright_magenta: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.bright_magenta,
-                                        ),
-                                        bright_cyan: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.bright_cyan,
-                                        ),
-                                        bright_white: crate::terminal::ThemeStyle::parse_hex(
-                                            &custom.bright_white,
-                                        ),
-                                    }
-                                } else {
-                                    crate::terminal::ThemeStyle::dark()
-                                }
-                            }
-                            _ => crate::terminal::ThemeStyle::dark(),
-                        };
-                        let theme = crate::terminal::TerminalTheme {
-                            style: theme_style,
-                            font_size: self.settings.terminal.font_size,
-                            line_height: 1.2,
-                            cursor_blink: self.settings.terminal.cursor_blink,
-                        };
-                        emulator.update_theme(theme.clone());
-
-                        // 在渲染前计算并更新终端仿真器的尺寸，确保 PTY 大小与 UI 匹配
-                        let font_id = egui::FontId::monospace(self.settings.terminal.font_size);
-                        let galley = ui.painter().layout_no_wrap(
-                            "W".to_string(),
-                            font_id.clone(),
-                            egui::Color32::WHITE,
-                        );
-                        let char_size = egui::vec2(galley.size().x, galley.size().y);
+        // 显示终端（滚动位置保存在缓冲区中，渲染器只绘制视口）
+        let mut shell_action = None;
+        let mut terminal_focused = false;
+        {
+            // 获取当前会话对应的终端仿真器
+            // 当前主题，单元格颜色由渲染器按它解析，切换后已有内容在下一帧随之变色
+            let theme_style = self.terminal_theme_style();
+            if let Some(ref session_name) = self.current_session
+                && let Some(ref mut emulator) = self.terminal_emulators.get_mut(session_name)
+            {
+                let profile = self
+                    .session_profiles
+                    .get(session_name)
+                    .cloned()
+                    .unwrap_or_default();
+                let theme = termlink::terminal::TerminalTheme {
+                    style: theme_style,
+                    font_size: self.settings.terminal.font_size,
+                    line_height: 1.2,
+                    cursor_blink: profile.cursor_blink(&self.settings.terminal),
+                    cursor_shape: profile.cursor_shape(&self.settings.terminal),
+                    cursor_blink_interval: profile.cursor_blink_interval(&self.settings.terminal),
+                };
+                emulator.set_palette(theme.palette());
+
+                // 在渲染前计算并更新终端仿真器的尺寸，确保 PTY 大小与 UI 匹配
+                let font_id = egui::FontId::monospace(self.settings.terminal.font_size);
+                let galley = ui.painter().layout_no_wrap(
+                    "W".to_string(),
+                    font_id.clone(),
+                    egui::Color32::WHITE,
+                );
+                let char_size = egui::vec2(galley.size().x, galley.size().y);
 
-                        let available_width = ui.available_width().max(100.0);
-                        let viewport_height = ui.clip_rect().height().max(100.0);
+                let available_width = ui.available_width().max(100.0);
+                let viewport_height = ui.available_height().max(100.0);
 
-                        let actual_cols = ((available_width - 8.0) / char_size.x).floor() as usize;
-                        let actual_rows =
-                            ((viewport_height - 4.0) / (char_size.y * 1.2)).floor() as usize;
+                let actual_cols = ((available_width
+                    - 8.0
+                    - termlink::terminal::renderer::GUTTER_WIDTH
+                    - termlink::terminal::renderer::SCROLLBAR_WIDTH)
+                    / char_size.x)
+                    .floor() as usize;
+                let actual_rows = ((viewport_height - 4.0) / (char_size.y * 1.2)).floor() as usize;
 
-                        // 只有在已连接的情况下才触发 PTY 大小变化通知
-                        let is_connected = matches!(state, SessionState::Connected);
+                // 图片按单元格的像素大小计算占用的行列数
+                emulator.set_cell_size(char_size.x, char_size.y * 1.2);
 
-                        // 如果尺寸发生了变化，则通知后端 PTY 调整
-                        let current_buffer = emulator.buffer();
-                        if is_connected
-                            && (actual_cols != current_buffer.cols
-                                || actual_rows != current_buffer.rows)
-                        {
-                            emulator.resize(actual_rows.max(1), actual_cols.max(1));
-                        }
+                // 只有在已连接的情况下才触发 PTY 大小变化通知
+                let is_connected = matches!(state, SessionState::Connected);
 
-                        // 先获取缓冲区用于渲染
-                        let buffer = emulator.buffer();
-                        let mut renderer =
-                            crate::terminal::TerminalRenderer::new(buffer.rows, buffer.cols, theme);
-                        // 更新缓冲区内容
-                        renderer.buffer = buffer;
+                // 如果尺寸发生了变化，则通知后端 PTY 调整
+                let current_size = emulator.with_buffer(|buffer| (buffer.rows, buffer.cols));
+                if is_connected && (actual_rows, actual_cols) != current_size {
+                    emulator.resize(actual_rows.max(1), actual_cols.max(1));
+                }
 
-                        let response = egui::Frame::canvas(ui.style())
-                            .inner_margin(0.0)
-                            .show(ui, |ui| renderer.render(ui))
-                            .inner;
+                // 借用缓冲区渲染，渲染器跨帧保留
+                let renderer = self
+                    .terminal_renderers
+                    .entry(session_name.clone())
+                    .or_insert_with(|| termlink::terminal::TerminalRenderer::new(theme.clone()));
+                renderer.set_theme(theme);
 
-                        if response.clicked() {
-                            ui.memory_mut(|mem| mem.request_focus(response.id));
-                        }
+                let response = egui::Frame::canvas(ui.style())
+                    .inner_margin(0.0)
+                    .show(ui, |ui| {
+                        emulator.with_buffer(|buffer| renderer.render(ui, buffer))
+                    })
+                    .inner;
+                let hovered_link = renderer.hovered_link.take();
+                let flash_color = renderer.theme.style.foreground;
 
-                        // 处理鼠标选择
-                        if response.drag_started() {
-                            println!("检测到拖拽开始");
-                            if let Some(pos) = response.interact_pointer_pos() {
-                                println!("拖拽开始位置: ({}, {})", pos.x, pos.y);
-                                self.text_selector.start_selection(pos.x, pos.y);
-                            }
-                        }
+                // 滚轮与滚动条的滚动交给缓冲区，下一帧生效
+                if let Some(scroll) = renderer.scroll_request.take() {
+                    emulator.scroll_display(scroll);
+                    ui.ctx().request_repaint();
+                }
 
-                        if response.dragged() {
-                            if let Some(pos) = response.interact_pointer_pos() {
-                                println!("拖拽中位置: ({}, {})", pos.x, pos.y);
-                                self.text_selector.update_selection(pos.x, pos.y);
-                                // 更新仿真器选择显示
-                                if let Some(emulator) =
-                                    self.terminal_emulators.get_mut(session_name)
-                                {
-                                    self.text_selector.update_emulator_selection(
-                                        emulator.as_mut(),
-                                        &response.rect,
-                                        char_size,
-                                        1.2, // 对应渲染时的 line_height
-                                    );
-                                }
-                            }
-                        }
+                // 响铃后短暂闪烁终端可见区域
+                let bell_elapsed = self
+                    .bell_states
+                    .get(session_name)
+                    .and_then(|bell| bell.last_ring)
+                    .map(|last| last.elapsed())
+                    .filter(|elapsed| *elapsed < BELL_FLASH_DURATION);
+                if let Some(elapsed) = bell_elapsed
+                    && self.settings.terminal.bell_visual
+                {
+                    let strength = 1.0 - elapsed.as_secs_f32() / BELL_FLASH_DURATION.as_secs_f32();
+                    ui.painter().rect_filled(
+                        ui.clip_rect().intersect(response.rect),
+                        0.0,
+                        flash_color.gamma_multiply(0.3 * strength),
+                    );
+                    ui.ctx().request_repaint();
+                }
 
-                        if response.drag_stopped() {
-                            println!("检测到拖拽结束");
-                            self.text_selector.end_selection();
-                        }
+                if response.clicked() {
+                    ui.memory_mut(|mem| mem.request_focus(response.id));
 
-                        // 如果获得焦点，则处理输入和IME
-                        if response.has_focus() {
-                            // 请求IME（输入法）支持，特别是中文输入
-                            ui.ctx().output_mut(|o| {
-                                // egui 0.33 IMEOutput 需要同时设置 rect 和 cursor_rect
-                                o.ime = Some(egui::output::IMEOutput {
-                                    rect: response.rect,
-                                    cursor_rect: response.rect,
-                                });
-                            });
-                            // 告诉egui不要处理这些按键，让它们传递给我们
-                            ui.memory_mut(|mem| {
-                                mem.set_focus_lock_filter(
-                                    response.id,
-                                    egui::EventFilter {
-                                        tab: true,
-                                        horizontal_arrows: true,
-                                        vertical_arrows: true,
-                                        escape: true,
-                                    },
-                                );
-                                // 注意：egui::EventFilter 不包含对 Ctrl 快捷键的过滤选项
-                                // 因此我们需要在 handle_terminal_input 中通过 consume_key 来处理
-                            });
-                            self.handle_terminal_input(ui);
+                    // Ctrl+点击链接
+                    if let Some(ref link) = hovered_link {
+                        self.activate_link(link);
+                    }
+                }
+
+                // 右键菜单：shell 集成 (OSC 133) 相关操作
+                response.context_menu(|ui| {
+                    for (action, key) in [
+                        (ShellAction::PreviousPrompt, I18nKey::PreviousPrompt),
+                        (ShellAction::NextPrompt, I18nKey::NextPrompt),
+                        (
+                            ShellAction::SelectLastOutput,
+                            I18nKey::SelectLastCommandOutput,
+                        ),
+                        (ShellAction::CopyLastOutput, I18nKey::CopyLastCommandOutput),
+                    ] {
+                        if ui.button(self.i18n.get(key)).clicked() {
+                            shell_action = Some(action);
+                            ui.close();
                         }
                     }
+                });
+
+                // 处理鼠标选择
+                if response.drag_started()
+                    && let Some(pos) = response.interact_pointer_pos()
+                {
+                    self.text_selector.start_selection(pos.x, pos.y);
+                }
+
+                if response.dragged()
+                    && let Some(pos) = response.interact_pointer_pos()
+                {
+                    self.text_selector.update_selection(pos.x, pos.y);
+                    // 更新仿真器选择显示
+                    if let Some(emulator) = self.terminal_emulators.get_mut(session_name) {
+                        self.text_selector.update_emulator_selection(
+                            emulator.as_mut(),
+                            &response.rect,
+                            char_size,
+                            1.2, // 对应渲染时的 line_height
+                        );
+                    }
                 }
-            });
+
+                if response.drag_stopped() {
+                    self.text_selector.end_selection();
+                }
+
+                // 终端控件与应用窗口同时有焦点才算获得焦点
+                terminal_focused = response.has_focus() && ui.input(|i| i.focused);
+
+                // 如果获得焦点，则处理输入和IME
+                if response.has_focus() {
+                    // 请求IME（输入法）支持，特别是中文输入
+                    ui.ctx().output_mut(|o| {
+                        // egui 0.33 IMEOutput 需要同时设置 rect 和 cursor_rect
+                        o.ime = Some(egui::output::IMEOutput {
+                            rect: response.rect,
+                            cursor_rect: response.rect,
+                        });
+                    });
+                    // 告诉egui不要处理这些按键，让它们传递给我们
+                    ui.memory_mut(|mem| {
+                        mem.set_focus_lock_filter(
+                            response.id,
+                            egui::EventFilter {
+                                tab: true,
+                                horizontal_arrows: true,
+                                vertical_arrows: true,
+                                escape: true,
+                            },
+                        );
+                        // 注意：egui::EventFilter 不包含对 Ctrl 快捷键的过滤选项
+                        // 因此我们需要在 handle_terminal_input 中通过 consume_key 来处理
+                    });
+                    self.handle_terminal_input(ui);
+                }
+            }
+        }
+        self.report_focus(terminal_focused);
+
+        match shell_action {
+            Some(ShellAction::PreviousPrompt) => self.jump_to_prompt(false),
+            Some(ShellAction::NextPrompt) => self.jump_to_prompt(true),
+            Some(ShellAction::SelectLastOutput) => self.select_last_command_output(),
+            Some(ShellAction::CopyLastOutput) => self.copy_last_command_output(),
+            None => {}
+        }
+    }
+
+    /// 跳转到上一个/下一个提示符 (OSC 133)
+    fn jump_to_prompt(&mut self, forward: bool) {
+        let Some(emulator) = self
+            .current_session
+            .as_ref()
+            .and_then(|name| self.terminal_emulators.get_mut(name))
+        else {
+            return;
+        };
+
+        let target = emulator.with_buffer(|buffer| {
+            let top_row = buffer.display_top();
+            if forward {
+                buffer.next_prompt(top_row)
+            } else {
+                buffer.previous_prompt(top_row)
+            }
+        });
+        if let Some(row) = target {
+            emulator.scroll_display(Scroll::ToRow(row));
+        }
+    }
+
+    /// 滚动当前会话的终端视口
+    fn scroll_terminal(&mut self, scroll: Scroll) {
+        if let Some(emulator) = self
+            .current_session
+            .as_ref()
+            .and_then(|name| self.terminal_emulators.get_mut(name))
+        {
+            emulator.scroll_display(scroll);
+        }
+    }
+
+    /// 选中最近一条命令的输出
+    fn select_last_command_output(&mut self) {
+        let Some(emulator) = self
+            .current_session
+            .as_ref()
+            .and_then(|name| self.terminal_emulators.get_mut(name))
+        else {
+            return;
+        };
+
+        if let Some((start_row, end_row)) =
+            emulator.with_buffer(|buffer| buffer.last_command_output())
+        {
+            self.text_selector
+                .select_rows(emulator.as_mut(), start_row, end_row);
+        }
+    }
+
+    /// 复制最近一条命令的输出（去掉各行行尾空白）
+    fn copy_last_command_output(&mut self) {
+        let Some(emulator) = self
+            .current_session
+            .as_ref()
+            .and_then(|name| self.terminal_emulators.get(name))
+        else {
+            return;
+        };
+
+        let text = emulator.with_buffer(|buffer| {
+            let (start_row, end_row) = buffer.last_command_output()?;
+            let range = termlink::terminal::SelectionRange {
+                start_row,
+                start_col: 0,
+                end_row,
+                end_col: buffer.cols.saturating_sub(1),
+            };
+            buffer.text_in_range(&range)
+        });
+        if let Some(text) = text {
+            let text: Vec<&str> = text.lines().map(str::trim_end).collect();
+            self.text_selector.set_clipboard_text(text.join("\n"));
+        }
     }
 
     /// 处理终端输入
     fn handle_terminal_input(&mut self, ui: &mut egui::Ui) {
         let mut input_to_send = Vec::new();
+        // 粘贴内容单独处理（需要清理控制字符、可能需要确认）
+        let mut paste_text: Option<String> = None;
 
         // 先检查 Context 级别的输入状态（在 egui 处理之前）
         let ctx = ui.ctx().clone();
@@ -1513,7 +2002,7 @@ impl App {
                     // 复制操作 (Ctrl+C)
                     if i.modifiers.ctrl && i.key_pressed(egui::Key::C) {
                         if self.text_selector.state()
-                            != &crate::terminal::selection::SelectionState::None
+                            != &termlink::terminal::selection::SelectionState::None
                         {
                             if self.text_selector.copy_selected_text(emulator.as_ref()) {
                                 // 成功手动复制，消耗掉该按键
@@ -1526,11 +2015,8 @@ impl App {
                     // 粘贴操作 (Ctrl+V)
                     if i.modifiers.ctrl && i.key_pressed(egui::Key::V) {
                         if let Some(text) = self.text_selector.get_clipboard_text() {
-                            println!(
-                                "[DEBUG] 手动通过 Ctrl+V 粘贴 (copypasta), 长度: {}",
-                                text.len()
-                            );
-                            input_to_send.extend_from_slice(text.as_bytes());
+                            tracing::debug!(bytes = text.len(), "通过 Ctrl+V 粘贴");
+                            paste_text = Some(text);
                             // 消耗掉该按键，防止 egui_winit 产生错误
                             i.consume_key(egui::Modifiers::CTRL, egui::Key::V);
                             return;
@@ -1540,7 +2026,7 @@ impl App {
                     // Ctrl+Shift+V
                     if i.modifiers.ctrl && i.modifiers.shift && i.key_pressed(egui::Key::V) {
                         if let Some(text) = self.text_selector.get_clipboard_text() {
-                            input_to_send.extend_from_slice(text.as_bytes());
+                            paste_text = Some(text);
                             i.consume_key(
                                 egui::Modifiers::CTRL | egui::Modifiers::SHIFT,
                                 egui::Key::V,
@@ -1568,7 +2054,7 @@ impl App {
                             }
                             egui::Event::Copy => {
                                 if self.text_selector.state()
-                                    != &crate::terminal::selection::SelectionState::None
+                                    != &termlink::terminal::selection::SelectionState::None
                                 {
                                     if let Some(text) = self
                                         .text_selector
@@ -1578,16 +2064,15 @@ impl App {
                                         let _ = self
                                             .text_selector
                                             .copy_selected_text(emulator.as_ref());
-                                        println!("已复制选中文本: {} 字符", text.len());
+                                        tracing::debug!(bytes = text.len(), "已复制选中文本");
                                         return;
                                     }
                                 }
-                                println!("[DEBUG] 拦截 Copy 事件，发送 Ctrl+C (0x03)");
                                 input_to_send.push(0x03);
                             }
                             egui::Event::Cut => {
                                 if self.text_selector.state()
-                                    != &crate::terminal::selection::SelectionState::None
+                                    != &termlink::terminal::selection::SelectionState::None
                                 {
                                     if let Some(text) = self
                                         .text_selector
@@ -1596,196 +2081,121 @@ impl App {
                                         let _ = self
                                             .text_selector
                                             .copy_selected_text(emulator.as_ref());
-                                        println!("已剪切选中文本（仅复制），{} 字符", text.len());
+                                        tracing::debug!(
+                                            bytes = text.len(),
+                                            "已剪切选中文本（仅复制）"
+                                        );
                                         return;
                                     }
                                 }
-                                println!("[DEBUG] 拦截 Cut 事件，发送 Ctrl+X (0x18)");
                                 input_to_send.push(0x18);
                             }
                             egui::Event::Paste(text) => {
                                 if !text.is_empty() {
-                                    input_to_send.extend_from_slice(text.as_bytes());
+                                    paste_text = Some(text.clone());
                                 } else if let Some(clipboard_text) =
                                     self.text_selector.get_clipboard_text()
                                 {
-                                    input_to_send.extend_from_slice(clipboard_text.as_bytes());
+                                    paste_text = Some(clipboard_text);
                                 }
                             }
                             _ => {}
                         }
                     }
-
-                    // 检查各个字母键是否被按下 (用于其他 Ctrl 组合键)
-                    if i.modifiers.ctrl && input_to_send.is_empty() {
-                        for (key_char, key_code, byte) in [
-                            ('A', egui::Key::A, 0x01),
-                            ('B', egui::Key::B, 0x02),
-                            ('D', egui::Key::D, 0x04),
-                            ('E', egui::Key::E, 0x05),
-                            ('F', egui::Key::F, 0x06),
-                            ('G', egui::Key::G, 0x07),
-                            ('H', egui::Key::H, 0x08),
-                            ('I', egui::Key::I, 0x09),
-                            ('J', egui::Key::J, 0x0A),
-                            ('K', egui::Key::K, 0x0B),
-                            ('L', egui::Key::L, 0x0C),
-                            ('M', egui::Key::M, 0x0D),
-                            ('N', egui::Key::N, 0x0E),
-                            ('O', egui::Key::O, 0x0F),
-                            ('P', egui::Key::P, 0x10),
-                            ('Q', egui::Key::Q, 0x11),
-                            ('R', egui::Key::R, 0x12),
-                            ('S', egui::Key::S, 0x13),
-                            ('T', egui::Key::T, 0x14),
-                            ('U', egui::Key::U, 0x15),
-                            ('W', egui::Key::W, 0x17),
-                            ('Y', egui::Key::Y, 0x19),
-                            ('Z', egui::Key::Z, 0x1A),
-                        ] {
-                            if i.key_pressed(key_code) {
-                                println!("[DEBUG] Ctrl+{} 通过 key_pressed 检测到", key_char);
-                                input_to_send.push(byte);
-                                break;
-                            }
-                        }
-                    }
                 });
             }
         }
 
+        // 按当前终端模式（DECCKM、kitty 键盘协议等）编码按键
+        let modes = self
+            .current_session
+            .as_ref()
+            .and_then(|name| self.terminal_emulators.get(name))
+            .map(|emulator| emulator.modes())
+            .unwrap_or_default();
+        let encoder = KeyEncoder::new(modes, self.settings.terminal.alt_sends_escape);
+
+        // Ctrl+Shift+↑/↓ 跳转到上一个/下一个提示符
+        let mut prompt_jump = None;
+        // Shift+PageUp/PageDown/Home/End 滚动视口
+        let mut view_scroll = None;
+
         // 使用 input_mut 以便能够消费事件
         ui.input_mut(|i| {
-            // 先处理原始按键事件，检测 Ctrl 组合键
             let mut keys_to_consume = Vec::new();
+            let current_modifiers = i.modifiers;
 
-            for event in &i.events {
-                if let egui::Event::Key {
-                    key,
-                    pressed: true,
-                    modifiers,
-                    ..
-                } = event
-                {
-                    if modifiers.ctrl {
-                        // 调试日志
-                        println!("[DEBUG] Ctrl 组合键检测到: {:?}", key);
-
-                        // 记录需要消费的按键
-                        keys_to_consume.push(*key);
-
-                        // 手动映射 Ctrl 组合键
-                        let code = match key {
-                            egui::Key::A => Some(0x01),
-                            egui::Key::B => Some(0x02),
-                            egui::Key::C => Some(0x03),
-                            egui::Key::D => Some(0x04),
-                            egui::Key::E => Some(0x05),
-                            egui::Key::F => Some(0x06),
-                            egui::Key::G => Some(0x07),
-                            egui::Key::H => Some(0x08),
-                            egui::Key::I => Some(0x09),
-                            egui::Key::J => Some(0x0A),
-                            egui::Key::K => Some(0x0B),
-                            egui::Key::L => Some(0x0C),
-                            egui::Key::M => Some(0x0D),
-                            egui::Key::N => Some(0x0E),
-                            egui::Key::O => Some(0x0F),
-                            egui::Key::P => Some(0x10),
-                            egui::Key::Q => Some(0x11),
-                            egui::Key::R => Some(0x12),
-                            egui::Key::S => Some(0x13),
-                            egui::Key::T => Some(0x14),
-                            egui::Key::U => Some(0x15),
-                            egui::Key::V => Some(0x16),
-                            egui::Key::W => Some(0x17),
-                            egui::Key::X => Some(0x18),
-                            egui::Key::Y => Some(0x19),
-                            egui::Key::Z => Some(0x1A),
-                            egui::Key::OpenBracket => Some(0x1B),
-                            egui::Key::Backslash => Some(0x1C),
-                            egui::Key::CloseBracket => Some(0x1D),
-                            egui::Key::Num6 => Some(0x1E),
-                            egui::Key::Minus => Some(0x1F),
-                            egui::Key::Num2 => Some(0x00), // Ctrl+@
-                            _ => None,
-                        };
-
-                        if let Some(c) = code {
-                            println!("[DEBUG] 发送控制码: 0x{:02X}", c);
-                            input_to_send.push(c);
-                        }
-                    }
-                }
-            }
-
-            // 消费掉 Ctrl 组合键，防止 egui 默认处理
-            for key in keys_to_consume {
-                i.consume_key(egui::Modifiers::CTRL, key);
-            }
-
-            // 再处理其他事件
             for event in &i.events {
                 match event {
                     // 特别处理 IME 提交事件
                     egui::Event::Ime(egui::ImeEvent::Commit(text)) => {
-                        println!("[DEBUG] IME Commit: {:?}", text);
                         input_to_send.extend_from_slice(text.as_bytes());
                     }
-                    // 普通文本输入
-                    egui::Event::Text(text) => {
-                        // 调试：显示所有文本字符（包括控制字符）
-                        for c in text.chars() {
-                            let code = c as u32;
-                            println!("[DEBUG] Text 事件字符: {:?} (0x{:02X})", c, code);
-
-                            // 检查是否是控制字符
-                            if code <= 0x1F {
-                                println!("[DEBUG] 检测到控制字符，直接发送: 0x{:02X}", code);
-                                input_to_send.push(code as u8);
-                            } else {
-                                let mut b = [0; 4];
-                                input_to_send.extend_from_slice(c.encode_utf8(&mut b).as_bytes());
-                            }
-                        }
+                    // 普通文本输入（修饰键组合由按键编码处理）
+                    egui::Event::Text(text) if !encoder.consumes_text(current_modifiers, text) => {
+                        input_to_send.extend_from_slice(text.as_bytes());
                     }
-                    // 按键事件（非 Ctrl 组合键）
                     egui::Event::Key {
                         key,
                         pressed: true,
                         modifiers,
                         ..
                     } => {
-                        println!(
-                            "[DEBUG] Key 事件: {:?}, Ctrl={}, Alt={}, Shift={}",
-                            key, modifiers.ctrl, modifiers.alt, modifiers.shift
-                        );
+                        if modifiers.ctrl {
+                            // 记录需要消费的 Ctrl 组合键（连同 Shift/Alt 等完整修饰键）
+                            keys_to_consume.push((*modifiers, *key));
+                        }
 
-                        if !modifiers.ctrl && modifiers.alt {
-                            // 处理 Alt 组合键 (Meta 键支持)
-                            if let Some(mut bytes) = self.key_to_bytes(*key) {
-                                input_to_send.push(0x1b); // 发送 ESC 前缀
-                                input_to_send.append(&mut bytes);
-                            }
-                        } else if !modifiers.ctrl && !modifiers.alt {
-                            // 普通功能按键 (Tab, ArrowKeys, Backspace, Home, End, F1-F12等)
-                            if let Some(key_code) = self.key_to_bytes(*key) {
-                                input_to_send.extend(key_code);
+                        if modifiers.ctrl
+                            && modifiers.shift
+                            && matches!(key, egui::Key::ArrowUp | egui::Key::ArrowDown)
+                        {
+                            prompt_jump = Some(*key == egui::Key::ArrowDown);
+                            continue;
+                        }
+
+                        if modifiers.shift_only() {
+                            let scroll = match key {
+                                egui::Key::PageUp => Some(Scroll::PageUp),
+                                egui::Key::PageDown => Some(Scroll::PageDown),
+                                egui::Key::Home => Some(Scroll::Top),
+                                egui::Key::End => Some(Scroll::Bottom),
+                                _ => None,
+                            };
+                            if scroll.is_some() {
+                                view_scroll = scroll;
+                                continue;
                             }
                         }
+
+                        if let Some(bytes) = encoder.encode(*key, *modifiers) {
+                            input_to_send.extend(bytes);
+                        }
                     }
                     _ => {}
                 }
             }
+
+            // 消费掉 Ctrl 组合键，防止 egui 默认处理
+            for (modifiers, key) in keys_to_consume {
+                i.consume_key(modifiers, key);
+            }
         });
 
+        if let Some(forward) = prompt_jump {
+            self.jump_to_prompt(forward);
+        }
+
+        if let Some(scroll) = view_scroll {
+            self.scroll_terminal(scroll);
+        }
+
+        if let Some(text) = paste_text {
+            self.paste_to_terminal(text);
+        }
+
         // 发送收集到的输入
         if !input_to_send.is_empty() {
-            println!(
-                "[DEBUG] 发送 {} 字节到终端: {:?}",
-                input_to_send.len(),
-                input_to_send
-            );
             self.send_to_terminal(&input_to_send);
         }
     }
@@ -1877,13 +2287,108 @@ impl App {
                                 }
                             });
 
+                        // 该连接的终端配置（未设置时跟随全局设置）
+                        ui.collapsing(self.i18n.get(I18nKey::TerminalSettings), |ui| {
+                            let i18n = &self.i18n;
+                            let profile = &mut self.connection_form.terminal_profile;
+                            let follow = i18n.get(I18nKey::FollowGlobalSetting);
+
+                            egui::ComboBox::from_label(i18n.get(I18nKey::CursorShape))
+                                .selected_text(
+                                    profile
+                                        .cursor_shape
+                                        .map(|shape| cursor_shape_label(i18n, shape))
+                                        .unwrap_or(follow),
+                                )
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut profile.cursor_shape, None, follow);
+                                    for shape in CURSOR_SHAPES {
+                                        ui.selectable_value(
+                                            &mut profile.cursor_shape,
+                                            Some(shape),
+                                            cursor_shape_label(i18n, shape),
+                                        );
+                                    }
+                                });
+
+                            egui::ComboBox::from_label(i18n.get(I18nKey::CursorBlink))
+                                .selected_text(match profile.cursor_blink {
+                                    None => follow,
+                                    Some(true) => i18n.get(I18nKey::CursorBlink),
+                                    Some(false) => i18n.get(I18nKey::CursorSteady),
+                                })
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut profile.cursor_blink, None, follow);
+                                    ui.selectable_value(
+                                        &mut profile.cursor_blink,
+                                        Some(true),
+                                        i18n.get(I18nKey::CursorBlink),
+                                    );
+                                    ui.selectable_value(
+                                        &mut profile.cursor_blink,
+                                        Some(false),
+                                        i18n.get(I18nKey::CursorSteady),
+                                    );
+                                });
+
+                            ui.horizontal(|ui| {
+                                let mut custom = profile.cursor_blink_interval.is_some();
+                                ui.checkbox(&mut custom, i18n.get(I18nKey::CursorBlinkInterval));
+                                if custom {
+                                    let interval = profile.cursor_blink_interval.get_or_insert(
+                                        self.settings.terminal.cursor_blink_interval,
+                                    );
+                                    ui.add(
+                                        egui::DragValue::new(interval)
+                                            .speed(10.0)
+                                            .range(100..=2000),
+                                    );
+                                } else {
+                                    profile.cursor_blink_interval = None;
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                let mut custom = profile.scrollback_lines.is_some();
+                                ui.checkbox(&mut custom, i18n.get(I18nKey::ScrollbackLines));
+                                if custom {
+                                    let lines = profile
+                                        .scrollback_lines
+                                        .get_or_insert(self.settings.terminal.scrollback_lines);
+                                    ui.add(
+                                        egui::DragValue::new(lines)
+                                            .speed(100.0)
+                                            .range(0..=10_000_000),
+                                    )
+                                    .on_hover_text(i18n.get(I18nKey::ScrollbackLinesHint));
+                                } else {
+                                    profile.scrollback_lines = None;
+                                }
+                            });
+
+                            egui::ComboBox::from_label(i18n.get(I18nKey::EmulatorEngine))
+                                .selected_text(emulator_engine_label(i18n, profile.emulator_engine))
+                                .show_ui(ui, |ui| {
+                                    for engine in [EmulatorEngine::Builtin, EmulatorEngine::WezTerm]
+                                    {
+                                        ui.selectable_value(
+                                            &mut profile.emulator_engine,
+                                            engine,
+                                            emulator_engine_label(i18n, engine),
+                                        );
+                                    }
+                                })
+                                .response
+                                .on_hover_text(i18n.get(I18nKey::EmulatorEngineHint));
+                        });
+
                         ui.separator();
 
                         // 显示测试状态
                         if self.is_testing_connection {
                             ui.group(|ui| {
                                 ui.vertical(|ui| {
-                                    ui.label("正在测试连接...");
+                                    ui.label(self.i18n.get(I18nKey::TestingConnection));
                                     ui.spinner();
                                 });
                             });
@@ -1898,7 +2403,9 @@ impl App {
                                     };
 
                                     ui.label(result_text);
-                                    ui.label(&result.message);
+                                    if let Some(ref error) = result.error {
+                                        ui.label(self.format_connect_error(error));
+                                    }
 
                                     if let Some(latency) = result.latency {
                                         ui.label(format!(
@@ -1956,9 +2463,9 @@ impl App {
     fn test_connection(&mut self) {
         // 检查必要字段是否填写
         if self.connection_form.host.is_empty() || self.connection_form.username.is_empty() {
-            self.test_result = Some(crate::ssh::ConnectionTestResult {
+            self.test_result = Some(termlink::ssh::ConnectionTestResult {
                 success: false,
-                message: "请填写主机地址和用户名".to_string(),
+                error: Some(ConnectError::MissingTarget),
                 latency: None,
             });
             return;
@@ -1988,6 +2495,7 @@ impl App {
             },
             last_connected: None,
             group: None,
+            terminal_profile: TerminalProfile::default(),
         };
 
         // 克隆需要的数据
@@ -1997,7 +2505,7 @@ impl App {
 
         // 在后台线程中执行测试
         std::thread::spawn(move || {
-            let result = crate::ssh::test_connection(&config_clone);
+            let result = termlink::ssh::test_connection(&config_clone);
 
             // 将结果存储到共享内存
             if let Ok(mut guard) = shared_result.lock() {
@@ -2043,6 +2551,7 @@ impl App {
                 },
                 last_connected: None,
                 group: Some(group_name_clone.clone()),
+                terminal_profile: self.connection_form.terminal_profile.clone(),
             };
 
             // 处理更新或添加
@@ -2091,7 +2600,7 @@ impl App {
             // 重置表单
             self.connection_form = ConnectionForm::default();
 
-            println!("连接 '{}' 已保存到分组 '{}'", config.name, group_name_clone);
+            tracing::info!("连接 '{}' 已保存到分组 '{}'", config.name, group_name_clone);
         }
     }
 
@@ -2109,6 +2618,7 @@ impl App {
             private_key_path: config.private_key_path.unwrap_or_default(),
             save_to_history: true,
             group: config.group.clone(),
+            terminal_profile: config.terminal_profile.clone(),
         };
 
         // 直接连接，不需要显示对话框
@@ -2141,6 +2651,7 @@ impl App {
             private_key_path: config.private_key_path.unwrap_or_default(),
             save_to_history: true,
             group: config.group.clone(),
+            terminal_profile: config.terminal_profile.clone(),
         };
 
         self.show_connection_dialog = true;
@@ -2222,7 +2733,7 @@ impl App {
                         connections: Vec::new(),
                     });
                 } else {
-                    println!("分组名称 '{}' 已存在", name);
+                    tracing::warn!("分组名称 '{}' 已存在", name);
                     return;
                 }
             }
@@ -2429,52 +2940,6 @@ impl App {
         session_name.to_string()
     }
 
-    /// 检查连接超时
-    fn check_connection_timeouts(&mut self) {
-        let manager = self.connection_manager.lock().unwrap();
-        let active_sessions = manager.get_active_sessions();
-        drop(manager);
-
-        let now = std::time::Instant::now();
-
-        for session_name in active_sessions {
-            // 检查会话是否在连接中状态
-            let manager = self.connection_manager.lock().unwrap();
-            let session_state = manager
-                .get_session(&session_name)
-                .map(|s| s.state().clone());
-            drop(manager);
-
-            if let Some(SessionState::Connecting) = session_state {
-                // 检查是否超时
-                if let Some(start_time) = self.session_start_times.get(&session_name) {
-                    let elapsed = now.duration_since(*start_time).as_secs();
-                    if elapsed > self.connection_timeout {
-                        // 超时，设置为错误状态
-                        let mut manager = self.connection_manager.lock().unwrap();
-                        if let Some(mut session) = manager.get_session(&session_name) {
-                            session.state = SessionState::Error(format!(
-                                "连接超时 ({}秒)",
-                                self.connection_timeout
-                            ));
-                            // 重新插入更新后的会话
-                            manager.add_session(session_name.clone(), session);
-                        }
-                        drop(manager);
-
-                        // 移除超时记录
-                        self.session_start_times.remove(&session_name);
-
-                        println!("会话 {} 连接超时", session_name);
-                    }
-                }
-            } else {
-                // 如果会话不再是连接中状态，移除时间记录
-                self.session_start_times.remove(&session_name);
-            }
-        }
-    }
-
     /// 渲染会话标签页
     fn render_session_tabs(&mut self, ui: &mut egui::Ui) {
         let manager = self.connection_manager.lock().unwrap();
@@ -2485,6 +2950,13 @@ impl App {
             ui.horizontal_wrapped(|ui| {
                 for session_name in active_sessions {
                     let is_selected = self.current_session.as_ref() == Some(&session_name);
+                    if is_selected && let Some(bell) = self.bell_states.get_mut(&session_name) {
+                        bell.attention = false;
+                    }
+                    let bell_attention = self
+                        .bell_states
+                        .get(&session_name)
+                        .is_some_and(|bell| bell.attention);
 
                     // 获取会话状态
                     let manager = self.connection_manager.lock().unwrap();
@@ -2497,7 +2969,7 @@ impl App {
                     // 根据状态确定图标、颜色和动画
                     let (icon_char, icon_color, is_spinning) = match session_state {
                         SessionState::Connected => ('●', egui::Color32::GREEN, false),
-                        SessionState::Connecting => ('◐', egui::Color32::ORANGE, true),
+                        SessionState::Connecting(_) => ('◐', egui::Color32::ORANGE, true),
                         SessionState::Error(_) => ('✖', egui::Color32::RED, false),
                         SessionState::Disconnected => ('○', egui::Color32::GRAY, false),
                     };
@@ -2528,8 +3000,22 @@ impl App {
 
                             ui.colored_label(icon_color, icon_text);
 
-                            // 会话名称
-                            let label_response = ui.selectable_label(is_selected, &session_name);
+                            // 后台会话响铃提醒
+                            if bell_attention {
+                                ui.colored_label(egui::Color32::ORANGE, "!")
+                                    .on_hover_text(self.i18n.get(I18nKey::BellAttention));
+                            }
+
+                            // 会话标题，过长时截断，悬停显示完整标题
+                            let title = self.session_title(&session_name);
+                            let label_response = if title.chars().count() > TAB_TITLE_MAX_CHARS {
+                                let short: String =
+                                    title.chars().take(TAB_TITLE_MAX_CHARS - 1).collect();
+                                ui.selectable_label(is_selected, format!("{}…", short))
+                                    .on_hover_text(&title)
+                            } else {
+                                ui.selectable_label(is_selected, &title)
+                            };
                             if label_response.middle_clicked() {
                                 self.close_session(&session_name);
                             }
@@ -2604,6 +3090,7 @@ impl App {
             },
             last_connected: Some(chrono::Local::now().to_rfc3339()),
             group: selected_group.clone(),
+            terminal_profile: self.connection_form.terminal_profile.clone(),
         };
 
         // 5. 设置当前活跃会话和 UI 状态
@@ -2617,7 +3104,7 @@ impl App {
         self.show_connection_dialog = false;
 
         // 6. 后台连接准备工作
-        let password_for_thread = if let Some(ref p) = form_password {
+        let password = if let Some(ref p) = form_password {
             if !p.is_empty() { Some(p.clone()) } else { None }
         } else {
             None
@@ -2627,83 +3114,26 @@ impl App {
         self.connection_form = ConnectionForm::default();
 
         // 8. 初始化终端仿真器
-        let mut emulator = TerminalEmulator::new(40, 120);
-        // let mut emulator = WezTermAdapter::new(40, 120, crate::terminal::TerminalTheme::default());
-
-        // 设置终端事件回调
-        let session_name_clone = session_name.clone();
-        let manager_clone = self.connection_manager.clone();
-        emulator.set_event_callback(move |event| {
-            if let crate::terminal::TerminalEvent::Resize { rows, cols } = event {
-                let manager = manager_clone.lock().unwrap();
-                if let Some(session) = manager.get_session(&session_name_clone) {
-                    if let Err(e) = session.resize_terminal(rows as u32, cols as u32) {
-                        eprintln!("调整终端大小失败: {}", e);
-                    }
-                }
-            }
-        });
-
+        let emulator =
+            self.create_terminal_emulator(&session_name, &config.terminal_profile, 40, 120);
         self.terminal_emulators
-            .insert(session_name.clone(), Box::new(emulator));
-
-        // 9. 记录连接开始时间（用于超时检查）
-        let start_time = std::time::Instant::now();
-        self.session_start_times
-            .insert(session_name.clone(), start_time);
+            .insert(session_name.clone(), emulator);
+        self.session_profiles
+            .insert(session_name.clone(), config.terminal_profile.clone());
 
-        // 10. 在后台线程中执行连接
-        let manager_arc = self.connection_manager.clone();
-        let config_clone = config.clone();
-        let session_name_clone = session_name.clone();
-
-        // 首先在 manager 中创建一个占位符
+        // 9. 在后台开始连接，连接任务逐步报告所处阶段，超时或关闭标签页时取消
         {
-            let mut manager = manager_arc.lock().unwrap();
+            let mut manager = self.connection_manager.lock().unwrap();
             // 注意：manager 里的 configs 是档案列表，session 是实例列表
-            manager.add_connection_config(config_clone.clone());
-
-            let mut session = SshSession::new(
-                session_name_clone.clone(),
-                config_clone.host.clone(),
-                config_clone.port,
+            manager.add_connection_config(config.clone());
+            manager.start_connect(
+                session_name,
+                &config,
+                password.as_deref(),
+                self.connection_timeout,
             );
-            session.state = crate::ssh::SessionState::Connecting;
-            manager.add_session(session_name_clone.clone(), session);
         }
 
-        std::thread::spawn(move || {
-            let mut session = SshSession::new(
-                session_name_clone.clone(),
-                config_clone.host.clone(),
-                config_clone.port,
-            );
-
-            println!("开始连接会话: {}", session_name_clone);
-            match session.connect(
-                &config_clone.username,
-                password_for_thread.as_deref(),
-                config_clone.private_key_path.as_deref(),
-            ) {
-                Ok(_) => {
-                    println!("会话连接成功: {}", session_name_clone);
-                    let manager = manager_arc.lock().unwrap();
-                    manager.add_session(session_name_clone, session);
-                }
-                Err(e) => {
-                    eprintln!("会话连接失败: {}", e);
-                    let manager = manager_arc.lock().unwrap();
-                    let mut err_session = SshSession::new(
-                        session_name_clone.clone(),
-                        config_clone.host.clone(),
-                        config_clone.port,
-                    );
-                    err_session.state = crate::ssh::SessionState::Error(e.to_string());
-                    manager.add_session(session_name_clone, err_session);
-                }
-            }
-        });
-
         // 10. 持久化到应用级别的历史记录
         if should_save_to_history {
             if let Some(old_name) = self.editing_connection_name.take() {
@@ -2761,9 +3191,9 @@ impl App {
 
         // 保存到文件 (AppSettings::save 已经在外部调用了，但 auto_save_state 也会处理)
         if let Err(e) = self.settings.save() {
-            eprintln!("保存设置失败: {}", e);
+            tracing::error!("保存设置失败: {}", e);
         } else {
-            println!("设置已保存并应用");
+            tracing::info!("设置已保存并应用");
         }
 
         // 更新所有终端的主题
@@ -2774,65 +3204,453 @@ impl App {
     fn format_session_state(&self, state: &SessionState) -> String {
         match state {
             SessionState::Disconnected => self.i18n.get(I18nKey::Disconnected).to_string(),
-            SessionState::Connecting => self.i18n.get(I18nKey::Connecting).to_string(),
+            SessionState::Connecting(stage) => format!(
+                "{}: {}",
+                self.i18n.get(I18nKey::Connecting),
+                self.format_connect_stage(*stage)
+            ),
             SessionState::Connected => self.i18n.get(I18nKey::Connected).to_string(),
-            SessionState::Error(e) => format!("{}: {}", self.i18n.get(I18nKey::ConnectionError), e),
+            SessionState::Error(e) => format!(
+                "{}: {}",
+                self.i18n.get(I18nKey::ConnectionError),
+                self.format_connect_error(e)
+            ),
+        }
+    }
+
+    /// 连接阶段的本地化名称
+    fn format_connect_stage(&self, stage: ConnectStage) -> &'static str {
+        self.i18n.get(match stage {
+            ConnectStage::Resolving => I18nKey::ConnectStageResolving,
+            ConnectStage::TcpConnect => I18nKey::ConnectStageTcp,
+            ConnectStage::Handshake => I18nKey::ConnectStageHandshake,
+            ConnectStage::HostKeyCheck => I18nKey::ConnectStageHostKey,
+            ConnectStage::Authenticating(AuthMethod::Password) => I18nKey::ConnectStageAuthPassword,
+            ConnectStage::Authenticating(AuthMethod::PublicKey) => {
+                I18nKey::ConnectStageAuthPublicKey
+            }
+            ConnectStage::OpeningShell => I18nKey::ConnectStageShell,
+        })
+    }
+
+    /// 连接错误的本地化说明，附带底层错误信息
+    fn format_connect_error(&self, error: &ConnectError) -> String {
+        let (key, detail) = match error {
+            ConnectError::MissingTarget => (I18nKey::ConnectErrorMissingTarget, None),
+            ConnectError::Resolve { host, detail } => (
+                I18nKey::ConnectErrorResolve,
+                Some(format!("{host}: {detail}")),
+            ),
+            ConnectError::Tcp(detail) => (I18nKey::ConnectErrorTcp, Some(detail.clone())),
+            ConnectError::Handshake(detail) => {
+                (I18nKey::ConnectErrorHandshake, Some(detail.clone()))
+            }
+            ConnectError::HostKeyMismatch { host } => {
+                (I18nKey::ConnectErrorHostKeyMismatch, Some(host.clone()))
+            }
+            ConnectError::UnknownHostKey(info) => (
+                I18nKey::ConnectErrorUnknownHostKey,
+                Some(format!("{}: {}", info.host, info.fingerprint)),
+            ),
+            ConnectError::NoAuthMethod => (I18nKey::ConnectErrorNoAuthMethod, None),
+            ConnectError::Auth { method, detail } => (
+                I18nKey::ConnectErrorAuth,
+                Some(if detail.is_empty() {
+                    method.to_string()
+                } else {
+                    format!("{method}: {detail}")
+                }),
+            ),
+            ConnectError::Shell(detail) => (I18nKey::ConnectErrorShell, Some(detail.clone())),
+            ConnectError::Timeout(secs) => {
+                (I18nKey::ConnectErrorTimeout, Some(format!("{secs} s")))
+            }
+            ConnectError::Cancelled => (I18nKey::ConnectErrorCancelled, None),
+        };
+        match detail {
+            Some(detail) => format!("{} ({})", self.i18n.get(key), detail),
+            None => self.i18n.get(key).to_string(),
+        }
+    }
+
+    /// 终端焦点变化时通知远端 (DECSET 1004)
+    ///
+    /// 切换会话时，原来有焦点的会话按失去焦点处理。
+    fn report_focus(&mut self, focused: bool) {
+        let current = self.current_session.clone();
+        let previous = self.terminal_focus.take();
+
+        if let Some((ref name, true)) = previous
+            && current.as_ref() != Some(name)
+        {
+            self.send_focus_event(name, false);
+        }
+
+        if let Some(name) = current {
+            let was_focused = matches!(previous, Some((ref prev, true)) if *prev == name);
+            if focused != was_focused {
+                self.send_focus_event(&name, focused);
+            }
+            self.terminal_focus = Some((name, focused));
+        }
+    }
+
+    /// 向开启了焦点报告的会话发送 CSI I（获得焦点）或 CSI O（失去焦点）
+    fn send_focus_event(&self, session_name: &str, focused: bool) {
+        let enabled = self
+            .terminal_emulators
+            .get(session_name)
+            .is_some_and(|emulator| emulator.modes().focus_reporting);
+        if !enabled {
+            return;
         }
+
+        let data: &[u8] = if focused { b"\x1b[I" } else { b"\x1b[O" };
+        self.write_to_session(session_name, data);
     }
 
     /// 发送数据到终端
     fn send_to_terminal(&mut self, data: &[u8]) {
+        // 输入时回到底部，跟随新的输出
+        self.scroll_terminal(Scroll::Bottom);
         if let Some(ref session_name) = self.current_session {
-            let manager = self.connection_manager.lock().unwrap();
-            if let Some(session) = manager.get_session(session_name) {
-                if session.is_connected() {
-                    if let Err(e) = session.write_terminal(data) {
-                        eprintln!("[TermLink] 发送数据失败: {}", e);
+            self.write_to_session(session_name, data);
+        }
+    }
+
+    /// 粘贴文本到当前终端，多行或大段文本需要先确认
+    fn paste_to_terminal(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+
+        if termlink::terminal::paste::needs_confirmation(
+            &text,
+            self.settings.terminal.confirm_multiline_paste,
+            self.settings.terminal.large_paste_threshold,
+        ) {
+            self.pending_paste = Some(text);
+        } else {
+            self.send_paste(&text);
+        }
+    }
+
+    /// 按当前终端模式编码并发送粘贴内容
+    fn send_paste(&mut self, text: &str) {
+        let bracketed = self
+            .current_session
+            .as_ref()
+            .and_then(|name| self.terminal_emulators.get(name))
+            .map(|emulator| emulator.modes().bracketed_paste)
+            .unwrap_or(false);
+
+        let bytes = termlink::terminal::paste::encode(text, bracketed);
+        if !bytes.is_empty() {
+            self.send_to_terminal(&bytes);
+        }
+    }
+
+    /// 渲染粘贴确认对话框
+    fn render_paste_confirm_dialog(&mut self, ctx: &egui::Context) {
+        let Some(text) = self.pending_paste.clone() else {
+            return;
+        };
+
+        let mut confirmed = false;
+        let mut cancelled = false;
+
+        egui::Window::new(self.i18n.get(I18nKey::PasteConfirmTitle))
+            .default_width(420.0)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(self.i18n.get(I18nKey::PasteConfirmMessage));
+                ui.add_space(6.0);
+
+                ui.horizontal(|ui| {
+                    ui.strong(self.i18n.get(I18nKey::PasteLineCount));
+                    ui.label(termlink::terminal::paste::line_count(&text).to_string());
+                    ui.separator();
+                    ui.strong(self.i18n.get(I18nKey::PasteByteCount));
+                    ui.label(text.len().to_string());
+                });
+
+                ui.add_space(6.0);
+
+                // 内容预览（只显示前一部分）
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        let preview: String = text.chars().take(2000).collect();
+                        ui.monospace(preview);
+                    });
+
+                ui.add_space(10.0);
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Min), |ui| {
+                    if ui.button(self.i18n.get(I18nKey::Cancel)).clicked() {
+                        cancelled = true;
                     }
-                }
+                    if ui.button(self.i18n.get(I18nKey::PasteAnyway)).clicked() {
+                        confirmed = true;
+                    }
+                });
+            });
+
+        if confirmed {
+            self.pending_paste = None;
+            self.send_paste(&text);
+        } else if cancelled {
+            self.pending_paste = None;
+        }
+    }
+
+    /// 渲染未知主机密钥的确认对话框，信任后写入 known_hosts 并重新连接
+    fn render_host_key_confirm_dialog(&mut self, ctx: &egui::Context) {
+        let Some((session_name, info)) = self.connection_manager.lock().unwrap().pending_host_key()
+        else {
+            return;
+        };
+
+        let mut trusted = false;
+        let mut cancelled = false;
+
+        egui::Window::new(self.i18n.get(I18nKey::HostKeyConfirmTitle))
+            .id(egui::Id::new("host_key_confirm"))
+            .default_width(420.0)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(self.i18n.get(I18nKey::HostKeyConfirmMessage));
+                ui.add_space(6.0);
+
+                egui::Grid::new("host_key_info")
+                    .num_columns(2)
+                    .spacing([8.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.strong(self.i18n.get(I18nKey::Host));
+                        ui.label(format!("{}:{}", info.host, info.port));
+                        ui.end_row();
+                        ui.strong(self.i18n.get(I18nKey::HostKeyType));
+                        ui.label(&info.key_type);
+                        ui.end_row();
+                        ui.strong(self.i18n.get(I18nKey::HostKeyFingerprint));
+                        ui.monospace(&info.fingerprint);
+                        ui.end_row();
+                    });
+
+                ui.add_space(10.0);
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Min), |ui| {
+                    if ui.button(self.i18n.get(I18nKey::Cancel)).clicked() {
+                        cancelled = true;
+                    }
+                    if ui.button(self.i18n.get(I18nKey::TrustHostKey)).clicked() {
+                        trusted = true;
+                    }
+                });
+            });
+
+        let mut manager = self.connection_manager.lock().unwrap();
+        if trusted {
+            if let Err(e) = manager.trust_host_key(&session_name) {
+                tracing::error!("写入 known_hosts 失败: {}", e);
             }
+        } else if cancelled {
+            manager.reject_host_key(&session_name);
         }
     }
 
-    /// 从终端读取数据
-    fn read_from_terminal(&mut self) {
-        // 移除轮询限制，让系统自然处理
-        // 但增加错误处理和非阻塞检查
+    /// 渲染远端写入剪贴板的确认对话框
+    fn render_clipboard_confirm_dialog(&mut self, ctx: &egui::Context) {
+        let Some(text) = self.pending_clipboard_write.clone() else {
+            return;
+        };
 
-        if let Some(ref session_name) = self.current_session {
-            let manager = self.connection_manager.lock().unwrap();
-            if let Some(session) = manager.get_session(session_name) {
-                // 只在确定可读时才尝试读取
-                if session.is_terminal_readable() {
-                    let mut buffer = [0u8; 256]; // 进一步减少缓冲区
-                    match session.read_terminal(&mut buffer) {
-                        Ok(n) if n > 0 => {
-                            // 获取当前会话对应的终端仿真器
-                            if let Some(ref session_name) = self.current_session {
-                                if let Some(ref mut emulator) =
-                                    self.terminal_emulators.get_mut(session_name)
-                                {
-                                    if let Err(e) = emulator.process_input(&buffer[..n]) {
-                                        eprintln!("处理终端输入失败: {}", e);
-                                    }
-                                }
-                            }
-                        }
-                        Ok(_) => {
-                            // 没有数据可读，正常情况
-                        }
-                        Err(e) => {
-                            // 对于阻塞错误，静默处理
-                            if !e.to_string().contains("would block")
-                                && !e.to_string().contains("timed out")
-                            {
-                                eprintln!("读取终端数据失败: {}", e);
-                            }
-                        }
+        let mut confirmed = false;
+        let mut cancelled = false;
+
+        egui::Window::new(self.i18n.get(I18nKey::ClipboardWriteConfirmTitle))
+            .default_width(420.0)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(self.i18n.get(I18nKey::ClipboardWriteConfirmMessage));
+                ui.add_space(6.0);
+
+                ui.horizontal(|ui| {
+                    ui.strong(self.i18n.get(I18nKey::PasteByteCount));
+                    ui.label(text.len().to_string());
+                });
+
+                ui.add_space(6.0);
+
+                // 内容预览（只显示前一部分）
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        let preview: String = text.chars().take(2000).collect();
+                        ui.monospace(preview);
+                    });
+
+                ui.add_space(10.0);
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Min), |ui| {
+                    if ui.button(self.i18n.get(I18nKey::Cancel)).clicked() {
+                        cancelled = true;
+                    }
+                    if ui
+                        .button(self.i18n.get(I18nKey::ClipboardAllowWrite))
+                        .clicked()
+                    {
+                        confirmed = true;
+                    }
+                });
+            });
+
+        if confirmed {
+            self.pending_clipboard_write = None;
+            self.text_selector.set_clipboard_text(text);
+        } else if cancelled {
+            self.pending_clipboard_write = None;
+        }
+    }
+
+    /// 打开终端中的链接
+    ///
+    /// 网页链接交给系统默认程序打开；文件路径、IP:端口位于远端主机，
+    /// 其他协议也不宜直接交给本机处理，这些都复制到剪贴板。
+    fn activate_link(&self, link: &termlink::terminal::buffer::Link) {
+        let openable = link.kind == termlink::terminal::buffer::LinkKind::Url
+            && ["http://", "https://", "ftp://", "mailto:"]
+                .iter()
+                .any(|scheme| link.target.starts_with(scheme));
+
+        if openable && termlink::utils::helpers::open_with_system(&link.target) {
+            return;
+        }
+        self.text_selector.set_clipboard_text(link.target.clone());
+    }
+
+    /// 创建终端事件回调
+    ///
+    /// 回调在会话 I/O 线程或界面线程处理终端数据时触发，
+    /// 事件都放入队列，由界面线程在 `process_terminal_events` 中处理。
+    fn terminal_event_callback(
+        &self,
+        session_name: &str,
+    ) -> impl Fn(termlink::terminal::TerminalEvent) + Send + Sync + 'static {
+        let session_name = session_name.to_string();
+        let events = self.terminal_events.clone();
+
+        move |event| match event {
+            termlink::terminal::TerminalEvent::Resize { .. }
+            | termlink::terminal::TerminalEvent::ClipboardWrite { .. }
+            | termlink::terminal::TerminalEvent::ClipboardRead { .. }
+            | termlink::terminal::TerminalEvent::Bell
+            | termlink::terminal::TerminalEvent::TitleChange(_) => {
+                events.lock().unwrap().push((session_name.clone(), event));
+            }
+            _ => {}
+        }
+    }
+
+    /// 处理终端事件队列
+    fn process_terminal_events(&mut self) {
+        let events = std::mem::take(&mut *self.terminal_events.lock().unwrap());
+
+        for (session_name, event) in events {
+            let span = tracing::info_span!("session", name = %session_name);
+            let _guard = span.enter();
+            match event {
+                termlink::terminal::TerminalEvent::Resize { rows, cols } => {
+                    if let Some(worker) = self.session_workers.get(&session_name) {
+                        worker.resize(rows as u32, cols as u32);
                     }
                 }
+                termlink::terminal::TerminalEvent::ClipboardWrite { selection, text } => {
+                    self.handle_clipboard_write(&selection, text);
+                }
+                termlink::terminal::TerminalEvent::ClipboardRead { selection } => {
+                    self.handle_clipboard_read(&session_name, &selection);
+                }
+                termlink::terminal::TerminalEvent::Bell => {
+                    self.handle_bell(&session_name);
+                }
+                termlink::terminal::TerminalEvent::TitleChange(title) => {
+                    self.session_titles.insert(session_name, title);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// 按标题模板生成会话的显示标题，远端未设置标题时直接使用会话名
+    fn session_title(&self, session_name: &str) -> String {
+        match self.session_titles.get(session_name) {
+            Some(title) if !title.trim().is_empty() => {
+                format_session_title(&self.settings.terminal.title_template, title, session_name)
+            }
+            _ => session_name.to_string(),
+        }
+    }
+
+    /// 处理响铃：限流后记录响铃时间，后台会话在标签页上提醒，按设置播放提示音
+    fn handle_bell(&mut self, session_name: &str) {
+        let now = std::time::Instant::now();
+        let bell = self
+            .bell_states
+            .entry(session_name.to_string())
+            .or_default();
+        if bell
+            .last_ring
+            .is_some_and(|last| now.duration_since(last) < BELL_MIN_INTERVAL)
+        {
+            return;
+        }
+
+        bell.last_ring = Some(now);
+        if self.current_session.as_deref() != Some(session_name) {
+            bell.attention = true;
+        }
+        if self.settings.terminal.bell_sound {
+            termlink::utils::helpers::play_bell_sound();
+        }
+    }
+
+    /// 按策略处理远端写入剪贴板的请求 (OSC 52)
+    fn handle_clipboard_write(&mut self, selection: &str, text: String) {
+        tracing::info!(selection, bytes = text.len(), "远端请求写入剪贴板");
+        if text.len() > self.settings.terminal.osc52_max_bytes {
+            tracing::warn!("忽略超出大小限制的剪贴板写入请求: {} 字节", text.len());
+            return;
+        }
+
+        match self.settings.terminal.osc52_policy {
+            ClipboardPolicy::Allow => {
+                self.text_selector.set_clipboard_text(text);
             }
+            ClipboardPolicy::Ask => {
+                self.pending_clipboard_write = Some(text);
+            }
+            ClipboardPolicy::Deny => {}
+        }
+    }
+
+    /// 处理远端读取剪贴板的请求 (OSC 52 ?)，需在设置中显式开启
+    fn handle_clipboard_read(&mut self, session_name: &str, selection: &str) {
+        if !self.settings.terminal.osc52_allow_read
+            || self.settings.terminal.osc52_policy == ClipboardPolicy::Deny
+        {
+            return;
         }
+
+        let text = self.text_selector.get_clipboard_text().unwrap_or_default();
+        let reply = termlink::terminal::clipboard::encode_osc52_reply(selection, &text);
+        self.write_to_session(session_name, &reply);
     }
 
     /// 保存应用状态到本地文件
@@ -2844,9 +3662,9 @@ impl App {
 
         // 保存到文件
         if let Err(e) = settings.save() {
-            eprintln!("保存应用状态失败: {}", e);
+            tracing::error!("保存应用状态失败: {}", e);
         } else {
-            println!("应用状态已保存");
+            tracing::debug!("应用状态已保存");
         }
     }
 
@@ -2857,10 +3675,10 @@ impl App {
                 self.settings = settings.clone();
                 self.connection_history = settings.connections;
                 self.connection_groups = settings.groups;
-                println!("应用状态已加载");
+                tracing::info!("应用状态已加载");
             }
             Err(e) => {
-                eprintln!("加载应用状态失败: {}", e);
+                tracing::error!("加载应用状态失败: {}", e);
                 // 使用默认设置
                 self.settings = AppSettings::default();
                 self.connection_history = Vec::new();
@@ -2874,75 +3692,75 @@ impl App {
         self.save_app_state();
     }
 
-    /// 将按键转换为字节序列
-    fn key_to_bytes(&self, key: egui::Key) -> Option<Vec<u8>> {
-        match key {
-            egui::Key::Enter => Some(b"\r".to_vec()),
-            egui::Key::Backspace => Some(b"\x7f".to_vec()),
-            egui::Key::Tab => Some(b"\t".to_vec()),
-            egui::Key::Escape => Some(b"\x1b".to_vec()),
-            egui::Key::ArrowUp => Some(b"\x1b[A".to_vec()),
-            egui::Key::ArrowDown => Some(b"\x1b[B".to_vec()),
-            egui::Key::ArrowLeft => Some(b"\x1b[D".to_vec()),
-            egui::Key::ArrowRight => Some(b"\x1b[C".to_vec()),
-            egui::Key::Delete => Some(b"\x1b[3~".to_vec()),
-            egui::Key::Insert => Some(b"\x1b[2~".to_vec()),
-            egui::Key::Home => Some(b"\x1b[H".to_vec()),
-            egui::Key::End => Some(b"\x1b[F".to_vec()),
-            egui::Key::PageUp => Some(b"\x1b[5~".to_vec()),
-            egui::Key::PageDown => Some(b"\x1b[6~".to_vec()),
-            _ => None,
-        }
-    }
-
-    /// 更新所有终端的主题
-    fn update_terminal_themes(&mut self) {
-        let theme_style = match self.settings.get_current_theme().as_str() {
-            "light" => crate::terminal::ThemeStyle::light(),
+    /// 当前设置对应的终端配色（深色、浅色或自定义）
+    fn terminal_theme_style(&self) -> termlink::terminal::ThemeStyle {
+        match self.settings.get_current_theme().as_str() {
+            "light" => termlink::terminal::ThemeStyle::light(),
             "custom" => {
                 if let Some(ref custom) = self.settings.terminal.custom_theme {
-                    crate::terminal::ThemeStyle {
-                        foreground: crate::terminal::ThemeStyle::parse_hex(&custom.foreground),
-                        background: crate::terminal::ThemeStyle::parse_hex(&custom.background),
-                        cursor: crate::terminal::ThemeStyle::parse_hex(&custom.cursor),
-                        selection: crate::terminal::ThemeStyle::parse_hex(&custom.selection),
-                        black: crate::terminal::ThemeStyle::parse_hex(&custom.black),
-                        red: crate::terminal::ThemeStyle::parse_hex(&custom.red),
-                        green: crate::terminal::ThemeStyle::parse_hex(&custom.green),
-                        yellow: crate::terminal::ThemeStyle::parse_hex(&custom.yellow),
-                        blue: crate::terminal::ThemeStyle::parse_hex(&custom.blue),
-                        magenta: crate::terminal::ThemeStyle::parse_hex(&custom.magenta),
-                        cyan: crate::terminal::ThemeStyle::parse_hex(&custom.cyan),
-                        white: crate::terminal::ThemeStyle::parse_hex(&custom.white),
-                        bright_black: crate::terminal::ThemeStyle::parse_hex(&custom.bright_black),
-                        bright_red: crate::terminal::ThemeStyle::parse_hex(&custom.bright_red),
-                        bright_green: crate::terminal::ThemeStyle::parse_hex(&custom.bright_green),
-                        bright_yellow: crate::terminal::ThemeStyle::parse_hex(
+                    termlink::terminal::ThemeStyle {
+                        foreground: termlink::terminal::ThemeStyle::parse_hex(&custom.foreground),
+                        background: termlink::terminal::ThemeStyle::parse_hex(&custom.background),
+                        cursor: termlink::terminal::ThemeStyle::parse_hex(&custom.cursor),
+                        selection: termlink::terminal::ThemeStyle::parse_hex(&custom.selection),
+                        black: termlink::terminal::ThemeStyle::parse_hex(&custom.black),
+                        red: termlink::terminal::ThemeStyle::parse_hex(&custom.red),
+                        green: termlink::terminal::ThemeStyle::parse_hex(&custom.green),
+                        yellow: termlink::terminal::ThemeStyle::parse_hex(&custom.yellow),
+                        blue: termlink::terminal::ThemeStyle::parse_hex(&custom.blue),
+                        magenta: termlink::terminal::ThemeStyle::parse_hex(&custom.magenta),
+                        cyan: termlink::terminal::ThemeStyle::parse_hex(&custom.cyan),
+                        white: termlink::terminal::ThemeStyle::parse_hex(&custom.white),
+                        bright_black: termlink::terminal::ThemeStyle::parse_hex(
+                            &custom.bright_black,
+                        ),
+                        bright_red: termlink::terminal::ThemeStyle::parse_hex(&custom.bright_red),
+                        bright_green: termlink::terminal::ThemeStyle::parse_hex(
+                            &custom.bright_green,
+                        ),
+                        bright_yellow: termlink::terminal::ThemeStyle::parse_hex(
                             &custom.bright_yellow,
                         ),
-                        bright_blue: crate::terminal::ThemeStyle::parse_hex(&custom.bright_blue),
-                        bright_magenta: crate::terminal::ThemeStyle::parse_hex(
+                        bright_blue: termlink::terminal::ThemeStyle::parse_hex(&custom.bright_blue),
+                        bright_magenta: termlink::terminal::ThemeStyle::parse_hex(
                             &custom.bright_magenta,
                         ),
-                        bright_cyan: crate::terminal::ThemeStyle::parse_hex(&custom.bright_cyan),
-                        bright_white: crate::terminal::ThemeStyle::parse_hex(&custom.bright_white),
+                        bright_cyan: termlink::terminal::ThemeStyle::parse_hex(&custom.bright_cyan),
+                        bright_white: termlink::terminal::ThemeStyle::parse_hex(
+                            &custom.bright_white,
+                        ),
                     }
                 } else {
-                    crate::terminal::ThemeStyle::dark()
+                    termlink::terminal::ThemeStyle::dark()
                 }
             }
-            _ => crate::terminal::ThemeStyle::dark(),
-        };
-        let theme = crate::terminal::TerminalTheme {
-            style: theme_style,
-            font_size: self.settings.terminal.font_size,
-            line_height: 1.2,
-            cursor_blink: self.settings.terminal.cursor_blink,
-        };
+            _ => termlink::terminal::ThemeStyle::dark(),
+        }
+    }
 
-        // 更新所有已存在的终端仿真器主题
-        for (_, emulator) in self.terminal_emulators.iter_mut() {
-            emulator.update_theme(theme.clone());
+    /// 更新所有终端的主题
+    fn update_terminal_themes(&mut self) {
+        let theme_style = self.terminal_theme_style();
+        // 更新所有已存在的终端渲染器主题与仿真器的回滚限制，光标与回滚行数按会话所属连接的配置覆盖
+        for (session_name, emulator) in self.terminal_emulators.iter_mut() {
+            let profile = self
+                .session_profiles
+                .get(session_name)
+                .cloned()
+                .unwrap_or_default();
+            let theme = termlink::terminal::TerminalTheme {
+                style: theme_style.clone(),
+                font_size: self.settings.terminal.font_size,
+                line_height: 1.2,
+                cursor_blink: profile.cursor_blink(&self.settings.terminal),
+                cursor_shape: profile.cursor_shape(&self.settings.terminal),
+                cursor_blink_interval: profile.cursor_blink_interval(&self.settings.terminal),
+            };
+            emulator.set_palette(theme.palette());
+            if let Some(renderer) = self.terminal_renderers.get_mut(session_name) {
+                renderer.set_theme(theme);
+            }
+            emulator.set_scrollback(profile.scrollback(&self.settings.terminal));
         }
     }
 
@@ -3062,27 +3880,27 @@ impl App {
                             self.settings = imported_settings;
                             self.connection_history = self.settings.connections.clone();
                             self.connection_groups = self.settings.groups.clone();
-                            println!("配置导入成功（覆盖模式）");
+                            tracing::info!("配置导入成功（覆盖模式）");
                         } else {
                             // 合并模式：合并连接和分组信息
                             self.merge_config(imported_settings);
-                            println!("配置导入成功（合并模式）");
+                            tracing::info!("配置导入成功（合并模式）");
                         }
 
                         // 保存配置
                         if let Err(e) = self.settings.save() {
-                            eprintln!("保存配置失败: {}", e);
+                            tracing::error!("保存配置失败: {}", e);
                         } else {
                             self.auto_save_state();
                         }
                     }
                     Err(e) => {
-                        eprintln!("解析配置文件失败: {}", e);
+                        tracing::error!("解析配置文件失败: {}", e);
                     }
                 }
             }
             Err(e) => {
-                eprintln!("读取配置文件失败: {}", e);
+                tracing::error!("读取配置文件失败: {}", e);
             }
         }
     }
@@ -3142,15 +3960,66 @@ impl App {
         match serde_json::to_string_pretty(&export_settings) {
             Ok(json_content) => match fs::write(&self.export_file_path, json_content) {
                 Ok(_) => {
-                    println!("配置导出成功: {}", self.export_file_path);
+                    tracing::info!("配置导出成功: {}", self.export_file_path);
                 }
                 Err(e) => {
-                    eprintln!("写入配置文件失败: {}", e);
+                    tracing::error!("写入配置文件失败: {}", e);
                 }
             },
             Err(e) => {
-                eprintln!("序列化配置失败: {}", e);
+                tracing::error!("序列化配置失败: {}", e);
             }
         }
     }
 }
+
+/// 终端右键菜单中的 shell 集成操作
+enum ShellAction {
+    PreviousPrompt,
+    NextPrompt,
+    SelectLastOutput,
+    CopyLastOutput,
+}
+
+/// 可选的光标形状
+const CURSOR_SHAPES: [CursorShape; 3] =
+    [CursorShape::Block, CursorShape::Underline, CursorShape::Bar];
+
+/// 填充标题模板中的 {title} 与 {name}，模板为空时只显示远端标题
+fn format_session_title(template: &str, title: &str, session_name: &str) -> String {
+    if template.trim().is_empty() {
+        return title.to_string();
+    }
+    // 逐段替换，避免远端标题中的占位符被再次展开
+    template
+        .split("{title}")
+        .map(|part| part.replace("{name}", session_name))
+        .collect::<Vec<_>>()
+        .join(title)
+}
+
+/// 剪贴板策略的显示名称
+fn clipboard_policy_label(i18n: &I18nManager, policy: ClipboardPolicy) -> &'static str {
+    match policy {
+        ClipboardPolicy::Allow => i18n.get(I18nKey::ClipboardPolicyAllow),
+        ClipboardPolicy::Ask => i18n.get(I18nKey::ClipboardPolicyAsk),
+        ClipboardPolicy::Deny => i18n.get(I18nKey::ClipboardPolicyDeny),
+    }
+}
+
+/// 光标形状的显示名称
+fn cursor_shape_label(i18n: &I18nManager, shape: CursorShape) -> &'static str {
+    match shape {
+        CursorShape::Block => i18n.get(I18nKey::CursorShapeBlock),
+        CursorShape::Underline => i18n.get(I18nKey::CursorShapeUnderline),
+        CursorShape::Bar => i18n.get(I18nKey::CursorShapeBar),
+    }
+}
+
+/// 终端仿真引擎的显示名称
+fn emulator_engine_label(i18n: &I18nManager, engine: EmulatorEngine) -> &'static str {
+    match engine {
+        EmulatorEngine::Builtin => i18n.get(I18nKey::EmulatorEngineBuiltin),
+        EmulatorEngine::WezTerm => i18n.get(I18nKey::EmulatorEngineWezTerm),
+    }
+}
@@ -1,6 +1,10 @@
 //! 应用设置管理
 //! 处理配置文件的读写和管理
 
+use crate::terminal::clipboard::ClipboardPolicy;
+use crate::terminal::cursor::CursorShape;
+use crate::terminal::emulator::EmulatorEngine;
+use crate::terminal::scrollback::ScrollbackConfig;
 use anyhow::Result;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
@@ -30,7 +34,7 @@ pub struct WindowSettings {
 }
 
 /// 连接配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ConnectionConfig {
     pub name: String,
     pub host: String,
@@ -41,6 +45,70 @@ pub struct ConnectionConfig {
     pub password: Option<String>, // 保存的密码
     pub last_connected: Option<String>,
     pub group: Option<String>, // 所属分组
+    /// 该连接的终端配置
+    #[serde(default)]
+    pub terminal_profile: TerminalProfile,
+}
+
+// 调试输出中隐藏保存的密码，避免写入日志
+impl std::fmt::Debug for ConnectionConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionConfig")
+            .field("name", &self.name)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("use_key_auth", &self.use_key_auth)
+            .field("private_key_path", &self.private_key_path)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("last_connected", &self.last_connected)
+            .field("group", &self.group)
+            .field("terminal_profile", &self.terminal_profile)
+            .finish()
+    }
+}
+
+/// 连接级别的终端配置，未设置的项使用全局终端设置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalProfile {
+    /// 默认光标形状
+    pub cursor_shape: Option<CursorShape>,
+    /// 光标是否闪烁
+    pub cursor_blink: Option<bool>,
+    /// 光标闪烁间隔（毫秒）
+    pub cursor_blink_interval: Option<u64>,
+    /// 回滚历史行数（0 表示不限制）
+    pub scrollback_lines: Option<usize>,
+    /// 终端仿真引擎
+    pub emulator_engine: EmulatorEngine,
+}
+
+impl TerminalProfile {
+    /// 光标形状，未设置时使用全局设置
+    pub fn cursor_shape(&self, global: &TerminalSettings) -> CursorShape {
+        self.cursor_shape.unwrap_or(global.cursor_shape)
+    }
+
+    /// 光标是否闪烁，未设置时使用全局设置
+    pub fn cursor_blink(&self, global: &TerminalSettings) -> bool {
+        self.cursor_blink.unwrap_or(global.cursor_blink)
+    }
+
+    /// 光标闪烁间隔，未设置时使用全局设置
+    pub fn cursor_blink_interval(&self, global: &TerminalSettings) -> u64 {
+        self.cursor_blink_interval
+            .unwrap_or(global.cursor_blink_interval)
+    }
+
+    /// 回滚历史的限制：行数可按配置覆盖，内存上限与压缩使用全局设置
+    pub fn scrollback(&self, global: &TerminalSettings) -> ScrollbackConfig {
+        ScrollbackConfig {
+            max_lines: self.scrollback_lines.unwrap_or(global.scrollback_lines),
+            max_bytes: global.scrollback_memory_mb.saturating_mul(1024 * 1024),
+            compress: global.scrollback_compress,
+        }
+    }
 }
 
 /// 连接分组
@@ -218,8 +286,83 @@ pub struct TerminalSettings {
     pub font_family: String,
     pub theme: String,
     pub cursor_blink: bool,
+    /// 默认光标形状
+    #[serde(default)]
+    pub cursor_shape: CursorShape,
+    /// 光标闪烁间隔（毫秒）
+    #[serde(default = "default_cursor_blink_interval")]
+    pub cursor_blink_interval: u64,
     /// 自定义主题颜色配置
     pub custom_theme: Option<CustomTheme>,
+    /// 粘贴多行文本前是否需要确认
+    #[serde(default = "default_true")]
+    pub confirm_multiline_paste: bool,
+    /// 超过该字节数的粘贴需要确认（0 表示不限制）
+    #[serde(default = "default_large_paste_threshold")]
+    pub large_paste_threshold: usize,
+    /// Alt 键作为 Meta 键（发送 ESC 前缀）
+    #[serde(default = "default_alt_sends_escape")]
+    pub alt_sends_escape: bool,
+    /// 远端通过 OSC 52 写入剪贴板的策略
+    #[serde(default)]
+    pub osc52_policy: ClipboardPolicy,
+    /// OSC 52 写入内容的最大字节数
+    #[serde(default = "default_osc52_max_bytes")]
+    pub osc52_max_bytes: usize,
+    /// 是否允许远端通过 OSC 52 读取剪贴板
+    #[serde(default)]
+    pub osc52_allow_read: bool,
+    /// 响铃时闪烁终端
+    #[serde(default = "default_true")]
+    pub bell_visual: bool,
+    /// 响铃时播放系统提示音
+    #[serde(default)]
+    pub bell_sound: bool,
+    /// 标签页与窗口标题模板，{title} 为远端设置的标题，{name} 为会话名
+    #[serde(default = "default_title_template")]
+    pub title_template: String,
+    /// 回滚历史行数（0 表示不限制，仍受内存上限约束）
+    #[serde(default = "default_scrollback_lines")]
+    pub scrollback_lines: usize,
+    /// 每个会话回滚历史的内存上限（MB，0 表示不限制）
+    #[serde(default = "default_scrollback_memory_mb")]
+    pub scrollback_memory_mb: usize,
+    /// 压缩较早的回滚历史以节省内存
+    #[serde(default)]
+    pub scrollback_compress: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// macOS 上 Option 键用于输入特殊字符，默认不作为 Meta 键
+fn default_alt_sends_escape() -> bool {
+    !cfg!(target_os = "macos")
+}
+
+fn default_large_paste_threshold() -> usize {
+    5000
+}
+
+fn default_cursor_blink_interval() -> u64 {
+    500
+}
+
+fn default_title_template() -> String {
+    "{title} — {name}".to_string()
+}
+
+fn default_osc52_max_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_scrollback_lines() -> usize {
+    ScrollbackConfig::default().max_lines
+}
+
+fn default_scrollback_memory_mb() -> usize {
+    ScrollbackConfig::default().max_bytes / (1024 * 1024)
 }
 
 /// 主题模式
@@ -266,7 +409,21 @@ impl Default for AppSettings {
                 font_family: "Consolas".to_string(),
                 theme: "dark".to_string(),
                 cursor_blink: true,
+                cursor_shape: CursorShape::Block,
+                cursor_blink_interval: default_cursor_blink_interval(),
                 custom_theme: None,
+                confirm_multiline_paste: true,
+                large_paste_threshold: default_large_paste_threshold(),
+                alt_sends_escape: default_alt_sends_escape(),
+                osc52_policy: ClipboardPolicy::Ask,
+                osc52_max_bytes: default_osc52_max_bytes(),
+                osc52_allow_read: false,
+                bell_visual: true,
+                bell_sound: false,
+                title_template: default_title_template(),
+                scrollback_lines: default_scrollback_lines(),
+                scrollback_memory_mb: default_scrollback_memory_mb(),
+                scrollback_compress: false,
             },
             appearance: AppearanceSettings {
                 theme_mode: ThemeMode::Auto,
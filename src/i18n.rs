@@ -192,6 +192,87 @@ pub enum I18nKey {
     TestConnectionSuccess,
     TestConnectionFailed,
     TestConnectionLatency,
+    // 粘贴确认
+    PasteConfirmTitle,
+    PasteConfirmMessage,
+    PasteLineCount,
+    PasteByteCount,
+    PasteAnyway,
+    ConfirmMultilinePaste,
+    LargePasteThreshold,
+    AltSendsEscape,
+    CursorShape,
+    CursorShapeBlock,
+    CursorShapeUnderline,
+    CursorShapeBar,
+    CursorBlinkInterval,
+    CursorSteady,
+    FollowGlobalSetting,
+    // OSC 52 剪贴板
+    Osc52Policy,
+    ClipboardPolicyAllow,
+    ClipboardPolicyAsk,
+    ClipboardPolicyDeny,
+    Osc52MaxBytes,
+    Osc52AllowRead,
+    ClipboardWriteConfirmTitle,
+    ClipboardWriteConfirmMessage,
+    ClipboardAllowWrite,
+    // 响铃
+    BellVisual,
+    BellSound,
+    BellAttention,
+    // 标题
+    TitleTemplate,
+    TitleTemplateHint,
+    // 回滚历史
+    ScrollbackLines,
+    ScrollbackLinesHint,
+    ScrollbackMemoryLimit,
+    ScrollbackCompress,
+    // 仿真引擎
+    EmulatorEngine,
+    EmulatorEngineHint,
+    EmulatorEngineBuiltin,
+    EmulatorEngineWezTerm,
+    // shell 集成
+    PreviousPrompt,
+    NextPrompt,
+    SelectLastCommandOutput,
+    CopyLastCommandOutput,
+    // 连接阶段与错误
+    ConnectStageResolving,
+    ConnectStageTcp,
+    ConnectStageHandshake,
+    ConnectStageHostKey,
+    ConnectStageAuthPassword,
+    ConnectStageAuthPublicKey,
+    ConnectStageShell,
+    CancelConnect,
+    TestingConnection,
+    ConnectErrorMissingTarget,
+    ConnectErrorResolve,
+    ConnectErrorTcp,
+    ConnectErrorHandshake,
+    ConnectErrorHostKeyMismatch,
+    ConnectErrorUnknownHostKey,
+    ConnectErrorNoAuthMethod,
+    ConnectErrorAuth,
+    ConnectErrorShell,
+    ConnectErrorTimeout,
+    ConnectErrorCancelled,
+    // 主机密钥确认
+    HostKeyConfirmTitle,
+    HostKeyConfirmMessage,
+    HostKeyType,
+    HostKeyFingerprint,
+    TrustHostKey,
+    // 日志窗口
+    MenuLogViewer,
+    LogViewerTitle,
+    LogLevel,
+    ClearLogs,
+    OpenLogFolder,
 }
 
 /// 国际化管理器
@@ -370,6 +451,113 @@ impl I18nManager {
         zh_translations.insert(I18nKey::TestConnectionFailed, "连接测试失败");
         zh_translations.insert(I18nKey::TestConnectionLatency, "延迟");
 
+        // 粘贴确认
+        zh_translations.insert(I18nKey::PasteConfirmTitle, "确认粘贴");
+        zh_translations.insert(
+            I18nKey::PasteConfirmMessage,
+            "剪贴板内容包含多行或体积较大，粘贴后可能会立即执行。确定要粘贴吗？",
+        );
+        zh_translations.insert(I18nKey::PasteLineCount, "行数:");
+        zh_translations.insert(I18nKey::PasteByteCount, "字节数:");
+        zh_translations.insert(I18nKey::PasteAnyway, "粘贴");
+        zh_translations.insert(I18nKey::ConfirmMultilinePaste, "粘贴多行文本前确认");
+        zh_translations.insert(I18nKey::AltSendsEscape, "Alt 键作为 Meta 键");
+        zh_translations.insert(I18nKey::CursorShape, "光标形状");
+        zh_translations.insert(I18nKey::CursorShapeBlock, "方块");
+        zh_translations.insert(I18nKey::CursorShapeUnderline, "下划线");
+        zh_translations.insert(I18nKey::CursorShapeBar, "竖线");
+        zh_translations.insert(I18nKey::CursorBlinkInterval, "闪烁间隔 (毫秒)");
+        zh_translations.insert(I18nKey::CursorSteady, "不闪烁");
+        zh_translations.insert(I18nKey::FollowGlobalSetting, "跟随全局设置");
+        zh_translations.insert(I18nKey::LargePasteThreshold, "大文本粘贴确认阈值 (字节)");
+
+        // OSC 52 剪贴板
+        zh_translations.insert(I18nKey::Osc52Policy, "远端写入剪贴板 (OSC 52)");
+        zh_translations.insert(I18nKey::ClipboardPolicyAllow, "允许");
+        zh_translations.insert(I18nKey::ClipboardPolicyAsk, "询问");
+        zh_translations.insert(I18nKey::ClipboardPolicyDeny, "拒绝");
+        zh_translations.insert(I18nKey::Osc52MaxBytes, "剪贴板写入上限 (字节)");
+        zh_translations.insert(I18nKey::Osc52AllowRead, "允许远端读取剪贴板");
+        zh_translations.insert(I18nKey::BellVisual, "响铃时闪烁终端");
+        zh_translations.insert(I18nKey::BellSound, "响铃时播放系统提示音");
+        zh_translations.insert(I18nKey::BellAttention, "该会话在后台响铃");
+        zh_translations.insert(I18nKey::TitleTemplate, "标题模板");
+        zh_translations.insert(
+            I18nKey::TitleTemplateHint,
+            "{title} 为远端程序设置的标题，{name} 为会话名",
+        );
+        zh_translations.insert(I18nKey::ScrollbackLines, "回滚行数");
+        zh_translations.insert(
+            I18nKey::ScrollbackLinesHint,
+            "0 表示不限制行数，仍受内存上限约束",
+        );
+        zh_translations.insert(I18nKey::ScrollbackMemoryLimit, "回滚内存上限 (MB)");
+        zh_translations.insert(I18nKey::ScrollbackCompress, "压缩较早的回滚内容");
+        zh_translations.insert(I18nKey::EmulatorEngine, "仿真引擎");
+        zh_translations.insert(
+            I18nKey::EmulatorEngineHint,
+            "新建会话时生效，WezTerm 引擎兼容完整的 xterm 序列",
+        );
+        zh_translations.insert(I18nKey::EmulatorEngineBuiltin, "内置");
+        zh_translations.insert(I18nKey::EmulatorEngineWezTerm, "WezTerm");
+        zh_translations.insert(I18nKey::ClipboardWriteConfirmTitle, "写入剪贴板");
+        zh_translations.insert(
+            I18nKey::ClipboardWriteConfirmMessage,
+            "远端程序请求将以下内容写入系统剪贴板，是否允许？",
+        );
+        zh_translations.insert(I18nKey::ClipboardAllowWrite, "允许");
+
+        // shell 集成
+        zh_translations.insert(I18nKey::PreviousPrompt, "上一个提示符");
+        zh_translations.insert(I18nKey::NextPrompt, "下一个提示符");
+        zh_translations.insert(I18nKey::SelectLastCommandOutput, "选中上一条命令的输出");
+        zh_translations.insert(I18nKey::CopyLastCommandOutput, "复制上一条命令的输出");
+
+        // 连接阶段与错误
+        zh_translations.insert(I18nKey::ConnectStageResolving, "解析主机地址");
+        zh_translations.insert(I18nKey::ConnectStageTcp, "建立 TCP 连接");
+        zh_translations.insert(I18nKey::ConnectStageHandshake, "SSH 握手");
+        zh_translations.insert(I18nKey::ConnectStageHostKey, "校验主机密钥");
+        zh_translations.insert(I18nKey::ConnectStageAuthPassword, "密码认证");
+        zh_translations.insert(I18nKey::ConnectStageAuthPublicKey, "密钥认证");
+        zh_translations.insert(I18nKey::ConnectStageShell, "打开 shell");
+        zh_translations.insert(I18nKey::CancelConnect, "取消连接");
+        zh_translations.insert(I18nKey::TestingConnection, "正在测试连接...");
+        zh_translations.insert(I18nKey::ConnectErrorMissingTarget, "请填写主机地址和用户名");
+        zh_translations.insert(I18nKey::ConnectErrorResolve, "无法解析主机地址");
+        zh_translations.insert(I18nKey::ConnectErrorTcp, "无法建立 TCP 连接");
+        zh_translations.insert(I18nKey::ConnectErrorHandshake, "SSH 握手失败");
+        zh_translations.insert(
+            I18nKey::ConnectErrorHostKeyMismatch,
+            "主机密钥与 known_hosts 中的记录不一致，连接可能被劫持",
+        );
+        zh_translations.insert(
+            I18nKey::ConnectErrorUnknownHostKey,
+            "主机密钥未在 known_hosts 中记录",
+        );
+        zh_translations.insert(I18nKey::ConnectErrorNoAuthMethod, "未提供密码或私钥");
+        zh_translations.insert(I18nKey::ConnectErrorAuth, "认证失败");
+        zh_translations.insert(I18nKey::ConnectErrorShell, "无法打开 shell");
+        zh_translations.insert(I18nKey::ConnectErrorTimeout, "连接超时");
+        zh_translations.insert(I18nKey::ConnectErrorCancelled, "连接已取消");
+
+        // 主机密钥确认
+        zh_translations.insert(I18nKey::HostKeyConfirmTitle, "未知主机");
+        zh_translations.insert(
+            I18nKey::HostKeyConfirmMessage,
+            "首次连接该主机，known_hosts 中没有它的密钥。请核对指纹，确认无误后再信任并连接。",
+        );
+        zh_translations.insert(I18nKey::HostKeyType, "密钥类型");
+        zh_translations.insert(I18nKey::HostKeyFingerprint, "指纹");
+        zh_translations.insert(I18nKey::TrustHostKey, "信任并连接");
+
+        // 日志窗口
+        zh_translations.insert(I18nKey::MenuLogViewer, "日志");
+        zh_translations.insert(I18nKey::LogViewerTitle, "日志");
+        zh_translations.insert(I18nKey::LogLevel, "级别");
+        zh_translations.insert(I18nKey::ClearLogs, "清空");
+        zh_translations.insert(I18nKey::OpenLogFolder, "打开日志目录");
+
         zh_translations.insert(I18nKey::Ok, "确定");
         zh_translations.insert(I18nKey::Close, "关闭");
         zh_translations.insert(I18nKey::Yes, "是");
@@ -528,6 +716,134 @@ impl I18nManager {
         en_translations.insert(I18nKey::TestConnectionFailed, "Connection test failed");
         en_translations.insert(I18nKey::TestConnectionLatency, "Latency");
 
+        // Paste confirmation
+        en_translations.insert(I18nKey::PasteConfirmTitle, "Confirm Paste");
+        en_translations.insert(
+            I18nKey::PasteConfirmMessage,
+            "The clipboard contains multiple lines or a large amount of text that may run immediately. Paste anyway?",
+        );
+        en_translations.insert(I18nKey::PasteLineCount, "Lines:");
+        en_translations.insert(I18nKey::PasteByteCount, "Bytes:");
+        en_translations.insert(I18nKey::PasteAnyway, "Paste");
+        en_translations.insert(
+            I18nKey::ConfirmMultilinePaste,
+            "Confirm before pasting multiple lines",
+        );
+        en_translations.insert(I18nKey::AltSendsEscape, "Use Alt as Meta key");
+        en_translations.insert(I18nKey::CursorShape, "Cursor Shape");
+        en_translations.insert(I18nKey::CursorShapeBlock, "Block");
+        en_translations.insert(I18nKey::CursorShapeUnderline, "Underline");
+        en_translations.insert(I18nKey::CursorShapeBar, "Bar");
+        en_translations.insert(I18nKey::CursorBlinkInterval, "Blink Interval (ms)");
+        en_translations.insert(I18nKey::CursorSteady, "Steady");
+        en_translations.insert(I18nKey::FollowGlobalSetting, "Use global setting");
+        en_translations.insert(
+            I18nKey::LargePasteThreshold,
+            "Large paste threshold (bytes)",
+        );
+
+        // OSC 52 clipboard
+        en_translations.insert(I18nKey::Osc52Policy, "Remote clipboard writes (OSC 52)");
+        en_translations.insert(I18nKey::ClipboardPolicyAllow, "Allow");
+        en_translations.insert(I18nKey::ClipboardPolicyAsk, "Ask");
+        en_translations.insert(I18nKey::ClipboardPolicyDeny, "Deny");
+        en_translations.insert(I18nKey::Osc52MaxBytes, "Clipboard write limit (bytes)");
+        en_translations.insert(I18nKey::Osc52AllowRead, "Allow remote clipboard reads");
+        en_translations.insert(I18nKey::BellVisual, "Flash terminal on bell");
+        en_translations.insert(I18nKey::BellSound, "Play system sound on bell");
+        en_translations.insert(I18nKey::BellAttention, "Bell rang in this session");
+        en_translations.insert(I18nKey::TitleTemplate, "Title template");
+        en_translations.insert(
+            I18nKey::TitleTemplateHint,
+            "{title} is the title set by the remote program, {name} is the session name",
+        );
+        en_translations.insert(I18nKey::ScrollbackLines, "Scrollback lines");
+        en_translations.insert(
+            I18nKey::ScrollbackLinesHint,
+            "0 means unlimited, still bounded by the memory limit",
+        );
+        en_translations.insert(
+            I18nKey::ScrollbackMemoryLimit,
+            "Scrollback memory limit (MB)",
+        );
+        en_translations.insert(I18nKey::EmulatorEngine, "Emulator engine");
+        en_translations.insert(
+            I18nKey::EmulatorEngineHint,
+            "Applies to new sessions; the WezTerm engine supports the full xterm sequence set",
+        );
+        en_translations.insert(I18nKey::EmulatorEngineBuiltin, "Built-in");
+        en_translations.insert(I18nKey::EmulatorEngineWezTerm, "WezTerm");
+        en_translations.insert(I18nKey::ScrollbackCompress, "Compress older scrollback");
+        en_translations.insert(I18nKey::ClipboardWriteConfirmTitle, "Clipboard Write");
+        en_translations.insert(
+            I18nKey::ClipboardWriteConfirmMessage,
+            "A remote program wants to copy the following text to the system clipboard. Allow it?",
+        );
+        en_translations.insert(I18nKey::ClipboardAllowWrite, "Allow");
+
+        // Shell integration
+        en_translations.insert(I18nKey::PreviousPrompt, "Previous Prompt");
+        en_translations.insert(I18nKey::NextPrompt, "Next Prompt");
+        en_translations.insert(
+            I18nKey::SelectLastCommandOutput,
+            "Select Last Command Output",
+        );
+        en_translations.insert(I18nKey::CopyLastCommandOutput, "Copy Last Command Output");
+
+        // Connection stages and errors
+        en_translations.insert(I18nKey::ConnectStageResolving, "Resolving host");
+        en_translations.insert(I18nKey::ConnectStageTcp, "Opening TCP connection");
+        en_translations.insert(I18nKey::ConnectStageHandshake, "SSH handshake");
+        en_translations.insert(I18nKey::ConnectStageHostKey, "Checking host key");
+        en_translations.insert(I18nKey::ConnectStageAuthPassword, "Password authentication");
+        en_translations.insert(
+            I18nKey::ConnectStageAuthPublicKey,
+            "Public key authentication",
+        );
+        en_translations.insert(I18nKey::ConnectStageShell, "Opening shell");
+        en_translations.insert(I18nKey::CancelConnect, "Cancel Connection");
+        en_translations.insert(I18nKey::TestingConnection, "Testing connection...");
+        en_translations.insert(
+            I18nKey::ConnectErrorMissingTarget,
+            "Host and username are required",
+        );
+        en_translations.insert(I18nKey::ConnectErrorResolve, "Could not resolve host");
+        en_translations.insert(I18nKey::ConnectErrorTcp, "Could not open TCP connection");
+        en_translations.insert(I18nKey::ConnectErrorHandshake, "SSH handshake failed");
+        en_translations.insert(
+            I18nKey::ConnectErrorHostKeyMismatch,
+            "Host key does not match known_hosts; the connection may be intercepted",
+        );
+        en_translations.insert(
+            I18nKey::ConnectErrorUnknownHostKey,
+            "Host key is not in known_hosts",
+        );
+        en_translations.insert(
+            I18nKey::ConnectErrorNoAuthMethod,
+            "No password or private key provided",
+        );
+        en_translations.insert(I18nKey::ConnectErrorAuth, "Authentication failed");
+        en_translations.insert(I18nKey::ConnectErrorShell, "Could not open shell");
+        en_translations.insert(I18nKey::ConnectErrorTimeout, "Connection timed out");
+        en_translations.insert(I18nKey::ConnectErrorCancelled, "Connection cancelled");
+
+        // Host key confirmation
+        en_translations.insert(I18nKey::HostKeyConfirmTitle, "Unknown host");
+        en_translations.insert(
+            I18nKey::HostKeyConfirmMessage,
+            "This host is not in known_hosts. Verify the fingerprint before trusting it and connecting.",
+        );
+        en_translations.insert(I18nKey::HostKeyType, "Key type");
+        en_translations.insert(I18nKey::HostKeyFingerprint, "Fingerprint");
+        en_translations.insert(I18nKey::TrustHostKey, "Trust and connect");
+
+        // Log viewer
+        en_translations.insert(I18nKey::MenuLogViewer, "Logs");
+        en_translations.insert(I18nKey::LogViewerTitle, "Logs");
+        en_translations.insert(I18nKey::LogLevel, "Level");
+        en_translations.insert(I18nKey::ClearLogs, "Clear");
+        en_translations.insert(I18nKey::OpenLogFolder, "Open Log Folder");
+
         en_translations.insert(I18nKey::Ok, "OK");
         en_translations.insert(I18nKey::Close, "Close");
         en_translations.insert(I18nKey::Yes, "Yes");
@@ -0,0 +1,11 @@
+//! TermLink 核心库
+//! SSH 连接、SFTP、终端仿真与配置管理，不依赖图形界面即可在其他工具和测试中复用；
+//! 图形界面（src/app）只是建立在这些模块之上的前端
+
+pub mod config;
+pub mod i18n;
+pub mod logging;
+pub mod sftp;
+pub mod ssh;
+pub mod terminal;
+pub mod utils;
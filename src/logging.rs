@@ -0,0 +1,549 @@
+//! 日志
+//! 各模块统一通过 tracing 记录日志：写入项目数据目录下按大小轮转的文件，并在内存中保留最近的记录，
+//! 供界面中的日志窗口按级别查看。
+//!
+//! 日志中不得出现密码、私钥口令或用户在终端中输入的内容。
+
+use chrono::{DateTime, Local};
+use directories::ProjectDirs;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::FormatTime;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, fmt};
+
+/// 日志文件名，轮转后的旧文件依次为 `termlink.log.1`、`termlink.log.2` ……
+pub const LOG_FILE_NAME: &str = "termlink.log";
+
+/// 单个日志文件的大小上限
+const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// 保留的旧日志文件数
+const MAX_ROTATED_FILES: usize = 4;
+
+/// 内存中保留的日志条数
+const BUFFER_CAPACITY: usize = 5000;
+
+/// 未设置 RUST_LOG 时的过滤规则：本程序记录调试信息，依赖库只记录警告
+const DEFAULT_FILTER: &str = "warn,termlink=debug,TermLink=debug";
+
+/// 日志文件所在目录
+pub fn log_dir() -> Option<PathBuf> {
+    let proj_dirs = ProjectDirs::from("com", "TermLink", "TermLink")?;
+    Some(proj_dirs.data_local_dir().join("logs"))
+}
+
+/// 初始化全局日志
+///
+/// 同时输出到日志文件、内存缓冲区和标准错误；`log` 库的记录（来自依赖库）也会转交给 tracing。
+/// 日志文件无法创建时只输出到后两者。
+pub fn init() {
+    let filter = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|spec| spec.parse::<Targets>().ok())
+        .unwrap_or_else(|| DEFAULT_FILTER.parse().unwrap());
+
+    let file = log_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "无法获取项目目录"))
+        .and_then(|dir| {
+            fs::create_dir_all(&dir)?;
+            RotatingFile::open(dir.join(LOG_FILE_NAME), MAX_FILE_SIZE, MAX_ROTATED_FILES)
+        });
+    let (file_layer, file_error) = match file {
+        Ok(file) => (
+            Some(
+                fmt::layer()
+                    .with_ansi(false)
+                    .with_timer(LocalTime)
+                    .with_writer(Mutex::new(file)),
+            ),
+            None,
+        ),
+        Err(e) => (None, Some(e)),
+    };
+
+    let result = tracing_subscriber::registry()
+        .with(filter)
+        .with(BufferLayer::new(buffer()))
+        .with(file_layer)
+        .with(fmt::layer().with_timer(LocalTime).with_writer(io::stderr))
+        .try_init();
+
+    if let Err(e) = result {
+        eprintln!("初始化日志失败: {}", e);
+    } else if let Some(e) = file_error {
+        tracing::warn!("无法创建日志文件，日志只保留在内存中: {}", e);
+    }
+}
+
+/// 全局日志缓冲区
+pub fn buffer() -> LogBuffer {
+    static BUFFER: OnceLock<LogBuffer> = OnceLock::new();
+    BUFFER.get_or_init(LogBuffer::default).clone()
+}
+
+/// 按本地时间输出时间戳
+struct LocalTime;
+
+impl FormatTime for LocalTime {
+    fn format_time(&self, w: &mut Writer<'_>) -> std::fmt::Result {
+        write!(w, "{}", Local::now().format("%Y-%m-%d %H:%M:%S%.3f"))
+    }
+}
+
+/// 一条日志
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    pub target: String,
+    /// 所在 span 的上下文、消息与附带字段
+    pub message: String,
+}
+
+/// 最近日志的环形缓冲区，超出容量时丢弃最早的记录
+#[derive(Clone)]
+pub struct LogBuffer {
+    records: Arc<Mutex<Records>>,
+    capacity: usize,
+}
+
+/// 缓冲区中的记录，每条记录按写入顺序编号
+#[derive(Default)]
+struct Records {
+    entries: VecDeque<LogRecord>,
+    /// 下一条记录的序号，清空缓冲区时不归零
+    next_seq: u64,
+}
+
+impl Records {
+    /// 最早一条记录的序号
+    fn first_seq(&self) -> u64 {
+        self.next_seq - self.entries.len() as u64
+    }
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(BUFFER_CAPACITY)
+    }
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(Records::default())),
+            capacity,
+        }
+    }
+
+    fn push(&self, record: LogRecord) {
+        let mut records = self.records.lock().unwrap();
+        if records.entries.len() >= self.capacity {
+            records.entries.pop_front();
+        }
+        records.entries.push_back(record);
+        records.next_seq += 1;
+    }
+
+    /// 级别不低于 `min_level` 的记录，按时间先后排列
+    pub fn records(&self, min_level: Level) -> Vec<LogRecord> {
+        self.records
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .filter(|record| record.level <= min_level)
+            .cloned()
+            .collect()
+    }
+
+    /// 清空缓冲区（不影响日志文件）
+    pub fn clear(&self) {
+        self.records.lock().unwrap().entries.clear();
+    }
+}
+
+/// 按级别过滤的日志列表，刷新时只复制上次之后新增的记录
+///
+/// 日志窗口每帧刷新一次，缓冲区没有变化时不复制任何记录。
+pub struct FilteredLogs {
+    buffer: LogBuffer,
+    level: Level,
+    /// 符合级别的记录及其序号
+    records: VecDeque<(u64, LogRecord)>,
+    /// 已读取到的位置
+    next_seq: u64,
+}
+
+impl FilteredLogs {
+    pub fn new(buffer: LogBuffer, level: Level) -> Self {
+        Self {
+            buffer,
+            level,
+            records: VecDeque::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// 显示的最低级别
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// 修改最低级别，下次刷新时重新过滤整个缓冲区
+    pub fn set_level(&mut self, level: Level) {
+        if level != self.level {
+            self.level = level;
+            self.records.clear();
+            self.next_seq = 0;
+        }
+    }
+
+    /// 读取新增的记录，丢弃已被缓冲区淘汰或清空的记录
+    pub fn refresh(&mut self) {
+        let records = self.buffer.records.lock().unwrap();
+        let first_seq = records.first_seq();
+        while self
+            .records
+            .front()
+            .is_some_and(|(seq, _)| *seq < first_seq)
+        {
+            self.records.pop_front();
+        }
+
+        let start = self.next_seq.max(first_seq);
+        for (seq, record) in (start..).zip(records.entries.range((start - first_seq) as usize..)) {
+            if record.level <= self.level {
+                self.records.push_back((seq, record.clone()));
+            }
+        }
+        self.next_seq = records.next_seq;
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// 过滤后第 `range` 条记录
+    pub fn range(&self, range: std::ops::Range<usize>) -> impl Iterator<Item = &LogRecord> {
+        self.records.range(range).map(|(_, record)| record)
+    }
+
+    /// 清空缓冲区与列表
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.records.clear();
+    }
+}
+
+/// 把事件格式化后存入 [`LogBuffer`] 的 tracing 层
+pub struct BufferLayer {
+    buffer: LogBuffer,
+}
+
+impl BufferLayer {
+    pub fn new(buffer: LogBuffer) -> Self {
+        Self { buffer }
+    }
+}
+
+/// span 创建与更新时记录的字段文本
+struct SpanFields(String);
+
+impl<S> Layer<S> for BufferLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = FieldVisitor::default();
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(SpanFields(fields.fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+            let mut visitor = FieldVisitor {
+                fields: std::mem::take(fields),
+                ..Default::default()
+            };
+            values.record(&mut visitor);
+            *fields = visitor.fields;
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut message = String::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                message.push_str(span.name());
+                if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>()
+                    && !fields.is_empty()
+                {
+                    let _ = write!(message, "{{{}}}", fields);
+                }
+                message.push_str(": ");
+            }
+        }
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        message.push_str(&visitor.message);
+        if !visitor.fields.is_empty() {
+            if !visitor.message.is_empty() {
+                message.push(' ');
+            }
+            message.push_str(&visitor.fields);
+        }
+
+        let metadata = event.metadata();
+        self.buffer.push(LogRecord {
+            time: Local::now(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message,
+        });
+    }
+}
+
+/// 收集消息与其他字段，字段按 `name=value` 以空格分隔
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &format_args!("{}", value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+            return;
+        }
+        // tracing-log 转交的 log 记录带有来源位置字段，对查看日志没有帮助
+        if field.name().starts_with("log.") {
+            return;
+        }
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={:?}", field.name(), value);
+    }
+}
+
+/// 按大小轮转的日志文件
+///
+/// 写入会使文件超过上限时，把 `<path>` 改名为 `<path>.1`，原有的 `<path>.N` 依次后移，
+/// 超出保留数量的最早文件被删除。
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_rotated: usize,
+}
+
+impl RotatingFile {
+    /// 以追加方式打开日志文件
+    pub fn open(path: PathBuf, max_size: u64, max_rotated: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            max_rotated,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{index}"));
+        name.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_rotated == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            remove_if_exists(&self.rotated_path(self.max_rotated))?;
+            for index in (1..self.max_rotated).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("termlink-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_rotating_file_keeps_limited_history() {
+        let dir = temp_dir("rotate");
+        let path = dir.join(LOG_FILE_NAME);
+        let mut file = RotatingFile::open(path.clone(), 10, 2).unwrap();
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n", "dddddddd\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |path: &Path| fs::read_to_string(path).unwrap();
+        assert_eq!(read(&path), "dddddddd\n");
+        assert_eq!(read(&dir.join("termlink.log.1")), "cccccccc\n");
+        assert_eq!(read(&dir.join("termlink.log.2")), "bbbbbbbb\n");
+        assert!(!dir.join("termlink.log.3").exists());
+
+        // 重新打开时接着已有大小计算
+        let mut file = RotatingFile::open(path.clone(), 10, 2).unwrap();
+        file.write_all(b"e").unwrap();
+        assert_eq!(read(&path), "dddddddd\ne");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_default_filter_parses() {
+        let filter: Targets = DEFAULT_FILTER.parse().unwrap();
+        assert!(filter.would_enable("termlink::ssh::connect", &Level::DEBUG));
+        assert!(!filter.would_enable("wgpu_core", &Level::INFO));
+    }
+
+    #[test]
+    fn test_buffer_layer_records_span_context_and_filters_levels() {
+        let buffer = LogBuffer::new(3);
+        let subscriber = tracing_subscriber::registry().with(BufferLayer::new(buffer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("session", name = "prod");
+            let _guard = span.enter();
+            tracing::debug!("忽略");
+            tracing::info!(bytes = 12, "已连接");
+            tracing::warn!("通道已关闭");
+            tracing::error!("失败");
+        });
+
+        // 容量为 3，最早的一条被丢弃
+        let all = buffer.records(Level::TRACE);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].message, "session{name=prod}: 已连接 bytes=12");
+
+        let warnings: Vec<_> = buffer
+            .records(Level::WARN)
+            .into_iter()
+            .map(|record| record.level)
+            .collect();
+        assert_eq!(warnings, [Level::WARN, Level::ERROR]);
+
+        buffer.clear();
+        assert!(buffer.records(Level::TRACE).is_empty());
+    }
+
+    #[test]
+    fn test_filtered_logs_copy_only_new_records() {
+        let buffer = LogBuffer::new(3);
+        let mut logs = FilteredLogs::new(buffer.clone(), Level::INFO);
+        let subscriber = tracing_subscriber::registry().with(BufferLayer::new(buffer.clone()));
+        let messages = |logs: &FilteredLogs| -> Vec<String> {
+            logs.range(0..logs.len())
+                .map(|record| record.message.clone())
+                .collect()
+        };
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("a");
+            tracing::debug!("b");
+            logs.refresh();
+            assert_eq!(messages(&logs), ["a"]);
+
+            // 没有新记录时列表不变
+            logs.refresh();
+            assert_eq!(messages(&logs), ["a"]);
+
+            // 容量为 3，"a" 被缓冲区淘汰后也从列表中移除
+            tracing::warn!("c");
+            tracing::info!("d");
+            logs.refresh();
+            assert_eq!(messages(&logs), ["c", "d"]);
+
+            logs.set_level(Level::DEBUG);
+            logs.refresh();
+            assert_eq!(messages(&logs), ["b", "c", "d"]);
+
+            buffer.clear();
+            logs.refresh();
+            assert!(logs.is_empty());
+            tracing::error!("e");
+            logs.refresh();
+            assert_eq!(messages(&logs), ["e"]);
+        });
+    }
+}
@@ -1,13 +1,7 @@
 use eframe::egui;
 
-// 引入项目模块
+// 图形界面前端，其余功能来自 termlink 库
 mod app;
-mod config;
-mod i18n;
-mod sftp;
-mod ssh;
-mod terminal;
-mod utils;
 
 use app::App;
 
@@ -75,7 +69,7 @@ fn try_system_chinese_fonts(fonts: &mut egui::FontDefinitions) -> bool {
 }
 
 fn main() -> Result<(), eframe::Error> {
-    env_logger::init(); // 初始化日志
+    termlink::logging::init(); // 初始化日志（文件、日志窗口与标准错误）
 
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
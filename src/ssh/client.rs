@@ -1,14 +1,14 @@
 //! SSH客户端实现
 //! 处理SSH连接的建立和管理
 
+use crate::ssh::connect::{self, CancelToken, ConnectError};
 use anyhow::Result;
 use ssh2::Session;
+use std::fmt;
 use std::io::Read;
-use std::net::TcpStream;
-use std::path::Path;
 
 /// SSH客户端配置
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct SshConfig {
     pub host: String,
     pub port: u16,
@@ -18,6 +18,20 @@ pub struct SshConfig {
     pub timeout: Option<u64>,
 }
 
+// 调试输出中隐藏密码，避免写入日志
+impl fmt::Debug for SshConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SshConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("private_key_path", &self.private_key_path)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
 impl Default for SshConfig {
     fn default() -> Self {
         Self {
@@ -40,43 +54,18 @@ pub struct SshClient {
 
 impl SshClient {
     /// 创建新的SSH客户端
-    pub fn new(config: SshConfig) -> Result<Self> {
-        // 设置TCP连接超时
-        let timeout = std::time::Duration::from_secs(config.timeout.unwrap_or(30));
-
-        let tcp = TcpStream::connect_timeout(
-            &std::net::SocketAddr::new(
-                config
-                    .host
-                    .parse()
-                    .map_err(|_| anyhow::anyhow!("Invalid host address"))?,
-                config.port,
-            ),
-            timeout,
-        )?;
-
-        let mut session = Session::new()?;
-        session.set_tcp_stream(tcp);
-
-        // 设置会话超时
-        session.set_timeout(timeout.as_millis() as u32);
-
-        session.handshake()?;
-
-        // 认证
-        if let Some(ref key_path) = config.private_key_path {
-            session.userauth_pubkey_file(&config.username, None, Path::new(key_path), None)?;
-        } else if let Some(ref password) = config.password {
-            session.userauth_password(&config.username, password)?;
-        } else {
-            return Err(anyhow::anyhow!("No authentication method provided"));
-        }
-
-        if !session.authenticated() {
-            return Err(anyhow::anyhow!("Authentication failed"));
-        }
+    ///
+    /// 在当前线程阻塞完成连接与认证，不打开 shell；需要取消或显示进度时使用 [`ConnectTask`]。
+    ///
+    /// [`ConnectTask`]: crate::ssh::ConnectTask
+    pub fn new(config: SshConfig) -> Result<Self, ConnectError> {
+        let connection = connect::establish(&config, false, &CancelToken::default(), &mut |_| {})?;
+        Ok(connection.client)
+    }
 
-        Ok(Self { session, config })
+    /// 用已完成认证的会话创建客户端
+    pub(crate) fn from_session(session: Session, config: SshConfig) -> Self {
+        Self { session, config }
     }
 
     /// 检查连接是否已认证
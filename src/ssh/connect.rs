@@ -0,0 +1,658 @@
+//! 连接流程
+//! 在后台线程中依次解析地址、建立 TCP 连接、握手、校验主机密钥、认证并打开 shell，
+//! 每进入一个阶段发出一个事件。任务可以随时取消：关闭底层套接字让阻塞中的握手或认证立即返回，
+//! 超过连接期限时按超时取消
+
+use crate::ssh::client::{SshClient, SshConfig};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use ssh2::{Channel, CheckResult, ErrorCode, HashType, KnownHostFileKind, Session};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 未配置超时时的连接期限（秒）
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// libssh2 的 LIBSSH2_ERROR_TIMEOUT
+const LIBSSH2_ERROR_TIMEOUT: i32 = -9;
+
+/// 认证方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    Password,
+    PublicKey,
+}
+
+impl fmt::Display for AuthMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuthMethod::Password => "password",
+            AuthMethod::PublicKey => "publickey",
+        })
+    }
+}
+
+/// 连接阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectStage {
+    /// 解析主机地址
+    Resolving,
+    /// 建立 TCP 连接
+    TcpConnect,
+    /// SSH 握手
+    Handshake,
+    /// 按 known_hosts 校验主机密钥
+    HostKeyCheck,
+    /// 使用指定方式认证
+    Authenticating(AuthMethod),
+    /// 申请终端并打开 shell
+    OpeningShell,
+}
+
+/// 服务端出示的主机密钥
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostKeyInfo {
+    pub host: String,
+    pub port: u16,
+    /// 密钥类型，如 `ssh-ed25519`
+    pub key_type: String,
+    /// base64 编码的公钥，与 known_hosts 中的写法相同
+    pub key: String,
+    /// OpenSSH 格式的指纹，如 `SHA256:...`
+    pub fingerprint: String,
+}
+
+impl HostKeyInfo {
+    fn from_session(session: &Session, host: &str, port: u16) -> Option<Self> {
+        let (key, _) = session.host_key()?;
+        let hash = session.host_key_hash(HashType::Sha256)?;
+        Some(Self {
+            host: host.to_string(),
+            port,
+            key_type: key_type_name(key).unwrap_or("unknown").to_string(),
+            key: STANDARD.encode(key),
+            fingerprint: format!("SHA256:{}", STANDARD_NO_PAD.encode(hash)),
+        })
+    }
+
+    /// known_hosts 中的一行，非 22 端口按 OpenSSH 的写法记为 `[host]:port`
+    fn known_hosts_line(&self) -> String {
+        let host = if self.port == 22 {
+            self.host.clone()
+        } else {
+            format!("[{}]:{}", self.host, self.port)
+        };
+        format!("{} {} {}", host, self.key_type, self.key)
+    }
+
+    /// 信任该主机密钥：追加到 ~/.ssh/known_hosts，之后的连接按已知主机校验
+    pub fn trust(&self) -> io::Result<()> {
+        let path = known_hosts_file()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "home directory not found"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        // 原文件末尾没有换行时先补上，避免和上一条记录连在一起
+        let needs_newline = fs::read(&path)
+            .map(|content| !content.is_empty() && !content.ends_with(b"\n"))
+            .unwrap_or(false);
+        if needs_newline {
+            writeln!(file)?;
+        }
+        writeln!(file, "{}", self.known_hosts_line())
+    }
+}
+
+/// 从密钥的线格式（4 字节长度 + 类型名）中取出类型名
+fn key_type_name(key: &[u8]) -> Option<&str> {
+    let len = u32::from_be_bytes(key.get(..4)?.try_into().ok()?) as usize;
+    std::str::from_utf8(key.get(4..4 + len)?).ok()
+}
+
+/// 连接失败的原因
+///
+/// 底层错误只保留文本，便于在会话状态中克隆与比较；界面按变体显示本地化的说明。
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConnectError {
+    #[error("host and username are required")]
+    MissingTarget,
+    #[error("failed to resolve {host}: {detail}")]
+    Resolve { host: String, detail: String },
+    #[error("TCP connection failed: {0}")]
+    Tcp(String),
+    #[error("SSH handshake failed: {0}")]
+    Handshake(String),
+    #[error("host key for {host} does not match known_hosts")]
+    HostKeyMismatch { host: String },
+    #[error("host key for {} is not in known_hosts ({})", .0.host, .0.fingerprint)]
+    UnknownHostKey(HostKeyInfo),
+    #[error("no authentication method provided")]
+    NoAuthMethod,
+    #[error("{method} authentication failed: {detail}")]
+    Auth { method: AuthMethod, detail: String },
+    #[error("failed to open shell: {0}")]
+    Shell(String),
+    #[error("timed out after {0} s")]
+    Timeout(u64),
+    #[error("cancelled")]
+    Cancelled,
+}
+
+/// 建立好的连接，请求打开 shell 时带有终端通道
+pub struct Connection {
+    pub client: SshClient,
+    pub channel: Option<Channel>,
+}
+
+/// 连接任务发出的事件
+pub enum ConnectEvent {
+    /// 进入新的阶段
+    Stage(ConnectStage),
+    /// 连接成功
+    Connected(Connection),
+    /// 连接失败或被取消
+    Failed(ConnectError),
+}
+
+/// 取消标记，同时持有已建立的套接字，取消时关闭它以打断阻塞中的 SSH 调用
+#[derive(Clone, Default)]
+pub(crate) struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    stream: Arc<Mutex<Option<TcpStream>>>,
+}
+
+impl CancelToken {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(stream) = self.stream.lock().unwrap().take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn check(&self) -> Result<(), ConnectError> {
+        if self.is_cancelled() {
+            Err(ConnectError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 记录已建立的套接字；登记前已取消时直接关闭
+    fn register(&self, stream: &TcpStream) -> Result<(), ConnectError> {
+        if let Ok(clone) = stream.try_clone() {
+            *self.stream.lock().unwrap() = Some(clone);
+        }
+        if self.is_cancelled() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        self.check()
+    }
+
+    /// 连接结束后不再需要打断，释放套接字副本
+    fn release(&self) {
+        self.stream.lock().unwrap().take();
+    }
+
+    /// 取消后底层调用报出的错误统一视为取消
+    fn map(&self, error: ConnectError) -> ConnectError {
+        if self.is_cancelled() {
+            ConnectError::Cancelled
+        } else {
+            error
+        }
+    }
+}
+
+/// 后台连接任务的句柄，丢弃时取消连接
+pub struct ConnectTask {
+    receiver: Receiver<ConnectEvent>,
+    cancel: CancelToken,
+    deadline: Instant,
+    timeout_secs: u64,
+    finished: bool,
+    span: tracing::Span,
+    config: SshConfig,
+}
+
+impl ConnectTask {
+    /// 在后台线程中开始连接，`open_shell` 为 true 时连接成功后打开终端 shell
+    pub fn spawn(name: &str, config: SshConfig, open_shell: bool) -> Self {
+        let timeout_secs = config.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS);
+        let (sender, receiver) = mpsc::channel();
+        let cancel = CancelToken::default();
+
+        let thread_cancel = cancel.clone();
+        let thread_sender = sender.clone();
+        // 每次连接尝试一个 span，只记录目标地址和用户名
+        let span = tracing::info_span!(
+            "connect",
+            session = %name,
+            host = %config.host,
+            port = config.port,
+            user = %config.username
+        );
+        let thread_span = span.clone();
+        let thread_config = config.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("connect-{}", name))
+            .spawn(move || {
+                let _guard = thread_span.entered();
+                let result = establish(&thread_config, open_shell, &thread_cancel, &mut |stage| {
+                    tracing::debug!(?stage, "进入连接阶段");
+                    let _ = thread_sender.send(ConnectEvent::Stage(stage));
+                });
+                let event = match result {
+                    Ok(connection) => {
+                        tracing::info!("连接成功");
+                        ConnectEvent::Connected(connection)
+                    }
+                    Err(ConnectError::Cancelled) => {
+                        tracing::info!("连接已取消");
+                        ConnectEvent::Failed(ConnectError::Cancelled)
+                    }
+                    Err(e) => {
+                        tracing::warn!("连接失败: {}", e);
+                        ConnectEvent::Failed(e)
+                    }
+                };
+                let _ = thread_sender.send(event);
+            });
+        if let Err(e) = spawned {
+            let _ = sender.send(ConnectEvent::Failed(ConnectError::Tcp(e.to_string())));
+        }
+
+        Self {
+            receiver,
+            cancel,
+            deadline: Instant::now() + Duration::from_secs(timeout_secs),
+            timeout_secs,
+            finished: false,
+            span,
+            config,
+        }
+    }
+
+    /// 任务使用的连接配置
+    pub fn config(&self) -> &SshConfig {
+        &self.config
+    }
+
+    /// 取出已发生的事件；超过期限仍未结束时取消任务并报告超时
+    pub fn poll(&mut self) -> Vec<ConnectEvent> {
+        let mut events = Vec::new();
+        while !self.finished {
+            match self.receiver.try_recv() {
+                Ok(event) => {
+                    self.finished = !matches!(event, ConnectEvent::Stage(_));
+                    events.push(event);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    events.push(ConnectEvent::Failed(ConnectError::Cancelled));
+                }
+            }
+        }
+
+        if !self.finished && Instant::now() >= self.deadline {
+            self.span.in_scope(|| {
+                tracing::warn!("连接超过 {} 秒未完成，已取消", self.timeout_secs);
+            });
+            self.cancel.cancel();
+            self.finished = true;
+            events.push(ConnectEvent::Failed(ConnectError::Timeout(
+                self.timeout_secs,
+            )));
+        }
+        events
+    }
+
+    /// 取消连接，此后不再产生事件
+    pub fn cancel(&mut self) {
+        self.cancel.cancel();
+        self.finished = true;
+    }
+
+    /// 任务是否已经结束（成功、失败或取消）
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl Drop for ConnectTask {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.cancel.cancel();
+        }
+    }
+}
+
+/// 在当前线程依次执行各连接阶段，每进入一个阶段调用 `on_stage`
+pub(crate) fn establish(
+    config: &SshConfig,
+    open_shell: bool,
+    cancel: &CancelToken,
+    on_stage: &mut dyn FnMut(ConnectStage),
+) -> Result<Connection, ConnectError> {
+    let result = run_stages(config, open_shell, cancel, on_stage).map_err(|e| cancel.map(e));
+    cancel.release();
+    let connection = result?;
+    cancel.check()?;
+    Ok(connection)
+}
+
+fn run_stages(
+    config: &SshConfig,
+    open_shell: bool,
+    cancel: &CancelToken,
+    on_stage: &mut dyn FnMut(ConnectStage),
+) -> Result<Connection, ConnectError> {
+    let host = config.host.trim();
+    if host.is_empty() || config.username.is_empty() {
+        return Err(ConnectError::MissingTarget);
+    }
+    let method = if config.private_key_path.is_some() {
+        AuthMethod::PublicKey
+    } else if config.password.is_some() {
+        AuthMethod::Password
+    } else {
+        return Err(ConnectError::NoAuthMethod);
+    };
+
+    let timeout_secs = config.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS);
+    let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+    let remaining = || {
+        deadline
+            .checked_duration_since(Instant::now())
+            .filter(|d| !d.is_zero())
+            .ok_or(ConnectError::Timeout(timeout_secs))
+    };
+    let ssh_error = |e: ssh2::Error, wrap: fn(String) -> ConnectError| {
+        if e.code() == ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT) {
+            ConnectError::Timeout(timeout_secs)
+        } else {
+            wrap(e.message().to_string())
+        }
+    };
+
+    // 1. 解析地址（系统解析器无法中断，超时由任务句柄处理）
+    cancel.check()?;
+    on_stage(ConnectStage::Resolving);
+    let addresses: Vec<SocketAddr> = (host, config.port)
+        .to_socket_addrs()
+        .map_err(|e| ConnectError::Resolve {
+            host: host.to_string(),
+            detail: e.to_string(),
+        })?
+        .collect();
+    if addresses.is_empty() {
+        return Err(ConnectError::Resolve {
+            host: host.to_string(),
+            detail: "no address".to_string(),
+        });
+    }
+
+    // 2. 依次尝试解析出的地址
+    cancel.check()?;
+    on_stage(ConnectStage::TcpConnect);
+    let mut last_error = None;
+    let mut stream = None;
+    for address in addresses {
+        cancel.check()?;
+        match TcpStream::connect_timeout(&address, remaining()?) {
+            Ok(tcp) => {
+                stream = Some(tcp);
+                break;
+            }
+            Err(e) if e.kind() == ErrorKind::TimedOut => {
+                last_error = Some(ConnectError::Timeout(timeout_secs));
+            }
+            Err(e) => last_error = Some(ConnectError::Tcp(e.to_string())),
+        }
+    }
+    let Some(stream) = stream else {
+        return Err(last_error.unwrap_or(ConnectError::Tcp("no address".to_string())));
+    };
+    cancel.register(&stream)?;
+
+    // 3. 握手，阻塞调用的超时为剩余期限
+    on_stage(ConnectStage::Handshake);
+    let mut session = Session::new().map_err(|e| ssh_error(e, ConnectError::Handshake))?;
+    session.set_tcp_stream(stream);
+    session.set_timeout(remaining()?.as_millis().min(u32::MAX as u128) as u32);
+    session
+        .handshake()
+        .map_err(|e| ssh_error(e, ConnectError::Handshake))?;
+
+    // 4. 主机密钥与 known_hosts 中的记录不一致时拒绝连接；未记录的主机交给界面确认后再连
+    cancel.check()?;
+    on_stage(ConnectStage::HostKeyCheck);
+    check_host_key(&session, host, config.port)?;
+
+    // 5. 认证
+    cancel.check()?;
+    on_stage(ConnectStage::Authenticating(method));
+    let auth_error = |e: ssh2::Error| {
+        if e.code() == ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT) {
+            ConnectError::Timeout(timeout_secs)
+        } else {
+            ConnectError::Auth {
+                method,
+                detail: e.message().to_string(),
+            }
+        }
+    };
+    match (method, &config.private_key_path, &config.password) {
+        (AuthMethod::PublicKey, Some(key_path), _) => session
+            .userauth_pubkey_file(&config.username, None, Path::new(key_path), None)
+            .map_err(auth_error)?,
+        (_, _, Some(password)) => session
+            .userauth_password(&config.username, password)
+            .map_err(auth_error)?,
+        _ => return Err(ConnectError::NoAuthMethod),
+    }
+    if !session.authenticated() {
+        return Err(ConnectError::Auth {
+            method,
+            detail: String::new(),
+        });
+    }
+
+    // 6. 打开 shell 后切换为非阻塞模式，由会话 I/O 线程轮询
+    let channel = if open_shell {
+        cancel.check()?;
+        on_stage(ConnectStage::OpeningShell);
+        let mut channel = session
+            .channel_session()
+            .map_err(|e| ssh_error(e, ConnectError::Shell))?;
+        channel
+            .request_pty("xterm-256color", None, Some((80, 24, 0, 0)))
+            .map_err(|e| ssh_error(e, ConnectError::Shell))?;
+        channel
+            .shell()
+            .map_err(|e| ssh_error(e, ConnectError::Shell))?;
+        session.set_blocking(false);
+        Some(channel)
+    } else {
+        None
+    };
+
+    Ok(Connection {
+        client: SshClient::from_session(session, config.clone()),
+        channel,
+    })
+}
+
+/// 按用户的 ~/.ssh/known_hosts 校验主机密钥
+///
+/// 未记录或无法读取 known_hosts 时返回 [`ConnectError::UnknownHostKey`]，由用户确认指纹后信任
+fn check_host_key(session: &Session, host: &str, port: u16) -> Result<(), ConnectError> {
+    let (Some((key, _)), Some(info)) = (
+        session.host_key(),
+        HostKeyInfo::from_session(session, host, port),
+    ) else {
+        return Err(ConnectError::Handshake("no host key".to_string()));
+    };
+    let result = match session.known_hosts() {
+        Ok(mut known_hosts) => {
+            if let Some(path) = known_hosts_file().filter(|path| path.exists()) {
+                let _ = known_hosts.read_file(&path, KnownHostFileKind::OpenSSH);
+            }
+            known_hosts.check_port(host, port, key)
+        }
+        Err(_) => CheckResult::Failure,
+    };
+    match result {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => {
+            tracing::warn!(
+                "{}:{} 的主机密钥与 known_hosts 不一致: {} {}",
+                host,
+                port,
+                info.key_type,
+                info.fingerprint
+            );
+            Err(ConnectError::HostKeyMismatch {
+                host: host.to_string(),
+            })
+        }
+        CheckResult::NotFound | CheckResult::Failure => {
+            tracing::warn!(
+                "{}:{} 的主机密钥未在 known_hosts 中记录: {} {}",
+                host,
+                port,
+                info.key_type,
+                info.fingerprint
+            );
+            Err(ConnectError::UnknownHostKey(info))
+        }
+    }
+}
+
+fn known_hosts_file() -> Option<PathBuf> {
+    let dirs = directories::BaseDirs::new()?;
+    Some(dirs.home_dir().join(".ssh").join("known_hosts"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// 只接受连接、从不应答的服务端，连接会停在握手阶段
+    fn silent_server() -> (TcpListener, SshConfig) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = SshConfig {
+            host: "127.0.0.1".to_string(),
+            port: listener.local_addr().unwrap().port(),
+            username: "user".to_string(),
+            password: Some("secret".to_string()),
+            private_key_path: None,
+            timeout: Some(1),
+        };
+        (listener, config)
+    }
+
+    /// 轮询任务直到结束，返回经过的阶段和最终结果
+    fn wait(task: &mut ConnectTask) -> (Vec<ConnectStage>, Option<ConnectError>) {
+        let mut stages = Vec::new();
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            for event in task.poll() {
+                match event {
+                    ConnectEvent::Stage(stage) => stages.push(stage),
+                    ConnectEvent::Connected(_) => return (stages, None),
+                    ConnectEvent::Failed(e) => return (stages, Some(e)),
+                }
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("连接任务没有结束");
+    }
+
+    #[test]
+    fn test_missing_target_fails_before_connecting() {
+        let config = SshConfig {
+            host: " ".to_string(),
+            ..SshConfig::default()
+        };
+        let mut task = ConnectTask::spawn("test", config, false);
+        let (stages, error) = wait(&mut task);
+        assert!(stages.is_empty());
+        assert_eq!(error, Some(ConnectError::MissingTarget));
+        assert!(task.is_finished());
+    }
+
+    #[test]
+    fn test_timeout_during_handshake() {
+        let (_listener, config) = silent_server();
+        let mut task = ConnectTask::spawn("test", config, true);
+        let (stages, error) = wait(&mut task);
+        assert_eq!(
+            stages,
+            [
+                ConnectStage::Resolving,
+                ConnectStage::TcpConnect,
+                ConnectStage::Handshake
+            ]
+        );
+        assert_eq!(error, Some(ConnectError::Timeout(1)));
+    }
+
+    #[test]
+    fn test_cancel_interrupts_handshake() {
+        let (_listener, mut config) = silent_server();
+        config.timeout = Some(30);
+        let cancel = CancelToken::default();
+
+        let thread_cancel = cancel.clone();
+        let handle =
+            std::thread::spawn(move || establish(&config, true, &thread_cancel, &mut |_| {}).err());
+        while cancel.stream.lock().unwrap().is_none() {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let start = Instant::now();
+        cancel.cancel();
+
+        assert_eq!(handle.join().unwrap(), Some(ConnectError::Cancelled));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_host_key_known_hosts_line() {
+        let mut blob = 11u32.to_be_bytes().to_vec();
+        blob.extend_from_slice(b"ssh-ed25519");
+        blob.extend_from_slice(&[0, 0, 0, 2, 0xab, 0xcd]);
+        assert_eq!(key_type_name(&blob), Some("ssh-ed25519"));
+        assert_eq!(key_type_name(&blob[..8]), None);
+
+        let mut info = HostKeyInfo {
+            host: "example.com".to_string(),
+            port: 22,
+            key_type: "ssh-ed25519".to_string(),
+            key: STANDARD.encode(&blob),
+            fingerprint: "SHA256:x".to_string(),
+        };
+        assert_eq!(
+            info.known_hosts_line(),
+            format!("example.com ssh-ed25519 {}", info.key)
+        );
+        info.port = 2222;
+        assert!(
+            info.known_hosts_line()
+                .starts_with("[example.com]:2222 ssh-ed25519 ")
+        );
+    }
+}
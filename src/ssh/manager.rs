@@ -2,9 +2,11 @@
 //! 统一管理SSH连接、配置和会话
 
 use crate::config::ConnectionConfig;
+use crate::ssh::connect::{ConnectError, ConnectEvent, ConnectStage, ConnectTask, HostKeyInfo};
 use crate::ssh::{SessionState, SshClient, SshConfig, SshSession};
 use anyhow::Result;
 use std::collections::HashMap;
+use std::io;
 use std::sync::{Arc, Mutex};
 
 /// 会话信息（用于UI显示）
@@ -22,6 +24,10 @@ pub struct ConnectionManager {
     sessions: Arc<Mutex<HashMap<String, SshSession>>>,
     /// 连接历史配置
     connection_configs: Vec<ConnectionConfig>,
+    /// 尚未结束的连接任务
+    connecting: HashMap<String, ConnectTask>,
+    /// 因主机密钥未知而停下、等待用户确认的连接，保留配置以便信任后重连
+    unknown_host_keys: HashMap<String, (SshConfig, HostKeyInfo)>,
 }
 
 impl ConnectionManager {
@@ -30,6 +36,8 @@ impl ConnectionManager {
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
             connection_configs: Vec::new(),
+            connecting: HashMap::new(),
+            unknown_host_keys: HashMap::new(),
         }
     }
 
@@ -38,6 +46,8 @@ impl ConnectionManager {
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
             connection_configs: configs,
+            connecting: HashMap::new(),
+            unknown_host_keys: HashMap::new(),
         }
     }
 
@@ -78,44 +88,119 @@ impl ConnectionManager {
         initial_len > self.connection_configs.len()
     }
 
-    /// 创建并连接到SSH会话
-    pub fn connect(&self, config: &ConnectionConfig, password: Option<&str>) -> Result<String> {
-        let session_name = config.name.clone();
-
-        // 创建处于连接中状态的会话并立即存入
+    /// 开始在后台连接，会话立即以连接中状态加入，之后由 [`poll_connections`] 更新
+    ///
+    /// 同名会话已有未完成的连接任务时，旧任务被取消。
+    ///
+    /// [`poll_connections`]: Self::poll_connections
+    pub fn start_connect(
+        &mut self,
+        session_name: String,
+        config: &ConnectionConfig,
+        password: Option<&str>,
+        timeout_secs: u64,
+    ) {
         let mut session = SshSession::new(session_name.clone(), config.host.clone(), config.port);
-        session.state = crate::ssh::SessionState::Connecting;
+        session.state = SessionState::Connecting(ConnectStage::Resolving);
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session_name.clone(), session);
 
-        {
-            let mut sessions = self.sessions.lock().unwrap();
-            sessions.insert(session_name.clone(), session.clone());
-        }
+        let ssh_config = SshConfig {
+            host: config.host.clone(),
+            port: config.port,
+            username: config.username.clone(),
+            password: password.map(|p| p.to_string()),
+            private_key_path: config.private_key_path.clone(),
+            timeout: Some(timeout_secs),
+        };
+        self.spawn_connect(session_name, ssh_config);
+    }
 
-        // 实际连接（如果是阻塞调用，则在当前线程执行；如果是异步调用，已在外部包装为线程）
-        match session.connect(
-            &config.username,
-            password,
-            config.private_key_path.as_deref(),
-        ) {
-            Ok(_) => {
-                // 连接成功，更新管理器中的会话
-                let mut sessions = self.sessions.lock().unwrap();
-                sessions.insert(session_name.clone(), session);
-                Ok(session_name)
-            }
-            Err(e) => {
-                // 连接失败，更新状态为错误
-                let mut sessions = self.sessions.lock().unwrap();
-                if let Some(s) = sessions.get_mut(&session_name) {
-                    s.state = crate::ssh::SessionState::Error(e.to_string());
+    fn spawn_connect(&mut self, session_name: String, config: SshConfig) {
+        self.unknown_host_keys.remove(&session_name);
+        let task = ConnectTask::spawn(&session_name, config, true);
+        self.connecting.insert(session_name, task);
+    }
+
+    /// 取出各连接任务的事件并更新对应会话的状态，移除已结束的任务
+    pub fn poll_connections(&mut self) {
+        let mut sessions = self.sessions.lock().unwrap();
+        for (name, task) in self.connecting.iter_mut() {
+            for event in task.poll() {
+                let Some(session) = sessions.get_mut(name) else {
+                    continue;
+                };
+                match event {
+                    ConnectEvent::Stage(stage) => session.state = SessionState::Connecting(stage),
+                    ConnectEvent::Connected(connection) => session.attach(connection),
+                    ConnectEvent::Failed(error) => {
+                        if let ConnectError::UnknownHostKey(info) = &error {
+                            self.unknown_host_keys
+                                .insert(name.clone(), (task.config().clone(), info.clone()));
+                        }
+                        session.state = SessionState::Error(error);
+                    }
                 }
-                Err(e)
             }
         }
+        self.connecting.retain(|_, task| !task.is_finished());
+    }
+
+    /// 等待用户确认主机密钥的一个会话及服务端出示的密钥
+    pub fn pending_host_key(&self) -> Option<(String, HostKeyInfo)> {
+        self.unknown_host_keys
+            .iter()
+            .min_by(|a, b| a.0.cmp(b.0))
+            .map(|(name, (_, info))| (name.clone(), info.clone()))
     }
 
-    /// 断开会话连接
-    pub fn disconnect(&self, session_name: &str) -> bool {
+    /// 信任会话连接时出示的主机密钥，写入 known_hosts 后用原配置重新连接
+    pub fn trust_host_key(&mut self, session_name: &str) -> io::Result<()> {
+        let Some((config, info)) = self.unknown_host_keys.remove(session_name) else {
+            return Ok(());
+        };
+        info.trust()?;
+        tracing::info!(
+            "已信任 {}:{} 的主机密钥 {}",
+            info.host,
+            info.port,
+            info.fingerprint
+        );
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(session_name) {
+            session.state = SessionState::Connecting(ConnectStage::Resolving);
+        }
+        self.spawn_connect(session_name.to_string(), config);
+        Ok(())
+    }
+
+    /// 拒绝未知的主机密钥，会话保留失败状态
+    pub fn reject_host_key(&mut self, session_name: &str) {
+        self.unknown_host_keys.remove(session_name);
+    }
+
+    /// 取消正在进行的连接，会话保留并显示为已取消
+    pub fn cancel_connect(&mut self, session_name: &str) -> bool {
+        let Some(mut task) = self.connecting.remove(session_name) else {
+            return false;
+        };
+        task.cancel();
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(session_name) {
+            session.state = SessionState::Error(ConnectError::Cancelled);
+        }
+        true
+    }
+
+    /// 会话是否有正在进行的连接任务
+    pub fn is_connecting(&self, session_name: &str) -> bool {
+        self.connecting.contains_key(session_name)
+    }
+
+    /// 断开会话连接，仍在连接中时取消连接任务
+    pub fn disconnect(&mut self, session_name: &str) -> bool {
+        self.connecting.remove(session_name);
+        self.unknown_host_keys.remove(session_name);
         let mut sessions = self.sessions.lock().unwrap();
         if let Some(mut session) = sessions.remove(session_name) {
             session.disconnect();
@@ -178,10 +263,10 @@ impl ConnectionManager {
     pub fn get_session_state(&self, session_name: &str) -> Option<String> {
         let sessions = self.sessions.lock().unwrap();
         sessions.get(session_name).map(|s| match s.state() {
-            crate::ssh::SessionState::Disconnected => "已断开".to_string(),
-            crate::ssh::SessionState::Connecting => "连接中".to_string(),
-            crate::ssh::SessionState::Connected => "已连接".to_string(),
-            crate::ssh::SessionState::Error(msg) => format!("错误: {}", msg),
+            SessionState::Disconnected => "已断开".to_string(),
+            SessionState::Connecting(_) => "连接中".to_string(),
+            SessionState::Connected => "已连接".to_string(),
+            SessionState::Error(e) => format!("错误: {}", e),
         })
     }
 }
@@ -196,7 +281,8 @@ impl Default for ConnectionManager {
 #[derive(Debug, Clone)]
 pub struct ConnectionTestResult {
     pub success: bool,
-    pub message: String,
+    /// 失败原因
+    pub error: Option<ConnectError>,
     pub latency: Option<u64>, // 延迟（毫秒）
 }
 
@@ -221,17 +307,14 @@ pub fn test_connection(config: &ConnectionConfig) -> ConnectionTestResult {
     };
 
     match SshClient::new(ssh_config) {
-        Ok(_) => {
-            let latency = start_time.elapsed().as_millis() as u64;
-            ConnectionTestResult {
-                success: true,
-                message: "连接成功".to_string(),
-                latency: Some(latency),
-            }
-        }
+        Ok(_) => ConnectionTestResult {
+            success: true,
+            error: None,
+            latency: Some(start_time.elapsed().as_millis() as u64),
+        },
         Err(e) => ConnectionTestResult {
             success: false,
-            message: format!("连接失败: {}", e),
+            error: Some(e),
             latency: None,
         },
     }
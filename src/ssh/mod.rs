@@ -2,10 +2,16 @@
 //! 处理SSH连接、会话管理和终端仿真
 
 pub mod client;
+pub mod connect;
 pub mod manager;
 pub mod session;
+pub mod worker;
 
 // 重新导出主要组件
 pub use client::{SshClient, SshConfig};
+pub use connect::{
+    AuthMethod, ConnectError, ConnectEvent, ConnectStage, ConnectTask, Connection, HostKeyInfo,
+};
 pub use manager::{ConnectionManager, ConnectionTestResult, test_connection};
 pub use session::{SessionState, SshSession};
+pub use worker::SessionWorker;
@@ -0,0 +1,257 @@
+//! 会话 I/O 线程
+//! 每个已连接的会话一个后台线程：持续读取终端通道交给仿真器，写入与调整大小请求通过消息通道发给该线程，
+//! 界面线程不再直接读写通道，也不必为此持有连接管理器的锁
+
+use ssh2::Channel;
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 每次从通道读取的字节数
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+
+/// 每轮最多读取的字节数，读满后先处理请求和待写入的数据，
+/// 远端持续输出时 Ctrl+C 和调整大小也能及时发出
+const READ_BUDGET: usize = 4 * READ_CHUNK_SIZE;
+
+/// 通道刚空闲时等待消息的时间，也是轮询通道的间隔
+const IDLE_POLL_MIN: Duration = Duration::from_millis(5);
+
+/// 持续空闲时轮询间隔逐步增长到的上限，收到数据或请求后回到最小值
+const IDLE_POLL_MAX: Duration = Duration::from_millis(50);
+
+/// 调整终端大小请求暂时发不出去时的最大重试次数
+const RESIZE_RETRIES: usize = 100;
+
+/// 发给 I/O 线程的请求
+#[derive(Debug)]
+pub enum SessionCommand {
+    /// 向远端写入数据
+    Write(Vec<u8>),
+    /// 调整远端终端大小
+    Resize { rows: u32, cols: u32 },
+    /// 结束线程
+    Shutdown,
+}
+
+/// 会话 I/O 线程的句柄，丢弃时通知线程退出
+pub struct SessionWorker {
+    sender: Sender<SessionCommand>,
+}
+
+impl SessionWorker {
+    /// 启动 I/O 线程
+    ///
+    /// `on_data` 处理读到的数据；`take_replies` 取出需要回复给远端的内容，每轮循环调用一次，
+    /// 仿真器在其他线程产生的回复也能及时发出；`wake` 在有新数据时唤醒界面。
+    pub fn spawn<D, R, W>(
+        name: &str,
+        channel: Arc<Mutex<Option<Channel>>>,
+        on_data: D,
+        take_replies: R,
+        wake: W,
+    ) -> std::io::Result<Self>
+    where
+        D: FnMut(&[u8]) + Send + 'static,
+        R: FnMut() -> Vec<u8> + Send + 'static,
+        W: Fn() + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let span = tracing::info_span!("session", name = %name);
+        std::thread::Builder::new()
+            .name(format!("session-io-{}", name))
+            .spawn(move || {
+                let _guard = span.entered();
+                run(channel, receiver, on_data, take_replies, wake)
+            })?;
+        Ok(Self { sender })
+    }
+
+    /// 向远端写入数据（由 I/O 线程异步发送）
+    pub fn write(&self, data: &[u8]) {
+        if !data.is_empty() {
+            let _ = self.sender.send(SessionCommand::Write(data.to_vec()));
+        }
+    }
+
+    /// 调整远端终端大小
+    pub fn resize(&self, rows: u32, cols: u32) {
+        let _ = self.sender.send(SessionCommand::Resize { rows, cols });
+    }
+}
+
+impl Drop for SessionWorker {
+    fn drop(&mut self) {
+        let _ = self.sender.send(SessionCommand::Shutdown);
+    }
+}
+
+/// I/O 线程主循环
+fn run<D, R, W>(
+    channel: Arc<Mutex<Option<Channel>>>,
+    receiver: Receiver<SessionCommand>,
+    mut on_data: D,
+    mut take_replies: R,
+    wake: W,
+) where
+    D: FnMut(&[u8]),
+    R: FnMut() -> Vec<u8>,
+    W: Fn(),
+{
+    let mut pending_writes: VecDeque<u8> = VecDeque::new();
+    let mut buffer = vec![0u8; READ_CHUNK_SIZE];
+    let mut idle_wait = IDLE_POLL_MIN;
+
+    loop {
+        // 1. 处理界面发来的请求
+        loop {
+            match receiver.try_recv() {
+                Ok(SessionCommand::Write(data)) => pending_writes.extend(data),
+                Ok(SessionCommand::Resize { rows, cols }) => resize(&channel, rows, cols),
+                Ok(SessionCommand::Shutdown) | Err(mpsc::TryRecvError::Disconnected) => return,
+                Err(mpsc::TryRecvError::Empty) => break,
+            }
+        }
+
+        // 2. 读取通道中已有的数据，最多读 READ_BUDGET 字节
+        let mut received = 0;
+        let mut closed = false;
+        while received < READ_BUDGET {
+            let result = {
+                let mut guard = channel.lock().unwrap();
+                let Some(ref mut channel) = *guard else {
+                    return;
+                };
+                match channel.read(&mut buffer) {
+                    Ok(0) if channel.eof() => Err(None),
+                    other => other.map_err(Some),
+                }
+            };
+
+            match result {
+                Ok(0) => break,
+                Ok(n) => {
+                    received += n;
+                    on_data(&buffer[..n]);
+                }
+                Err(Some(e)) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    if let Some(e) = e {
+                        tracing::warn!("读取终端数据失败: {}", e);
+                    }
+                    closed = true;
+                    break;
+                }
+            }
+        }
+        let received = received > 0;
+        if received {
+            wake();
+        }
+        if closed {
+            tracing::info!("终端通道已关闭");
+            wake();
+            return;
+        }
+
+        // 3. 尽量发出待写入的数据（包括仿真器的回复），通道暂时写不进时留到下一轮
+        pending_writes.extend(take_replies());
+        if !pending_writes.is_empty() {
+            flush_writes(&channel, &mut pending_writes);
+        }
+
+        // 4. 没有数据往来时等待请求，同时作为轮询通道的间隔；持续空闲时逐步放慢轮询
+        if received {
+            idle_wait = IDLE_POLL_MIN;
+        }
+        if !received && pending_writes.is_empty() {
+            match receiver.recv_timeout(idle_wait) {
+                Ok(SessionCommand::Write(data)) => {
+                    pending_writes.extend(data);
+                    idle_wait = IDLE_POLL_MIN;
+                }
+                Ok(SessionCommand::Resize { rows, cols }) => {
+                    resize(&channel, rows, cols);
+                    idle_wait = IDLE_POLL_MIN;
+                }
+                Ok(SessionCommand::Shutdown) | Err(RecvTimeoutError::Disconnected) => return,
+                Err(RecvTimeoutError::Timeout) => idle_wait = next_idle_wait(idle_wait),
+            }
+        } else if !received {
+            // 有数据等待写入但通道写不进，稍等再试
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+/// 空闲等待时间加倍，不超过上限
+fn next_idle_wait(current: Duration) -> Duration {
+    (current * 2).min(IDLE_POLL_MAX)
+}
+
+/// 写入待发送的数据，遇到 WouldBlock 时停止
+fn flush_writes(channel: &Arc<Mutex<Option<Channel>>>, pending: &mut VecDeque<u8>) {
+    let mut guard = channel.lock().unwrap();
+    let Some(ref mut channel) = *guard else {
+        return;
+    };
+
+    while !pending.is_empty() {
+        let (front, _) = pending.as_slices();
+        match channel.write(front) {
+            Ok(0) => break,
+            Ok(n) => {
+                pending.drain(..n);
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) => {
+                tracing::warn!("发送数据失败: {}", e);
+                pending.clear();
+                break;
+            }
+        }
+    }
+}
+
+/// 调整远端终端大小，非阻塞模式下请求暂时发不出去时短暂重试
+fn resize(channel: &Arc<Mutex<Option<Channel>>>, rows: u32, cols: u32) {
+    for _ in 0..RESIZE_RETRIES {
+        let result = {
+            let mut guard = channel.lock().unwrap();
+            let Some(ref mut channel) = *guard else {
+                return;
+            };
+            channel.request_pty_size(cols, rows, Some(0), Some(0))
+        };
+
+        match result {
+            Ok(()) => return,
+            Err(e) if e.code() == ssh2::ErrorCode::Session(-37) => {
+                // LIBSSH2_ERROR_EAGAIN
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            Err(e) => {
+                tracing::warn!("调整终端大小失败: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_wait_backs_off_to_limit() {
+        let mut wait = IDLE_POLL_MIN;
+        let mut waits = Vec::new();
+        for _ in 0..6 {
+            wait = next_idle_wait(wait);
+            waits.push(wait.as_millis());
+        }
+        assert_eq!(waits, vec![10, 20, 40, 50, 50, 50]);
+    }
+}
@@ -1,11 +1,24 @@
-use eframe::egui;
+use crate::terminal::color::{CellColor, PaletteOverrides};
+use crate::terminal::cursor::CursorStyle;
+use crate::terminal::graphics::{ImageCell, ImageStore};
+use crate::terminal::scrollback::Scrollback;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+
+static NEXT_BUFFER_ID: AtomicU64 = AtomicU64::new(1);
 
 /// 终端字符单元
 #[derive(Debug, Clone)]
 pub struct TerminalCell {
+    /// 字素簇的首个字符
     pub character: char,
-    pub fg_color: egui::Color32,
-    pub bg_color: egui::Color32,
+    /// 与首字符组成同一字素簇的后续字符（组合音标、变体选择符、ZWJ序列等）
+    pub combining: Option<Box<str>>,
+    pub fg_color: CellColor,
+    pub bg_color: CellColor,
     pub bold: bool,
     pub italic: bool,
     pub underline: bool,
@@ -13,23 +26,218 @@ pub struct TerminalCell {
     pub is_continuation: bool,
     /// 是否被选中（用于显示）
     pub is_selected: bool,
+    /// OSC 8 超链接在超链接表中的索引
+    pub hyperlink: Option<HyperlinkId>,
+    /// 覆盖在该单元格上的图片切片 (sixel / kitty)
+    pub image: Option<Box<ImageCell>>,
+}
+
+impl TerminalCell {
+    /// 单元格显示的完整字素簇
+    pub fn text(&self) -> String {
+        let mut text = String::from(self.character);
+        if let Some(ref combining) = self.combining {
+            text.push_str(combining);
+        }
+        text
+    }
+
+    /// 向字素簇追加字符
+    pub fn push_combining(&mut self, c: char) {
+        let mut combining = self.combining.take().map(String::from).unwrap_or_default();
+        combining.push(c);
+        self.combining = Some(combining.into_boxed_str());
+    }
+
+    /// 是否为未写入内容的空白单元格（重排时可去除的行尾空白）
+    pub fn is_blank(&self) -> bool {
+        self.character == ' '
+            && self.combining.is_none()
+            && self.bg_color == CellColor::Background
+            && !self.underline
+            && !self.is_continuation
+            && self.hyperlink.is_none()
+            && self.image.is_none()
+    }
 }
 
 impl Default for TerminalCell {
     fn default() -> Self {
         Self {
             character: ' ',
-            fg_color: egui::Color32::WHITE,
-            bg_color: egui::Color32::TRANSPARENT,
+            combining: None,
+            fg_color: CellColor::Foreground,
+            bg_color: CellColor::Background,
             bold: false,
             italic: false,
             underline: false,
             is_continuation: false,
             is_selected: false,
+            hyperlink: None,
+            image: None,
         }
     }
 }
 
+/// 超链接表中的索引
+pub type HyperlinkId = u32;
+
+/// OSC 8 超链接
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hyperlink {
+    /// 远端指定的链接 ID，用于把不相邻的单元格归为同一个链接
+    pub id: Option<String>,
+    pub uri: String,
+}
+
+/// 超链接表至少登记这么多条目后才开始清理
+const HYPERLINK_PRUNE_MIN: usize = 256;
+
+/// 超链接表：相同的超链接只保存一份，单元格中只记录索引
+///
+/// 不再被任何单元格引用的条目由 [`TerminalBuffer::prune_hyperlinks`] 清理，
+/// 空出的索引留给之后登记的超链接复用。
+#[derive(Debug, Clone, Default)]
+pub struct HyperlinkTable {
+    links: Vec<Option<Arc<Hyperlink>>>,
+    index: HashMap<Arc<Hyperlink>, HyperlinkId>,
+    /// 已清理、可复用的索引
+    free: Vec<HyperlinkId>,
+    /// 条目数达到该值时需要清理
+    prune_at: usize,
+}
+
+impl HyperlinkTable {
+    /// 登记超链接并返回其索引，已存在时复用原索引
+    pub fn intern(&mut self, link: Hyperlink) -> HyperlinkId {
+        if let Some(&id) = self.index.get(&link) {
+            return id;
+        }
+
+        let link = Arc::new(link);
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.links[id as usize] = Some(link.clone());
+                id
+            }
+            None => {
+                self.links.push(Some(link.clone()));
+                (self.links.len() - 1) as HyperlinkId
+            }
+        };
+        self.index.insert(link, id);
+        id
+    }
+
+    pub fn get(&self, id: HyperlinkId) -> Option<&Hyperlink> {
+        self.links.get(id as usize)?.as_deref()
+    }
+
+    /// 当前登记的超链接数
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// 条目数是否增长到需要清理（每次清理后阈值翻倍，摊销扫描缓冲区的开销）
+    pub fn needs_prune(&self) -> bool {
+        self.len() >= self.prune_at.max(HYPERLINK_PRUNE_MIN)
+    }
+
+    /// 只保留仍被引用的超链接
+    fn retain(&mut self, live: &HashSet<HyperlinkId>) {
+        for (id, slot) in self.links.iter_mut().enumerate() {
+            let id = id as HyperlinkId;
+            if !live.contains(&id)
+                && let Some(link) = slot.take()
+            {
+                self.index.remove(&link);
+                self.free.push(id);
+            }
+        }
+        self.prune_at = self.len() * 2;
+    }
+}
+
+/// 链接类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// URL（包括 OSC 8 超链接）
+    Url,
+    /// 文件路径
+    FilePath,
+    /// IP:端口
+    HostPort,
+}
+
+/// 终端中的链接，来自 OSC 8 超链接或对文本的自动识别
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub target: String,
+    pub kind: LinkKind,
+    /// 所在行（历史与屏幕合并计算的行号）
+    pub row: usize,
+    pub start_col: usize,
+    /// 结束列（不含）
+    pub end_col: usize,
+}
+
+/// shell 集成标记 (OSC 133 / FinalTerm)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellMark {
+    /// A：提示符开始
+    PromptStart,
+    /// B：命令输入开始
+    CommandStart,
+    /// C：命令输出开始
+    OutputStart,
+    /// D：命令结束，附带退出码
+    CommandEnd(Option<i32>),
+}
+
+/// shell 集成 (OSC 133) 记录在行上的命令边界
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineMark {
+    /// 提示符从该行开始 (OSC 133;A)
+    pub prompt: bool,
+    /// 命令输入从该行开始 (OSC 133;B)
+    pub command: bool,
+    /// 命令输出从该行开始 (OSC 133;C)
+    pub output: bool,
+    /// 命令结束时的退出码 (OSC 133;D)，记录在该命令的提示符所在行
+    pub exit_code: Option<i32>,
+}
+
+impl LineMark {
+    /// 合并另一行的标记（重排时多行合并为一条逻辑行）
+    fn merge(&mut self, other: LineMark) {
+        self.prompt |= other.prompt;
+        self.command |= other.command;
+        self.output |= other.output;
+        self.exit_code = self.exit_code.or(other.exit_code);
+    }
+}
+
+/// 视口的滚动方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scroll {
+    /// 按行滚动，正数向上（查看更早的内容）
+    Delta(isize),
+    /// 向上翻一页
+    PageUp,
+    /// 向下翻一页
+    PageDown,
+    /// 历史记录的最顶端
+    Top,
+    /// 回到底部，跟随新的输出
+    Bottom,
+    /// 使指定行（合并行号）显示在视口顶部
+    ToRow(usize),
+}
+
 /// 选择范围
 #[derive(Debug, Clone, Default)]
 pub struct SelectionRange {
@@ -39,6 +247,25 @@ pub struct SelectionRange {
     pub end_col: usize,
 }
 
+/// 屏幕内容（不含历史）的快照，同步输出期间用来继续显示更新开始前的画面
+#[derive(Debug, Clone)]
+pub struct ScreenSnapshot {
+    cells: Vec<TerminalCell>,
+    wrapped: Vec<bool>,
+    marks: Vec<LineMark>,
+    row_versions: Vec<u64>,
+    cursor: (usize, usize),
+    cursor_visible: bool,
+    cursor_style: Option<CursorStyle>,
+}
+
+impl ScreenSnapshot {
+    /// 快照中单元格引用的超链接
+    pub fn hyperlinks(&self) -> impl Iterator<Item = HyperlinkId> + '_ {
+        self.cells.iter().filter_map(|cell| cell.hyperlink)
+    }
+}
+
 /// 终端缓冲区
 #[derive(Debug, Clone)]
 pub struct TerminalBuffer {
@@ -47,11 +274,41 @@ pub struct TerminalBuffer {
     pub cells: Vec<TerminalCell>,
     pub cursor_row: usize,
     pub cursor_col: usize,
-    /// 历史回溯缓冲区 (最多存储1000行)
-    pub history: Vec<Vec<TerminalCell>>,
-    pub max_history: usize,
+    /// 光标是否可见 (DECTCEM)
+    pub cursor_visible: bool,
+    /// 远端通过 DECSCUSR 设置的光标样式，None 表示使用配置中的默认样式
+    pub cursor_style: Option<CursorStyle>,
+    /// 已写到行尾最后一列，下一个可打印字符写入前需要先折行
+    pub pending_wrap: bool,
+    /// 制表位
+    pub tab_stops: Vec<bool>,
+    /// 屏幕各行是否软换行（因写满一行自动折到下一行）
+    pub wrapped: Vec<bool>,
+    /// 历史回溯缓冲区（含各行的软换行标志与 shell 集成标记）
+    pub history: Scrollback,
+    /// 屏幕各行的 shell 集成标记
+    pub marks: Vec<LineMark>,
+    /// 视口相对于底部向上滚动的行数，0 表示显示当前屏幕并跟随输出
+    display_offset: usize,
+    /// 滚动区域的上下边界（含），由 DECSTBM 设置，默认整屏
+    scroll_top: usize,
+    scroll_bottom: usize,
     /// 当前选择范围
     pub selection: Option<SelectionRange>,
+    /// OSC 8 超链接表
+    pub hyperlinks: HyperlinkTable,
+    /// 单元格引用的图片
+    pub images: ImageStore,
+    /// 远端通过 OSC 4/10/11 修改的颜色，渲染时优先于主题
+    pub palette: PaletteOverrides,
+    /// 缓冲区的全局唯一标识，克隆得到的快照沿用同一标识
+    id: u64,
+    /// 内容修改计数，每次修改后递增
+    version: u64,
+    /// 屏幕各行最近一次修改时的计数
+    row_versions: Vec<u64>,
+    /// 历史记录最近一次修改时的计数
+    history_version: u64,
 }
 
 impl TerminalBuffer {
@@ -65,35 +322,451 @@ impl TerminalBuffer {
             cells,
             cursor_row: 0,
             cursor_col: 0,
-            history: Vec::new(),
-            max_history: 1000,
+            cursor_visible: true,
+            cursor_style: None,
+            pending_wrap: false,
+            tab_stops: default_tab_stops(cols),
+            wrapped: vec![false; rows],
+            history: Scrollback::default(),
+            marks: vec![LineMark::default(); rows],
+            display_offset: 0,
+            scroll_top: 0,
+            scroll_bottom: rows.saturating_sub(1),
             selection: None,
+            hyperlinks: HyperlinkTable::default(),
+            images: ImageStore::default(),
+            palette: PaletteOverrides::default(),
+            id: NEXT_BUFFER_ID.fetch_add(1, Ordering::Relaxed),
+            version: 0,
+            row_versions: vec![0; rows],
+            history_version: 0,
         }
     }
 
+    /// 缓冲区的全局唯一标识
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// 某一行（历史与屏幕统一编号）最近一次修改时的计数
+    ///
+    /// 渲染器据此判断缓存的行图形是否仍然有效。
+    pub fn line_version(&self, row: usize) -> u64 {
+        let history_len = self.history.len();
+        if row < history_len {
+            self.history_version
+        } else {
+            self.row_versions
+                .get(row - history_len)
+                .copied()
+                .unwrap_or(self.version)
+        }
+    }
+
+    /// 标记屏幕某一行已修改
+    fn touch_row(&mut self, row: usize) {
+        self.version += 1;
+        if let Some(version) = self.row_versions.get_mut(row) {
+            *version = self.version;
+        }
+    }
+
+    /// 标记整个缓冲区（包括历史）已修改，用于滚动、清屏、调整大小等整体变化
+    pub fn touch_all(&mut self) {
+        self.version += 1;
+        self.row_versions.clear();
+        self.row_versions.resize(self.rows, self.version);
+        self.history_version = self.version;
+    }
+
+    /// 替换远端修改的颜色，有变化时所有行都需要重绘
+    pub fn set_palette(&mut self, palette: PaletteOverrides) {
+        if self.palette != palette {
+            self.palette = palette;
+            self.touch_all();
+        }
+    }
+
+    /// 视口相对于底部向上滚动的行数
+    pub fn display_offset(&self) -> usize {
+        self.display_offset.min(self.history.len())
+    }
+
+    /// 视口第一行的合并行号，视口共显示 rows 行
+    pub fn display_top(&self) -> usize {
+        self.history.len() - self.display_offset()
+    }
+
+    /// 滚动视口，超出历史范围时停在两端
+    pub fn scroll_display(&mut self, scroll: Scroll) {
+        let max = self.history.len();
+        let offset = self.display_offset() as isize;
+        let page = self.rows.max(1) as isize;
+        let target = match scroll {
+            Scroll::Delta(lines) => offset + lines,
+            Scroll::PageUp => offset + page,
+            Scroll::PageDown => offset - page,
+            Scroll::Top => max as isize,
+            Scroll::Bottom => 0,
+            Scroll::ToRow(row) => max as isize - row as isize,
+        };
+        self.display_offset = target.clamp(0, max as isize) as usize;
+    }
+
+    /// 调整终端大小
+    ///
+    /// 列数变化时将软换行的行合并为逻辑行后按新宽度重新折行（屏幕与历史一起处理），
+    /// 光标保持在原来所在的字符上。只有行数变化时不重排历史，屏幕上下多出或缺少的行
+    /// 直接推入历史或从历史取回。
     pub fn resize(&mut self, new_rows: usize, new_cols: usize) {
         if new_rows == self.rows && new_cols == self.cols {
             return;
         }
 
-        let mut new_cells = vec![TerminalCell::default(); new_rows * new_cols];
+        self.pending_wrap = false;
+        self.scroll_top = 0;
+        self.scroll_bottom = new_rows.saturating_sub(1);
+        let kept = self.cols.min(new_cols);
+        let mut tab_stops = default_tab_stops(new_cols);
+        tab_stops[..kept].copy_from_slice(&self.tab_stops[..kept]);
+        self.tab_stops = tab_stops;
 
-        // 复制旧内容
-        for r in 0..self.rows.min(new_rows) {
-            for c in 0..self.cols.min(new_cols) {
-                if let Some(old_idx) = self.get_cell_index(r, c) {
-                    new_cells[r * new_cols + c] = self.cells[old_idx].clone();
-                }
+        // 重排后历史的行号全部变化，视口回到底部
+        self.display_offset = 0;
+
+        if new_rows == 0 || new_cols == 0 {
+            self.rows = new_rows;
+            self.cols = new_cols;
+            self.cells = vec![TerminalCell::default(); new_rows * new_cols];
+            self.wrapped = vec![false; new_rows];
+            self.marks = vec![LineMark::default(); new_rows];
+            self.cursor_row = 0;
+            self.cursor_col = 0;
+            self.touch_all();
+            return;
+        }
+
+        // 选择范围的坐标在重排后失效
+        self.clear_selection();
+
+        if new_cols == self.cols && self.rows > 0 {
+            self.resize_rows(new_rows);
+            return;
+        }
+
+        let (lines, cursor_line, cursor_offset) = self.take_logical_lines();
+
+        // 按新宽度重新折行，逻辑行的标记放在其第一行
+        let mut new_lines: Vec<(Vec<TerminalCell>, bool, LineMark)> = Vec::new();
+        let mut cursor = (0, 0);
+        for (index, (line, mark)) in lines.into_iter().enumerate() {
+            let first_row = new_lines.len();
+            let offset = (index == cursor_line).then_some(cursor_offset);
+            let (rows, line_cursor) = rewrap_line(line, new_cols, offset);
+            if let Some((row, col)) = line_cursor {
+                cursor = (first_row + row, col);
             }
+            let last = rows.len() - 1;
+            new_lines.extend(rows.into_iter().enumerate().map(|(i, row)| {
+                let row_mark = if i == 0 { mark } else { LineMark::default() };
+                (row, i < last, row_mark)
+            }));
+        }
+
+        // 屏幕显示最后 new_rows 行，但光标所在行不能被推入历史
+        let screen_start = new_lines.len().saturating_sub(new_rows).min(cursor.0);
+        let screen_end = (screen_start + new_rows).min(new_lines.len());
+        new_lines.truncate(screen_end);
+        let screen_lines = new_lines.split_off(screen_start);
+
+        let mut cells = Vec::with_capacity(new_rows * new_cols);
+        let mut wrapped = Vec::with_capacity(new_rows);
+        let mut marks = Vec::with_capacity(new_rows);
+        for (row, is_wrapped, mark) in screen_lines {
+            cells.extend(row);
+            wrapped.push(is_wrapped);
+            marks.push(mark);
+        }
+        cells.resize(new_rows * new_cols, TerminalCell::default());
+        wrapped.resize(new_rows, false);
+        marks.resize(new_rows, LineMark::default());
+
+        // 超出回滚限制的行由历史自行淘汰
+        for (row, is_wrapped, mark) in new_lines {
+            self.history.push(&row, is_wrapped, mark);
         }
 
-        self.cells = new_cells;
+        self.cells = cells;
+        self.wrapped = wrapped;
+        self.marks = marks;
         self.rows = new_rows;
         self.cols = new_cols;
+        self.cursor_row = cursor.0 - screen_start;
+        self.cursor_col = cursor.1.min(new_cols.saturating_sub(1));
+        self.touch_all();
+    }
+
+    /// 只改变行数，列数不变
+    fn resize_rows(&mut self, new_rows: usize) {
+        let mut rows: Vec<(Vec<TerminalCell>, bool, LineMark)> = (0..self.used_rows())
+            .map(|r| (self.row_cells(r).to_vec(), self.wrapped[r], self.marks[r]))
+            .collect();
+        let mut cursor_row = self.cursor_row;
+
+        // 放不下时把顶部的行推入历史，但光标所在行要留在屏幕上
+        let overflow = rows.len().saturating_sub(new_rows).min(cursor_row);
+        for (cells, wrapped, mark) in rows.drain(..overflow) {
+            self.history.push(&cells, wrapped, mark);
+        }
+        cursor_row -= overflow;
+
+        // 有空余的行时从历史取回
+        let pulled: Vec<_> = std::iter::from_fn(|| self.history.pop())
+            .take(new_rows.saturating_sub(rows.len()))
+            .collect();
+        cursor_row += pulled.len();
+        rows.splice(0..0, pulled.into_iter().rev());
+        rows.truncate(new_rows);
 
-        // 确保光标在有效范围内
+        let mut cells = Vec::with_capacity(new_rows * self.cols);
+        let mut wrapped = Vec::with_capacity(new_rows);
+        let mut marks = Vec::with_capacity(new_rows);
+        for (row, is_wrapped, mark) in rows {
+            cells.extend(row);
+            wrapped.push(is_wrapped);
+            marks.push(mark);
+        }
+        cells.resize(new_rows * self.cols, TerminalCell::default());
+        wrapped.resize(new_rows, false);
+        marks.resize(new_rows, LineMark::default());
+
+        self.cells = cells;
+        self.wrapped = wrapped;
+        self.marks = marks;
+        self.rows = new_rows;
+        self.cursor_row = cursor_row.min(new_rows - 1);
+        self.touch_all();
+    }
+
+    /// 只重排历史记录并把屏幕清空为新的尺寸
+    ///
+    /// 供自行维护屏幕内容的仿真引擎（wezterm）使用：屏幕由引擎重排后整体写回。
+    pub fn resize_history(&mut self, new_rows: usize, new_cols: usize) {
+        if new_rows == self.rows && new_cols == self.cols {
+            return;
+        }
+
+        self.pending_wrap = false;
+        self.scroll_top = 0;
+        self.scroll_bottom = new_rows.saturating_sub(1);
+        let kept = self.cols.min(new_cols);
+        let mut tab_stops = default_tab_stops(new_cols);
+        tab_stops[..kept].copy_from_slice(&self.tab_stops[..kept]);
+        self.tab_stops = tab_stops;
+        self.display_offset = 0;
+        self.clear_selection();
+
+        if new_cols != self.cols && new_cols > 0 {
+            let mut current: Vec<TerminalCell> = Vec::new();
+            let mut current_mark = LineMark::default();
+            let mut lines = Vec::new();
+            for (mut row, is_wrapped, mark) in self.history.take_all() {
+                if !is_wrapped {
+                    while row.last().is_some_and(TerminalCell::is_blank) {
+                        row.pop();
+                    }
+                }
+                current.extend(row);
+                current_mark.merge(mark);
+                if !is_wrapped {
+                    lines.push((
+                        std::mem::take(&mut current),
+                        std::mem::take(&mut current_mark),
+                        false,
+                    ));
+                }
+            }
+            // 最后一行折到了屏幕上，重排后仍保持软换行
+            if !current.is_empty() {
+                lines.push((current, current_mark, true));
+            }
+
+            for (line, mark, continued) in lines {
+                let (rows, _) = rewrap_line(line, new_cols, None);
+                let last = rows.len() - 1;
+                for (i, row) in rows.into_iter().enumerate() {
+                    let row_mark = if i == 0 { mark } else { LineMark::default() };
+                    self.history.push(&row, i < last || continued, row_mark);
+                }
+            }
+        }
+
+        self.cells = vec![TerminalCell::default(); new_rows * new_cols];
+        self.wrapped = vec![false; new_rows];
+        self.marks = vec![LineMark::default(); new_rows];
+        self.rows = new_rows;
+        self.cols = new_cols;
         self.cursor_row = self.cursor_row.min(new_rows.saturating_sub(1));
         self.cursor_col = self.cursor_col.min(new_cols.saturating_sub(1));
+        self.touch_all();
+    }
+
+    /// 把一行推入历史记录，查看历史时视口停留在原来的内容上
+    ///
+    /// 历史达到上限后最早的行被淘汰，各历史行号对应的内容随之改变，因此整个历史都标记为已修改。
+    pub fn push_history(&mut self, cells: &[TerminalCell], wrapped: bool, mark: LineMark) {
+        self.history.push(cells, wrapped, mark);
+        self.version += 1;
+        self.history_version = self.version;
+        if self.display_offset > 0 {
+            self.display_offset = (self.display_offset + 1).min(self.history.len());
+        }
+    }
+
+    /// 整行替换屏幕某一行的内容，保留单元格的选中状态
+    ///
+    /// 供自行维护屏幕内容的仿真引擎（wezterm）写回变化的行，多余的单元格被截断，不足的补空白。
+    pub fn replace_row(&mut self, row: usize, cells: &[TerminalCell], wrapped: bool) {
+        if row >= self.rows {
+            return;
+        }
+
+        let cols = self.cols;
+        let target = &mut self.cells[row * cols..(row + 1) * cols];
+        for (col, cell) in target.iter_mut().enumerate() {
+            let is_selected = cell.is_selected;
+            *cell = cells.get(col).cloned().unwrap_or_default();
+            cell.is_selected = is_selected;
+        }
+        self.wrapped[row] = wrapped;
+        self.touch_row(row);
+    }
+
+    /// 取出历史与屏幕内容并合并为逻辑行
+    ///
+    /// 返回逻辑行列表（附带合并后的标记）以及光标所在的逻辑行和行内偏移。
+    /// 屏幕底部光标以下的空白行不参与重排。
+    fn take_logical_lines(&mut self) -> (Vec<(Vec<TerminalCell>, LineMark)>, usize, usize) {
+        let used_rows = self.used_rows();
+
+        let cursor_abs = self.history.len() + self.cursor_row;
+        let mut rows = self.history.take_all();
+        for r in 0..used_rows {
+            rows.push((self.row_cells(r).to_vec(), self.wrapped[r], self.marks[r]));
+        }
+
+        let mut lines = Vec::new();
+        let mut current: Vec<TerminalCell> = Vec::new();
+        let mut current_mark = LineMark::default();
+        let mut cursor = (0, 0);
+        for (index, (mut row, is_wrapped, mark)) in rows.into_iter().enumerate() {
+            if index == cursor_abs {
+                cursor = (lines.len(), current.len() + self.cursor_col);
+            }
+            if !is_wrapped {
+                while row.last().is_some_and(TerminalCell::is_blank) {
+                    row.pop();
+                }
+            }
+            current.extend(row);
+            current_mark.merge(mark);
+            if !is_wrapped {
+                lines.push((
+                    std::mem::take(&mut current),
+                    std::mem::take(&mut current_mark),
+                ));
+            }
+        }
+        if !current.is_empty() {
+            lines.push((current, current_mark));
+        }
+
+        (lines, cursor.0, cursor.1)
+    }
+
+    /// 屏幕上有内容的行数，至少到光标所在行
+    fn used_rows(&self) -> usize {
+        (0..self.rows)
+            .rev()
+            .find(|&r| self.wrapped[r] || !self.row_cells(r).iter().all(TerminalCell::is_blank))
+            .map_or(0, |r| r + 1)
+            .max(self.cursor_row + 1)
+            .min(self.rows)
+    }
+
+    /// 复制当前屏幕的内容与光标
+    pub fn snapshot_screen(&self) -> ScreenSnapshot {
+        ScreenSnapshot {
+            cells: self.cells.clone(),
+            wrapped: self.wrapped.clone(),
+            marks: self.marks.clone(),
+            row_versions: self.row_versions.clone(),
+            cursor: (self.cursor_row, self.cursor_col),
+            cursor_visible: self.cursor_visible,
+            cursor_style: self.cursor_style,
+        }
+    }
+
+    /// 与快照交换屏幕内容与光标，再调用一次即换回；尺寸不同时不交换并返回 false
+    ///
+    /// 行的修改计数随内容一起交换，渲染器缓存的行图形不会错配。
+    pub fn swap_screen(&mut self, snapshot: &mut ScreenSnapshot) -> bool {
+        if snapshot.cells.len() != self.cells.len() || snapshot.wrapped.len() != self.rows {
+            return false;
+        }
+        std::mem::swap(&mut self.cells, &mut snapshot.cells);
+        std::mem::swap(&mut self.wrapped, &mut snapshot.wrapped);
+        std::mem::swap(&mut self.marks, &mut snapshot.marks);
+        std::mem::swap(&mut self.row_versions, &mut snapshot.row_versions);
+        let cursor = (self.cursor_row, self.cursor_col);
+        (self.cursor_row, self.cursor_col) = snapshot.cursor;
+        snapshot.cursor = cursor;
+        std::mem::swap(&mut self.cursor_visible, &mut snapshot.cursor_visible);
+        std::mem::swap(&mut self.cursor_style, &mut snapshot.cursor_style);
+        true
+    }
+
+    /// 屏幕某一行的单元格
+    pub fn row_cells(&self, row: usize) -> &[TerminalCell] {
+        &self.cells[row * self.cols..(row + 1) * self.cols]
+    }
+
+    /// 设置某一行的软换行标志
+    pub fn set_wrapped(&mut self, row: usize, wrapped: bool) {
+        if let Some(flag) = self.wrapped.get_mut(row) {
+            *flag = wrapped;
+        }
+    }
+
+    /// 光标前一个字符所在的单元格
+    ///
+    /// 跳过宽字符的延续位，光标在行首时回到上一软换行行的末尾。
+    pub fn previous_cell_position(&self) -> Option<(usize, usize)> {
+        let (mut row, mut col) = (self.cursor_row, self.cursor_col);
+        if self.pending_wrap {
+            // 待折行时光标所在的最后一列就是刚写入的字符
+            col += 1;
+        }
+        loop {
+            if col > 0 {
+                col -= 1;
+            } else if row > 0 && self.cols > 0 && self.wrapped[row - 1] {
+                row -= 1;
+                col = self.cols - 1;
+            } else {
+                return None;
+            }
+
+            if !self.get_cell(row, col)?.is_continuation {
+                return Some((row, col));
+            }
+        }
+    }
+
+    /// 自动换行：标记当前行为软换行后移动到下一行
+    pub fn wrap_line(&mut self) {
+        self.set_wrapped(self.cursor_row, true);
+        self.newline();
     }
 
     pub fn get_cell_index(&self, row: usize, col: usize) -> Option<usize> {
@@ -106,6 +779,7 @@ impl TerminalBuffer {
 
     pub fn get_cell_mut(&mut self, row: usize, col: usize) -> Option<&mut TerminalCell> {
         let idx = self.get_cell_index(row, col)?;
+        self.touch_row(row);
         Some(&mut self.cells[idx])
     }
 
@@ -118,41 +792,140 @@ impl TerminalBuffer {
         if row < self.rows && col < self.cols {
             self.cursor_row = row;
             self.cursor_col = col;
+            self.pending_wrap = false;
+        }
+    }
+
+    /// 光标前进到第 n 个制表位 (HT / CHT)，没有更多制表位时停在最后一列
+    pub fn tab_forward(&mut self, n: usize) {
+        for _ in 0..n {
+            self.cursor_col = (self.cursor_col + 1..self.cols)
+                .find(|&col| self.tab_stops[col])
+                .unwrap_or(self.cols.saturating_sub(1));
+        }
+        self.pending_wrap = false;
+    }
+
+    /// 光标后退到第 n 个制表位 (CBT)，没有更多制表位时停在行首
+    pub fn tab_backward(&mut self, n: usize) {
+        for _ in 0..n {
+            self.cursor_col = (0..self.cursor_col)
+                .rev()
+                .find(|&col| self.tab_stops[col])
+                .unwrap_or(0);
         }
+        self.pending_wrap = false;
+    }
+
+    /// 在光标所在列设置制表位 (HTS)
+    pub fn set_tab_stop(&mut self) {
+        if let Some(stop) = self.tab_stops.get_mut(self.cursor_col) {
+            *stop = true;
+        }
+    }
+
+    /// 清除光标所在列的制表位 (TBC 0)
+    pub fn clear_tab_stop(&mut self) {
+        if let Some(stop) = self.tab_stops.get_mut(self.cursor_col) {
+            *stop = false;
+        }
+    }
+
+    /// 清除所有制表位 (TBC 3)
+    pub fn clear_all_tab_stops(&mut self) {
+        self.tab_stops.fill(false);
     }
 
     pub fn clear(&mut self) {
         for cell in &mut self.cells {
             *cell = TerminalCell::default();
         }
+        self.wrapped.fill(false);
+        self.marks.fill(LineMark::default());
         self.cursor_row = 0;
         self.cursor_col = 0;
+        self.pending_wrap = false;
+        self.touch_all();
+    }
+
+    /// 滚动区域的上下边界（含）
+    pub fn scroll_region(&self) -> (usize, usize) {
+        (self.scroll_top, self.scroll_bottom)
+    }
+
+    /// 设置滚动区域 (DECSTBM)，边界无效时恢复为整屏
+    pub fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        if top < bottom && bottom < self.rows {
+            self.scroll_top = top;
+            self.scroll_bottom = bottom;
+        } else {
+            self.scroll_top = 0;
+            self.scroll_bottom = self.rows.saturating_sub(1);
+        }
     }
 
+    /// 换行：光标在滚动区域底部时区域内容上滚一行，只有整屏滚动时顶行才进入历史
     pub fn newline(&mut self) {
-        if self.cursor_row < self.rows - 1 {
+        self.pending_wrap = false;
+        if self.rows == 0 || self.cols == 0 {
+            return;
+        }
+        let full_screen = self.scroll_top == 0 && self.scroll_bottom + 1 == self.rows;
+        if self.cursor_row == self.scroll_bottom && !full_screen {
+            self.scroll_up(1);
+        } else if self.cursor_row + 1 < self.rows {
             self.cursor_row += 1;
-        } else {
-            // 将顶行推入历史回溯缓冲区
-            let top_line = self.cells[0..self.cols].to_vec();
-            self.history.push(top_line);
-            if self.history.len() > self.max_history {
-                self.history.remove(0);
+        } else if full_screen {
+            self.scroll_up(1);
+        }
+        // 光标在滚动区域以下的最后一行时不滚动；重置光标列到行首
+        self.cursor_col = 0;
+    }
+
+    /// 滚动区域整体上移 n 行，底部补空行
+    ///
+    /// 滚动区域为整屏时，移出的行推入历史回溯缓冲区（选中状态只用于显示，不随行保存）。
+    pub fn scroll_up(&mut self, n: usize) {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+        let n = n.min(bottom + 1 - top);
+        if n == 0 || self.rows == 0 || self.cols == 0 {
+            return;
+        }
+        let cols = self.cols;
+        let full_screen = top == 0 && bottom + 1 == self.rows;
+        if full_screen {
+            for row in 0..n {
+                let mut line = self.cells[row * cols..(row + 1) * cols].to_vec();
+                for cell in &mut line {
+                    cell.is_selected = false;
+                }
+                self.push_history(&line, self.wrapped[row], self.marks[row]);
             }
+        }
 
-            // 屏幕整体向上滚动
-            self.cells.drain(0..self.cols);
-            self.cells.extend(vec![TerminalCell::default(); self.cols]);
+        let first_blank = bottom + 1 - n;
+        self.cells[top * cols..(bottom + 1) * cols].rotate_left(n * cols);
+        self.cells[first_blank * cols..(bottom + 1) * cols].fill(TerminalCell::default());
+        self.wrapped[top..=bottom].rotate_left(n);
+        self.wrapped[first_blank..=bottom].fill(false);
+        self.marks[top..=bottom].rotate_left(n);
+        self.marks[first_blank..=bottom].fill(LineMark::default());
+        if full_screen {
+            self.touch_all();
+        } else {
+            for row in top..=bottom {
+                self.touch_row(row);
+            }
         }
-        // 重置光标列到行首
-        self.cursor_col = 0;
     }
 
     pub fn carriage_return(&mut self) {
         self.cursor_col = 0;
+        self.pending_wrap = false;
     }
 
     pub fn backspace(&mut self) {
+        self.pending_wrap = false;
         if self.cursor_col > 0 {
             self.cursor_col -= 1;
         }
@@ -194,35 +967,15 @@ impl TerminalBuffer {
         // 先清除旧的选择显示
         self.clear_selection_display();
 
-        if let Some(ref selection) = self.selection {
-            let (start_row, start_col, end_row, end_col) = self.normalize_selection(selection);
-            let history_len = self.history.len();
-
-            for r in start_row..=end_row {
-                let col_start = if r == start_row { start_col } else { 0 };
-                let col_end = if r == end_row {
-                    end_col
-                } else {
-                    self.cols.saturating_sub(1)
-                };
-
-                if r < history_len {
-                    // 处理历史记录
-                    if let Some(line) = self.history.get_mut(r) {
-                        for c in col_start..=col_end.min(line.len().saturating_sub(1)) {
-                            line[c].is_selected = true;
-                        }
-                    }
-                } else {
-                    // 处理当前屏幕
-                    let screen_r = r - history_len;
-                    if screen_r < self.rows {
-                        for c in col_start..=col_end.min(self.cols.saturating_sub(1)) {
-                            if let Some(cell) = self.get_cell_mut(screen_r, c) {
-                                cell.is_selected = true;
-                            }
-                        }
-                    }
+        // 历史行的选中状态在读取时由 line 标注，这里只处理当前屏幕
+        let history_len = self.history.len();
+        for screen_r in 0..self.rows {
+            let Some((col_start, col_end)) = self.selected_cols(history_len + screen_r) else {
+                continue;
+            };
+            for c in col_start..=col_end.min(self.cols.saturating_sub(1)) {
+                if let Some(cell) = self.get_cell_mut(screen_r, c) {
+                    cell.is_selected = true;
                 }
             }
         }
@@ -233,12 +986,23 @@ impl TerminalBuffer {
         for cell in &mut self.cells {
             cell.is_selected = false;
         }
-        // 清除历史记录中的选中状态
-        for line in &mut self.history {
-            for cell in line {
-                cell.is_selected = false;
-            }
+        self.touch_all();
+    }
+
+    /// 某一行（合并行号）被选中的列范围（包含两端）
+    fn selected_cols(&self, row: usize) -> Option<(usize, usize)> {
+        let selection = self.selection.as_ref()?;
+        let (start_row, start_col, end_row, end_col) = self.normalize_selection(selection);
+        if !(start_row..=end_row).contains(&row) {
+            return None;
         }
+        let col_start = if row == start_row { start_col } else { 0 };
+        let col_end = if row == end_row {
+            end_col
+        } else {
+            self.cols.saturating_sub(1)
+        };
+        Some((col_start, col_end))
     }
 
     /// 标准化选择范围（确保start <= end）
@@ -270,51 +1034,822 @@ impl TerminalBuffer {
 
     /// 获取选中的文本
     pub fn get_selected_text(&self) -> Option<String> {
-        if let Some(ref selection) = self.selection {
-            let (start_row, start_col, end_row, end_col) = self.normalize_selection(selection);
-            let mut result = String::new();
-            let history_len = self.history.len();
-
-            for r in start_row..=end_row {
-                let col_start = if r == start_row { start_col } else { 0 };
-                let col_end = if r == end_row {
-                    end_col
-                } else {
-                    self.cols.saturating_sub(1)
-                };
+        self.selection
+            .as_ref()
+            .and_then(|selection| self.text_in_range(selection))
+    }
 
-                if r < history_len {
-                    // 从历史记录中获取文本
-                    if let Some(line) = self.history.get(r) {
-                        for c in col_start..=col_end.min(line.len().saturating_sub(1)) {
-                            result.push(line[c].character);
-                        }
+    /// 获取指定范围内的文本
+    pub fn text_in_range(&self, selection: &SelectionRange) -> Option<String> {
+        let (start_row, start_col, end_row, end_col) = self.normalize_selection(selection);
+        let mut result = String::new();
+        let history_len = self.history.len();
+
+        for r in start_row..=end_row {
+            let col_start = if r == start_row { start_col } else { 0 };
+            let col_end = if r == end_row {
+                end_col
+            } else {
+                self.cols.saturating_sub(1)
+            };
+
+            if r < history_len {
+                // 从历史记录中获取文本
+                if let Some(line) = self.history.cells(r) {
+                    for cell in line
+                        .iter()
+                        .take(col_end + 1)
+                        .skip(col_start)
+                        .filter(|cell| !cell.is_continuation)
+                    {
+                        result.push_str(&cell.text());
                     }
-                } else {
-                    // 从当前屏幕获取文本
-                    let screen_r = r - history_len;
-                    if screen_r < self.rows {
-                        for c in col_start..=col_end.min(self.cols.saturating_sub(1)) {
-                            if let Some(cell) = self.get_cell(screen_r, c) {
-                                result.push(cell.character);
-                            }
+                }
+            } else {
+                // 从当前屏幕获取文本
+                let screen_r = r - history_len;
+                if screen_r < self.rows {
+                    for c in col_start..=col_end.min(self.cols.saturating_sub(1)) {
+                        if let Some(cell) = self
+                            .get_cell(screen_r, c)
+                            .filter(|cell| !cell.is_continuation)
+                        {
+                            result.push_str(&cell.text());
                         }
                     }
                 }
+            }
 
-                // 在行尾添加换行符，除非是最后一行
-                if r < end_row {
-                    result.push('\n');
+            // 在行尾添加换行符，除非是最后一行
+            if r < end_row {
+                result.push('\n');
+            }
+        }
+
+        if result.is_empty() {
+            None
+        } else {
+            Some(result)
+        }
+    }
+
+    /// 按合并行号获取行标记
+    pub fn line_mark(&self, row: usize) -> Option<&LineMark> {
+        let history_len = self.history.len();
+        if row < history_len {
+            self.history.mark(row)
+        } else {
+            self.marks.get(row - history_len)
+        }
+    }
+
+    /// 记录 shell 集成标记 (OSC 133)
+    ///
+    /// A/B/C 标记在光标所在行；D 的退出码记录到光标之上最近的提示符行。
+    pub fn set_line_mark(&mut self, shell_mark: ShellMark) {
+        let cursor_row = self.cursor_row;
+        let Some(mark) = self.marks.get_mut(cursor_row) else {
+            return;
+        };
+
+        match shell_mark {
+            ShellMark::PromptStart => mark.prompt = true,
+            ShellMark::CommandStart => mark.command = true,
+            ShellMark::OutputStart => mark.output = true,
+            ShellMark::CommandEnd(exit_code) => {
+                let history_len = self.history.len();
+                let prompt_row = self.previous_prompt(history_len + cursor_row + 1);
+                let mark = match prompt_row {
+                    Some(row) if row < history_len => self.history.mark_mut(row),
+                    Some(row) => self.marks.get_mut(row - history_len),
+                    None => None,
+                };
+                if let Some(mark) = mark {
+                    mark.exit_code = exit_code;
                 }
             }
+        }
+    }
 
-            if result.is_empty() {
-                None
-            } else {
-                Some(result)
+    /// 合并行号小于 row 的最近一个提示符行
+    pub fn previous_prompt(&self, row: usize) -> Option<usize> {
+        (0..row)
+            .rev()
+            .find(|&r| self.line_mark(r).is_some_and(|mark| mark.prompt))
+    }
+
+    /// 合并行号大于 row 的下一个提示符行
+    pub fn next_prompt(&self, row: usize) -> Option<usize> {
+        (row + 1..self.history.len() + self.marks.len())
+            .find(|&r| self.line_mark(r).is_some_and(|mark| mark.prompt))
+    }
+
+    /// 最近一条命令输出所在的行范围（合并行号，包含两端）
+    ///
+    /// 光标所在的提示符还没有输出时（等待输入）取上一条命令；命令仍在运行时取到光标所在行。
+    /// 没有 C 标记的 shell 以命令输入的下一行作为输出起点。
+    pub fn last_command_output(&self) -> Option<(usize, usize)> {
+        let cursor = self.history.len() + self.cursor_row;
+        let mut prompt = self.previous_prompt(cursor + 1)?;
+        let mut end = cursor;
+
+        // 从提示符的下一行开始查找输出标记，直到下一个提示符所在行
+        // （没有输出的命令，其 C 标记与下一个提示符在同一行）
+        let output_row = |from: usize, to: usize| {
+            (from..=to).find(|&r| self.line_mark(r).is_some_and(|mark| mark.output))
+        };
+        if output_row(prompt + 1, end).is_none() {
+            end = prompt.checked_sub(1)?;
+            prompt = self.previous_prompt(prompt)?;
+        }
+
+        let start = output_row(prompt + 1, end + 1)
+            .or_else(|| {
+                (prompt..=end)
+                    .find(|&r| self.line_mark(r).is_some_and(|mark| mark.command))
+                    .map(|r| r + 1)
+            })
+            .unwrap_or(prompt + 1);
+
+        (start <= end).then_some((start, end))
+    }
+
+    /// 按合并行号（先历史后屏幕）获取一行单元格
+    ///
+    /// 历史行需要从紧凑格式解码，并在此标注选中状态。
+    pub fn line(&self, row: usize) -> Option<Cow<'_, [TerminalCell]>> {
+        let history_len = self.history.len();
+        if row < history_len {
+            let mut cells = self.history.cells(row)?;
+            if let Some((col_start, col_end)) = self.selected_cols(row) {
+                for cell in cells.iter_mut().take(col_end + 1).skip(col_start) {
+                    cell.is_selected = true;
+                }
             }
+            Some(Cow::Owned(cells))
+        } else if row - history_len < self.rows {
+            Some(Cow::Borrowed(self.row_cells(row - history_len)))
         } else {
             None
         }
     }
+
+    /// 清理不再被屏幕和历史中任何单元格引用的超链接
+    ///
+    /// `keep` 为仿真器仍持有、但可能尚未写入单元格的超链接。
+    pub fn prune_hyperlinks(&mut self, keep: impl IntoIterator<Item = HyperlinkId>) {
+        let live: HashSet<HyperlinkId> = self
+            .cells
+            .iter()
+            .filter_map(|cell| cell.hyperlink)
+            .chain(self.history.hyperlinks())
+            .chain(keep)
+            .collect();
+        self.hyperlinks.retain(&live);
+    }
+
+    /// 合并行号对应的行是否软换行到下一行
+    fn line_wrapped(&self, row: usize) -> bool {
+        let history_len = self.history.len();
+        if row < history_len {
+            self.history.is_wrapped(row)
+        } else {
+            self.wrapped
+                .get(row - history_len)
+                .copied()
+                .unwrap_or(false)
+        }
+    }
+
+    /// 获取指定位置的链接
+    ///
+    /// 优先使用 OSC 8 超链接，否则识别该行文本中的 URL、文件路径和 IP:端口。
+    /// 软换行的行先拼接成逻辑行再识别，返回的范围截取到所在行。
+    pub fn link_at(&self, row: usize, col: usize) -> Option<Link> {
+        let cells = self.line(row)?;
+        let cell = cells.get(col)?;
+
+        if let Some(id) = cell.hyperlink {
+            let hyperlink = self.hyperlinks.get(id)?;
+            let start_col = cells[..col]
+                .iter()
+                .rposition(|cell| cell.hyperlink != Some(id))
+                .map_or(0, |i| i + 1);
+            let end_col = cells[col..]
+                .iter()
+                .position(|cell| cell.hyperlink != Some(id))
+                .map_or(cells.len(), |i| col + i);
+            return Some(Link {
+                target: hyperlink.uri.clone(),
+                kind: LinkKind::Url,
+                row,
+                start_col,
+                end_col,
+            });
+        }
+
+        let total = self.history.len() + self.rows;
+        let mut first = row;
+        while first > 0 && row - first < MAX_LINK_ROWS && self.line_wrapped(first - 1) {
+            first -= 1;
+        }
+        let mut last = row;
+        while last + 1 < total && last - row < MAX_LINK_ROWS && self.line_wrapped(last) {
+            last += 1;
+        }
+        if first == last {
+            return detect_links(row, &cells)
+                .into_iter()
+                .find(|link| (link.start_col..link.end_col).contains(&col));
+        }
+
+        // 拼接逻辑行，记录所在行在其中的起始位置
+        let mut logical = Vec::new();
+        let mut offset = 0;
+        for r in first..=last {
+            if r == row {
+                offset = logical.len();
+                logical.extend_from_slice(&cells);
+            } else {
+                logical.extend_from_slice(&self.line(r)?);
+            }
+        }
+
+        let col = offset + col;
+        let link = detect_links(row, &logical)
+            .into_iter()
+            .find(|link| (link.start_col..link.end_col).contains(&col))?;
+        Some(Link {
+            start_col: link.start_col.max(offset) - offset,
+            end_col: link.end_col.min(offset + cells.len()) - offset,
+            ..link
+        })
+    }
+}
+
+/// 识别跨软换行的链接时，向前、向后最多拼接的行数
+const MAX_LINK_ROWS: usize = 8;
+
+/// 自动识别链接的正则：URL、IP:端口、以 / ~/ ./ ../ 开头的文件路径
+static LINK_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r#"(?P<url>[a-zA-Z][a-zA-Z0-9+.\-]*://[^\s<>"'`]+|www\.[^\s<>"'`]+)"#,
+        r"|(?P<host>\b(?:\d{1,3}\.){3}\d{1,3}:\d{1,5}\b)",
+        r"|(?P<path>(?:~|\.{1,2})?/[\w.\-+@~/]+)",
+    ))
+    .unwrap()
+});
+
+/// 识别一行单元格中的纯文本链接
+fn detect_links(row: usize, cells: &[TerminalCell]) -> Vec<Link> {
+    // 拼接行文本，并记录每个字节所在的列
+    let mut text = String::new();
+    let mut byte_cols = Vec::new();
+    for (col, cell) in cells.iter().enumerate() {
+        if cell.is_continuation {
+            continue;
+        }
+        let cell_text = cell.text();
+        byte_cols.extend(std::iter::repeat_n(col, cell_text.len()));
+        text.push_str(&cell_text);
+    }
+    byte_cols.push(cells.len());
+
+    let mut links = Vec::new();
+    for caps in LINK_PATTERN.captures_iter(&text) {
+        let (m, kind) = if let Some(m) = caps.name("url") {
+            (m, LinkKind::Url)
+        } else if let Some(m) = caps.name("host") {
+            (m, LinkKind::HostPort)
+        } else if let Some(m) = caps.name("path") {
+            // 路径前紧跟单词字符时（如 and/or）不视为路径
+            let preceded_by_word = text[..m.start()]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || "_.-~".contains(c));
+            if preceded_by_word {
+                continue;
+            }
+            (m, LinkKind::FilePath)
+        } else {
+            continue;
+        };
+
+        // 去掉句末标点以及不成对的右括号
+        let mut matched = m.as_str();
+        loop {
+            let trimmed = matched.trim_end_matches(['.', ',', ':', ';', '!', '?', '\'', '"']);
+            let trimmed = match trimmed.strip_suffix(')') {
+                Some(rest) if !rest.contains('(') => rest,
+                _ => trimmed,
+            };
+            if trimmed.len() == matched.len() {
+                break;
+            }
+            matched = trimmed;
+        }
+        if matched.is_empty() {
+            continue;
+        }
+
+        let target = if kind == LinkKind::Url && matched.starts_with("www.") {
+            format!("http://{}", matched)
+        } else {
+            matched.to_string()
+        };
+
+        links.push(Link {
+            target,
+            kind,
+            row,
+            start_col: byte_cols[m.start()],
+            end_col: byte_cols[m.start() + matched.len()],
+        });
+    }
+
+    links
+}
+
+/// 默认制表位：每8列一个
+fn default_tab_stops(cols: usize) -> Vec<bool> {
+    (0..cols).map(|col| col % 8 == 0).collect()
+}
+
+/// 将一条逻辑行按指定宽度折成多行
+///
+/// 宽字符及其延续位不会被拆到两行，放不下时在行尾补空白。
+/// `cursor_offset` 为光标在逻辑行中的偏移，返回其在折行后的（行, 列）。
+fn rewrap_line(
+    line: Vec<TerminalCell>,
+    cols: usize,
+    cursor_offset: Option<usize>,
+) -> (Vec<Vec<TerminalCell>>, Option<(usize, usize)>) {
+    let line_len = line.len();
+    let spans: Vec<usize> = (0..line_len)
+        .map(|i| {
+            1 + line[i + 1..]
+                .iter()
+                .take_while(|cell| cell.is_continuation)
+                .count()
+        })
+        .collect();
+    let mut rows: Vec<Vec<TerminalCell>> = vec![Vec::with_capacity(cols)];
+    let mut cursor = None;
+
+    for (index, cell) in line.into_iter().enumerate() {
+        let row_len = rows[rows.len() - 1].len();
+        let span = if cell.is_continuation {
+            1
+        } else {
+            spans[index]
+        };
+        if row_len == cols || (span <= cols && row_len + span > cols) {
+            rows.last_mut()
+                .unwrap()
+                .resize(cols, TerminalCell::default());
+            rows.push(Vec::with_capacity(cols));
+        }
+        if cursor_offset == Some(index) {
+            cursor = Some((rows.len() - 1, rows[rows.len() - 1].len()));
+        }
+        rows.last_mut().unwrap().push(cell);
+    }
+
+    // 光标位于文本末尾之后
+    if let Some(offset) = cursor_offset.filter(|&offset| offset >= line_len) {
+        let last = rows.len() - 1;
+        let pos = rows[last].len() + (offset - line_len);
+        cursor = Some((last + pos / cols, pos % cols));
+        while rows.len() <= last + pos / cols {
+            rows.push(Vec::with_capacity(cols));
+        }
+    }
+
+    for row in &mut rows {
+        row.resize(cols, TerminalCell::default());
+    }
+
+    (rows, cursor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::scrollback::ScrollbackConfig;
+
+    fn write(buffer: &mut TerminalBuffer, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\n' => buffer.newline(),
+                c => {
+                    let (row, col) = (buffer.cursor_row, buffer.cursor_col);
+                    buffer.get_cell_mut(row, col).unwrap().character = c;
+                    if col + 1 < buffer.cols {
+                        buffer.cursor_col += 1;
+                    } else {
+                        buffer.wrap_line();
+                    }
+                }
+            }
+        }
+    }
+
+    fn row_text(cells: &[TerminalCell]) -> String {
+        cells
+            .iter()
+            .map(|cell| cell.character)
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+
+    fn screen_lines(buffer: &TerminalBuffer) -> Vec<String> {
+        (0..buffer.rows)
+            .map(|r| row_text(buffer.row_cells(r)))
+            .collect()
+    }
+
+    #[test]
+    fn test_reflow_narrower_and_back() {
+        let mut buffer = TerminalBuffer::new(4, 10);
+        write(&mut buffer, "abcdefgh\n$ ");
+
+        buffer.resize(4, 4);
+        assert_eq!(screen_lines(&buffer), vec!["abcd", "efgh", "$", ""]);
+        assert_eq!(buffer.wrapped, vec![true, false, false, false]);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (2, 2));
+
+        buffer.resize(4, 10);
+        assert_eq!(screen_lines(&buffer), vec!["abcdefgh", "$", "", ""]);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (1, 2));
+    }
+
+    #[test]
+    fn test_reflow_joins_soft_wrapped_rows() {
+        let mut buffer = TerminalBuffer::new(3, 4);
+        write(&mut buffer, "abcdefg");
+        assert!(buffer.wrapped[0]);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (1, 3));
+
+        buffer.resize(3, 8);
+        assert_eq!(screen_lines(&buffer), vec!["abcdefg", "", ""]);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (0, 7));
+    }
+
+    #[test]
+    fn test_reflow_moves_overflow_into_history() {
+        let mut buffer = TerminalBuffer::new(3, 6);
+        write(&mut buffer, "one\ntwo\nthreeX");
+
+        buffer.resize(3, 3);
+        let history: Vec<String> = (0..buffer.history.len())
+            .map(|i| row_text(&buffer.history.cells(i).unwrap()))
+            .collect();
+        assert_eq!(history, vec!["one", "two"]);
+        assert_eq!(screen_lines(&buffer), vec!["thr", "eeX", ""]);
+        assert_eq!(buffer.wrapped, vec![true, true, false]);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (2, 0));
+
+        // 加宽后历史行重新回到屏幕
+        buffer.resize(3, 6);
+        let history: Vec<String> = (0..buffer.history.len())
+            .map(|i| row_text(&buffer.history.cells(i).unwrap()))
+            .collect();
+        assert_eq!(history, vec!["one"]);
+        assert_eq!(screen_lines(&buffer), vec!["two", "threeX", ""]);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (2, 0));
+    }
+
+    #[test]
+    fn test_reflow_keeps_wide_chars_whole() {
+        let mut buffer = TerminalBuffer::new(2, 6);
+        write(&mut buffer, "ab");
+        let col = buffer.cursor_col;
+        buffer.get_cell_mut(0, col).unwrap().character = '中';
+        buffer.get_cell_mut(0, col + 1).unwrap().is_continuation = true;
+
+        buffer.resize(2, 3);
+        assert_eq!(buffer.get_cell(0, 2).unwrap().character, ' ');
+        assert_eq!(buffer.get_cell(1, 0).unwrap().character, '中');
+        assert!(buffer.get_cell(1, 1).unwrap().is_continuation);
+        assert!(buffer.wrapped[0]);
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        let mut buffer = TerminalBuffer::new(2, 20);
+        buffer.tab_forward(1);
+        assert_eq!(buffer.cursor_col, 8);
+        buffer.tab_forward(2);
+        assert_eq!(buffer.cursor_col, 19);
+
+        buffer.set_cursor(0, 3);
+        buffer.set_tab_stop();
+        buffer.carriage_return();
+        buffer.tab_forward(1);
+        assert_eq!(buffer.cursor_col, 3);
+
+        buffer.set_cursor(0, 12);
+        buffer.tab_backward(1);
+        assert_eq!(buffer.cursor_col, 8);
+
+        buffer.clear_all_tab_stops();
+        buffer.carriage_return();
+        buffer.tab_forward(1);
+        assert_eq!(buffer.cursor_col, 19);
+
+        // 加宽后新增列使用默认制表位
+        buffer.resize(2, 30);
+        buffer.carriage_return();
+        buffer.tab_forward(1);
+        assert_eq!(buffer.cursor_col, 24);
+    }
+
+    #[test]
+    fn test_rows_only_resize_keeps_cursor_line() {
+        let mut buffer = TerminalBuffer::new(4, 8);
+        write(&mut buffer, "1\n2\n3\n4");
+
+        buffer.resize(2, 8);
+        assert_eq!(screen_lines(&buffer), vec!["3", "4"]);
+        assert_eq!(buffer.history.len(), 2);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (1, 1));
+
+        // 增加行数时从历史取回，软换行标记随行保留
+        buffer.wrapped[0] = true;
+        buffer.resize(3, 8);
+        assert_eq!(screen_lines(&buffer), vec!["2", "3", "4"]);
+        assert_eq!(buffer.wrapped, vec![false, true, false]);
+        assert_eq!(buffer.history.len(), 1);
+        buffer.resize(6, 8);
+        assert_eq!(screen_lines(&buffer), vec!["1", "2", "3", "4", "", ""]);
+        assert_eq!(buffer.history.len(), 0);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (3, 1));
+    }
+
+    #[test]
+    fn test_detect_links() {
+        let mut buffer = TerminalBuffer::new(2, 60);
+        write(
+            &mut buffer,
+            "see https://example.com/a_(b). at /var/log/x, 10.0.0.1:22",
+        );
+
+        let links = detect_links(0, buffer.row_cells(0));
+        let found: Vec<(&str, LinkKind, usize, usize)> = links
+            .iter()
+            .map(|link| {
+                (
+                    link.target.as_str(),
+                    link.kind,
+                    link.start_col,
+                    link.end_col,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("https://example.com/a_(b)", LinkKind::Url, 4, 29),
+                ("/var/log/x", LinkKind::FilePath, 34, 44),
+                ("10.0.0.1:22", LinkKind::HostPort, 46, 57),
+            ]
+        );
+
+        assert_eq!(
+            buffer.link_at(0, 10).map(|link| link.target),
+            Some("https://example.com/a_(b)".to_string())
+        );
+        assert_eq!(buffer.link_at(0, 1), None);
+
+        // 单词中间的斜杠不是路径
+        let mut buffer = TerminalBuffer::new(1, 20);
+        write(&mut buffer, "and/or ./run.sh");
+        let links = detect_links(0, buffer.row_cells(0));
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].target, "./run.sh");
+    }
+
+    #[test]
+    fn test_line_marks_survive_history_trimming() {
+        let mut buffer = TerminalBuffer::new(3, 10);
+        buffer.history.set_config(ScrollbackConfig {
+            max_lines: 2,
+            ..Default::default()
+        });
+
+        for i in 0..3 {
+            buffer.set_line_mark(ShellMark::PromptStart);
+            write(&mut buffer, &format!("$ cmd{}\n", i));
+            buffer.set_line_mark(ShellMark::OutputStart);
+            write(&mut buffer, &format!("out{}\n", i));
+            buffer.set_line_mark(ShellMark::CommandEnd(Some(i)));
+        }
+        buffer.set_line_mark(ShellMark::PromptStart);
+
+        // 共 7 行，历史只保留 2 行，第一条命令已被裁掉
+        assert_eq!(buffer.history.len(), 2);
+        let prompts: Vec<usize> = (0..5)
+            .filter(|&r| buffer.line_mark(r).is_some_and(|mark| mark.prompt))
+            .collect();
+        assert_eq!(prompts, vec![0, 2, 4]);
+        assert_eq!(buffer.line_mark(0).unwrap().exit_code, Some(1));
+        assert_eq!(buffer.line_mark(2).unwrap().exit_code, Some(2));
+        assert_eq!(buffer.line_mark(4).unwrap().exit_code, None);
+
+        assert_eq!(buffer.previous_prompt(4), Some(2));
+        assert_eq!(buffer.next_prompt(0), Some(2));
+        assert_eq!(buffer.next_prompt(4), None);
+        assert_eq!(buffer.last_command_output(), Some((3, 3)));
+
+        // 重排后标记仍在对应的行上（"$ cmd1" 被折成两行后被裁掉）
+        buffer.resize(3, 5);
+        let prompt_lines: Vec<String> = (0..buffer.history.len() + buffer.rows)
+            .filter(|&r| buffer.line_mark(r).is_some_and(|mark| mark.prompt))
+            .map(|r| {
+                buffer.line(r).unwrap()[..2]
+                    .iter()
+                    .map(|cell| cell.character)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(prompt_lines, vec!["$ ", "  "]);
+    }
+
+    #[test]
+    fn test_selection_spans_history_and_screen() {
+        let mut buffer = TerminalBuffer::new(2, 5);
+        write(&mut buffer, "one\ntwo\nthre");
+        assert_eq!(buffer.history.len(), 1);
+
+        buffer.start_selection(0, 1);
+        buffer.update_selection(1, 1);
+        let selected = |buffer: &TerminalBuffer, row: usize| -> Vec<bool> {
+            buffer
+                .line(row)
+                .unwrap()
+                .iter()
+                .map(|cell| cell.is_selected)
+                .collect()
+        };
+        assert_eq!(selected(&buffer, 0), vec![false, true, true, true, true]);
+        assert_eq!(selected(&buffer, 1), vec![true, true, false, false, false]);
+        assert_eq!(buffer.get_selected_text().unwrap(), "ne  \ntw");
+
+        // 选中的屏幕行滚入历史后按选择范围标注，与滚动前一致
+        write(&mut buffer, "\n");
+        assert_eq!(selected(&buffer, 1), vec![true, true, false, false, false]);
+        assert!(selected(&buffer, 2).iter().all(|&s| !s));
+    }
+
+    #[test]
+    fn test_last_command_output_while_running() {
+        let mut buffer = TerminalBuffer::new(6, 10);
+        buffer.set_line_mark(ShellMark::PromptStart);
+        write(&mut buffer, "$ ls\n");
+        buffer.set_line_mark(ShellMark::OutputStart);
+        write(&mut buffer, "a\nb");
+        assert_eq!(buffer.last_command_output(), Some((1, 2)));
+
+        // 没有输出的命令
+        write(&mut buffer, "\n");
+        buffer.set_line_mark(ShellMark::CommandEnd(Some(0)));
+        buffer.set_line_mark(ShellMark::PromptStart);
+        write(&mut buffer, "$ true\n");
+        buffer.set_line_mark(ShellMark::OutputStart);
+        buffer.set_line_mark(ShellMark::CommandEnd(Some(0)));
+        buffer.set_line_mark(ShellMark::PromptStart);
+        assert_eq!(buffer.last_command_output(), None);
+    }
+
+    #[test]
+    fn test_hyperlink_table_interns() {
+        let mut table = HyperlinkTable::default();
+        let link = Hyperlink {
+            id: None,
+            uri: "https://example.com".to_string(),
+        };
+        let first = table.intern(link.clone());
+        assert_eq!(table.intern(link.clone()), first);
+        assert_ne!(
+            table.intern(Hyperlink {
+                id: Some("1".to_string()),
+                ..link
+            }),
+            first
+        );
+        assert_eq!(table.get(first).unwrap().uri, "https://example.com");
+    }
+
+    #[test]
+    fn test_prune_hyperlinks_keeps_referenced_entries() {
+        let mut buffer = TerminalBuffer::new(2, 4);
+        let link = |uri: &str| Hyperlink {
+            id: None,
+            uri: uri.to_string(),
+        };
+        let on_screen = buffer.hyperlinks.intern(link("https://a"));
+        let dropped = buffer.hyperlinks.intern(link("https://b"));
+        let in_history = buffer.hyperlinks.intern(link("https://c"));
+        let pending = buffer.hyperlinks.intern(link("https://d"));
+
+        buffer.get_cell_mut(0, 0).unwrap().hyperlink = Some(on_screen);
+        let mut cells = vec![TerminalCell::default(); 4];
+        cells[1].hyperlink = Some(in_history);
+        buffer.push_history(&cells, false, LineMark::default());
+
+        buffer.prune_hyperlinks(Some(pending));
+        assert_eq!(buffer.hyperlinks.len(), 3);
+        assert!(buffer.hyperlinks.get(dropped).is_none());
+        assert_eq!(buffer.hyperlinks.get(in_history).unwrap().uri, "https://c");
+        assert_eq!(buffer.hyperlinks.get(pending).unwrap().uri, "https://d");
+
+        // 清理出的索引被复用
+        assert_eq!(buffer.hyperlinks.intern(link("https://e")), dropped);
+    }
+
+    #[test]
+    fn test_link_at_joins_soft_wrapped_rows() {
+        let mut buffer = TerminalBuffer::new(2, 10);
+        write(&mut buffer, "go https://example.com/path!");
+        assert_eq!(buffer.history.len(), 1);
+
+        let url = "https://example.com/path";
+        let link = buffer.link_at(0, 5).unwrap();
+        assert_eq!(link.target, url);
+        assert_eq!((link.row, link.start_col, link.end_col), (0, 3, 10));
+
+        let link = buffer.link_at(1, 4).unwrap();
+        assert_eq!(link.target, url);
+        assert_eq!((link.row, link.start_col, link.end_col), (1, 0, 10));
+
+        let link = buffer.link_at(2, 2).unwrap();
+        assert_eq!(link.target, url);
+        assert_eq!((link.row, link.start_col, link.end_col), (2, 0, 7));
+        assert_eq!(buffer.link_at(2, 7), None);
+    }
+
+    #[test]
+    fn test_line_versions_track_changes() {
+        let mut buffer = TerminalBuffer::new(3, 4);
+        let before: Vec<u64> = (0..3).map(|row| buffer.line_version(row)).collect();
+
+        buffer.get_cell_mut(1, 0).unwrap().character = 'x';
+        assert_eq!(buffer.line_version(0), before[0]);
+        assert_ne!(buffer.line_version(1), before[1]);
+        assert_eq!(buffer.line_version(2), before[2]);
+
+        // 快照沿用同一标识与计数
+        let snapshot = buffer.clone();
+        assert_eq!(snapshot.id(), buffer.id());
+        assert_eq!(snapshot.line_version(1), buffer.line_version(1));
+        assert_ne!(TerminalBuffer::new(3, 4).id(), buffer.id());
+
+        // 滚动后所有行（包括新进入历史的行）都已修改
+        let version = buffer.line_version(2);
+        buffer.cursor_row = 2;
+        buffer.newline();
+        assert_eq!(buffer.history.len(), 1);
+        assert!((0..4).all(|row| buffer.line_version(row) > version));
+    }
+
+    #[test]
+    fn test_scroll_display_clamps_to_history() {
+        let mut buffer = TerminalBuffer::new(2, 4);
+        write(&mut buffer, "a\nb\nc\nd\ne");
+        assert_eq!(buffer.history.len(), 3);
+        assert_eq!(buffer.display_top(), 3);
+
+        buffer.scroll_display(Scroll::Delta(2));
+        assert_eq!(buffer.display_offset(), 2);
+        assert_eq!(buffer.display_top(), 1);
+
+        buffer.scroll_display(Scroll::PageUp);
+        assert_eq!(buffer.display_offset(), 3);
+        buffer.scroll_display(Scroll::Delta(-10));
+        assert_eq!(buffer.display_offset(), 0);
+
+        buffer.scroll_display(Scroll::ToRow(1));
+        assert_eq!(buffer.display_top(), 1);
+        buffer.scroll_display(Scroll::Bottom);
+        assert_eq!(buffer.display_top(), 3);
+    }
+
+    #[test]
+    fn test_scrolled_viewport_stays_on_content() {
+        let mut buffer = TerminalBuffer::new(2, 4);
+        write(&mut buffer, "a\nb\nc");
+        buffer.scroll_display(Scroll::Top);
+        assert_eq!(row_text(&buffer.line(buffer.display_top()).unwrap()), "a");
+
+        // 新的输出进入历史后视口仍显示原来的行
+        write(&mut buffer, "\nd\ne");
+        assert_eq!(row_text(&buffer.line(buffer.display_top()).unwrap()), "a");
+
+        // 跟随输出时视口始终在底部
+        buffer.scroll_display(Scroll::Bottom);
+        write(&mut buffer, "\nf");
+        assert_eq!(buffer.display_offset(), 0);
+        assert_eq!(row_text(&buffer.line(buffer.display_top()).unwrap()), "e");
+    }
 }
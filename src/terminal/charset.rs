@@ -0,0 +1,170 @@
+//! 字符集切换
+//! 实现 G0-G3 字符集指定 (SCS) 与 SI/SO 等移位控制，支持 DEC 特殊图形字符集（制表符）
+
+/// 可指定到 G0-G3 的字符集
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Charset {
+    /// US ASCII (ESC ( B)
+    #[default]
+    Ascii,
+    /// 英国字符集 (ESC ( A)，`#` 显示为 `£`
+    Uk,
+    /// DEC 特殊图形字符集 (ESC ( 0)，用于绘制表格线
+    DecSpecialGraphics,
+}
+
+impl Charset {
+    /// 根据 SCS 序列的结束字节获取字符集
+    pub fn from_designator(byte: u8) -> Option<Self> {
+        match byte {
+            b'B' => Some(Charset::Ascii),
+            b'A' => Some(Charset::Uk),
+            b'0' => Some(Charset::DecSpecialGraphics),
+            _ => None,
+        }
+    }
+
+    /// 将字符映射为该字符集下的显示字符
+    pub fn map(self, c: char) -> char {
+        match self {
+            Charset::Ascii => c,
+            Charset::Uk => {
+                if c == '#' {
+                    '£'
+                } else {
+                    c
+                }
+            }
+            Charset::DecSpecialGraphics => dec_special_graphics(c),
+        }
+    }
+}
+
+/// DEC 特殊图形字符映射（0x5f-0x7e）
+fn dec_special_graphics(c: char) -> char {
+    match c {
+        '_' => ' ',
+        '`' => '◆',
+        'a' => '▒',
+        'b' => '␉',
+        'c' => '␌',
+        'd' => '␍',
+        'e' => '␊',
+        'f' => '°',
+        'g' => '±',
+        'h' => '␤',
+        'i' => '␋',
+        'j' => '┘',
+        'k' => '┐',
+        'l' => '┌',
+        'm' => '└',
+        'n' => '┼',
+        'o' => '⎺',
+        'p' => '⎻',
+        'q' => '─',
+        'r' => '⎼',
+        's' => '⎽',
+        't' => '├',
+        'u' => '┤',
+        'v' => '┴',
+        'w' => '┬',
+        'x' => '│',
+        'y' => '≤',
+        'z' => '≥',
+        '{' => 'π',
+        '|' => '≠',
+        '}' => '£',
+        '~' => '·',
+        _ => c,
+    }
+}
+
+/// 字符集状态：G0-G3 的指定以及当前映射到 GL 的字符集
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CharsetState {
+    /// G0-G3 对应的字符集
+    slots: [Charset; 4],
+    /// 当前锁定移位到 GL 的槽位
+    active: usize,
+    /// 单次移位 (SS2/SS3) 指定的槽位，只作用于下一个字符
+    single_shift: Option<usize>,
+}
+
+impl CharsetState {
+    /// 指定 G0-G3 中某个槽位的字符集
+    pub fn designate(&mut self, slot: usize, charset: Charset) {
+        if let Some(target) = self.slots.get_mut(slot) {
+            *target = charset;
+        }
+    }
+
+    /// 锁定移位 (SI: G0, SO: G1, LS2: G2, LS3: G3)
+    pub fn lock_shift(&mut self, slot: usize) {
+        if slot < self.slots.len() {
+            self.active = slot;
+        }
+    }
+
+    /// 单次移位 (SS2: G2, SS3: G3)
+    pub fn single_shift(&mut self, slot: usize) {
+        if slot < self.slots.len() {
+            self.single_shift = Some(slot);
+        }
+    }
+
+    /// 将待打印字符映射为当前字符集下的字符
+    pub fn map(&mut self, c: char) -> char {
+        let slot = self.single_shift.take().unwrap_or(self.active);
+        self.slots[slot].map(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dec_special_graphics_mapping() {
+        let charset = Charset::DecSpecialGraphics;
+        let mapped: String = "lqqkx  xmqqj".chars().map(|c| charset.map(c)).collect();
+        assert_eq!(mapped, "┌──┐│  │└──┘");
+        assert_eq!(charset.map('n'), '┼');
+        assert_eq!(charset.map('`'), '◆');
+        // 映射范围外的字符保持不变
+        assert_eq!(charset.map('A'), 'A');
+        assert_eq!(charset.map('1'), '1');
+    }
+
+    #[test]
+    fn test_shift_in_and_out() {
+        let mut state = CharsetState::default();
+        state.designate(1, Charset::DecSpecialGraphics);
+        assert_eq!(state.map('q'), 'q');
+
+        state.lock_shift(1);
+        assert_eq!(state.map('q'), '─');
+
+        state.lock_shift(0);
+        assert_eq!(state.map('q'), 'q');
+    }
+
+    #[test]
+    fn test_single_shift_applies_once() {
+        let mut state = CharsetState::default();
+        state.designate(2, Charset::Uk);
+        state.single_shift(2);
+        assert_eq!(state.map('#'), '£');
+        assert_eq!(state.map('#'), '#');
+    }
+
+    #[test]
+    fn test_designator_bytes() {
+        assert_eq!(
+            Charset::from_designator(b'0'),
+            Some(Charset::DecSpecialGraphics)
+        );
+        assert_eq!(Charset::from_designator(b'B'), Some(Charset::Ascii));
+        assert_eq!(Charset::from_designator(b'A'), Some(Charset::Uk));
+        assert_eq!(Charset::from_designator(b'5'), None);
+    }
+}
@@ -0,0 +1,98 @@
+//! OSC 52 剪贴板
+//! 解析远端程序（tmux、vim 等）通过 OSC 52 发起的剪贴板读写请求，并按策略决定是否执行
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+
+/// 远端写入剪贴板的策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ClipboardPolicy {
+    /// 直接写入
+    Allow,
+    /// 写入前询问用户
+    #[default]
+    Ask,
+    /// 拒绝
+    Deny,
+}
+
+/// OSC 52 请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Osc52Request {
+    /// 写入剪贴板
+    Write { selection: String, text: String },
+    /// 读取剪贴板（负载为 `?`）
+    Read { selection: String },
+}
+
+/// 解析 OSC 52 参数（`52;Pc;Pd`，params 已按分号切分）
+///
+/// 选择目标为空时按 xterm 的约定视为 `s0`；负载无法解码为 UTF-8 文本时返回 None。
+pub fn parse_osc52(params: &[&[u8]]) -> Option<Osc52Request> {
+    if params.first() != Some(&&b"52"[..]) || params.len() < 3 {
+        return None;
+    }
+
+    let selection = match params[1] {
+        b"" => "s0".to_string(),
+        pc => String::from_utf8_lossy(pc).into_owned(),
+    };
+
+    // 负载本身不含分号，但保险起见把多余的参数拼回去
+    let payload = params[2..].join(&b';');
+    if payload == b"?" {
+        return Some(Osc52Request::Read { selection });
+    }
+
+    let decoded = STANDARD.decode(&payload).ok()?;
+    let text = String::from_utf8(decoded).ok()?;
+    Some(Osc52Request::Write { selection, text })
+}
+
+/// 编码剪贴板读取请求的回复 (`OSC 52 ; Pc ; base64 ST`)
+pub fn encode_osc52_reply(selection: &str, text: &str) -> Vec<u8> {
+    format!("\x1b]52;{};{}\x1b\\", selection, STANDARD.encode(text)).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_write() {
+        let params: [&[u8]; 3] = [b"52", b"c", b"aGVsbG8="];
+        assert_eq!(
+            parse_osc52(&params),
+            Some(Osc52Request::Write {
+                selection: "c".to_string(),
+                text: "hello".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_read_and_invalid() {
+        let read: [&[u8]; 3] = [b"52", b"", b"?"];
+        assert_eq!(
+            parse_osc52(&read),
+            Some(Osc52Request::Read {
+                selection: "s0".to_string(),
+            })
+        );
+
+        let invalid: [&[u8]; 3] = [b"52", b"c", b"!!!"];
+        assert_eq!(parse_osc52(&invalid), None);
+
+        let title: [&[u8]; 2] = [b"2", b"title"];
+        assert_eq!(parse_osc52(&title), None);
+    }
+
+    #[test]
+    fn test_encode_reply() {
+        assert_eq!(
+            encode_osc52_reply("c", "hi"),
+            b"\x1b]52;c;aGk=\x1b\\".to_vec()
+        );
+    }
+}
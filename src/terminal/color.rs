@@ -0,0 +1,431 @@
+//! 终端单元格颜色
+//! 单元格只记录颜色的来源（默认色、调色板索引或真彩色），渲染时再由主题解析为具体颜色；
+//! 远端通过 OSC 4/10/11 修改的颜色单独记录，同样在渲染时生效
+
+use std::collections::BTreeMap;
+
+/// 颜色的 RGB 分量
+pub type Rgb = (u8, u8, u8);
+
+/// 标准 256 色调色板 (xterm)
+pub const COLOR_256: [Rgb; 256] = [
+    // 标准颜色 (0-15)
+    (0, 0, 0),
+    (128, 0, 0),
+    (0, 128, 0),
+    (128, 128, 0),
+    (0, 0, 128),
+    (128, 0, 128),
+    (0, 128, 128),
+    (192, 192, 192),
+    (128, 128, 128),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (0, 0, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+    // 6x6x6 立方体颜色 (16-231)
+    (0, 0, 0),
+    (0, 0, 95),
+    (0, 0, 135),
+    (0, 0, 175),
+    (0, 0, 215),
+    (0, 0, 255),
+    (0, 95, 0),
+    (0, 95, 95),
+    (0, 95, 135),
+    (0, 95, 175),
+    (0, 95, 215),
+    (0, 95, 255),
+    (0, 135, 0),
+    (0, 135, 95),
+    (0, 135, 135),
+    (0, 135, 175),
+    (0, 135, 215),
+    (0, 135, 255),
+    (0, 175, 0),
+    (0, 175, 95),
+    (0, 175, 135),
+    (0, 175, 175),
+    (0, 175, 215),
+    (0, 175, 255),
+    (0, 215, 0),
+    (0, 215, 95),
+    (0, 215, 135),
+    (0, 215, 175),
+    (0, 215, 215),
+    (0, 215, 255),
+    (0, 255, 0),
+    (0, 255, 95),
+    (0, 255, 135),
+    (0, 255, 175),
+    (0, 255, 215),
+    (0, 255, 255),
+    (95, 0, 0),
+    (95, 0, 95),
+    (95, 0, 135),
+    (95, 0, 175),
+    (95, 0, 215),
+    (95, 0, 255),
+    (95, 95, 0),
+    (95, 95, 95),
+    (95, 95, 135),
+    (95, 95, 175),
+    (95, 95, 215),
+    (95, 95, 255),
+    (95, 135, 0),
+    (95, 135, 95),
+    (95, 135, 135),
+    (95, 135, 175),
+    (95, 135, 215),
+    (95, 135, 255),
+    (95, 175, 0),
+    (95, 175, 95),
+    (95, 175, 135),
+    (95, 175, 175),
+    (95, 175, 215),
+    (95, 175, 255),
+    (95, 215, 0),
+    (95, 215, 95),
+    (95, 215, 135),
+    (95, 215, 175),
+    (95, 215, 215),
+    (95, 215, 255),
+    (95, 255, 0),
+    (95, 255, 95),
+    (95, 255, 135),
+    (95, 255, 175),
+    (95, 255, 215),
+    (95, 255, 255),
+    (135, 0, 0),
+    (135, 0, 95),
+    (135, 0, 135),
+    (135, 0, 175),
+    (135, 0, 215),
+    (135, 0, 255),
+    (135, 95, 0),
+    (135, 95, 95),
+    (135, 95, 135),
+    (135, 95, 175),
+    (135, 95, 215),
+    (135, 95, 255),
+    (135, 135, 0),
+    (135, 135, 95),
+    (135, 135, 135),
+    (135, 135, 175),
+    (135, 135, 215),
+    (135, 135, 255),
+    (135, 175, 0),
+    (135, 175, 95),
+    (135, 175, 135),
+    (135, 175, 175),
+    (135, 175, 215),
+    (135, 175, 255),
+    (135, 215, 0),
+    (135, 215, 95),
+    (135, 215, 135),
+    (135, 215, 175),
+    (135, 215, 215),
+    (135, 215, 255),
+    (135, 255, 0),
+    (135, 255, 95),
+    (135, 255, 135),
+    (135, 255, 175),
+    (135, 255, 215),
+    (135, 255, 255),
+    (175, 0, 0),
+    (175, 0, 95),
+    (175, 0, 135),
+    (175, 0, 175),
+    (175, 0, 215),
+    (175, 0, 255),
+    (175, 95, 0),
+    (175, 95, 95),
+    (175, 95, 135),
+    (175, 95, 175),
+    (175, 95, 215),
+    (175, 95, 255),
+    (175, 135, 0),
+    (175, 135, 95),
+    (175, 135, 135),
+    (175, 135, 175),
+    (175, 135, 215),
+    (175, 135, 255),
+    (175, 175, 0),
+    (175, 175, 95),
+    (175, 175, 135),
+    (175, 175, 175),
+    (175, 175, 215),
+    (175, 175, 255),
+    (175, 215, 0),
+    (175, 215, 95),
+    (175, 215, 135),
+    (175, 215, 175),
+    (175, 215, 215),
+    (175, 215, 255),
+    (175, 255, 0),
+    (175, 255, 95),
+    (175, 255, 135),
+    (175, 255, 175),
+    (175, 255, 215),
+    (175, 255, 255),
+    (215, 0, 0),
+    (215, 0, 95),
+    (215, 0, 135),
+    (215, 0, 175),
+    (215, 0, 215),
+    (215, 0, 255),
+    (215, 95, 0),
+    (215, 95, 95),
+    (215, 95, 135),
+    (215, 95, 175),
+    (215, 95, 215),
+    (215, 95, 255),
+    (215, 135, 0),
+    (215, 135, 95),
+    (215, 135, 135),
+    (215, 135, 175),
+    (215, 135, 215),
+    (215, 135, 255),
+    (215, 175, 0),
+    (215, 175, 95),
+    (215, 175, 135),
+    (215, 175, 175),
+    (215, 175, 215),
+    (215, 175, 255),
+    (215, 215, 0),
+    (215, 215, 95),
+    (215, 215, 135),
+    (215, 215, 175),
+    (215, 215, 215),
+    (215, 215, 255),
+    (215, 255, 0),
+    (215, 255, 95),
+    (215, 255, 135),
+    (215, 255, 175),
+    (215, 255, 215),
+    (215, 255, 255),
+    (255, 0, 0),
+    (255, 0, 95),
+    (255, 0, 135),
+    (255, 0, 175),
+    (255, 0, 215),
+    (255, 0, 255),
+    (255, 95, 0),
+    (255, 95, 95),
+    (255, 95, 135),
+    (255, 95, 175),
+    (255, 95, 215),
+    (255, 95, 255),
+    (255, 135, 0),
+    (255, 135, 95),
+    (255, 135, 135),
+    (255, 135, 175),
+    (255, 135, 215),
+    (255, 135, 255),
+    (255, 175, 0),
+    (255, 175, 95),
+    (255, 175, 135),
+    (255, 175, 175),
+    (255, 175, 215),
+    (255, 175, 255),
+    (255, 215, 0),
+    (255, 215, 95),
+    (255, 215, 135),
+    (255, 215, 175),
+    (255, 215, 215),
+    (255, 215, 255),
+    (255, 255, 0),
+    (255, 255, 95),
+    (255, 255, 135),
+    (255, 255, 175),
+    (255, 255, 215),
+    (255, 255, 255),
+    // 灰度颜色 (232-255)
+    (8, 8, 8),
+    (18, 18, 18),
+    (28, 28, 28),
+    (38, 38, 38),
+    (48, 48, 48),
+    (58, 58, 58),
+    (68, 68, 68),
+    (78, 78, 78),
+    (88, 88, 88),
+    (98, 98, 98),
+    (108, 108, 108),
+    (118, 118, 118),
+    (128, 128, 128),
+    (138, 138, 138),
+    (148, 148, 148),
+    (158, 158, 158),
+    (168, 168, 168),
+    (178, 178, 178),
+    (188, 188, 188),
+    (198, 198, 198),
+    (208, 208, 208),
+    (218, 218, 218),
+    (228, 228, 228),
+    (238, 238, 238),
+];
+
+/// 单元格颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CellColor {
+    /// 主题的默认前景色
+    Foreground,
+    /// 主题的默认背景色（作为背景时不单独绘制）
+    Background,
+    /// 调色板索引：0-15 为主题的 ANSI 颜色，16-255 为 256 色调色板
+    Indexed(u8),
+    /// 真彩色
+    Rgb(u8, u8, u8),
+}
+
+impl CellColor {
+    /// 编码为 4 个字节（类型 + 数据），用于回滚历史的紧凑存储
+    pub fn to_bytes(self) -> [u8; 4] {
+        match self {
+            CellColor::Foreground => [0, 0, 0, 0],
+            CellColor::Background => [1, 0, 0, 0],
+            CellColor::Indexed(index) => [2, index, 0, 0],
+            CellColor::Rgb(r, g, b) => [3, r, g, b],
+        }
+    }
+
+    /// 从 `to_bytes` 的结果还原，无法识别的类型按默认前景色处理
+    pub fn from_bytes(bytes: [u8; 4]) -> Self {
+        match bytes {
+            [1, ..] => CellColor::Background,
+            [2, index, ..] => CellColor::Indexed(index),
+            [3, r, g, b] => CellColor::Rgb(r, g, b),
+            _ => CellColor::Foreground,
+        }
+    }
+}
+
+/// 仿真器使用的具体配色，由界面主题转换而来，用于回答远端的颜色查询
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub foreground: Rgb,
+    pub background: Rgb,
+    pub colors: [Rgb; 256],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            foreground: (255, 255, 255),
+            background: (0, 0, 0),
+            colors: COLOR_256,
+        }
+    }
+}
+
+/// 远端修改过的颜色 (OSC 4/10/11)，未修改的项沿用主题
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaletteOverrides {
+    pub foreground: Option<Rgb>,
+    pub background: Option<Rgb>,
+    pub colors: BTreeMap<u8, Rgb>,
+}
+
+impl PaletteOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.foreground.is_none() && self.background.is_none() && self.colors.is_empty()
+    }
+
+    /// 单元格颜色被修改后的值，加粗的标准色 (0-7) 按对应的亮色查找
+    pub fn get(&self, color: CellColor, bold: bool) -> Option<Rgb> {
+        match color {
+            CellColor::Foreground => self.foreground,
+            CellColor::Background => self.background,
+            CellColor::Indexed(index) if bold && index < 8 => {
+                self.colors.get(&(index + 8)).copied()
+            }
+            CellColor::Indexed(index) => self.colors.get(&index).copied(),
+            CellColor::Rgb(..) => None,
+        }
+    }
+}
+
+/// 解析 X11 颜色格式：`rgb:r/g/b`（每个分量 1-4 位十六进制）或 `#rgb` 系列
+pub fn parse_color_spec(spec: &str) -> Option<Rgb> {
+    if let Some(rgb) = spec.strip_prefix("rgb:") {
+        let mut parts = rgb.split('/');
+        let mut component = || {
+            let part = parts.next()?;
+            if part.is_empty() || part.len() > 4 {
+                return None;
+            }
+            let value = u32::from_str_radix(part, 16).ok()?;
+            let max = (1u32 << (4 * part.len())) - 1;
+            Some((value * 255 / max) as u8)
+        };
+        let color = (component()?, component()?, component()?);
+        return parts.next().is_none().then_some(color);
+    }
+
+    // #rgb 形式只取每个分量的最高 8 位
+    let hex = spec.strip_prefix('#')?;
+    if hex.is_empty() || hex.len() % 3 != 0 || hex.len() > 12 {
+        return None;
+    }
+    let digits = hex.len() / 3;
+    let component = |index: usize| {
+        let value = u32::from_str_radix(hex.get(index * digits..(index + 1) * digits)?, 16).ok()?;
+        Some(if digits == 1 {
+            (value << 4) as u8
+        } else {
+            (value >> (4 * (digits - 2))) as u8
+        })
+    };
+    Some((component(0)?, component(1)?, component(2)?))
+}
+
+/// 按 xterm 回答颜色查询的格式输出：`rgb:rrrr/gggg/bbbb`
+pub fn format_color_spec((r, g, b): Rgb) -> String {
+    format!("rgb:{r:02x}{r:02x}/{g:02x}{g:02x}/{b:02x}{b:02x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_round_trip() {
+        for color in [
+            CellColor::Foreground,
+            CellColor::Background,
+            CellColor::Indexed(0),
+            CellColor::Indexed(255),
+            CellColor::Rgb(1, 2, 3),
+        ] {
+            assert_eq!(CellColor::from_bytes(color.to_bytes()), color);
+        }
+    }
+
+    #[test]
+    fn test_parse_color_spec() {
+        assert_eq!(parse_color_spec("rgb:ff/80/00"), Some((255, 128, 0)));
+        assert_eq!(parse_color_spec("rgb:ffff/0000/8080"), Some((255, 0, 128)));
+        assert_eq!(parse_color_spec("rgb:f/0/8"), Some((255, 0, 136)));
+        assert_eq!(parse_color_spec("#ff8000"), Some((255, 128, 0)));
+        assert_eq!(parse_color_spec("#f80"), Some((240, 128, 0)));
+        assert_eq!(parse_color_spec("#ffff80000000"), Some((255, 128, 0)));
+        assert_eq!(parse_color_spec("rgb:ff/80"), None);
+        assert_eq!(parse_color_spec("rgb:ff/80/00/00"), None);
+        assert_eq!(parse_color_spec("red"), None);
+        assert_eq!(format_color_spec((255, 128, 0)), "rgb:ffff/8080/0000");
+    }
+
+    #[test]
+    fn test_overrides_brighten_bold() {
+        let mut overrides = PaletteOverrides::default();
+        overrides.colors.insert(9, (1, 2, 3));
+        assert_eq!(overrides.get(CellColor::Indexed(1), true), Some((1, 2, 3)));
+        assert_eq!(overrides.get(CellColor::Indexed(1), false), None);
+        assert_eq!(overrides.get(CellColor::Indexed(9), false), Some((1, 2, 3)));
+    }
+}
@@ -0,0 +1,424 @@
+//! 仿真器一致性测试
+//! 把 tests/corpus 中录制的字节流交给每个仿真引擎，与带属性的单元格快照逐一比较；
+//! 另用随机字节流和已知的恶意序列检查 process_input 在任意尺寸下都不会 panic
+//!
+//! 新增或更新快照：`UPDATE_SNAPSHOTS=1 cargo test conformance`。
+//! 每份语料只有一份快照 `<name>.snap`，由 wezterm 引擎生成；快照记录的是该引擎当前的输出，
+//! 不是独立的参考结果（见 tests/corpus/README.md）。
+//! 其他引擎已知的差异登记在 [`EXPECTED_FAILURES`] 中并注明原因。
+
+use crate::terminal::buffer::{Scroll, TerminalBuffer, TerminalCell};
+use crate::terminal::color::CellColor;
+use crate::terminal::emulator::{EmulatorEngine, TerminalEmulator, TerminalEmulatorTrait};
+use crate::terminal::theme::TerminalTheme;
+use crate::terminal::wezterm_adapter::WezTermAdapter;
+use egui::Color32;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 所有仿真引擎及其在快照文件名中的标识，第一个为生成共用快照的参考引擎
+const ENGINES: [(EmulatorEngine, &str); 2] = [
+    (EmulatorEngine::WezTerm, "wezterm"),
+    (EmulatorEngine::Builtin, "builtin"),
+];
+
+/// 已知与快照不一致的（引擎, 语料, 原因）
+///
+/// 登记的组合必须仍然不一致，引擎修好后测试失败，提醒删除对应条目。
+const EXPECTED_FAILURES: &[(EmulatorEngine, &str, &str)] = &[
+    (
+        EmulatorEngine::Builtin,
+        "insert_delete",
+        "内置引擎未实现 ICH/DCH/IL/DL/ECH",
+    ),
+    (
+        EmulatorEngine::Builtin,
+        "top",
+        "写满最后一列（待换行）后 EL 会擦掉该列，wezterm 保留",
+    ),
+    (
+        EmulatorEngine::Builtin,
+        "vttest_cursor_movement",
+        "内置引擎未实现 DECALN (ESC # 8) 与 IND/NEL/RI (ESC D/E/M)",
+    ),
+];
+
+/// 快照文件中没有尺寸时使用的终端尺寸
+const DEFAULT_SIZE: (usize, usize) = (24, 80);
+
+/// 录制数据分块送入的大小，故意取奇数让转义序列和 UTF-8 字符被截断在块边界
+const CORPUS_CHUNK_SIZE: usize = 61;
+
+fn create_emulator(
+    engine: EmulatorEngine,
+    rows: usize,
+    cols: usize,
+) -> Box<dyn TerminalEmulatorTrait> {
+    match engine {
+        EmulatorEngine::Builtin => Box::new(TerminalEmulator::new(rows, cols)),
+        EmulatorEngine::WezTerm => Box::new(WezTermAdapter::new(rows, cols)),
+    }
+}
+
+/// 单元格的显示属性，与默认值相同的属性为 None / false
+#[derive(Debug, Clone, PartialEq, Default)]
+struct CellAttrs {
+    fg: Option<Color32>,
+    bg: Option<Color32>,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    link: Option<String>,
+}
+
+impl CellAttrs {
+    fn of(buffer: &TerminalBuffer, cell: &TerminalCell, theme: &TerminalTheme) -> Self {
+        // 空白单元格的前景色不可见，不同引擎的取值也不同，不计入快照
+        let visible_fg = cell.character != ' ' || cell.combining.is_some() || cell.underline;
+        let fg = theme.resolve(cell.fg_color, cell.bold, &buffer.palette);
+        Self {
+            fg: (visible_fg && fg != theme.style.foreground).then_some(fg),
+            bg: (cell.bg_color != CellColor::Background)
+                .then(|| theme.resolve(cell.bg_color, false, &buffer.palette)),
+            bold: cell.bold,
+            italic: cell.italic,
+            underline: cell.underline,
+            link: cell
+                .hyperlink
+                .and_then(|id| buffer.hyperlinks.get(id))
+                .map(|link| link.uri.clone()),
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(fg) = self.fg {
+            parts.push(format!("fg={}", hex(fg)));
+        }
+        if let Some(bg) = self.bg {
+            parts.push(format!("bg={}", hex(bg)));
+        }
+        for (flag, name) in [
+            (self.bold, "bold"),
+            (self.italic, "italic"),
+            (self.underline, "underline"),
+        ] {
+            if flag {
+                parts.push(name.to_string());
+            }
+        }
+        if let Some(ref link) = self.link {
+            parts.push(format!("link={link}"));
+        }
+        parts.join(" ")
+    }
+}
+
+fn hex(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// 把缓冲区（历史与屏幕）转换为文本快照
+///
+/// 每行先写文本，再按列区间列出与默认值不同的属性；宽字符的延续位沿用前一格的属性。
+fn snapshot(buffer: &TerminalBuffer) -> String {
+    let theme = TerminalTheme::default();
+    let history = buffer.history.len();
+    let mut out = String::new();
+    let _ = writeln!(out, "size {}x{}", buffer.rows, buffer.cols);
+    let _ = writeln!(
+        out,
+        "cursor {},{} {}",
+        buffer.cursor_row,
+        buffer.cursor_col,
+        if buffer.cursor_visible {
+            "visible"
+        } else {
+            "hidden"
+        }
+    );
+    let _ = writeln!(out, "history {history}");
+
+    for row in 0..history + buffer.rows {
+        let Some(cells) = buffer.line(row) else {
+            continue;
+        };
+        let text: String = cells
+            .iter()
+            .filter(|cell| !cell.is_continuation)
+            .map(TerminalCell::text)
+            .collect();
+        let _ = writeln!(out, "{row:4}|{}", text.trim_end());
+
+        let mut spans: Vec<(usize, usize, CellAttrs)> = Vec::new();
+        let mut previous = CellAttrs::default();
+        for (col, cell) in cells.iter().enumerate() {
+            let attrs = if cell.is_continuation {
+                previous.clone()
+            } else {
+                CellAttrs::of(buffer, cell, &theme)
+            };
+            match spans.last_mut() {
+                Some((_, end, last)) if *end == col && *last == attrs => *end = col + 1,
+                _ if attrs.is_default() => {}
+                _ => spans.push((col, col + 1, attrs.clone())),
+            }
+            previous = attrs;
+        }
+        for (start, end, attrs) in spans {
+            let _ = writeln!(out, "    |  {start}..{end} {}", attrs.describe());
+        }
+    }
+
+    out
+}
+
+/// 从快照头读取终端尺寸
+fn snapshot_size(snapshot: &str) -> Option<(usize, usize)> {
+    let size = snapshot.lines().next()?.strip_prefix("size ")?;
+    let (rows, cols) = size.split_once('x')?;
+    Some((rows.parse().ok()?, cols.parse().ok()?))
+}
+
+fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus")
+}
+
+/// 录制的字节流，按名称排序
+fn corpus_inputs() -> Vec<(String, Vec<u8>)> {
+    let mut inputs: Vec<_> = fs::read_dir(corpus_dir())
+        .expect("缺少 tests/corpus 目录")
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "in" {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?.to_string();
+            Some((name, fs::read(&path).ok()?))
+        })
+        .collect();
+    inputs.sort();
+    inputs
+}
+
+/// 第一处不同的行，用于失败信息
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (e, a) if e != a => {
+                return format!(
+                    "第 {line} 行\n  期望: {}\n  实际: {}",
+                    e.unwrap_or("<无>"),
+                    a.unwrap_or("<无>")
+                );
+            }
+            _ => {}
+        }
+    }
+    String::new()
+}
+
+#[test]
+fn test_corpus_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let dir = corpus_dir();
+    let inputs = corpus_inputs();
+    assert!(!inputs.is_empty(), "tests/corpus 中没有录制数据");
+
+    let mut failures = Vec::new();
+    for (name, data) in inputs {
+        let golden_path = dir.join(format!("{name}.snap"));
+        let mut golden = fs::read_to_string(&golden_path).ok();
+        let (rows, cols) = golden
+            .as_deref()
+            .and_then(snapshot_size)
+            .unwrap_or(DEFAULT_SIZE);
+
+        for (index, (engine, engine_name)) in ENGINES.into_iter().enumerate() {
+            let mut emulator = create_emulator(engine, rows, cols);
+            for chunk in data.chunks(CORPUS_CHUNK_SIZE) {
+                emulator.process_input(chunk).unwrap();
+            }
+            let actual = emulator.with_buffer(snapshot);
+
+            // 只有参考引擎的结果会写入快照
+            if update && index == 0 {
+                fs::write(&golden_path, &actual).unwrap();
+                golden = Some(actual.clone());
+            }
+            let Some(ref expected) = golden else {
+                failures.push(format!("{name}: 缺少快照 {}", golden_path.display()));
+                break;
+            };
+
+            let expected_failure = EXPECTED_FAILURES
+                .iter()
+                .find(|(e, n, _)| *e == engine && *n == name);
+            match (expected_failure, *expected == actual) {
+                (None, true) | (Some(_), false) => {}
+                (None, false) => failures.push(format!(
+                    "{name} ({engine_name}): {}",
+                    first_difference(expected, &actual)
+                )),
+                (Some((_, _, reason)), true) => failures.push(format!(
+                    "{name} ({engine_name}): 已与快照一致，请从 EXPECTED_FAILURES 中删除（{reason}）"
+                )),
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "快照不一致（确认后用 UPDATE_SNAPSHOTS=1 cargo test conformance 更新）:\n{}",
+        failures.join("\n")
+    );
+}
+
+/// 测试用的伪随机数生成器 (xorshift64)，保证每次运行的输入相同
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// 随机输入的组成片段：转义序列前缀、参数、控制字符与多字节字符
+const FRAGMENTS: &[&[u8]] = &[
+    b"\x1b[",
+    b"\x1b[?",
+    b"\x1b[>",
+    b"\x1b]",
+    b"\x1bP",
+    b"\x1b_G",
+    b"\x1b#8",
+    b"\x1b(0",
+    b"\x1b7",
+    b"\x1b8",
+    b"\x1bD",
+    b"\x1bM",
+    b"\x1bc",
+    b"\x1b\\",
+    b"0",
+    b"1",
+    b"65535",
+    b"4294967296",
+    b";",
+    b":",
+    b"\x07",
+    b"\x08",
+    b"\t",
+    b"\n",
+    b"\r",
+    "中".as_bytes(),
+    "e\u{301}".as_bytes(),
+    "👩‍💻".as_bytes(),
+    b"\xe4\xb8",
+    b"\xff",
+];
+
+/// 生成一段随机输入，多数由片段拼成，夹杂随机字节与 CSI 结束符
+fn random_input(rng: &mut XorShift) -> Vec<u8> {
+    let mut data = Vec::new();
+    for _ in 0..rng.below(64) + 1 {
+        match rng.below(4) {
+            0 => data.push(rng.next() as u8),
+            1 => data.push(b"@ABCDEFGHIJKLMPSTXZ`abcdefghlmnqrstuz"[rng.below(37)]),
+            _ => data.extend_from_slice(FRAGMENTS[rng.below(FRAGMENTS.len())]),
+        }
+    }
+    data
+}
+
+#[test]
+fn test_random_input_never_panics() {
+    let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+    let sizes = [(0, 0), (0, 10), (10, 0), (1, 1), (2, 3), (24, 80)];
+
+    for (engine, _) in ENGINES {
+        for (rows, cols) in sizes {
+            let mut emulator = create_emulator(engine, rows, cols);
+            for _ in 0..300 {
+                emulator.process_input(&random_input(&mut rng)).unwrap();
+                match rng.below(16) {
+                    0 => emulator.resize(rng.below(6), rng.below(12)),
+                    1 => emulator.scroll_display(Scroll::Delta(rng.below(9) as isize - 4)),
+                    2 => {
+                        emulator.start_selection(rng.below(8), rng.below(12));
+                        emulator.update_selection(rng.below(8), rng.below(12));
+                        let _ = emulator.get_selected_text();
+                    }
+                    _ => {}
+                }
+            }
+            emulator.with_buffer(snapshot);
+        }
+    }
+}
+
+/// 曾导致 panic 或长时间卡住的序列，以及图片、超链接等带长参数的序列
+const HOSTILE_INPUTS: &[&[u8]] = &[
+    // kitty 图片宽高相乘溢出
+    b"\x1b_Ga=T,f=32,s=4294967295,v=4294967295;AAAA\x1b\\",
+    b"\x1b_Ga=T,f=100,s=4294967295,v=4294967295;AAAA\x1b\\",
+    // kitty 图片占用极多的行列
+    b"\x1b_Ga=T,f=24,s=1,v=1,r=20000000,c=4294967295;AQID\x1b\\",
+    b"\x1b_Ga=T,f=24,s=1,v=1,i=7;AQID\x1b\\\x1b_Ga=p,i=7,r=4294967295,C=1\x1b\\",
+    // 分块传输没有结束
+    b"\x1b_Ga=T,f=24,s=1,v=1,m=1;AQ\x1b\\\x1b_Gm=1;ID\x1b\\",
+    // sixel：超出范围的颜色分量与寄存器、极大的栅格尺寸与重复次数
+    b"\x1bPq#300;2;300;300;300#1;1;720;300;300!4294967295~\x1b\\",
+    b"\x1bPq\"1;1;4294967295;4294967295#1~-~\x1b\\",
+    b"\x1bP0;1;0q!65535~$-!65535~\x1b\\",
+    // OSC：超链接、剪贴板、调色板
+    b"\x1b]8;id=x;https://example.com\x1b\\link\x1b]8;;\x1b\\",
+    b"\x1b]8;;\x1b\\\x1b]8;;\x07",
+    b"\x1b]52;c;!!!not base64\x07\x1b]52;;\x07",
+    b"\x1b]4;256;rgb:ff/ff/ff\x07\x1b]4;-1;#fff\x07\x1b]104;999999999\x07",
+    b"\x1b]133;A\x07\x1b]133;Z\x07\x1b]7;file://\x07",
+    // 滚动区域与原点模式
+    b"\x1b[5;2r\x1b[?6h\x1b[4294967295;4294967295H\x1b[65535r\x1b[?6l",
+    // 极大的编辑次数：wezterm 按次数逐个插入或重复字符
+    b"\x1b[4294967295@\x1b[4294967295P\x1b[4294967295L\x1b[4294967295M\x1b[4294967295X",
+    b"x\x1b[4294967295b\x1b[4294967295S\x1b[4294967295T",
+];
+
+#[test]
+fn test_hostile_input_never_panics() {
+    let sizes = [(0, 0), (1, 1), (3, 5), (24, 80)];
+    for (engine, _) in ENGINES {
+        for (rows, cols) in sizes {
+            for input in HOSTILE_INPUTS {
+                let start = std::time::Instant::now();
+                let mut emulator = create_emulator(engine, rows, cols);
+                // 也按字节逐个送入，检查跨块的状态
+                emulator.process_input(input).unwrap();
+                for byte in input.iter() {
+                    emulator.process_input(std::slice::from_ref(byte)).unwrap();
+                }
+                emulator.resize(2, 3);
+                emulator.with_buffer(snapshot);
+                assert!(
+                    start.elapsed() < std::time::Duration::from_secs(5),
+                    "{engine:?} {rows}x{cols} 处理 {:?} 过慢",
+                    String::from_utf8_lossy(input)
+                );
+            }
+        }
+    }
+}
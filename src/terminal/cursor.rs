@@ -0,0 +1,75 @@
+//! 光标样式
+//! 光标形状与闪烁设置，远端程序可通过 DECSCUSR (CSI Ps SP q) 切换
+
+use serde::{Deserialize, Serialize};
+
+/// 光标形状
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CursorShape {
+    /// 方块
+    #[default]
+    Block,
+    /// 下划线
+    Underline,
+    /// 竖线
+    Bar,
+}
+
+/// 光标样式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorStyle {
+    pub shape: CursorShape,
+    pub blinking: bool,
+}
+
+impl CursorStyle {
+    /// 解析 DECSCUSR 参数
+    ///
+    /// 1/2 闪烁/稳定方块，3/4 闪烁/稳定下划线，5/6 闪烁/稳定竖线。
+    /// 参数 0（恢复默认样式）与未知参数返回 None。
+    pub fn from_decscusr(ps: u16) -> Option<Self> {
+        let shape = match ps {
+            1 | 2 => CursorShape::Block,
+            3 | 4 => CursorShape::Underline,
+            5 | 6 => CursorShape::Bar,
+            _ => return None,
+        };
+
+        Some(Self {
+            shape,
+            blinking: ps % 2 == 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decscusr_params() {
+        assert_eq!(
+            CursorStyle::from_decscusr(1),
+            Some(CursorStyle {
+                shape: CursorShape::Block,
+                blinking: true
+            })
+        );
+        assert_eq!(
+            CursorStyle::from_decscusr(4),
+            Some(CursorStyle {
+                shape: CursorShape::Underline,
+                blinking: false
+            })
+        );
+        assert_eq!(
+            CursorStyle::from_decscusr(5),
+            Some(CursorStyle {
+                shape: CursorShape::Bar,
+                blinking: true
+            })
+        );
+        assert_eq!(CursorStyle::from_decscusr(0), None);
+        assert_eq!(CursorStyle::from_decscusr(7), None);
+    }
+}
@@ -1,16 +1,27 @@
 //! 专业终端仿真器实现
 //! 使用vte库实现VT100/VT220终端仿真
 
-use crate::terminal::buffer::TerminalBuffer;
-use crate::terminal::theme::TerminalTheme;
+use crate::terminal::buffer::{
+    Hyperlink, HyperlinkId, ScreenSnapshot, Scroll, ShellMark, TerminalBuffer,
+};
+use crate::terminal::charset::{Charset, CharsetState};
+use crate::terminal::clipboard::{Osc52Request, parse_osc52};
+use crate::terminal::color::{CellColor, Palette, format_color_spec, parse_color_spec};
+use crate::terminal::cursor::CursorStyle;
+use crate::terminal::graphics::kitty::{self, KittyCommand};
+use crate::terminal::graphics::sixel::SixelDecoder;
+use crate::terminal::graphics::{ImageCell, ImageId, TerminalImage, cell_span, cell_uv};
+use crate::terminal::modes::TerminalModes;
+use crate::terminal::scrollback::ScrollbackConfig;
+use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
 use vte::{Parser, Perform};
 
 /// 终端事件回调
 #[derive(Debug, Clone)]
 pub enum TerminalEvent {
-    /// 输出内容更新
-    Output(String),
     /// 光标位置改变
     CursorPosition { row: usize, col: usize },
     /// 终端大小改变
@@ -19,6 +30,22 @@ pub enum TerminalEvent {
     RequestInput,
     /// 终端标题改变
     TitleChange(String),
+    /// 远端请求写入剪贴板 (OSC 52)
+    ClipboardWrite { selection: String, text: String },
+    /// 远端请求读取剪贴板 (OSC 52 ?)
+    ClipboardRead { selection: String },
+    /// 响铃 (BEL)
+    Bell,
+}
+
+/// 终端仿真引擎
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EmulatorEngine {
+    /// 内置的 vte 仿真器
+    #[default]
+    Builtin,
+    /// 基于 wezterm-term 状态机的仿真器
+    WezTerm,
 }
 
 /// 终端状态
@@ -35,14 +62,14 @@ pub trait TerminalEmulatorTrait {
     /// 处理输入数据
     fn process_input(&mut self, data: &[u8]) -> anyhow::Result<()>;
 
-    /// 获取终端缓冲区
-    fn buffer(&self) -> TerminalBuffer;
+    /// 借用当前应显示的缓冲区并交给访问函数，不拷贝缓冲区
+    fn visit_buffer(&self, visitor: &mut dyn FnMut(&TerminalBuffer));
 
     /// 调整终端大小
     fn resize(&mut self, rows: usize, cols: usize);
 
-    /// 更新主题
-    fn update_theme(&self, theme: TerminalTheme);
+    /// 设置主题配色，用于回答远端的颜色查询（单元格颜色在渲染时解析）
+    fn set_palette(&self, palette: Palette);
 
     /// 获取终端状态
     fn state(&self) -> &TerminalState;
@@ -64,48 +91,208 @@ pub trait TerminalEmulatorTrait {
 
     /// 获取选中的文本
     fn get_selected_text(&self) -> Option<String>;
+
+    /// 获取当前终端模式
+    fn modes(&self) -> TerminalModes {
+        TerminalModes::default()
+    }
+
+    /// 取出仿真器需要回复给远端的数据（如状态查询应答）
+    fn take_pending_output(&mut self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// 设置单元格的像素大小，用于计算内联图片占用的单元格
+    fn set_cell_size(&mut self, _width: f32, _height: f32) {}
+
+    /// 设置回滚历史的限制
+    fn set_scrollback(&mut self, _config: ScrollbackConfig) {}
+
+    /// 滚动视口（查看历史记录）
+    fn scroll_display(&mut self, _scroll: Scroll) {}
+
+    /// 供后台 I/O 线程使用的输入句柄，不支持时返回 None
+    fn input_handle(&self) -> Option<TerminalInput> {
+        None
+    }
+}
+
+impl dyn TerminalEmulatorTrait + '_ {
+    /// 借用当前应显示的缓冲区计算结果
+    pub fn with_buffer<R>(&self, f: impl FnOnce(&TerminalBuffer) -> R) -> R {
+        let mut f = Some(f);
+        let mut result = None;
+        self.visit_buffer(&mut |buffer| {
+            if let Some(f) = f.take() {
+                result = Some(f(buffer));
+            }
+        });
+        result.expect("visit_buffer 未调用访问函数")
+    }
+}
+
+/// SGR 设置的字符属性，写入字符时应用到单元格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SgrState {
+    fg: CellColor,
+    bg: CellColor,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    /// 反显：写入时交换前景色与背景色
+    reverse: bool,
+    /// 隐藏：前景色与背景色相同
+    hidden: bool,
+}
+
+impl Default for SgrState {
+    fn default() -> Self {
+        Self {
+            fg: CellColor::Foreground,
+            bg: CellColor::Background,
+            bold: false,
+            italic: false,
+            underline: false,
+            reverse: false,
+            hidden: false,
+        }
+    }
+}
+
+/// 保存的光标状态 (DECSC)
+#[derive(Debug, Clone, Copy, Default)]
+struct SavedCursor {
+    row: usize,
+    col: usize,
+    pending_wrap: bool,
+    sgr: SgrState,
+    charsets: CharsetState,
+    origin: bool,
 }
 
 /// VTE性能实现
 struct VtePerform {
     /// 终端缓冲区
     pub term_buffer: TerminalBuffer,
-    /// 终端主题（用于颜色映射）
-    pub theme: TerminalTheme,
+    /// 主题配色（回答 OSC 4/10/11 颜色查询）
+    palette: Palette,
     /// 旧的字符串缓冲区（用于向后兼容）
     pub buffer: String,
     /// 事件回调
     callback: Option<Box<dyn Fn(TerminalEvent) + Send + Sync>>,
-    /// 当前前景色
-    current_fg: Option<u8>,
-    /// 当前背景色
-    current_bg: Option<u8>,
-    /// 256色前景色
-    current_fg_256: Option<u16>,
-    /// 256色背景色
-    current_bg_256: Option<u16>,
-    /// RGB前景色
-    current_fg_rgb: Option<(u8, u8, u8)>,
-    /// RGB背景色
-    current_bg_rgb: Option<(u8, u8, u8)>,
-    /// 是否加粗
-    bold: bool,
+    /// 当前的颜色与字符属性
+    sgr: SgrState,
+    /// 终端模式
+    modes: TerminalModes,
+    /// G0-G3 字符集状态
+    charsets: CharsetState,
+    /// DECSC 保存的光标状态
+    saved_cursor: Option<SavedCursor>,
+    /// kitty 键盘协议标志栈（保存被压入前的标志）
+    kitty_keyboard_stack: Vec<u8>,
+    /// 待回复给远端的数据
+    pending_output: Vec<u8>,
+    /// 当前 OSC 8 超链接，写入的字符都指向该链接
+    current_hyperlink: Option<HyperlinkId>,
+    /// 正在接收的 sixel 图片 (DCS q)
+    sixel: Option<SixelDecoder>,
+    /// kitty 图形协议分块传输中的指令，负载逐块累加
+    kitty_upload: Option<KittyCommand>,
+    /// 单元格的像素大小（宽, 高）
+    cell_size: (f32, f32),
+    /// 远端设置的窗口标题 (OSC 0/2)
+    title: String,
+    /// 标题栈 (CSI 22 t 压入，CSI 23 t 弹出)
+    title_stack: Vec<String>,
+    /// 同步输出开始前的屏幕画面及开始时间 (DECSET 2026)
+    sync_frame: Option<(ScreenSnapshot, Instant)>,
+}
+
+/// kitty 键盘协议标志栈的最大深度，超出时丢弃最早的条目
+const KITTY_KEYBOARD_STACK_LIMIT: usize = 16;
+
+/// 标题栈的最大深度，超出时丢弃最早的条目
+const TITLE_STACK_LIMIT: usize = 10;
+
+/// 同步输出的超时时间，远端迟迟不结束更新时改为显示实时内容
+const SYNC_OUTPUT_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// 界面尚未报告字体大小时使用的单元格像素大小
+const DEFAULT_CELL_SIZE: (f32, f32) = (8.0, 16.0);
+
+impl SgrState {
+    /// 写入单元格的（前景色, 背景色），已应用反显与隐藏
+    fn colors(&self) -> (CellColor, CellColor) {
+        let (mut fg, mut bg) = (self.fg, self.bg);
+        if self.reverse {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        if self.hidden {
+            fg = bg;
+        }
+        (fg, bg)
+    }
+}
+
+/// 解析 SGR 38/48 的扩展颜色（256 色或 RGB）
+///
+/// 支持冒号子参数（38:5:n、38:2::r:g:b）与分号分隔（38;5;n、38;2;r;g;b）两种写法，
+/// 分号写法的颜色参数从 `rest` 中取出。
+fn extended_color<'a>(
+    param: &[u16],
+    rest: &mut impl Iterator<Item = &'a [u16]>,
+) -> Option<CellColor> {
+    if param.len() > 1 {
+        return match param[1] {
+            5 => u8::try_from(*param.get(2)?).ok().map(CellColor::Indexed),
+            2 => {
+                // 色彩空间参数可以省略
+                let rgb = if param.len() >= 6 {
+                    &param[3..6]
+                } else {
+                    param.get(2..5)?
+                };
+                Some(CellColor::Rgb(rgb[0] as u8, rgb[1] as u8, rgb[2] as u8))
+            }
+            _ => None,
+        };
+    }
+
+    match *rest.next()?.first()? {
+        5 => u8::try_from(*rest.next()?.first()?)
+            .ok()
+            .map(CellColor::Indexed),
+        2 => {
+            let mut rgb = [0u8; 3];
+            for value in &mut rgb {
+                *value = *rest.next()?.first()? as u8;
+            }
+            Some(CellColor::Rgb(rgb[0], rgb[1], rgb[2]))
+        }
+        _ => None,
+    }
 }
 
 impl VtePerform {
     fn new(rows: usize, cols: usize) -> Self {
         Self {
             term_buffer: TerminalBuffer::new(rows, cols),
-            theme: TerminalTheme::default(),
+            palette: Palette::default(),
             buffer: String::new(),
             callback: None,
-            current_fg: None,
-            current_bg: None,
-            current_fg_256: None,
-            current_bg_256: None,
-            current_fg_rgb: None,
-            current_bg_rgb: None,
-            bold: false,
+            sgr: SgrState::default(),
+            modes: TerminalModes::default(),
+            charsets: CharsetState::default(),
+            saved_cursor: None,
+            kitty_keyboard_stack: Vec::new(),
+            pending_output: Vec::new(),
+            current_hyperlink: None,
+            sixel: None,
+            kitty_upload: None,
+            cell_size: DEFAULT_CELL_SIZE,
+            title: String::new(),
+            title_stack: Vec::new(),
+            sync_frame: None,
         }
     }
 
@@ -122,189 +309,870 @@ impl VtePerform {
         }
     }
 
-    /// 解析扩展颜色格式（256色和RGB）
-    fn parse_extended_color(&mut self, is_foreground: bool, params: &vte::Params) {
-        let mut param_iter = params.iter();
+    /// 若字符与光标前的字符属于同一字素簇（组合音标、ZWJ序列、emoji修饰符等），
+    /// 则附加到该单元格，整个字素簇作为一个字形显示
+    ///
+    /// 光标仍按该字符自身的宽度前进（用延续位填充），与远端 wcwidth 的计算保持一致。
+    fn join_previous_cluster(&mut self, c: char, width: usize) -> bool {
+        if c.is_ascii() {
+            return false;
+        }
+        let Some((row, col)) = self.term_buffer.previous_cell_position() else {
+            return false;
+        };
+        let Some(cell) = self.term_buffer.get_cell_mut(row, col) else {
+            return false;
+        };
 
-        // 跳过第一个参数（38或48）
-        let _ = param_iter.next();
+        if width > 0 {
+            let mut cluster = cell.text();
+            cluster.push(c);
+            if cluster.graphemes(true).nth(1).is_some() {
+                return false;
+            }
+        }
+        cell.push_combining(c);
+
+        for _ in 0..width {
+            self.wrap_if_pending();
+            let (row, col) = (self.term_buffer.cursor_row, self.term_buffer.cursor_col);
+            if let Some(cell) = self.term_buffer.get_cell_mut(row, col) {
+                *cell = crate::terminal::buffer::TerminalCell {
+                    is_continuation: true,
+                    hyperlink: self.current_hyperlink,
+                    ..Default::default()
+                };
+            }
+            self.advance_cursor(1);
+        }
 
-        // 获取颜色类型参数
-        let color_type = if let Some(type_param) = param_iter.next() {
-            if !type_param.is_empty() {
-                type_param[0]
-            } else {
-                return;
+        true
+    }
+
+    /// 开始或结束 OSC 8 超链接 (OSC 8 ; 参数 ; URI)，URI 为空时结束
+    fn set_hyperlink(&mut self, params: &[&[u8]]) {
+        // URI 中可能含有分号，被拆成了多个参数
+        let uri = params
+            .get(2..)
+            .map(|rest| rest.join(&b';'))
+            .unwrap_or_default();
+        if uri.is_empty() {
+            self.current_hyperlink = None;
+            return;
+        }
+
+        let id = params.get(1).and_then(|link_params| {
+            String::from_utf8_lossy(link_params)
+                .split(':')
+                .find_map(|param| param.strip_prefix("id=").map(str::to_string))
+        });
+        let hyperlink = Hyperlink {
+            id,
+            uri: String::from_utf8_lossy(&uri).into_owned(),
+        };
+        if self.term_buffer.hyperlinks.needs_prune() {
+            // 同步输出期间显示的旧画面同样引用超链接
+            let frame_links = self
+                .sync_frame
+                .iter()
+                .flat_map(|(frame, _)| frame.hyperlinks());
+            self.term_buffer
+                .prune_hyperlinks(self.current_hyperlink.into_iter().chain(frame_links));
+        }
+        self.current_hyperlink = Some(self.term_buffer.hyperlinks.intern(hyperlink));
+    }
+
+    /// 借用供界面显示的缓冲区
+    ///
+    /// 同步输出 (DECSET 2026) 期间把更新开始前的屏幕画面临时换入缓冲区，避免显示更新到一半的内容，
+    /// 历史记录不受影响；超时后放弃同步，显示实时内容。
+    fn with_visible_buffer<R>(&mut self, f: impl FnOnce(&TerminalBuffer) -> R) -> R {
+        if self
+            .sync_frame
+            .as_ref()
+            .is_some_and(|(_, started)| started.elapsed() >= SYNC_OUTPUT_TIMEOUT)
+        {
+            self.sync_frame = None;
+            self.modes.synchronized_output = false;
+        }
+        let Some((ref mut frame, _)) = self.sync_frame else {
+            return f(&self.term_buffer);
+        };
+        if !self.term_buffer.swap_screen(frame) {
+            self.sync_frame = None;
+            return f(&self.term_buffer);
+        }
+        let result = f(&self.term_buffer);
+        self.term_buffer.swap_screen(frame);
+        result
+    }
+
+    /// 更新窗口标题并通知界面
+    fn set_title(&mut self, title: String) {
+        self.title = title.clone();
+        self.send_event(TerminalEvent::TitleChange(title));
+    }
+
+    /// 上一个字符写到了行尾，在写入新字符前折到下一行
+    fn wrap_if_pending(&mut self) {
+        if self.term_buffer.pending_wrap {
+            self.term_buffer.wrap_line();
+        }
+    }
+
+    /// 写入字符后光标右移
+    ///
+    /// 到达行尾时光标停在最后一列，开启自动换行 (DECAWM) 时等到下一个字符再折行。
+    fn advance_cursor(&mut self, width: usize) {
+        let col = self.term_buffer.cursor_col + width;
+        if col < self.term_buffer.cols {
+            self.term_buffer.cursor_col = col;
+        } else {
+            self.term_buffer.cursor_col = self.term_buffer.cols.saturating_sub(1);
+            self.term_buffer.pending_wrap = self.modes.autowrap;
+        }
+    }
+
+    /// 移动光标到（行, 列），超出屏幕时停在边缘；原点模式下行号相对于滚动区域顶部并限制在区域内
+    fn cursor_to(&mut self, row: usize, col: usize) {
+        let buffer = &mut self.term_buffer;
+        let (top, bottom) = if self.modes.origin {
+            buffer.scroll_region()
+        } else {
+            (0, buffer.rows.saturating_sub(1))
+        };
+        let row = (top + row).min(bottom);
+        let col = col.min(buffer.cols.saturating_sub(1));
+        buffer.set_cursor(row, col);
+    }
+
+    /// 保存光标位置与属性 (DECSC / CSI s)
+    fn save_cursor(&mut self) {
+        self.saved_cursor = Some(SavedCursor {
+            row: self.term_buffer.cursor_row,
+            col: self.term_buffer.cursor_col,
+            pending_wrap: self.term_buffer.pending_wrap,
+            sgr: self.sgr,
+            charsets: self.charsets,
+            origin: self.modes.origin,
+        });
+    }
+
+    /// 恢复光标位置与属性 (DECRC / CSI u)，未保存过时回到左上角并重置属性
+    fn restore_cursor(&mut self) {
+        let saved = self.saved_cursor.unwrap_or_default();
+        let row = saved.row.min(self.term_buffer.rows.saturating_sub(1));
+        let col = saved.col.min(self.term_buffer.cols.saturating_sub(1));
+        self.term_buffer.set_cursor(row, col);
+        self.term_buffer.pending_wrap = saved.pending_wrap;
+        self.sgr = saved.sgr;
+        self.charsets = saved.charsets;
+        self.modes.origin = saved.origin;
+    }
+
+    /// 处理 kitty 键盘协议序列 (CSI > flags u / CSI < n u / CSI = flags ; mode u / CSI ? u)
+    fn kitty_keyboard(&mut self, intermediates: &[u8], params: &vte::Params) {
+        let mut it = params.iter().map(|p| p.first().copied().unwrap_or(0));
+        let first = it.next().unwrap_or(0);
+
+        match intermediates {
+            b">" => {
+                if self.kitty_keyboard_stack.len() >= KITTY_KEYBOARD_STACK_LIMIT {
+                    self.kitty_keyboard_stack.remove(0);
+                }
+                self.kitty_keyboard_stack
+                    .push(self.modes.kitty_keyboard_flags);
+                self.modes.kitty_keyboard_flags = first as u8;
+            }
+            b"<" => {
+                for _ in 0..first.max(1) {
+                    match self.kitty_keyboard_stack.pop() {
+                        Some(flags) => self.modes.kitty_keyboard_flags = flags,
+                        None => {
+                            self.modes.kitty_keyboard_flags = 0;
+                            break;
+                        }
+                    }
+                }
+            }
+            b"=" => {
+                let flags = first as u8;
+                match it.next().unwrap_or(1) {
+                    2 => self.modes.kitty_keyboard_flags |= flags,
+                    3 => self.modes.kitty_keyboard_flags &= !flags,
+                    _ => self.modes.kitty_keyboard_flags = flags,
+                }
             }
+            b"?" => {
+                let reply = format!("\x1b[?{}u", self.modes.kitty_keyboard_flags);
+                self.pending_output.extend_from_slice(reply.as_bytes());
+            }
+            _ => {}
+        }
+    }
+
+    /// 从光标处开始把图片铺在 cols x rows 个单元格上
+    ///
+    /// 占用的行列数不超过屏幕大小。move_cursor 为 true 时放不下的部分一次滚屏让出，
+    /// 光标最后停在图片最后一行；否则光标不动，超出屏幕的部分被裁掉。
+    fn place_image(&mut self, image: ImageId, cols: usize, rows: usize, move_cursor: bool) {
+        self.term_buffer.pending_wrap = false;
+        let (start_row, start_col) = (self.term_buffer.cursor_row, self.term_buffer.cursor_col);
+        let cols = cols.min(self.term_buffer.cols);
+        let rows = rows.min(self.term_buffer.rows);
+        if rows == 0 || cols == 0 {
+            return;
+        }
+
+        let mut top = start_row;
+        if move_cursor {
+            let (region_top, region_bottom) = self.term_buffer.scroll_region();
+            if start_row <= region_bottom {
+                let overflow = (start_row + rows).saturating_sub(region_bottom + 1);
+                let scroll = overflow.min(start_row.saturating_sub(region_top));
+                self.term_buffer.scroll_up(scroll);
+                top = start_row - scroll;
+            }
+        }
+
+        let visible_rows = rows.min(self.term_buffer.rows - top);
+        let visible_cols = cols.min(self.term_buffer.cols.saturating_sub(start_col));
+        for r in 0..visible_rows {
+            for c in 0..visible_cols {
+                if let Some(cell) = self.term_buffer.get_cell_mut(top + r, start_col + c) {
+                    *cell = crate::terminal::buffer::TerminalCell {
+                        image: Some(Box::new(ImageCell {
+                            image,
+                            uv: cell_uv(r, c, rows, cols),
+                        })),
+                        ..Default::default()
+                    };
+                }
+            }
+        }
+
+        if move_cursor {
+            self.term_buffer
+                .set_cursor(top + visible_rows - 1, start_col);
         } else {
+            self.term_buffer.set_cursor(start_row, start_col);
+        }
+    }
+
+    /// 保存解码完成的 sixel 图片并显示在光标处，光标移到图片下一行行首
+    fn finish_sixel(&mut self) {
+        let Some(image) = self.sixel.take().and_then(SixelDecoder::finish) else {
             return;
         };
+        let (cols, rows) = cell_span(image.width, image.height, self.cell_size);
+        let id = self.term_buffer.images.insert(image);
+        self.place_image(id, cols, rows, true);
+        self.term_buffer.newline();
+    }
 
-        match color_type {
-            5 => {
-                // 256色
-                if let Some(color_param) = param_iter.next() {
-                    if !color_param.is_empty() {
-                        let color_index = color_param[0] as u16;
-                        if is_foreground {
-                            self.current_fg_256 = Some(color_index);
-                            self.current_fg_rgb = None; // 清除RGB设置
-                        } else {
-                            self.current_bg_256 = Some(color_index);
-                            self.current_bg_rgb = None; // 清除RGB设置
-                        }
+    /// 处理 APC 字符串，目前只有 kitty 图形协议 (ESC _ G ... ESC \)
+    fn apc_dispatch(&mut self, data: &[u8]) {
+        if let Some(rest) = data.strip_prefix(b"G") {
+            self.kitty_graphics(rest);
+        }
+    }
+
+    /// 处理 kitty 图形协议指令
+    fn kitty_graphics(&mut self, data: &[u8]) {
+        let mut command = KittyCommand::parse(data);
+
+        // 分块传输：后续分块只带 m 等少数键，控制数据沿用第一个分块
+        if let Some(mut upload) = self.kitty_upload.take() {
+            if upload.payload.len() + command.payload.len() > kitty::MAX_PAYLOAD_BYTES {
+                self.kitty_reply(&upload, Err("EFBIG:image too large".to_string()));
+                return;
+            }
+            upload.payload.extend_from_slice(&command.payload);
+            if command.more {
+                self.kitty_upload = Some(upload);
+                return;
+            }
+            upload.more = false;
+            command = upload;
+        } else if command.more && matches!(command.action, b't' | b'T' | b'q') {
+            self.kitty_upload = Some(command);
+            return;
+        }
+
+        let result = match command.action {
+            b'q' => command.decode_image().map(|_| ()),
+            b't' | b'T' => command.decode_image().map(|image| {
+                let (cols, rows) = self.kitty_span(&command, &image);
+                let id = self
+                    .term_buffer
+                    .images
+                    .insert_kitty(command.image_id, image);
+                if command.action == b'T' {
+                    self.place_kitty_image(&command, id, cols, rows);
+                }
+            }),
+            b'p' => match self.term_buffer.images.kitty_image(command.image_id) {
+                Some(id) => {
+                    let image = self.term_buffer.images.get(id).cloned();
+                    if let Some(image) = image {
+                        let (cols, rows) = self.kitty_span(&command, &image);
+                        self.place_kitty_image(&command, id, cols, rows);
                     }
+                    Ok(())
                 }
+                None => Err("ENOENT:no such image".to_string()),
+            },
+            b'd' => {
+                self.kitty_delete(&command);
+                return;
             }
-            2 => {
-                // RGB颜色
-                let mut rgb_values = [0u8; 3];
-                let mut valid_count = 0;
-
-                for i in 0..3 {
-                    if let Some(rgb_param) = param_iter.next() {
-                        if !rgb_param.is_empty() {
-                            rgb_values[i] = rgb_param[0] as u8;
-                            valid_count += 1;
-                        }
+            _ => Err("EINVAL:unsupported action".to_string()),
+        };
+
+        self.kitty_reply(&command, result);
+    }
+
+    /// 图片显示时占用的列数与行数，c / r 未指定时按像素大小计算
+    fn kitty_span(&self, command: &KittyCommand, image: &TerminalImage) -> (usize, usize) {
+        let (cols, rows) = cell_span(image.width, image.height, self.cell_size);
+        let cols = if command.cols > 0 {
+            command.cols as usize
+        } else {
+            cols
+        };
+        let rows = if command.rows > 0 {
+            command.rows as usize
+        } else {
+            rows
+        };
+        (cols, rows)
+    }
+
+    /// 显示 kitty 图片，默认光标移到图片右侧（C=1 时不移动）
+    fn place_kitty_image(&mut self, command: &KittyCommand, id: ImageId, cols: usize, rows: usize) {
+        let start_col = self.term_buffer.cursor_col;
+        self.place_image(id, cols, rows, !command.no_cursor_movement);
+        if !command.no_cursor_movement {
+            self.term_buffer.cursor_col =
+                (start_col + cols).min(self.term_buffer.cols.saturating_sub(1));
+        }
+    }
+
+    /// 删除 kitty 图片的显示 (a=d)
+    ///
+    /// d=a 删除屏幕上所有图片，d=i 删除指定 ID 的图片；大写形式同时释放图片数据。
+    fn kitty_delete(&mut self, command: &KittyCommand) {
+        let free = command.delete.is_ascii_uppercase();
+        self.term_buffer.touch_all();
+        match command.delete.to_ascii_lowercase() {
+            b'i' => {
+                let Some(id) = self.term_buffer.images.kitty_image(command.image_id) else {
+                    return;
+                };
+                let buffer = &mut self.term_buffer;
+                for cell in &mut buffer.cells {
+                    if cell.image.as_ref().is_some_and(|image| image.image == id) {
+                        cell.image = None;
                     }
                 }
+                buffer.history.retain_images(|image| image.image != id);
+                if free {
+                    buffer.images.remove(id);
+                }
+            }
+            _ => {
+                for cell in &mut self.term_buffer.cells {
+                    cell.image = None;
+                }
+                if free {
+                    self.term_buffer.images.clear();
+                    self.term_buffer.history.retain_images(|_| false);
+                }
+            }
+        }
+    }
+
+    /// 回复 kitty 图形指令的结果，只有指定了图片 ID 时才回复，q 可抑制回复
+    fn kitty_reply(&mut self, command: &KittyCommand, result: Result<(), String>) {
+        if command.image_id == 0 {
+            return;
+        }
+        let message = match result {
+            Ok(()) if command.quiet == 0 => "OK".to_string(),
+            Err(error) if command.quiet < 2 => error,
+            _ => return,
+        };
+        let reply = kitty::encode_response(command.image_id, command.placement_id, &message);
+        self.pending_output.extend_from_slice(&reply);
+    }
 
-                if valid_count == 3 {
-                    let (r, g, b) = (rgb_values[0], rgb_values[1], rgb_values[2]);
-                    if is_foreground {
-                        self.current_fg_rgb = Some((r, g, b));
-                        self.current_fg_256 = None; // 清除256色设置
+    /// 修改、查询或重置颜色 (OSC 4/10/11/104/110/111)
+    ///
+    /// 修改只记录在缓冲区中，渲染时覆盖主题；查询按修改后的颜色回答，
+    /// 回复使用与请求相同的结束符。
+    fn change_colors(&mut self, params: &[&[u8]], bell_terminated: bool) {
+        let terminator = if bell_terminated { "\x07" } else { "\x1b\\" };
+        let mut overrides = self.term_buffer.palette.clone();
+        let mut reply = String::new();
+
+        match params[0] {
+            b"4" => {
+                for pair in params[1..].chunks(2) {
+                    let [index, spec] = pair else {
+                        break;
+                    };
+                    let Some(index) = std::str::from_utf8(index)
+                        .ok()
+                        .and_then(|index| index.parse::<u8>().ok())
+                    else {
+                        continue;
+                    };
+                    if *spec == b"?" {
+                        let rgb = overrides
+                            .colors
+                            .get(&index)
+                            .copied()
+                            .unwrap_or(self.palette.colors[index as usize]);
+                        reply += &format!("\x1b]4;{index};{}{terminator}", format_color_spec(rgb));
+                    } else if let Some(rgb) =
+                        std::str::from_utf8(spec).ok().and_then(parse_color_spec)
+                    {
+                        overrides.colors.insert(index, rgb);
+                    }
+                }
+            }
+            b"10" | b"11" => {
+                // OSC 10 之后可以接着给出背景色（与 OSC 11 相同）
+                let first = if params[0] == b"10" { 10 } else { 11 };
+                for (code, spec) in (first..=11).zip(&params[1..]) {
+                    let (current, default) = if code == 10 {
+                        (&mut overrides.foreground, self.palette.foreground)
                     } else {
-                        self.current_bg_rgb = Some((r, g, b));
-                        self.current_bg_256 = None; // 清除256色设置
+                        (&mut overrides.background, self.palette.background)
+                    };
+                    if *spec == b"?" {
+                        let rgb = current.unwrap_or(default);
+                        reply += &format!("\x1b]{code};{}{terminator}", format_color_spec(rgb));
+                    } else if let Some(rgb) =
+                        std::str::from_utf8(spec).ok().and_then(parse_color_spec)
+                    {
+                        *current = Some(rgb);
                     }
                 }
             }
+            b"104" => {
+                // 不带参数时重置全部索引色
+                let indices: Vec<u8> = params[1..]
+                    .iter()
+                    .filter_map(|index| std::str::from_utf8(index).ok()?.parse().ok())
+                    .collect();
+                if indices.is_empty() {
+                    overrides.colors.clear();
+                }
+                for index in indices {
+                    overrides.colors.remove(&index);
+                }
+            }
+            b"110" => overrides.foreground = None,
+            b"111" => overrides.background = None,
             _ => {}
         }
+
+        self.pending_output.extend_from_slice(reply.as_bytes());
+        self.term_buffer.set_palette(overrides);
+    }
+
+    /// 处理 SGR 序列，更新之后写入字符使用的颜色与属性
+    fn select_graphic_rendition(&mut self, params: &vte::Params) {
+        // 没有参数等同于 SGR 0
+        if params.is_empty() {
+            self.sgr = SgrState::default();
+            return;
+        }
+
+        let mut iter = params.iter();
+        while let Some(param) = iter.next() {
+            let sgr = &mut self.sgr;
+            match param.first().copied().unwrap_or(0) {
+                0 => *sgr = SgrState::default(),
+                1 => sgr.bold = true,
+                3 => sgr.italic = true,
+                // 4:0 关闭下划线，其余下划线样式都显示为单下划线
+                4 => sgr.underline = param.get(1) != Some(&0),
+                7 => sgr.reverse = true,
+                8 => sgr.hidden = true,
+                21 => sgr.underline = true,
+                22 => sgr.bold = false,
+                23 => sgr.italic = false,
+                24 => sgr.underline = false,
+                27 => sgr.reverse = false,
+                28 => sgr.hidden = false,
+                code @ 30..=37 => sgr.fg = CellColor::Indexed((code - 30) as u8),
+                38 => {
+                    if let Some(color) = extended_color(param, &mut iter) {
+                        sgr.fg = color;
+                    }
+                }
+                39 => sgr.fg = CellColor::Foreground,
+                code @ 40..=47 => sgr.bg = CellColor::Indexed((code - 40) as u8),
+                48 => {
+                    if let Some(color) = extended_color(param, &mut iter) {
+                        sgr.bg = color;
+                    }
+                }
+                49 => sgr.bg = CellColor::Background,
+                code @ 90..=97 => sgr.fg = CellColor::Indexed((code - 90 + 8) as u8),
+                code @ 100..=107 => sgr.bg = CellColor::Indexed((code - 100 + 8) as u8),
+                _ => {}
+            }
+        }
     }
 }
 
 impl Perform for VtePerform {
     fn print(&mut self, c: char) {
+        // 按当前字符集映射（如 DEC 特殊图形字符集中的制表符）
+        let c = self.charsets.map(c);
+
         // 获取字符宽度
         let width = crate::utils::helpers::get_char_width(c);
 
         // 更新旧的字符串缓冲区
         self.buffer.push(c);
 
+        // 与前一个字符组成同一字素簇时附加到该单元格
+        if self.join_previous_cluster(c, width) {
+            return;
+        }
+
+        // 无处附着的零宽字符直接丢弃
+        if width == 0 {
+            return;
+        }
+
+        self.wrap_if_pending();
+
+        // 宽字符在行末放不下时先折到下一行（关闭自动换行时写在倒数第二列）
+        if width == 2
+            && self.term_buffer.cols > 1
+            && self.term_buffer.cursor_col + 1 >= self.term_buffer.cols
+        {
+            if self.modes.autowrap {
+                self.term_buffer.wrap_line();
+            } else {
+                self.term_buffer.cursor_col = self.term_buffer.cols - 2;
+            }
+        }
+
         // 更新单元格缓冲区
         let row = self.term_buffer.cursor_row;
         let col = self.term_buffer.cursor_col;
 
         if let Some(cell) = self.term_buffer.get_cell_mut(row, col) {
             cell.character = c;
-
-            // 优先级：RGB > 256色 > 标准色
-            cell.fg_color = if let Some((r, g, b)) = self.current_fg_rgb {
-                self.theme.parse_rgb_color(r, g, b)
-            } else if let Some(fg_256) = self.current_fg_256 {
-                self.theme.get_color_256(fg_256)
-            } else if let Some(fg) = self.current_fg {
-                self.theme.get_color(fg, self.bold)
-            } else {
-                self.theme.style.foreground
-            };
-
-            cell.bg_color = if let Some((r, g, b)) = self.current_bg_rgb {
-                self.theme.parse_rgb_color(r, g, b)
-            } else if let Some(bg_256) = self.current_bg_256 {
-                self.theme.get_color_256(bg_256)
-            } else if let Some(bg) = self.current_bg {
-                self.theme.get_color(bg, false)
-            } else {
-                egui::Color32::TRANSPARENT
-            };
-
-            cell.bold = self.bold;
+            cell.combining = None;
+
+            // 具体颜色在渲染时由主题决定
+            let (fg, bg) = self.sgr.colors();
+            cell.fg_color = fg;
+            cell.bg_color = bg;
+            cell.bold = self.sgr.bold;
+            cell.italic = self.sgr.italic;
+            cell.underline = self.sgr.underline;
             cell.is_continuation = false;
+            cell.hyperlink = self.current_hyperlink;
+            cell.image = None;
         }
 
         // 如果是宽字符且后面还有位置，标记下一格为延续位
-        if width == 2 && col < self.term_buffer.cols - 1 {
-            if let Some(next_cell) = self.term_buffer.get_cell_mut(row, col + 1) {
-                next_cell.character = ' ';
-                next_cell.is_continuation = true;
-            }
+        if width == 2
+            && col + 1 < self.term_buffer.cols
+            && let Some(next_cell) = self.term_buffer.get_cell_mut(row, col + 1)
+        {
+            next_cell.character = ' ';
+            next_cell.combining = None;
+            next_cell.is_continuation = true;
+            next_cell.hyperlink = self.current_hyperlink;
+            next_cell.image = None;
         }
 
         // 移动光标
-        let move_cols = width;
-        if col + move_cols < self.term_buffer.cols {
-            self.term_buffer.cursor_col += move_cols;
-        } else {
-            self.term_buffer.newline();
-            self.term_buffer.cursor_col = 0;
-        }
-
-        self.send_event(TerminalEvent::Output(c.to_string()));
+        self.advance_cursor(width);
     }
 
     fn execute(&mut self, byte: u8) {
         match byte {
+            0x09 => {
+                // 水平制表 (HT)
+                self.term_buffer.tab_forward(1);
+            }
+            0x0e => {
+                // SO: G1 移入 GL
+                self.charsets.lock_shift(1);
+            }
+            0x0f => {
+                // SI: G0 移入 GL
+                self.charsets.lock_shift(0);
+            }
             0x08 | 0x7f => {
                 // Backspace (0x08) 或者 Delete (0x7f)
                 // 大多数现代系统将 0x7f 作为退格处理
                 self.term_buffer.backspace();
             }
-            0x0a => {
-                // Line feed
+            0x0a..=0x0c => {
+                // Line feed（VT 与 FF 按 LF 处理）
                 self.term_buffer.newline();
                 self.buffer.push('\n');
-                self.send_event(TerminalEvent::Output("\n".to_string()));
             }
             0x0d => {
                 // Carriage return
                 self.term_buffer.carriage_return();
-                // 不添加到缓冲区，但触发回调
-                self.send_event(TerminalEvent::Output("\r".to_string()));
             }
-            _ => {
-                // 其他控制字符
-                self.send_event(TerminalEvent::Output(format!("{}", byte as char)));
+            0x07 => {
+                // 响铃 (BEL)
+                self.send_event(TerminalEvent::Bell);
             }
+            _ => {}
         }
     }
 
-    fn hook(&mut self, _params: &vte::Params, _intermediates: &[u8], _ignore: bool, _c: char) {
-        // 处理CSI序列开始
+    fn hook(&mut self, params: &vte::Params, intermediates: &[u8], _ignore: bool, c: char) {
+        // DCS P1;P2;P3 q：sixel 图片
+        if c == 'q' && intermediates.is_empty() {
+            let params: Vec<u16> = params
+                .iter()
+                .map(|p| p.first().copied().unwrap_or(0))
+                .collect();
+            self.sixel = Some(SixelDecoder::new(&params));
+        }
     }
 
-    fn put(&mut self, _byte: u8) {
-        // 处理CSI序列中的字节
+    fn put(&mut self, byte: u8) {
+        if let Some(ref mut sixel) = self.sixel {
+            sixel.put(byte);
+        }
     }
 
     fn unhook(&mut self) {
-        // 处理CSI序列结束
+        self.finish_sixel();
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        // 处理ESC序列
+        match (intermediates, byte) {
+            ([], b'7') => {
+                // DECSC 保存光标
+                self.save_cursor();
+            }
+            ([], b'8') => {
+                // DECRC 恢复光标
+                self.restore_cursor();
+            }
+            ([], b'H') => {
+                // HTS 在当前列设置制表位
+                self.term_buffer.set_tab_stop();
+            }
+            ([], b'=') => {
+                // DECKPAM 小键盘应用模式
+                self.modes.application_keypad = true;
+            }
+            ([], b'>') => {
+                // DECKPNM 小键盘数字模式
+                self.modes.application_keypad = false;
+            }
+            ([slot @ (b'(' | b')' | b'*' | b'+')], designator) => {
+                // SCS 指定 G0-G3 字符集，不支持的字符集按 ASCII 处理
+                let charset = Charset::from_designator(designator).unwrap_or_default();
+                self.charsets.designate((slot - b'(') as usize, charset);
+            }
+            ([], b'n') => {
+                // LS2: G2 移入 GL
+                self.charsets.lock_shift(2);
+            }
+            ([], b'o') => {
+                // LS3: G3 移入 GL
+                self.charsets.lock_shift(3);
+            }
+            ([], b'N') => {
+                // SS2: 下一个字符使用 G2
+                self.charsets.single_shift(2);
+            }
+            ([], b'O') => {
+                // SS3: 下一个字符使用 G3
+                self.charsets.single_shift(3);
+            }
+            _ => {}
+        }
     }
 
-    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+    fn osc_dispatch(&mut self, params: &[&[u8]], bell_terminated: bool) {
         // 处理OSC序列
         if params.len() > 0 {
             if params[0] == b"0" || params[0] == b"2" {
                 if params.len() > 1 {
-                    let title = String::from_utf8_lossy(params[1]);
-                    self.send_event(TerminalEvent::TitleChange(title.to_string()));
+                    // 标题中的分号被当作参数分隔符拆开了，拼回去
+                    let title = String::from_utf8_lossy(&params[1..].join(&b';')).into_owned();
+                    self.set_title(title);
+                }
+            } else if params[0] == b"8" {
+                self.set_hyperlink(params);
+            } else if params[0] == b"133" {
+                // shell 集成标记：133;A 提示符、B 命令输入、C 命令输出、D[;退出码] 命令结束
+                let shell_mark = match params.get(1).copied() {
+                    Some(b"A") => Some(ShellMark::PromptStart),
+                    Some(b"B") => Some(ShellMark::CommandStart),
+                    Some(b"C") => Some(ShellMark::OutputStart),
+                    Some(b"D") => {
+                        let exit_code = params
+                            .get(2)
+                            .and_then(|code| std::str::from_utf8(code).ok())
+                            .and_then(|code| code.parse().ok());
+                        Some(ShellMark::CommandEnd(exit_code))
+                    }
+                    _ => None,
+                };
+                if let Some(shell_mark) = shell_mark {
+                    self.term_buffer.set_line_mark(shell_mark);
+                }
+            } else if matches!(params[0], b"4" | b"10" | b"11" | b"104" | b"110" | b"111") {
+                self.change_colors(params, bell_terminated);
+            } else if params[0] == b"52" {
+                match parse_osc52(params) {
+                    Some(Osc52Request::Write { selection, text }) => {
+                        self.send_event(TerminalEvent::ClipboardWrite { selection, text });
+                    }
+                    Some(Osc52Request::Read { selection }) => {
+                        self.send_event(TerminalEvent::ClipboardRead { selection });
+                    }
+                    None => {}
                 }
             }
         }
     }
 
-    fn csi_dispatch(
-        &mut self,
-        params: &vte::Params,
-        _intermediates: &[u8],
-        _ignore: bool,
-        c: char,
-    ) {
+    fn csi_dispatch(&mut self, params: &vte::Params, intermediates: &[u8], _ignore: bool, c: char) {
         // 处理CSI控制序列
         match c {
+            'h' | 'l' if intermediates == b"?" => {
+                // DEC私有模式设置/重置 (CSI ? Pm h / CSI ? Pm l)
+                let enabled = c == 'h';
+                for param in params.iter() {
+                    if let Some(&mode) = param.first() {
+                        self.modes.set_dec_private(mode, enabled);
+                        if mode == 6 {
+                            // 切换原点模式时光标回到原点
+                            self.cursor_to(0, 0);
+                        }
+                        if mode == 2026 {
+                            // 开始同步时记下当前的屏幕画面，结束时丢弃
+                            if !enabled {
+                                self.sync_frame = None;
+                            } else if self.sync_frame.is_none() {
+                                self.sync_frame =
+                                    Some((self.term_buffer.snapshot_screen(), Instant::now()));
+                            }
+                        }
+                    }
+                }
+                self.term_buffer.cursor_visible = self.modes.cursor_visible;
+                if !self.modes.autowrap {
+                    self.term_buffer.pending_wrap = false;
+                }
+            }
+            'p' if intermediates == b"?$" => {
+                // DECRQM 查询 DEC 私有模式：1 已设置，2 已重置，0 不支持
+                let mode = params.iter().next().and_then(|p| p.first()).copied();
+                let mode = mode.unwrap_or(0);
+                let state = match self.modes.dec_private(mode) {
+                    Some(true) => 1,
+                    Some(false) => 2,
+                    None => 0,
+                };
+                let reply = format!("\x1b[?{};{}$y", mode, state);
+                self.pending_output.extend_from_slice(reply.as_bytes());
+            }
+            'q' if intermediates == b" " => {
+                // DECSCUSR 设置光标样式，0 恢复默认
+                let ps = params.iter().next().and_then(|p| p.first()).copied();
+                match ps.unwrap_or(0) {
+                    0 => self.term_buffer.cursor_style = None,
+                    ps => {
+                        if let Some(style) = CursorStyle::from_decscusr(ps) {
+                            self.term_buffer.cursor_style = Some(style);
+                        }
+                    }
+                }
+            }
+            's' if intermediates.is_empty() => {
+                // SCOSC 保存光标
+                self.save_cursor();
+            }
+            'u' if intermediates.is_empty() => {
+                // SCORC 恢复光标
+                self.restore_cursor();
+            }
+            'I' => {
+                // CHT 光标前进 n 个制表位
+                let n = params.iter().next().and_then(|p| p.first()).copied();
+                self.term_buffer.tab_forward(n.unwrap_or(1).max(1) as usize);
+            }
+            'Z' => {
+                // CBT 光标后退 n 个制表位
+                let n = params.iter().next().and_then(|p| p.first()).copied();
+                self.term_buffer
+                    .tab_backward(n.unwrap_or(1).max(1) as usize);
+            }
+            't' if intermediates.is_empty() => {
+                // 窗口操作：支持标题栈与像素大小查询（供 sixel / kitty 图形程序计算图片尺寸）
+                let (cell_width, cell_height) = self.cell_size;
+                let reply = match params.iter().next().and_then(|p| p.first()).copied() {
+                    // 文本区域像素大小
+                    Some(14) => format!(
+                        "\x1b[4;{};{}t",
+                        (cell_height * self.term_buffer.rows as f32).round() as usize,
+                        (cell_width * self.term_buffer.cols as f32).round() as usize
+                    ),
+                    // 单元格像素大小
+                    Some(16) => format!(
+                        "\x1b[6;{};{}t",
+                        cell_height.round() as usize,
+                        cell_width.round() as usize
+                    ),
+                    // 压入标题（22;0 与 22;2 都按窗口标题处理，22;1 图标标题不支持）
+                    Some(22) => {
+                        if params.iter().nth(1).and_then(|p| p.first()).copied() != Some(1) {
+                            if self.title_stack.len() >= TITLE_STACK_LIMIT {
+                                self.title_stack.remove(0);
+                            }
+                            self.title_stack.push(self.title.clone());
+                        }
+                        return;
+                    }
+                    // 弹出标题
+                    Some(23) => {
+                        if params.iter().nth(1).and_then(|p| p.first()).copied() != Some(1)
+                            && let Some(title) = self.title_stack.pop()
+                        {
+                            self.set_title(title);
+                        }
+                        return;
+                    }
+                    _ => return,
+                };
+                self.pending_output.extend_from_slice(reply.as_bytes());
+            }
+            'g' => {
+                // TBC 清除制表位
+                match params.iter().next().and_then(|p| p.first()).copied() {
+                    Some(0) | None => self.term_buffer.clear_tab_stop(),
+                    Some(3) => self.term_buffer.clear_all_tab_stops(),
+                    _ => {}
+                }
+            }
+            'u' if !intermediates.is_empty() => {
+                // kitty 键盘协议
+                self.kitty_keyboard(intermediates, params);
+            }
             'H' | 'f' => {
                 // 光标定位 (CSI Row;Col H/f)
                 let mut it = params.iter();
@@ -319,35 +1187,62 @@ impl Perform for VtePerform {
                     .map(|&v| if v > 0 { v as usize } else { 1 })
                     .unwrap_or(1);
 
-                self.term_buffer
-                    .set_cursor(row.saturating_sub(1), col.saturating_sub(1));
+                self.cursor_to(row.saturating_sub(1), col.saturating_sub(1));
                 self.send_event(TerminalEvent::CursorPosition { row, col });
             }
+            'r' if intermediates.is_empty() => {
+                // DECSTBM 设置滚动区域 (CSI top;bottom r)，省略时为整屏，之后光标回到原点
+                let mut it = params.iter();
+                let top = it.next().and_then(|p| p.first()).copied().unwrap_or(0);
+                let bottom = it.next().and_then(|p| p.first()).copied().unwrap_or(0);
+                let top = (top.max(1) - 1) as usize;
+                let bottom = match bottom as usize {
+                    0 => self.term_buffer.rows,
+                    bottom => bottom.min(self.term_buffer.rows),
+                };
+                if top + 1 < bottom {
+                    self.term_buffer.set_scroll_region(top, bottom - 1);
+                    self.cursor_to(0, 0);
+                }
+            }
             'A' => {
                 // 光标上移
+                // 从滚动区域内出发时停在上边界
                 let n = params.iter().next().and_then(|p| p.get(0)).unwrap_or(&1);
-                let new_row = self.term_buffer.cursor_row.saturating_sub(*n as usize);
-                self.term_buffer.cursor_row = new_row;
+                let (top, _) = self.term_buffer.scroll_region();
+                let row = self.term_buffer.cursor_row;
+                let limit = if row >= top { top } else { 0 };
+                self.term_buffer.cursor_row = row.saturating_sub((*n).max(1) as usize).max(limit);
+                self.term_buffer.pending_wrap = false;
             }
             'B' => {
                 // 光标下移
+                // 从滚动区域内出发时停在下边界
                 let n = params.iter().next().and_then(|p| p.get(0)).unwrap_or(&1);
-                let new_row =
-                    (self.term_buffer.cursor_row + *n as usize).min(self.term_buffer.rows - 1);
-                self.term_buffer.cursor_row = new_row;
+                let (_, bottom) = self.term_buffer.scroll_region();
+                let row = self.term_buffer.cursor_row;
+                let limit = if row <= bottom {
+                    bottom
+                } else {
+                    self.term_buffer.rows.saturating_sub(1)
+                };
+                self.term_buffer.cursor_row = (row + (*n).max(1) as usize).min(limit);
+                self.term_buffer.pending_wrap = false;
             }
             'C' => {
                 // 光标右移
                 let n = params.iter().next().and_then(|p| p.get(0)).unwrap_or(&1);
-                let new_col =
-                    (self.term_buffer.cursor_col + *n as usize).min(self.term_buffer.cols - 1);
+                let new_col = (self.term_buffer.cursor_col + *n as usize)
+                    .min(self.term_buffer.cols.saturating_sub(1));
                 self.term_buffer.cursor_col = new_col;
+                self.term_buffer.pending_wrap = false;
             }
             'D' => {
                 // 光标左移
                 let n = params.iter().next().and_then(|p| p.get(0)).unwrap_or(&1);
                 let new_col = self.term_buffer.cursor_col.saturating_sub(*n as usize);
                 self.term_buffer.cursor_col = new_col;
+                self.term_buffer.pending_wrap = false;
             }
             'K' => {
                 // 清除行 (CSI n K)
@@ -361,6 +1256,7 @@ impl Perform for VtePerform {
                                 *cell = crate::terminal::buffer::TerminalCell::default();
                             }
                         }
+                        self.term_buffer.set_wrapped(row, false);
                     }
                     1 => {
                         // 从行首清除到光标
@@ -377,6 +1273,7 @@ impl Perform for VtePerform {
                                 *cell = crate::terminal::buffer::TerminalCell::default();
                             }
                         }
+                        self.term_buffer.set_wrapped(row, false);
                     }
                     _ => {}
                 }
@@ -394,68 +1291,8 @@ impl Perform for VtePerform {
                         self.term_buffer.clear();
                     }
                 }
-                self.send_event(TerminalEvent::Output("\x1b[2J\x1b[H".to_string()));
-            }
-            'm' => {
-                // 颜色和样式
-                // 处理ANSI颜色代码
-                if params.len() == 0 {
-                    // 重置所有属性
-                    self.current_fg = None;
-                    self.current_bg = None;
-                    self.bold = false;
-                } else {
-                    // 处理颜色参数
-                    for param in params.iter() {
-                        if param.len() > 0 {
-                            match param[0] {
-                                0 => {
-                                    // 重置
-                                    self.current_fg = None;
-                                    self.current_bg = None;
-                                    self.current_fg_256 = None;
-                                    self.current_bg_256 = None;
-                                    self.current_fg_rgb = None;
-                                    self.current_bg_rgb = None;
-                                    self.bold = false;
-                                }
-                                1 => {
-                                    // 加粗
-                                    self.bold = true;
-                                }
-                                30..=37 => {
-                                    // 前景色
-                                    self.current_fg = Some((param[0] - 30) as u8);
-                                }
-                                40..=47 => {
-                                    // 背景色
-                                    self.current_bg = Some((param[0] - 40) as u8);
-                                }
-                                90..=97 => {
-                                    // 亮前景色
-                                    self.current_fg = Some((param[0] - 90 + 8) as u8);
-                                }
-                                100..=107 => {
-                                    // 亮背景色
-                                    self.current_bg = Some((param[0] - 100 + 8) as u8);
-                                }
-                                38 => {
-                                    // 前景色扩展（256色或RGB）
-                                    self.parse_extended_color(true, params);
-                                    break; // 跳过后续参数处理
-                                }
-                                48 => {
-                                    // 背景色扩展（256色或RGB）
-                                    self.parse_extended_color(false, params);
-                                    break; // 跳过后续参数处理
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                }
-                self.send_event(TerminalEvent::Output("\x1b[m".to_string()));
             }
+            'm' => self.select_graphic_rendition(params),
             _ => {
                 // 其他CSI序列
             }
@@ -467,7 +1304,6 @@ impl Perform for VtePerform {
 mod tests {
     use super::*;
     use crate::terminal::buffer::TerminalBuffer;
-    use crate::terminal::theme::TerminalTheme;
 
     #[test]
     fn test_basic_emulator_creation() {
@@ -552,6 +1388,243 @@ mod tests {
         assert_eq!(buffer.cols, 60);
     }
 
+    #[test]
+    fn test_resize_reflows_wrapped_output() {
+        let mut emulator = TerminalEmulator::new(5, 10);
+        emulator.process_input(b"0123456789abcde\r\n$ ").unwrap();
+
+        let buffer = emulator.buffer();
+        assert!(buffer.wrapped[0]);
+        assert!(!buffer.wrapped[1]);
+
+        emulator.resize(5, 20);
+        let buffer = emulator.buffer();
+        let first_row: String = (0..15)
+            .map(|c| buffer.get_cell(0, c).unwrap().character)
+            .collect();
+        assert_eq!(first_row, "0123456789abcde");
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (1, 2));
+    }
+
+    fn cell_text(buffer: &TerminalBuffer, row: usize, col: usize) -> String {
+        buffer.get_cell(row, col).unwrap().text()
+    }
+
+    #[test]
+    fn test_wide_char_widths() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        emulator.process_input("中a😀".as_bytes()).unwrap();
+
+        let buffer = emulator.buffer();
+        assert_eq!(cell_text(&buffer, 0, 0), "中");
+        assert!(buffer.get_cell(0, 1).unwrap().is_continuation);
+        assert_eq!(cell_text(&buffer, 0, 2), "a");
+        assert_eq!(cell_text(&buffer, 0, 3), "😀");
+        assert!(buffer.get_cell(0, 4).unwrap().is_continuation);
+        assert_eq!(buffer.cursor_col, 5);
+    }
+
+    #[test]
+    fn test_combining_marks_attach_to_previous_cell() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        emulator.process_input("e\u{301}x".as_bytes()).unwrap();
+
+        let buffer = emulator.buffer();
+        assert_eq!(cell_text(&buffer, 0, 0), "e\u{301}");
+        assert_eq!(cell_text(&buffer, 0, 1), "x");
+        assert_eq!(buffer.cursor_col, 2);
+    }
+
+    #[test]
+    fn test_zwj_sequence_is_one_glyph() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        emulator.process_input(family.as_bytes()).unwrap();
+
+        let buffer = emulator.buffer();
+        assert_eq!(cell_text(&buffer, 0, 0), family);
+        // 光标按 wcwidth 之和前进 (2 + 0 + 2 + 0 + 2)
+        assert_eq!(buffer.cursor_col, 6);
+        assert!((1..6).all(|col| buffer.get_cell(0, col).unwrap().is_continuation));
+
+        // 选中复制时整个序列只输出一次
+        emulator.start_selection(0, 0);
+        emulator.update_selection(0, 5);
+        assert_eq!(emulator.get_selected_text().as_deref(), Some(family));
+    }
+
+    #[test]
+    fn test_wide_char_wraps_at_line_end() {
+        let mut emulator = TerminalEmulator::new(5, 4);
+        emulator.process_input("abc中".as_bytes()).unwrap();
+
+        let buffer = emulator.buffer();
+        assert_eq!(cell_text(&buffer, 1, 0), "中");
+        assert!(buffer.wrapped[0]);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (1, 2));
+    }
+
+    #[test]
+    fn test_dec_line_drawing() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        emulator
+            .process_input(b"\x1b(0lqk\x1b(Bq\x1b)0\x0eq\x0fq")
+            .unwrap();
+
+        let buffer = emulator.buffer();
+        let line: String = (0..6)
+            .map(|col| buffer.get_cell(0, col).unwrap().character)
+            .collect();
+        assert_eq!(line, "┌─┐q─q");
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        let mut emulator = TerminalEmulator::new(5, 40);
+        emulator.process_input(b"a\tb").unwrap();
+        assert_eq!(emulator.buffer().get_cell(0, 8).unwrap().character, 'b');
+
+        // 清除全部制表位后在第4列设置一个
+        emulator
+            .process_input(b"\r\x1b[3g\x1b[1;4H\x1bH\r\tX")
+            .unwrap();
+        let buffer = emulator.buffer();
+        assert_eq!(buffer.get_cell(0, 3).unwrap().character, 'X');
+
+        // CHT 越过所有制表位停在最后一列
+        emulator.process_input(b"\r\x1b[2I").unwrap();
+        assert_eq!(emulator.buffer().cursor_col, 39);
+
+        // CBT 回到第4列
+        emulator.process_input(b"\x1b[Z").unwrap();
+        assert_eq!(emulator.buffer().cursor_col, 3);
+    }
+
+    #[test]
+    fn test_save_restore_cursor_with_attributes() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        emulator
+            .process_input(b"\x1b[3;5H\x1b[1;31m\x1b(0\x1b7\x1b[0m\x1b(B\x1b[H")
+            .unwrap();
+        emulator.process_input(b"\x1b8q").unwrap();
+
+        let buffer = emulator.buffer();
+        let cell = buffer.get_cell(2, 4).unwrap();
+        assert_eq!(cell.character, '─');
+        assert!(cell.bold);
+
+        // CSI s / CSI u
+        emulator
+            .process_input(b"\x1b[0m\x1b[2;2H\x1b[s\x1b[5;10H\x1b[u")
+            .unwrap();
+        let buffer = emulator.buffer();
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (1, 1));
+    }
+
+    #[test]
+    fn test_save_restore_cursor_keeps_full_sgr_state() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        emulator
+            .process_input(b"\x1b[1;3;4;7;38;5;100;48;2;1;2;3m\x1b7\x1b[0m\x1b[2;1Hx\x1b8y")
+            .unwrap();
+
+        let buffer = emulator.buffer();
+        let plain = buffer.get_cell(1, 0).unwrap();
+        assert!(!plain.bold && !plain.italic && !plain.underline);
+        assert_eq!(plain.fg_color, CellColor::Foreground);
+
+        // ESC 8 恢复 ESC 7 时的全部属性（反显时前景色与背景色交换）
+        let restored = buffer.get_cell(0, 0).unwrap();
+        assert_eq!(restored.character, 'y');
+        assert!(restored.bold && restored.italic && restored.underline);
+        assert_eq!(restored.fg_color, CellColor::Rgb(1, 2, 3));
+        assert_eq!(restored.bg_color, CellColor::Indexed(100));
+    }
+
+    #[test]
+    fn test_scroll_region_and_origin_mode() {
+        let mut emulator = TerminalEmulator::new(5, 10);
+        emulator.process_input(b"1\r\n2\r\n3\r\n4\r\n5").unwrap();
+
+        // 区域为第 2-4 行，原点模式下行号相对于区域顶部
+        emulator
+            .process_input(b"\x1b[2;4r\x1b[?6h\x1b[3;1Hx\n")
+            .unwrap();
+        let buffer = emulator.buffer();
+        let column: String = (0..5)
+            .map(|row| buffer.get_cell(row, 0).unwrap().character)
+            .collect();
+        assert_eq!(column, "13x 5");
+        assert_eq!(buffer.history.len(), 0);
+        assert_eq!(buffer.cursor_row, 3);
+
+        // 原点模式下定位不会离开区域
+        emulator.process_input(b"\x1b[9;1H").unwrap();
+        assert_eq!(emulator.buffer().cursor_row, 3);
+        emulator.process_input(b"\x1b[10A").unwrap();
+        assert_eq!(emulator.buffer().cursor_row, 1);
+
+        // 恢复整屏后回到原来的滚动行为
+        emulator
+            .process_input(b"\x1b[?6l\x1b[r\x1b[5;1H\n")
+            .unwrap();
+        assert_eq!(emulator.buffer().history.len(), 1);
+    }
+
+    #[test]
+    fn test_deferred_autowrap() {
+        let mut emulator = TerminalEmulator::new(3, 5);
+
+        // 恰好写满一行后回车换行不应多出空行
+        emulator.process_input(b"abcde\r\nf").unwrap();
+        let buffer = emulator.buffer();
+        assert_eq!(buffer.get_cell(1, 0).unwrap().character, 'f');
+        assert!(!buffer.wrapped[0]);
+
+        // 关闭 DECAWM 后行尾字符被覆盖
+        emulator.process_input(b"\x1b[?7l\x1b[3;1Hvwxyz12").unwrap();
+        let buffer = emulator.buffer();
+        assert_eq!(buffer.get_cell(2, 4).unwrap().character, '2');
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (2, 4));
+    }
+
+    #[test]
+    fn test_cursor_visibility_and_origin_mode() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        assert!(emulator.buffer().cursor_visible);
+
+        emulator.process_input(b"\x1b[?25l").unwrap();
+        assert!(!emulator.buffer().cursor_visible);
+        emulator.process_input(b"\x1b[?25h").unwrap();
+        assert!(emulator.buffer().cursor_visible);
+
+        emulator.process_input(b"\x1b[3;3H\x1b[?6h").unwrap();
+        let buffer = emulator.buffer();
+        assert!(emulator.modes().origin);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (0, 0));
+    }
+
+    #[test]
+    fn test_cursor_shape_sequence() {
+        use crate::terminal::cursor::CursorShape;
+
+        let mut emulator = TerminalEmulator::new(5, 20);
+        assert_eq!(emulator.buffer().cursor_style, None);
+
+        emulator.process_input(b"\x1b[6 q").unwrap();
+        let style = emulator.buffer().cursor_style.unwrap();
+        assert_eq!(style.shape, CursorShape::Bar);
+        assert!(!style.blinking);
+
+        emulator.process_input(b"\x1b[3 q").unwrap();
+        let style = emulator.buffer().cursor_style.unwrap();
+        assert_eq!(style.shape, CursorShape::Underline);
+        assert!(style.blinking);
+
+        emulator.process_input(b"\x1b[0 q").unwrap();
+        assert_eq!(emulator.buffer().cursor_style, None);
+    }
+
     #[test]
     fn test_state_management() {
         let mut emulator = TerminalEmulator::new(10, 40);
@@ -635,6 +1708,43 @@ mod tests {
         assert!(buffer.cursor_col > 0);
     }
 
+    #[test]
+    fn test_sgr_attributes_set_and_clear() {
+        let mut emulator = TerminalEmulator::new(2, 20);
+        emulator
+            .process_input(b"\x1b[1;3;4;7;31ma\x1b[22;23;24;27mb\x1b[1;38:2::1:2:3;48;5;4mc")
+            .unwrap();
+        emulator.process_input(b"\x1b[39;49md\x1b[8me").unwrap();
+
+        let buffer = emulator.buffer();
+        let a = buffer.get_cell(0, 0).unwrap();
+        assert!(a.bold && a.italic && a.underline);
+        // 反显交换前景色与背景色
+        assert_eq!(a.fg_color, CellColor::Background);
+        assert_eq!(a.bg_color, CellColor::Indexed(1));
+
+        let b = buffer.get_cell(0, 1).unwrap();
+        assert!(!b.bold && !b.italic && !b.underline);
+        assert_eq!(b.fg_color, CellColor::Indexed(1));
+        assert_eq!(b.bg_color, CellColor::Background);
+
+        // 扩展颜色之后的参数仍然生效
+        let c = buffer.get_cell(0, 2).unwrap();
+        assert!(c.bold);
+        assert_eq!(c.fg_color, CellColor::Rgb(1, 2, 3));
+        assert_eq!(c.bg_color, CellColor::Indexed(4));
+
+        let d = buffer.get_cell(0, 3).unwrap();
+        assert_eq!(d.fg_color, CellColor::Foreground);
+        assert_eq!(d.bg_color, CellColor::Background);
+
+        // 隐藏的文字与背景同色
+        assert_eq!(
+            buffer.get_cell(0, 4).unwrap().fg_color,
+            CellColor::Background
+        );
+    }
+
     #[test]
     fn test_rgb_color_support() {
         let mut emulator = TerminalEmulator::new(10, 40);
@@ -655,12 +1765,524 @@ mod tests {
         let buffer = emulator.buffer();
         assert!(buffer.cursor_col > 0);
     }
+
+    #[test]
+    fn test_bracketed_paste_mode() {
+        let mut emulator = TerminalEmulator::new(10, 40);
+        assert!(!emulator.modes().bracketed_paste);
+
+        emulator.process_input(b"\x1b[?2004h").unwrap();
+        assert!(emulator.modes().bracketed_paste);
+
+        // 未知模式与已知模式混合时只影响已知模式
+        emulator.process_input(b"\x1b[?1049;2004l").unwrap();
+        assert!(!emulator.modes().bracketed_paste);
+
+        // 非私有模式的 2004 不应生效
+        emulator.process_input(b"\x1b[2004h").unwrap();
+        assert!(!emulator.modes().bracketed_paste);
+    }
+
+    #[test]
+    fn test_focus_reporting_mode() {
+        let mut emulator = TerminalEmulator::new(10, 40);
+        assert!(!emulator.modes().focus_reporting);
+
+        emulator.process_input(b"\x1b[?1004h\x1b[?1004$p").unwrap();
+        assert!(emulator.modes().focus_reporting);
+        assert_eq!(emulator.take_pending_output(), b"\x1b[?1004;1$y".to_vec());
+
+        emulator.process_input(b"\x1b[?1004l").unwrap();
+        assert!(!emulator.modes().focus_reporting);
+    }
+
+    #[test]
+    fn test_osc_palette_changes() {
+        let mut emulator = TerminalEmulator::new(10, 40);
+        emulator.process_input(b"\x1b[31mR").unwrap();
+
+        // 修改只记录在缓冲区中，单元格仍是调色板索引
+        emulator
+            .process_input(b"\x1b]4;1;#ff8000\x07\x1b]10;rgb:00/00/ff;rgb:ff/ff/ff\x07")
+            .unwrap();
+        let buffer = emulator.buffer();
+        assert_eq!(
+            buffer.get_cell(0, 0).unwrap().fg_color,
+            CellColor::Indexed(1)
+        );
+        assert_eq!(buffer.palette.colors.get(&1), Some(&(255, 128, 0)));
+        assert_eq!(buffer.palette.foreground, Some((0, 0, 255)));
+        assert_eq!(buffer.palette.background, Some((255, 255, 255)));
+
+        // 查询按修改后的颜色回答，未修改的按主题配色回答
+        emulator.set_palette(Palette::default());
+        emulator
+            .process_input(b"\x1b]4;1;?;2;?\x1b\\\x1b]11;?\x07")
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(emulator.take_pending_output()).unwrap(),
+            "\x1b]4;1;rgb:ffff/8080/0000\x1b\\\x1b]4;2;rgb:0000/8080/0000\x1b\\\x1b]11;rgb:ffff/ffff/ffff\x07"
+        );
+
+        emulator
+            .process_input(b"\x1b]104\x07\x1b]110\x07\x1b]111\x07")
+            .unwrap();
+        assert!(emulator.buffer().palette.is_empty());
+    }
+
+    #[test]
+    fn test_cursor_and_keypad_modes() {
+        let mut emulator = TerminalEmulator::new(10, 40);
+
+        emulator.process_input(b"\x1b[?1h\x1b=").unwrap();
+        assert!(emulator.modes().application_cursor);
+        assert!(emulator.modes().application_keypad);
+
+        emulator.process_input(b"\x1b[?1l\x1b>").unwrap();
+        assert!(!emulator.modes().application_cursor);
+        assert!(!emulator.modes().application_keypad);
+    }
+
+    #[test]
+    fn test_kitty_keyboard_flags() {
+        let mut emulator = TerminalEmulator::new(10, 40);
+
+        emulator.process_input(b"\x1b[>1u").unwrap();
+        assert_eq!(emulator.modes().kitty_keyboard_flags, 1);
+        emulator.process_input(b"\x1b[>9u").unwrap();
+        assert_eq!(emulator.modes().kitty_keyboard_flags, 9);
+
+        emulator.process_input(b"\x1b[?u").unwrap();
+        assert_eq!(emulator.take_pending_output(), b"\x1b[?9u".to_vec());
+        assert!(emulator.take_pending_output().is_empty());
+
+        emulator.process_input(b"\x1b[<u").unwrap();
+        assert_eq!(emulator.modes().kitty_keyboard_flags, 1);
+
+        emulator.process_input(b"\x1b[=8;2u").unwrap();
+        assert_eq!(emulator.modes().kitty_keyboard_flags, 9);
+        emulator.process_input(b"\x1b[=1;3u").unwrap();
+        assert_eq!(emulator.modes().kitty_keyboard_flags, 8);
+
+        // 弹出超过栈深度时恢复为0
+        emulator.process_input(b"\x1b[<5u").unwrap();
+        assert_eq!(emulator.modes().kitty_keyboard_flags, 0);
+    }
+
+    #[test]
+    fn test_osc8_hyperlink() {
+        let mut emulator = TerminalEmulator::new(5, 40);
+        emulator
+            .process_input(
+                b"go \x1b]8;id=x;https://example.com/?a=1;b=2\x1b\\here\x1b]8;;\x1b\\ now",
+            )
+            .unwrap();
+
+        let buffer = emulator.buffer();
+        let link = buffer.link_at(0, 4).unwrap();
+        assert_eq!(link.target, "https://example.com/?a=1;b=2");
+        assert_eq!((link.start_col, link.end_col), (3, 7));
+        assert!(buffer.get_cell(0, 8).unwrap().hyperlink.is_none());
+        assert_eq!(buffer.link_at(0, 9), None);
+    }
+
+    #[test]
+    fn test_osc133_shell_marks() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        emulator
+            .process_input(b"\x1b]133;A\x07$ \x1b]133;B\x07false\r\n\x1b]133;C\x07")
+            .unwrap();
+        emulator
+            .process_input(b"\x1b]133;D;1\x07\x1b]133;A\x07$ ")
+            .unwrap();
+
+        let buffer = emulator.buffer();
+        let first = buffer.line_mark(0).unwrap();
+        assert!(first.prompt && first.command);
+        assert_eq!(first.exit_code, Some(1));
+        let second = buffer.line_mark(1).unwrap();
+        assert!(second.output && second.prompt);
+        assert_eq!(second.exit_code, None);
+    }
+
+    #[test]
+    fn test_synchronized_output() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        emulator.process_input(b"old").unwrap();
+        emulator.process_input(b"\x1b[?2026h\r\x1b[Knew").unwrap();
+
+        // 同步期间显示更新开始前的画面
+        assert_eq!(emulator.buffer().get_cell(0, 0).unwrap().character, 'o');
+        emulator.process_input(b"\x1b[?2026$p").unwrap();
+        assert_eq!(emulator.take_pending_output(), b"\x1b[?2026;1$y".to_vec());
+
+        emulator.process_input(b"\x1b[?2026l").unwrap();
+        assert_eq!(emulator.buffer().get_cell(0, 0).unwrap().character, 'n');
+        emulator.process_input(b"\x1b[?2026$p\x1b[?9999$p").unwrap();
+        assert_eq!(
+            emulator.take_pending_output(),
+            b"\x1b[?2026;2$y\x1b[?9999;0$y".to_vec()
+        );
+
+        // 只冻结屏幕：同步期间滚出的行直接进入历史，屏幕行的修改计数与旧画面一致
+        let frozen_version = emulator.with_buffer(|buffer| buffer.line_version(0));
+        emulator
+            .process_input(b"\x1b[?2026h\x1b[5;1H\n\n\nscrolled")
+            .unwrap();
+        emulator.with_buffer(|buffer| {
+            assert_eq!(buffer.history.len(), 3);
+            assert_eq!(buffer.get_cell(0, 0).unwrap().character, 'n');
+            assert_eq!(buffer.line_version(3), frozen_version);
+        });
+        emulator.process_input(b"\x1b[?2026l").unwrap();
+        emulator.with_buffer(|buffer| {
+            assert_eq!(buffer.get_cell(4, 0).unwrap().character, 's');
+            assert_ne!(buffer.line_version(3), frozen_version);
+        });
+    }
+
+    #[test]
+    fn test_title_stack() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        let titles = Arc::new(Mutex::new(Vec::new()));
+        let titles_clone = titles.clone();
+        emulator.set_event_callback(move |event| {
+            if let TerminalEvent::TitleChange(title) = event {
+                titles_clone.lock().unwrap().push(title);
+            }
+        });
+
+        emulator
+            .process_input(b"\x1b]2;vim a;b\x07\x1b[22;0t\x1b]0;htop\x1b\\\x1b[23;0t\x1b[23t")
+            .unwrap();
+
+        // 栈空时弹出不产生事件
+        assert_eq!(
+            *titles.lock().unwrap(),
+            vec![
+                "vim a;b".to_string(),
+                "htop".to_string(),
+                "vim a;b".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bell_event() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        let bells = Arc::new(Mutex::new(0));
+        let bells_clone = bells.clone();
+        emulator.set_event_callback(move |event| {
+            if matches!(event, TerminalEvent::Bell) {
+                *bells_clone.lock().unwrap() += 1;
+            }
+        });
+
+        // 作为 OSC 结束符的 BEL 不算响铃
+        emulator.process_input(b"\x1b]0;title\x07a\x07").unwrap();
+        assert_eq!(*bells.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_osc52_clipboard_events() {
+        let mut emulator = TerminalEmulator::new(10, 40);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        emulator.set_event_callback(move |event| {
+            if matches!(
+                event,
+                TerminalEvent::ClipboardWrite { .. } | TerminalEvent::ClipboardRead { .. }
+            ) {
+                events_clone.lock().unwrap().push(event);
+            }
+        });
+
+        emulator.process_input(b"\x1b]52;c;aGVsbG8=\x07").unwrap();
+        emulator.process_input(b"\x1b]52;c;?\x1b\\").unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            TerminalEvent::ClipboardWrite { selection, text } if selection == "c" && text == "hello"
+        ));
+        assert!(matches!(
+            &events[1],
+            TerminalEvent::ClipboardRead { selection } if selection == "c"
+        ));
+    }
+
+    #[test]
+    fn test_sixel_image_placement() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        // 16x12 像素，默认单元格 8x16 时占 2 列 1 行
+        emulator
+            .process_input(b"ab\x1bPq\"1;1;16;12#1;2;100;0;0#1!16~-!16~\x1b\\c")
+            .unwrap();
+
+        let buffer = emulator.buffer();
+        let image = buffer.get_cell(0, 2).unwrap().image.clone().unwrap();
+        assert!(buffer.get_cell(0, 3).unwrap().image.is_some());
+        assert!(buffer.get_cell(0, 4).unwrap().image.is_none());
+        assert_eq!(image.uv[2], 0.5);
+
+        let pixels = buffer.images.get(image.image).unwrap();
+        assert_eq!((pixels.width, pixels.height), (16, 12));
+        // 图片之后光标移到下一行行首
+        assert_eq!(buffer.get_cell(1, 0).unwrap().character, 'c');
+    }
+
+    #[test]
+    fn test_kitty_graphics_across_chunks() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        // 分块传输，且 APC 的 ESC 与 `_` 被拆在两次读取中
+        emulator
+            .process_input(b"x\x1b_Ga=T,f=24,s=1,v=1,i=3,m=1;AQ\x1b\\\x1b")
+            .unwrap();
+        emulator.process_input(b"_Gm=0;ID\x1b\\y\x1b").unwrap();
+        emulator.process_input(b"[2Cz").unwrap();
+
+        let buffer = emulator.buffer();
+        assert_eq!(buffer.get_cell(0, 0).unwrap().character, 'x');
+        let image = buffer.get_cell(0, 1).unwrap().image.clone().unwrap();
+        assert_eq!(
+            buffer.images.get(image.image).unwrap().rgba,
+            vec![1, 2, 3, 255]
+        );
+        assert_eq!(buffer.get_cell(0, 2).unwrap().character, 'y');
+        assert_eq!(buffer.get_cell(0, 5).unwrap().character, 'z');
+        assert_eq!(
+            emulator.take_pending_output(),
+            b"\x1b_Gi=3;OK\x1b\\".to_vec()
+        );
+
+        emulator.process_input(b"\x1b_Ga=p,i=9\x1b\\").unwrap();
+        assert_eq!(
+            emulator.take_pending_output(),
+            b"\x1b_Gi=9;ENOENT:no such image\x1b\\".to_vec()
+        );
+
+        emulator.process_input(b"\x1b_Ga=d,d=I,i=3\x1b\\").unwrap();
+        let buffer = emulator.buffer();
+        assert!(buffer.get_cell(0, 1).unwrap().image.is_none());
+        assert_eq!(buffer.images.kitty_image(3), None);
+    }
+
+    #[test]
+    fn test_kitty_image_size_overflow_is_rejected() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        // 宽高相乘会溢出，应按图片过大拒绝而不是 panic 或绕过大小上限
+        emulator
+            .process_input(b"\x1b_Ga=T,f=32,s=4294967295,v=4294967295,i=1;AAAA\x1b\\")
+            .unwrap();
+        assert_eq!(
+            emulator.take_pending_output(),
+            b"\x1b_Gi=1;EFBIG:image too large\x1b\\".to_vec()
+        );
+        assert_eq!(emulator.buffer().images.kitty_image(1), None);
+    }
+
+    #[test]
+    fn test_kitty_placement_is_clamped_to_screen() {
+        let mut emulator = TerminalEmulator::new(5, 20);
+        emulator.process_input(b"\r\n\r\nab").unwrap();
+        let start = Instant::now();
+        emulator
+            .process_input(b"\x1b_Ga=T,f=24,s=1,v=1,r=20000000,c=4000000;AQID\x1b\\")
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // 最多占满整屏：滚动两行让出空间，光标停在最后一行
+        let buffer = emulator.buffer();
+        assert_eq!(buffer.history.len(), 2);
+        assert_eq!(buffer.get_cell(0, 1).unwrap().character, 'b');
+        assert!(buffer.get_cell(0, 2).unwrap().image.is_some());
+        assert!(buffer.get_cell(4, 19).unwrap().image.is_some());
+        assert_eq!(buffer.cursor_row, 4);
+    }
+}
+
+/// APC 字符串的长度上限，超出部分被丢弃
+const APC_LIMIT: usize = 64 * 1024 * 1024;
+
+/// APC 提取器的状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum ApcState {
+    #[default]
+    Ground,
+    /// 刚收到 ESC
+    Escape,
+    /// APC 字符串内
+    Apc,
+    /// APC 字符串内收到 ESC，等待 `\`
+    ApcEscape,
+}
+
+/// 提取器交出的数据
+enum ApcChunk<'a> {
+    /// 交给 vte 的普通数据
+    Bytes(&'a [u8]),
+    /// 完整的 APC 字符串内容
+    Apc(&'a [u8]),
+}
+
+/// APC 字符串提取器
+///
+/// vte 会直接丢弃 APC 字符串 (ESC _ ... ESC \)，kitty 图形协议需要在交给 vte 之前先取出来。
+/// 状态跨数据块保留，序列被拆在两次读取中也能正确识别。
+#[derive(Debug, Default)]
+struct ApcScanner {
+    state: ApcState,
+    data: Vec<u8>,
+}
+
+impl ApcScanner {
+    fn feed(&mut self, input: &[u8], mut handle: impl FnMut(ApcChunk<'_>)) {
+        // 尚未交出的普通数据的起点
+        let mut start = 0;
+
+        for (i, &byte) in input.iter().enumerate() {
+            match self.state {
+                ApcState::Ground => {
+                    if byte == 0x1b {
+                        self.state = ApcState::Escape;
+                    }
+                }
+                ApcState::Escape => {
+                    if byte == b'_' {
+                        // ESC 在上一块末尾时 i 为 0，之前的数据已经交出
+                        handle(ApcChunk::Bytes(&input[start..i.saturating_sub(1)]));
+                        self.data.clear();
+                        self.state = ApcState::Apc;
+                    } else {
+                        if i == 0 {
+                            // 补上上一块末尾暂存的 ESC
+                            handle(ApcChunk::Bytes(b"\x1b"));
+                        }
+                        self.state = if byte == 0x1b {
+                            ApcState::Escape
+                        } else {
+                            ApcState::Ground
+                        };
+                    }
+                }
+                ApcState::Apc => {
+                    if byte == 0x1b {
+                        self.state = ApcState::ApcEscape;
+                    } else if self.data.len() < APC_LIMIT {
+                        self.data.push(byte);
+                    }
+                }
+                ApcState::ApcEscape => {
+                    let data = std::mem::take(&mut self.data);
+                    if byte == b'\\' {
+                        handle(ApcChunk::Apc(&data));
+                        self.state = ApcState::Ground;
+                        start = i + 1;
+                    } else {
+                        // ESC 后不是 ST：放弃该 APC，ESC 连同当前字节交给 vte
+                        handle(ApcChunk::Bytes(b"\x1b"));
+                        self.state = if byte == 0x1b {
+                            ApcState::Escape
+                        } else {
+                            ApcState::Ground
+                        };
+                        start = i;
+                    }
+                }
+            }
+        }
+
+        match self.state {
+            ApcState::Ground => handle(ApcChunk::Bytes(&input[start..])),
+            // 末尾的 ESC 暂存到下一块，看它是否开始一个 APC
+            ApcState::Escape if !input.is_empty() => {
+                handle(ApcChunk::Bytes(&input[start..input.len() - 1]))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 输入解析状态：VTE解析器与 APC 字符串提取器
+struct InputParser {
+    vte: Parser,
+    apc: ApcScanner,
+}
+
+impl InputParser {
+    fn new() -> Self {
+        Self {
+            vte: Parser::new(),
+            apc: ApcScanner::default(),
+        }
+    }
+
+    /// 解析数据：APC 字符串交给处理器的 apc_dispatch，其余交给 vte
+    fn advance(&mut self, performer: &mut VtePerform, data: &[u8]) {
+        let vte = &mut self.vte;
+        self.apc.feed(data, |chunk| match chunk {
+            ApcChunk::Bytes(bytes) => vte.advance(performer, bytes),
+            ApcChunk::Apc(apc) => performer.apc_dispatch(apc),
+        });
+    }
+}
+
+/// 仿真引擎接收远端数据的一端，由各引擎实现
+pub trait InputSink: Send + Sync {
+    /// 处理远端发来的数据
+    fn process(&self, data: &[u8]);
+
+    /// 取出需要回复给远端的数据
+    fn take_pending_output(&self) -> Vec<u8>;
+}
+
+/// 向仿真器输入数据的句柄，可交给后台 I/O 线程使用
+#[derive(Clone)]
+pub struct TerminalInput {
+    sink: Arc<dyn InputSink>,
+}
+
+impl TerminalInput {
+    pub fn new(sink: Arc<dyn InputSink>) -> Self {
+        Self { sink }
+    }
+
+    /// 处理远端发来的数据
+    pub fn process(&self, data: &[u8]) {
+        self.sink.process(data);
+    }
+
+    /// 取出需要回复给远端的数据
+    pub fn take_pending_output(&self) -> Vec<u8> {
+        self.sink.take_pending_output()
+    }
+}
+
+/// 内置仿真器的输入端
+///
+/// 与仿真器共享解析器和处理器，先锁解析器再锁处理器。
+struct VteInput {
+    parser: Arc<Mutex<InputParser>>,
+    performer: Arc<Mutex<VtePerform>>,
+}
+
+impl InputSink for VteInput {
+    fn process(&self, data: &[u8]) {
+        let mut parser = self.parser.lock().unwrap();
+        let mut performer = self.performer.lock().unwrap();
+        parser.advance(&mut performer, data);
+    }
+
+    fn take_pending_output(&self) -> Vec<u8> {
+        std::mem::take(&mut self.performer.lock().unwrap().pending_output)
+    }
 }
 
 /// 专业终端仿真器
 pub struct TerminalEmulator {
-    /// VTE解析器
-    parser: Parser,
+    /// VTE解析器与 APC 字符串提取器
+    parser: Arc<Mutex<InputParser>>,
     /// VTE处理器
     performer: Arc<Mutex<VtePerform>>,
     /// 终端状态
@@ -669,24 +2291,22 @@ pub struct TerminalEmulator {
 
 impl TerminalEmulatorTrait for TerminalEmulator {
     fn process_input(&mut self, data: &[u8]) -> anyhow::Result<()> {
-        let mut performer = self.performer.lock().unwrap();
-        self.parser.advance(&mut *performer, data);
-        Ok(())
+        TerminalEmulator::process_input(self, data)
     }
 
-    fn buffer(&self) -> TerminalBuffer {
-        self.performer.lock().unwrap().term_buffer.clone()
+    fn visit_buffer(&self, visitor: &mut dyn FnMut(&TerminalBuffer)) {
+        self.performer
+            .lock()
+            .unwrap()
+            .with_visible_buffer(|buffer| visitor(buffer));
     }
 
     fn resize(&mut self, rows: usize, cols: usize) {
-        // 调整大小的实现
-        let mut performer = self.performer.lock().unwrap();
-        performer.term_buffer.resize(rows, cols);
+        TerminalEmulator::resize(self, rows, cols);
     }
 
-    fn update_theme(&self, theme: TerminalTheme) {
-        let mut performer = self.performer.lock().unwrap();
-        performer.theme = theme;
+    fn set_palette(&self, palette: Palette) {
+        self.performer.lock().unwrap().palette = palette;
     }
 
     fn state(&self) -> &TerminalState {
@@ -722,6 +2342,30 @@ impl TerminalEmulatorTrait for TerminalEmulator {
         let perf = self.performer.lock().unwrap();
         perf.term_buffer.get_selected_text()
     }
+
+    fn modes(&self) -> TerminalModes {
+        self.performer.lock().unwrap().modes
+    }
+
+    fn take_pending_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.performer.lock().unwrap().pending_output)
+    }
+
+    fn set_cell_size(&mut self, width: f32, height: f32) {
+        TerminalEmulator::set_cell_size(self, width, height);
+    }
+
+    fn set_scrollback(&mut self, config: ScrollbackConfig) {
+        TerminalEmulator::set_scrollback(self, config);
+    }
+
+    fn scroll_display(&mut self, scroll: Scroll) {
+        TerminalEmulator::scroll_display(self, scroll);
+    }
+
+    fn input_handle(&self) -> Option<TerminalInput> {
+        Some(self.input())
+    }
 }
 
 impl TerminalEmulator {
@@ -730,21 +2374,21 @@ impl TerminalEmulator {
         let performer = VtePerform::new(rows, cols);
 
         Self {
-            parser: Parser::new(),
+            parser: Arc::new(Mutex::new(InputParser::new())),
             performer: Arc::new(Mutex::new(performer)),
             state: TerminalState::Disconnected,
         }
     }
 
-    /// 更新主题
-    pub fn update_theme(&self, theme: TerminalTheme) {
-        let mut performer = self.performer.lock().unwrap();
-        performer.theme = theme;
+    /// 获取终端缓冲区的完整拷贝（包括历史记录），测试中检查内容用
+    #[cfg(test)]
+    pub fn buffer(&self) -> TerminalBuffer {
+        self.with_buffer(TerminalBuffer::clone)
     }
 
-    /// 获取终端缓冲区
-    pub fn buffer(&self) -> TerminalBuffer {
-        self.performer.lock().unwrap().term_buffer.clone()
+    /// 借用当前应显示的缓冲区计算结果，访问期间后台线程无法写入
+    pub fn with_buffer<R>(&self, f: impl FnOnce(&TerminalBuffer) -> R) -> R {
+        self.performer.lock().unwrap().with_visible_buffer(f)
     }
 
     /// 设置事件回调
@@ -757,15 +2401,21 @@ impl TerminalEmulator {
 
     /// 处理输入数据
     pub fn process_input(&mut self, data: &[u8]) -> anyhow::Result<()> {
-        let mut performer = self.performer.lock().unwrap();
-        self.parser.advance(&mut *performer, data);
+        self.input().process(data);
         Ok(())
     }
 
+    /// 获取输入句柄
+    pub fn input(&self) -> TerminalInput {
+        TerminalInput::new(Arc::new(VteInput {
+            parser: self.parser.clone(),
+            performer: self.performer.clone(),
+        }))
+    }
+
     /// 发送输出到终端
     pub fn send_output(&mut self, output: &str) {
-        let mut performer = self.performer.lock().unwrap();
-        self.parser.advance(&mut *performer, output.as_bytes());
+        self.input().process(output.as_bytes());
     }
 
     /// 获取终端缓冲区内容
@@ -790,10 +2440,37 @@ impl TerminalEmulator {
         perf.term_buffer.clear();
     }
 
+    /// 设置单元格的像素大小，用于计算内联图片占用的单元格与回复像素大小查询
+    pub fn set_cell_size(&mut self, width: f32, height: f32) {
+        if width > 0.0 && height > 0.0 {
+            self.performer.lock().unwrap().cell_size = (width, height);
+        }
+    }
+
+    /// 设置回滚历史的限制，超出新限制的历史行立即淘汰
+    pub fn set_scrollback(&mut self, config: ScrollbackConfig) {
+        let mut perf = self.performer.lock().unwrap();
+        if perf.term_buffer.history.config() != config {
+            perf.term_buffer.history.set_config(config);
+            perf.term_buffer.touch_all();
+        }
+    }
+
+    /// 滚动视口
+    pub fn scroll_display(&mut self, scroll: Scroll) {
+        self.performer
+            .lock()
+            .unwrap()
+            .term_buffer
+            .scroll_display(scroll);
+    }
+
     /// 调整终端大小
     pub fn resize(&mut self, rows: usize, cols: usize) {
         let mut perf = self.performer.lock().unwrap();
         perf.term_buffer.resize(rows, cols);
+        // 尺寸变化后同步开始前的画面已失效
+        perf.sync_frame = None;
         if let Some(ref callback) = perf.callback {
             callback(TerminalEvent::Resize { rows, cols });
         }
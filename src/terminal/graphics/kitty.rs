@@ -0,0 +1,270 @@
+//! kitty 图形协议
+//! 解析 `ESC _ G <键=值,...> ; <base64 负载> ESC \` 形式的 APC 指令
+
+use super::TerminalImage;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::io::Read;
+
+/// 单张图片解码后数据的上限
+const MAX_IMAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// 分块传输中累计 base64 负载的上限
+pub const MAX_PAYLOAD_BYTES: usize = MAX_IMAGE_BYTES / 3 * 4;
+
+/// kitty 图形指令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KittyCommand {
+    /// a：t 传输、T 传输并显示、p 显示、d 删除、q 查询
+    pub action: u8,
+    /// f：24 RGB、32 RGBA、100 PNG
+    pub format: u32,
+    /// t：传输介质，只支持 d（直接传输）
+    pub medium: u8,
+    /// s / v：像素宽高（RGB/RGBA 必需）
+    pub width: u32,
+    pub height: u32,
+    /// i：图片 ID
+    pub image_id: u32,
+    /// p：放置 ID
+    pub placement_id: u32,
+    /// m=1：后面还有分块
+    pub more: bool,
+    /// o=z：负载经过 zlib 压缩
+    pub compressed: bool,
+    /// q：1 不回复成功，2 不回复任何结果
+    pub quiet: u32,
+    /// c / r：显示时占用的列数与行数，0 表示按图片大小计算
+    pub cols: u32,
+    pub rows: u32,
+    /// C=1：显示后不移动光标
+    pub no_cursor_movement: bool,
+    /// d：删除的范围
+    pub delete: u8,
+    /// base64 编码的负载
+    pub payload: Vec<u8>,
+}
+
+impl Default for KittyCommand {
+    fn default() -> Self {
+        Self {
+            action: b't',
+            format: 32,
+            medium: b'd',
+            width: 0,
+            height: 0,
+            image_id: 0,
+            placement_id: 0,
+            more: false,
+            compressed: false,
+            quiet: 0,
+            cols: 0,
+            rows: 0,
+            no_cursor_movement: false,
+            delete: b'a',
+            payload: Vec::new(),
+        }
+    }
+}
+
+impl KittyCommand {
+    /// 解析 APC 内容（去掉开头的 `G`）
+    pub fn parse(data: &[u8]) -> Self {
+        let (control, payload) = match data.iter().position(|&b| b == b';') {
+            Some(pos) => (&data[..pos], &data[pos + 1..]),
+            None => (data, &[][..]),
+        };
+
+        let mut command = KittyCommand {
+            payload: payload.to_vec(),
+            ..Default::default()
+        };
+
+        for pair in control.split(|&b| b == b',') {
+            let Some(eq) = pair.iter().position(|&b| b == b'=') else {
+                continue;
+            };
+            let (key, value) = (&pair[..eq], &pair[eq + 1..]);
+            let number = || {
+                std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|v| v.parse::<u32>().ok())
+                    .unwrap_or(0)
+            };
+            let letter = value.first().copied().unwrap_or(0);
+
+            match key {
+                b"a" => command.action = letter,
+                b"f" => command.format = number(),
+                b"t" => command.medium = letter,
+                b"s" => command.width = number(),
+                b"v" => command.height = number(),
+                b"i" => command.image_id = number(),
+                b"p" => command.placement_id = number(),
+                b"m" => command.more = number() == 1,
+                b"o" => command.compressed = letter == b'z',
+                b"q" => command.quiet = number(),
+                b"c" => command.cols = number(),
+                b"r" => command.rows = number(),
+                b"C" => command.no_cursor_movement = number() == 1,
+                b"d" => command.delete = letter,
+                _ => {}
+            }
+        }
+
+        command
+    }
+
+    /// 解码负载为图片，失败时返回 kitty 约定的错误信息
+    pub fn decode_image(&self) -> Result<TerminalImage, String> {
+        if self.medium != b'd' {
+            return Err("EINVAL:unsupported transmission medium".to_string());
+        }
+
+        let mut data = STANDARD
+            .decode(self.payload.as_slice())
+            .map_err(|_| "EINVAL:invalid base64 payload".to_string())?;
+
+        if self.compressed {
+            let mut inflated = Vec::new();
+            flate2::read::ZlibDecoder::new(data.as_slice())
+                .take(MAX_IMAGE_BYTES as u64 + 1)
+                .read_to_end(&mut inflated)
+                .map_err(|_| "EINVAL:invalid zlib data".to_string())?;
+            if inflated.len() > MAX_IMAGE_BYTES {
+                return Err("EFBIG:image too large".to_string());
+            }
+            data = inflated;
+        }
+
+        match self.format {
+            24 | 32 => self.decode_raw(data),
+            100 => decode_png(&data),
+            _ => Err("EINVAL:unsupported format".to_string()),
+        }
+    }
+
+    fn decode_raw(&self, data: Vec<u8>) -> Result<TerminalImage, String> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let channels = if self.format == 24 { 3 } else { 4 };
+        if width == 0 || height == 0 {
+            return Err("EINVAL:missing image size".to_string());
+        }
+        if rgba_size(width, height).is_none() {
+            return Err("EFBIG:image too large".to_string());
+        }
+        if data.len() < width * height * channels {
+            return Err("ENODATA:insufficient image data".to_string());
+        }
+
+        let rgba = if channels == 4 {
+            data[..width * height * 4].to_vec()
+        } else {
+            data[..width * height * 3]
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect()
+        };
+        Ok(TerminalImage::new(width, height, rgba))
+    }
+}
+
+/// RGBA 像素数据的字节数，超过上限或乘法溢出时为 None
+fn rgba_size(width: usize, height: usize) -> Option<usize> {
+    width
+        .checked_mul(height)?
+        .checked_mul(4)
+        .filter(|size| *size <= MAX_IMAGE_BYTES)
+}
+
+fn decode_png(data: &[u8]) -> Result<TerminalImage, String> {
+    let invalid = |_| "EBADPNG:invalid png data".to_string();
+
+    let mut decoder = png::Decoder::new(std::io::Cursor::new(data));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let (width, height) = {
+        let info = reader.info();
+        (info.width as usize, info.height as usize)
+    };
+    if rgba_size(width, height).is_none() {
+        return Err("EFBIG:image too large".to_string());
+    }
+
+    let size = reader
+        .output_buffer_size()
+        .ok_or_else(|| "EFBIG:image too large".to_string())?;
+    let mut buf = vec![0; size];
+    let frame = reader.next_frame(&mut buf).map_err(invalid)?;
+    buf.truncate(frame.buffer_size());
+
+    let rgba = match frame.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+            .collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return Err("EBADPNG:unexpanded palette".to_string()),
+    };
+    Ok(TerminalImage::new(width, height, rgba))
+}
+
+/// 编码指令结果的回复 (`ESC _ G i=<id>[,p=<id>];<message> ESC \`)
+pub fn encode_response(image_id: u32, placement_id: u32, message: &str) -> Vec<u8> {
+    let placement = if placement_id != 0 {
+        format!(",p={}", placement_id)
+    } else {
+        String::new()
+    };
+    format!("\x1b_Gi={}{};{}\x1b\\", image_id, placement, message).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let command = KittyCommand::parse(b"a=T,f=24,s=2,v=1,i=5,m=1,q=2;AAAA");
+
+        assert_eq!(command.action, b'T');
+        assert_eq!(command.format, 24);
+        assert_eq!((command.width, command.height), (2, 1));
+        assert_eq!(command.image_id, 5);
+        assert!(command.more);
+        assert_eq!(command.quiet, 2);
+        assert_eq!(command.payload, b"AAAA".to_vec());
+    }
+
+    #[test]
+    fn test_decode_rgb_and_compressed() {
+        let mut command = KittyCommand::parse(b"f=24,s=1,v=1");
+        command.payload = STANDARD.encode([1u8, 2, 3]).into_bytes();
+        let image = command.decode_image().unwrap();
+        assert_eq!(image.rgba, vec![1, 2, 3, 255]);
+
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, &[9, 8, 7, 6]).unwrap();
+        let mut command = KittyCommand::parse(b"f=32,s=1,v=1,o=z");
+        command.payload = STANDARD.encode(encoder.finish().unwrap()).into_bytes();
+        assert_eq!(command.decode_image().unwrap().rgba, vec![9, 8, 7, 6]);
+
+        let command = KittyCommand::parse(b"f=32,s=4,v=4;AAAA");
+        assert!(command.decode_image().unwrap_err().starts_with("ENODATA"));
+    }
+
+    #[test]
+    fn test_encode_response() {
+        assert_eq!(encode_response(3, 0, "OK"), b"\x1b_Gi=3;OK\x1b\\".to_vec());
+        assert_eq!(
+            encode_response(3, 4, "ENOENT:no such image"),
+            b"\x1b_Gi=3,p=4;ENOENT:no such image\x1b\\".to_vec()
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! 终端内联图片
+//! 支持 sixel 与 kitty 图形协议，解码后的图片按单元格切片挂在缓冲区上，随内容一起滚动
+
+pub mod kitty;
+pub mod sixel;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 图片表中的索引
+pub type ImageId = u32;
+
+/// 图片表占用内存的上限，超过后淘汰最早加入的图片
+const IMAGE_MEMORY_LIMIT: usize = 256 * 1024 * 1024;
+
+static NEXT_IMAGE_KEY: AtomicU64 = AtomicU64::new(1);
+
+/// 解码后的图片 (RGBA8)
+#[derive(Debug, PartialEq, Eq)]
+pub struct TerminalImage {
+    /// 全局唯一的键，渲染器按它缓存纹理
+    pub key: u64,
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+impl TerminalImage {
+    pub fn new(width: usize, height: usize, rgba: Vec<u8>) -> Self {
+        debug_assert_eq!(rgba.len(), width * height * 4);
+        Self {
+            key: NEXT_IMAGE_KEY.fetch_add(1, Ordering::Relaxed),
+            width,
+            height,
+            rgba,
+        }
+    }
+}
+
+/// 单元格上的图片切片
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageCell {
+    pub image: ImageId,
+    /// 该单元格对应的纹理坐标 (0..1)：左、上、右、下
+    pub uv: [f32; 4],
+}
+
+/// 图片表：保存缓冲区引用的图片数据
+#[derive(Debug, Clone, Default)]
+pub struct ImageStore {
+    images: HashMap<ImageId, Arc<TerminalImage>>,
+    /// 按加入顺序排列，用于淘汰
+    order: VecDeque<ImageId>,
+    /// kitty 协议中远端指定的图片 ID
+    kitty_ids: HashMap<u32, ImageId>,
+    next_id: ImageId,
+    total_bytes: usize,
+}
+
+impl ImageStore {
+    /// 加入图片并返回索引，超出内存上限时淘汰最早的图片
+    pub fn insert(&mut self, image: TerminalImage) -> ImageId {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        self.total_bytes += image.rgba.len();
+        self.images.insert(id, Arc::new(image));
+        self.order.push_back(id);
+
+        while self.total_bytes > IMAGE_MEMORY_LIMIT && self.order.len() > 1 {
+            if let Some(oldest) = self.order.front().copied() {
+                self.remove(oldest);
+            }
+        }
+        id
+    }
+
+    /// 加入通过 kitty 协议传输的图片，同一 kitty ID 的旧图片会被替换
+    pub fn insert_kitty(&mut self, kitty_id: u32, image: TerminalImage) -> ImageId {
+        if let Some(old) = self.kitty_ids.remove(&kitty_id) {
+            self.remove(old);
+        }
+        let id = self.insert(image);
+        if kitty_id != 0 {
+            self.kitty_ids.insert(kitty_id, id);
+        }
+        id
+    }
+
+    pub fn get(&self, id: ImageId) -> Option<&Arc<TerminalImage>> {
+        self.images.get(&id)
+    }
+
+    /// 按 kitty 图片 ID 查找
+    pub fn kitty_image(&self, kitty_id: u32) -> Option<ImageId> {
+        self.kitty_ids.get(&kitty_id).copied()
+    }
+
+    pub fn remove(&mut self, id: ImageId) {
+        if let Some(image) = self.images.remove(&id) {
+            self.total_bytes -= image.rgba.len();
+        }
+        self.order.retain(|&other| other != id);
+        self.kitty_ids.retain(|_, &mut other| other != id);
+    }
+
+    pub fn clear(&mut self) {
+        self.images.clear();
+        self.order.clear();
+        self.kitty_ids.clear();
+        self.total_bytes = 0;
+    }
+}
+
+/// 按单元格像素大小计算图片占用的列数与行数
+pub fn cell_span(width: usize, height: usize, cell_size: (f32, f32)) -> (usize, usize) {
+    let cols = (width as f32 / cell_size.0.max(1.0)).ceil() as usize;
+    let rows = (height as f32 / cell_size.1.max(1.0)).ceil() as usize;
+    (cols.max(1), rows.max(1))
+}
+
+/// 图片铺在 cols x rows 个单元格上时第 (row, col) 个单元格的纹理坐标
+pub fn cell_uv(row: usize, col: usize, rows: usize, cols: usize) -> [f32; 4] {
+    [
+        col as f32 / cols as f32,
+        row as f32 / rows as f32,
+        (col + 1) as f32 / cols as f32,
+        (row + 1) as f32 / rows as f32,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_replaces_kitty_ids() {
+        let mut store = ImageStore::default();
+        let first = store.insert_kitty(7, TerminalImage::new(1, 1, vec![0; 4]));
+        let second = store.insert_kitty(7, TerminalImage::new(2, 1, vec![0; 8]));
+
+        assert!(store.get(first).is_none());
+        assert_eq!(store.kitty_image(7), Some(second));
+        assert_eq!(store.get(second).map(|image| image.width), Some(2));
+
+        store.remove(second);
+        assert_eq!(store.kitty_image(7), None);
+    }
+
+    #[test]
+    fn test_cell_span() {
+        assert_eq!(cell_span(20, 40, (8.0, 16.0)), (3, 3));
+        assert_eq!(cell_span(0, 0, (8.0, 16.0)), (1, 1));
+    }
+}
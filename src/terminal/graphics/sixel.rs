@@ -0,0 +1,328 @@
+//! sixel 图片解码
+//! 数据来自 DCS P1;P2;P3 q ... ST，由 VtePerform 的 hook/put/unhook 逐字节送入
+
+use super::TerminalImage;
+
+/// 图片宽高的上限（像素），防止恶意数据耗尽内存
+const MAX_SIXEL_SIZE: usize = 4096;
+
+/// 数值参数的上限，超过后不再累加
+const MAX_PARAM: u32 = 0xFFFF;
+
+/// VT340 默认调色板（百分比 RGB）
+const VT340_PALETTE: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [20, 20, 80],
+    [80, 13, 13],
+    [20, 80, 20],
+    [80, 20, 80],
+    [20, 80, 80],
+    [80, 80, 20],
+    [53, 53, 53],
+    [26, 26, 26],
+    [33, 33, 60],
+    [60, 26, 26],
+    [33, 60, 33],
+    [60, 33, 60],
+    [33, 60, 60],
+    [60, 60, 33],
+    [80, 80, 80],
+];
+
+/// 正在解析的带参数指令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    None,
+    /// `!` 重复
+    Repeat,
+    /// `#` 颜色定义/选择
+    Color,
+    /// `"` 光栅属性
+    Raster,
+}
+
+/// sixel 解码器
+#[derive(Debug)]
+pub struct SixelDecoder {
+    palette: Vec<[u8; 3]>,
+    color: usize,
+    /// P2 == 1：未绘制的像素保持透明
+    transparent: bool,
+    command: Command,
+    params: Vec<u32>,
+    x: usize,
+    /// 当前 sixel 带（6 像素高）的顶端
+    y: usize,
+    /// 已绘制区域（或光栅属性声明）的宽高
+    width: usize,
+    height: usize,
+    /// 像素缓冲区，alpha 为 0 表示尚未绘制
+    pixels: Vec<u8>,
+    stride: usize,
+    rows: usize,
+}
+
+impl SixelDecoder {
+    /// 按 DCS 参数创建解码器
+    pub fn new(params: &[u16]) -> Self {
+        let mut palette = vec![[0, 0, 0]; 256];
+        for (slot, rgb) in palette.iter_mut().zip(VT340_PALETTE) {
+            *slot = rgb.map(percent_to_byte);
+        }
+
+        Self {
+            palette,
+            color: 0,
+            transparent: params.get(1) == Some(&1),
+            command: Command::None,
+            params: Vec::new(),
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+            stride: 0,
+            rows: 0,
+        }
+    }
+
+    /// 送入一个字节
+    pub fn put(&mut self, byte: u8) {
+        if self.command != Command::None {
+            match byte {
+                b'0'..=b'9' => {
+                    if self.params.is_empty() {
+                        self.params.push(0);
+                    }
+                    if let Some(last) = self.params.last_mut() {
+                        *last = (*last * 10 + (byte - b'0') as u32).min(MAX_PARAM);
+                    }
+                    return;
+                }
+                b';' => {
+                    if self.params.is_empty() {
+                        self.params.push(0);
+                    }
+                    self.params.push(0);
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        let repeat = self.finish_command();
+
+        match byte {
+            b'!' => self.command = Command::Repeat,
+            b'#' => self.command = Command::Color,
+            b'"' => self.command = Command::Raster,
+            b'$' => self.x = 0,
+            b'-' => {
+                self.x = 0;
+                self.y += 6;
+            }
+            0x3F..=0x7E => self.draw_sixel(byte - 0x3F, repeat),
+            _ => {}
+        }
+    }
+
+    /// 结束解码，返回图片；没有绘制任何内容时返回 None
+    pub fn finish(mut self) -> Option<TerminalImage> {
+        self.finish_command();
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+
+        self.ensure_size(self.width, self.height);
+        let background = [
+            self.palette[0][0],
+            self.palette[0][1],
+            self.palette[0][2],
+            255,
+        ];
+        let mut rgba = Vec::with_capacity(self.width * self.height * 4);
+        for row in 0..self.height {
+            let start = row * self.stride * 4;
+            rgba.extend_from_slice(&self.pixels[start..start + self.width * 4]);
+        }
+        if !self.transparent {
+            for pixel in rgba.chunks_exact_mut(4) {
+                if pixel[3] == 0 {
+                    pixel.copy_from_slice(&background);
+                }
+            }
+        }
+
+        Some(TerminalImage::new(self.width, self.height, rgba))
+    }
+
+    /// 结束带参数的指令，返回后续 sixel 字符的重复次数
+    fn finish_command(&mut self) -> usize {
+        let command = std::mem::replace(&mut self.command, Command::None);
+        let params = std::mem::take(&mut self.params);
+        let param = |i: usize| params.get(i).copied().unwrap_or(0);
+
+        match command {
+            Command::None => 1,
+            Command::Repeat => param(0).max(1) as usize,
+            Command::Color => {
+                let register = param(0) as usize % self.palette.len();
+                if params.len() >= 5 {
+                    self.palette[register] = match param(1) {
+                        1 => hls_to_rgb(param(2), param(3), param(4)),
+                        _ => [param(2), param(3), param(4)]
+                            .map(|v| percent_to_byte(v.min(100) as u8)),
+                    };
+                }
+                self.color = register;
+                1
+            }
+            Command::Raster => {
+                // "Pan;Pad;Ph;Pv：声明图片宽高
+                let width = (param(2) as usize).min(MAX_SIXEL_SIZE);
+                let height = (param(3) as usize).min(MAX_SIXEL_SIZE);
+                self.width = self.width.max(width);
+                self.height = self.height.max(height);
+                1
+            }
+        }
+    }
+
+    /// 在当前位置绘制 repeat 列 sixel
+    fn draw_sixel(&mut self, bits: u8, repeat: usize) {
+        let end = (self.x + repeat).min(MAX_SIXEL_SIZE);
+        if bits != 0 && self.y < MAX_SIXEL_SIZE {
+            let bottom = (self.y + 6).min(MAX_SIXEL_SIZE);
+            self.ensure_size(end, bottom);
+            let rgb = self.palette[self.color];
+            let color = [rgb[0], rgb[1], rgb[2], 255];
+
+            for bit in 0..6 {
+                let y = self.y + bit;
+                if bits & (1 << bit) == 0 || y >= MAX_SIXEL_SIZE {
+                    continue;
+                }
+                for x in self.x..end {
+                    let offset = (y * self.stride + x) * 4;
+                    self.pixels[offset..offset + 4].copy_from_slice(&color);
+                }
+                self.height = self.height.max(y + 1);
+            }
+            self.width = self.width.max(end);
+        }
+        self.x = end;
+    }
+
+    /// 扩大像素缓冲区，使其至少容纳 width x height
+    fn ensure_size(&mut self, width: usize, height: usize) {
+        if width <= self.stride && height <= self.rows {
+            return;
+        }
+
+        let stride = if width > self.stride {
+            width.max(self.stride * 2).min(MAX_SIXEL_SIZE)
+        } else {
+            self.stride
+        };
+        let rows = if height > self.rows {
+            height.max(self.rows * 2).min(MAX_SIXEL_SIZE)
+        } else {
+            self.rows
+        };
+
+        let mut pixels = vec![0; stride * rows * 4];
+        for row in 0..self.rows {
+            let old = row * self.stride * 4;
+            let new = row * stride * 4;
+            pixels[new..new + self.stride * 4]
+                .copy_from_slice(&self.pixels[old..old + self.stride * 4]);
+        }
+        self.pixels = pixels;
+        self.stride = stride;
+        self.rows = rows;
+    }
+}
+
+fn percent_to_byte(value: u8) -> u8 {
+    (value.min(100) as u32 * 255 / 100) as u8
+}
+
+/// DEC HLS 转 RGB（色相 0° 为蓝色，120° 为红色，240° 为绿色）
+fn hls_to_rgb(hue: u32, lightness: u32, saturation: u32) -> [u8; 3] {
+    let h = ((hue + 240) % 360) as f32 / 360.0;
+    let l = lightness.min(100) as f32 / 100.0;
+    let s = saturation.min(100) as f32 / 100.0;
+
+    if s == 0.0 {
+        let v = (l * 255.0).round() as u8;
+        return [v, v, v];
+    }
+
+    let q = if l < 0.5 {
+        l * (1.0 + s)
+    } else {
+        l + s - l * s
+    };
+    let p = 2.0 * l - q;
+    let channel = |t: f32| {
+        let t = t.rem_euclid(1.0);
+        let v = if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        };
+        (v * 255.0).round() as u8
+    };
+
+    [channel(h + 1.0 / 3.0), channel(h), channel(h - 1.0 / 3.0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(params: &[u16], data: &[u8]) -> Option<TerminalImage> {
+        let mut decoder = SixelDecoder::new(params);
+        for &byte in data {
+            decoder.put(byte);
+        }
+        decoder.finish()
+    }
+
+    #[test]
+    fn test_decode_colors_and_repeat() {
+        // 寄存器 1 定义为红色，画 3 列满 6 像素；换带后用寄存器 2（HLS 绿色）画 1 列最上面的像素
+        let image = decode(&[0, 1], b"#1;2;100;0;0#1!3~-#2;1;240;50;100#2@").unwrap();
+
+        assert_eq!((image.width, image.height), (3, 7));
+        assert_eq!(&image.rgba[0..4], &[255, 0, 0, 255]);
+        let below = (6 * 3) * 4;
+        assert_eq!(&image.rgba[below..below + 4], &[0, 255, 0, 255]);
+        // P2 = 1：未绘制的像素保持透明
+        assert_eq!(image.rgba[(6 * 3 + 1) * 4 + 3], 0);
+    }
+
+    #[test]
+    fn test_raster_attributes_and_background() {
+        let image = decode(&[0, 0], b"\"1;1;4;2#3?").unwrap();
+
+        assert_eq!((image.width, image.height), (4, 2));
+        // 未绘制的像素使用寄存器 0 的背景色
+        assert!(image.rgba.chunks(4).all(|pixel| pixel == [0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_rgb_components_above_100_clamp() {
+        let image = decode(&[0, 1], b"#1;2;300;0;100#1~").unwrap();
+        assert_eq!(&image.rgba[0..4], &[255, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_empty_image() {
+        assert!(decode(&[], b"#0;2;0;0;0").is_none());
+    }
+}
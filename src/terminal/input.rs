@@ -0,0 +1,381 @@
+//! 键盘输入编码
+//! 根据终端当前模式将按键转换为发送给远端的 xterm 字节序列
+
+use crate::terminal::modes::TerminalModes;
+
+/// kitty 键盘协议：消除转义码歧义
+pub const KITTY_DISAMBIGUATE: u8 = 0b1;
+/// kitty 键盘协议：所有按键都以转义码报告
+pub const KITTY_REPORT_ALL_KEYS: u8 = 0b1000;
+
+/// 键盘编码器
+///
+/// 数字小键盘在 egui 中与主键盘无法区分，因此应用小键盘模式 (DECKPAM)
+/// 仅被记录，不会改变主键盘按键的编码，这与 xterm 对主键盘的处理一致。
+#[derive(Debug, Clone, Copy)]
+pub struct KeyEncoder {
+    /// 终端当前模式
+    pub modes: TerminalModes,
+    /// Alt 键作为 Meta 键（发送 ESC 前缀）
+    pub alt_sends_escape: bool,
+}
+
+impl KeyEncoder {
+    pub fn new(modes: TerminalModes, alt_sends_escape: bool) -> Self {
+        Self {
+            modes,
+            alt_sends_escape,
+        }
+    }
+
+    /// 当前修饰键组合下，文本事件是否由编码器负责（调用方应忽略对应的文本事件）
+    ///
+    /// kitty 的 REPORT_ALL_KEYS 模式只接管按键事件能覆盖的 ASCII 字符，
+    /// 其他键盘布局产生的非 ASCII 文本没有对应的按键事件，仍按文本发送。
+    pub fn consumes_text(&self, modifiers: egui::Modifiers, text: &str) -> bool {
+        modifiers.ctrl
+            || (modifiers.alt && self.alt_sends_escape)
+            || (self.kitty_flag(KITTY_REPORT_ALL_KEYS) && text.is_ascii())
+    }
+
+    /// 将按键编码为字节序列，返回 None 表示该按键应由文本事件处理或无需发送
+    pub fn encode(&self, key: egui::Key, modifiers: egui::Modifiers) -> Option<Vec<u8>> {
+        if let Some(bytes) = self.encode_functional(key, modifiers) {
+            return Some(bytes);
+        }
+
+        let c = key_char(key)?;
+        if self.kitty_flag(KITTY_REPORT_ALL_KEYS)
+            || (self.kitty_flag(KITTY_DISAMBIGUATE) && (modifiers.ctrl || modifiers.alt))
+        {
+            return Some(kitty_csi_u(c as u32, modifiers));
+        }
+
+        let meta = modifiers.alt && self.alt_sends_escape;
+        let mut bytes = Vec::new();
+
+        if modifiers.ctrl {
+            let code = ctrl_code(c)?;
+            if meta {
+                bytes.push(0x1b);
+            }
+            bytes.push(code);
+            return Some(bytes);
+        }
+
+        if meta {
+            let c = if modifiers.shift {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            };
+            bytes.push(0x1b);
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            return Some(bytes);
+        }
+
+        // 普通文本交给文本事件处理
+        None
+    }
+
+    fn kitty_flag(&self, flag: u8) -> bool {
+        self.modes.kitty_keyboard_flags & flag != 0
+    }
+
+    /// 编码功能键（方向键、编辑键、F1-F12、回车、退格等）
+    fn encode_functional(&self, key: egui::Key, modifiers: egui::Modifiers) -> Option<Vec<u8>> {
+        use egui::Key;
+
+        let param = modifier_param(modifiers);
+        let kitty = self.modes.kitty_keyboard_flags != 0;
+
+        let bytes = match key {
+            Key::ArrowUp => self.cursor_key(b'A', param),
+            Key::ArrowDown => self.cursor_key(b'B', param),
+            Key::ArrowRight => self.cursor_key(b'C', param),
+            Key::ArrowLeft => self.cursor_key(b'D', param),
+            Key::Home => self.cursor_key(b'H', param),
+            Key::End => self.cursor_key(b'F', param),
+            Key::Insert => tilde_key(2, param),
+            Key::Delete => tilde_key(3, param),
+            Key::PageUp => tilde_key(5, param),
+            Key::PageDown => tilde_key(6, param),
+            Key::F1 => self.f1_to_f4(b'P', param),
+            Key::F2 => self.f1_to_f4(b'Q', param),
+            Key::F3 => self.f1_to_f4(b'R', param),
+            Key::F4 => self.f1_to_f4(b'S', param),
+            Key::F5 => tilde_key(15, param),
+            Key::F6 => tilde_key(17, param),
+            Key::F7 => tilde_key(18, param),
+            Key::F8 => tilde_key(19, param),
+            Key::F9 => tilde_key(20, param),
+            Key::F10 => tilde_key(21, param),
+            Key::F11 => tilde_key(23, param),
+            Key::F12 => tilde_key(24, param),
+            Key::Escape if kitty => kitty_csi_u(27, modifiers),
+            Key::Enter | Key::Tab | Key::Backspace
+                if self.kitty_flag(KITTY_REPORT_ALL_KEYS) || (kitty && param > 1) =>
+            {
+                let code = match key {
+                    Key::Enter => 13,
+                    Key::Tab => 9,
+                    _ => 127,
+                };
+                kitty_csi_u(code, modifiers)
+            }
+            Key::Escape => self.with_meta(b"\x1b", modifiers),
+            Key::Enter => self.with_meta(b"\r", modifiers),
+            Key::Tab if modifiers.shift => b"\x1b[Z".to_vec(),
+            Key::Tab => self.with_meta(b"\t", modifiers),
+            Key::Backspace if modifiers.ctrl => self.with_meta(b"\x08", modifiers),
+            Key::Backspace => self.with_meta(b"\x7f", modifiers),
+            _ => return None,
+        };
+
+        Some(bytes)
+    }
+
+    /// 方向键与 Home/End：无修饰键时受光标键模式 (DECCKM) 影响
+    fn cursor_key(&self, final_byte: u8, param: u8) -> Vec<u8> {
+        if param > 1 {
+            format!("\x1b[1;{}{}", param, final_byte as char).into_bytes()
+        } else if self.modes.application_cursor {
+            vec![0x1b, b'O', final_byte]
+        } else {
+            vec![0x1b, b'[', final_byte]
+        }
+    }
+
+    /// F1-F4：传统模式使用 SS3，带修饰键或启用 kitty 协议时使用 CSI
+    ///
+    /// kitty 协议下 F3 使用 CSI 13 ~，避免与光标位置报告 (CSI row;col R) 混淆
+    fn f1_to_f4(&self, final_byte: u8, param: u8) -> Vec<u8> {
+        let kitty = self.modes.kitty_keyboard_flags != 0;
+        if kitty && final_byte == b'R' {
+            tilde_key(13, param)
+        } else if param > 1 {
+            format!("\x1b[1;{}{}", param, final_byte as char).into_bytes()
+        } else if kitty {
+            vec![0x1b, b'[', final_byte]
+        } else {
+            vec![0x1b, b'O', final_byte]
+        }
+    }
+
+    /// Alt 作为 Meta 时为按键添加 ESC 前缀
+    fn with_meta(&self, bytes: &[u8], modifiers: egui::Modifiers) -> Vec<u8> {
+        let mut result = Vec::with_capacity(bytes.len() + 1);
+        if modifiers.alt && self.alt_sends_escape {
+            result.push(0x1b);
+        }
+        result.extend_from_slice(bytes);
+        result
+    }
+}
+
+/// xterm 修饰键参数：1 + Shift(1) + Alt(2) + Ctrl(4)
+fn modifier_param(modifiers: egui::Modifiers) -> u8 {
+    let mut param = 1;
+    if modifiers.shift {
+        param += 1;
+    }
+    if modifiers.alt {
+        param += 2;
+    }
+    if modifiers.ctrl {
+        param += 4;
+    }
+    param
+}
+
+/// CSI n ~ 形式的按键
+fn tilde_key(code: u8, param: u8) -> Vec<u8> {
+    if param > 1 {
+        format!("\x1b[{};{}~", code, param).into_bytes()
+    } else {
+        format!("\x1b[{}~", code).into_bytes()
+    }
+}
+
+/// kitty 协议 CSI code ; modifiers u 形式的按键
+fn kitty_csi_u(code: u32, modifiers: egui::Modifiers) -> Vec<u8> {
+    let param = modifier_param(modifiers);
+    if param > 1 {
+        format!("\x1b[{};{}u", code, param).into_bytes()
+    } else {
+        format!("\x1b[{}u", code).into_bytes()
+    }
+}
+
+/// 可打印按键对应的字符（小写、未施加Shift）
+fn key_char(key: egui::Key) -> Option<char> {
+    use egui::Key;
+
+    let c = match key {
+        Key::Space => ' ',
+        Key::Colon => ':',
+        Key::Comma => ',',
+        Key::Backslash => '\\',
+        Key::Slash => '/',
+        Key::Pipe => '|',
+        Key::Questionmark => '?',
+        Key::Exclamationmark => '!',
+        Key::OpenBracket => '[',
+        Key::CloseBracket => ']',
+        Key::OpenCurlyBracket => '{',
+        Key::CloseCurlyBracket => '}',
+        Key::Backtick => '`',
+        Key::Minus => '-',
+        Key::Period => '.',
+        Key::Plus => '+',
+        Key::Equals => '=',
+        Key::Semicolon => ';',
+        Key::Quote => '\'',
+        _ => {
+            let name = key.name();
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphanumeric() => c.to_ascii_lowercase(),
+                _ => return None,
+            }
+        }
+    };
+
+    Some(c)
+}
+
+/// Ctrl 组合键对应的控制码
+fn ctrl_code(c: char) -> Option<u8> {
+    match c {
+        'a'..='z' => Some(c as u8 - b'a' + 1),
+        ' ' | '2' | '@' => Some(0x00),
+        '3' | '[' | '{' => Some(0x1b),
+        '4' | '\\' | '|' => Some(0x1c),
+        '5' | ']' | '}' => Some(0x1d),
+        '6' | '^' => Some(0x1e),
+        '7' | '-' | '/' => Some(0x1f),
+        '8' | '?' => Some(0x7f),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Key, Modifiers};
+
+    fn encoder() -> KeyEncoder {
+        KeyEncoder::new(TerminalModes::default(), true)
+    }
+
+    fn encode(encoder: &KeyEncoder, key: Key, modifiers: Modifiers) -> Vec<u8> {
+        encoder.encode(key, modifiers).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_arrow_keys_follow_cursor_mode() {
+        let mut enc = encoder();
+        assert_eq!(encode(&enc, Key::ArrowUp, Modifiers::NONE), b"\x1b[A");
+
+        enc.modes.application_cursor = true;
+        assert_eq!(encode(&enc, Key::ArrowUp, Modifiers::NONE), b"\x1bOA");
+        assert_eq!(encode(&enc, Key::Home, Modifiers::NONE), b"\x1bOH");
+
+        // 带修饰键时总是使用 CSI 1;m 形式
+        assert_eq!(encode(&enc, Key::ArrowLeft, Modifiers::CTRL), b"\x1b[1;5D");
+    }
+
+    #[test]
+    fn test_modified_cursor_keys() {
+        let enc = encoder();
+        assert_eq!(encode(&enc, Key::ArrowUp, Modifiers::SHIFT), b"\x1b[1;2A");
+        assert_eq!(encode(&enc, Key::ArrowRight, Modifiers::ALT), b"\x1b[1;3C");
+        assert_eq!(
+            encode(&enc, Key::ArrowDown, Modifiers::CTRL | Modifiers::SHIFT),
+            b"\x1b[1;6B"
+        );
+        assert_eq!(encode(&enc, Key::Delete, Modifiers::CTRL), b"\x1b[3;5~");
+    }
+
+    #[test]
+    fn test_function_keys() {
+        let enc = encoder();
+        assert_eq!(encode(&enc, Key::F1, Modifiers::NONE), b"\x1bOP");
+        assert_eq!(encode(&enc, Key::F4, Modifiers::NONE), b"\x1bOS");
+        assert_eq!(encode(&enc, Key::F1, Modifiers::SHIFT), b"\x1b[1;2P");
+        assert_eq!(encode(&enc, Key::F5, Modifiers::NONE), b"\x1b[15~");
+        assert_eq!(encode(&enc, Key::F10, Modifiers::NONE), b"\x1b[21~");
+        assert_eq!(encode(&enc, Key::F12, Modifiers::CTRL), b"\x1b[24;5~");
+    }
+
+    #[test]
+    fn test_ctrl_and_meta_keys() {
+        let enc = encoder();
+        assert_eq!(encode(&enc, Key::C, Modifiers::CTRL), vec![0x03]);
+        assert_eq!(encode(&enc, Key::OpenBracket, Modifiers::CTRL), vec![0x1b]);
+        assert_eq!(encode(&enc, Key::Space, Modifiers::CTRL), vec![0x00]);
+        assert_eq!(encode(&enc, Key::B, Modifiers::ALT), b"\x1bb");
+        assert_eq!(
+            encode(&enc, Key::B, Modifiers::ALT | Modifiers::SHIFT),
+            b"\x1bB"
+        );
+        assert_eq!(
+            encode(&enc, Key::X, Modifiers::ALT | Modifiers::CTRL),
+            b"\x1b\x18"
+        );
+        assert_eq!(encode(&enc, Key::Backspace, Modifiers::ALT), b"\x1b\x7f");
+
+        // 普通字母交给文本事件
+        assert!(enc.encode(Key::A, Modifiers::NONE).is_none());
+        assert!(enc.encode(Key::A, Modifiers::SHIFT).is_none());
+
+        // 关闭 Alt-as-Meta 后 Alt 组合不再加 ESC
+        let no_meta = KeyEncoder::new(TerminalModes::default(), false);
+        assert!(no_meta.encode(Key::B, Modifiers::ALT).is_none());
+        assert!(!no_meta.consumes_text(Modifiers::ALT, "b"));
+    }
+
+    #[test]
+    fn test_editing_keys() {
+        let enc = encoder();
+        assert_eq!(encode(&enc, Key::Enter, Modifiers::NONE), b"\r");
+        assert_eq!(encode(&enc, Key::Tab, Modifiers::NONE), b"\t");
+        assert_eq!(encode(&enc, Key::Tab, Modifiers::SHIFT), b"\x1b[Z");
+        assert_eq!(encode(&enc, Key::Backspace, Modifiers::NONE), b"\x7f");
+        assert_eq!(encode(&enc, Key::Backspace, Modifiers::CTRL), b"\x08");
+        assert_eq!(encode(&enc, Key::Escape, Modifiers::NONE), b"\x1b");
+        assert_eq!(encode(&enc, Key::PageUp, Modifiers::NONE), b"\x1b[5~");
+    }
+
+    #[test]
+    fn test_kitty_keyboard_protocol() {
+        let mut enc = encoder();
+        enc.modes.kitty_keyboard_flags = KITTY_DISAMBIGUATE;
+
+        assert_eq!(encode(&enc, Key::Escape, Modifiers::NONE), b"\x1b[27u");
+        assert_eq!(encode(&enc, Key::C, Modifiers::CTRL), b"\x1b[99;5u");
+        assert_eq!(encode(&enc, Key::Enter, Modifiers::SHIFT), b"\x1b[13;2u");
+        assert_eq!(encode(&enc, Key::Enter, Modifiers::NONE), b"\r");
+        assert_eq!(encode(&enc, Key::F1, Modifiers::NONE), b"\x1b[P");
+        assert!(enc.encode(Key::A, Modifiers::NONE).is_none());
+
+        enc.modes.kitty_keyboard_flags = KITTY_DISAMBIGUATE | KITTY_REPORT_ALL_KEYS;
+        assert_eq!(encode(&enc, Key::A, Modifiers::NONE), b"\x1b[97u");
+        assert_eq!(encode(&enc, Key::Enter, Modifiers::NONE), b"\x1b[13u");
+        assert!(enc.consumes_text(Modifiers::NONE, "a"));
+        // 非 ASCII 文本没有对应的按键事件，不能被吞掉
+        assert!(!enc.consumes_text(Modifiers::NONE, "é"));
+    }
+
+    #[test]
+    fn test_kitty_f3_does_not_collide_with_cpr() {
+        let mut enc = encoder();
+        assert_eq!(encode(&enc, Key::F3, Modifiers::NONE), b"\x1bOR");
+
+        enc.modes.kitty_keyboard_flags = KITTY_DISAMBIGUATE;
+        assert_eq!(encode(&enc, Key::F3, Modifiers::NONE), b"\x1b[13~");
+        assert_eq!(encode(&enc, Key::F3, Modifiers::SHIFT), b"\x1b[13;2~");
+        assert_eq!(encode(&enc, Key::F2, Modifiers::NONE), b"\x1b[Q");
+    }
+}
@@ -1,15 +1,30 @@
 //! 终端仿真器模块
 //! 提供SSH终端仿真功能
+//!
+//! 缓冲区、回滚历史与仿真引擎不依赖 egui，单元格只保存抽象颜色；
+//! renderer、theme、input、selection 是面向 egui 界面的部分，负责解析颜色、绘制与处理输入。
 
 pub mod buffer;
+pub mod charset;
+pub mod clipboard;
+pub mod color;
+#[cfg(test)]
+mod conformance;
+pub mod cursor;
 pub mod emulator;
+pub mod graphics;
+pub mod input;
+pub mod modes;
+pub mod paste;
 pub mod renderer;
+pub mod scrollback;
 pub mod selection;
 pub mod theme;
 pub mod wezterm_adapter;
 
 // 重新导出主要组件
 pub use buffer::SelectionRange; // Keep what might be needed for external use
+pub use color::CellColor;
 pub use emulator::{TerminalEmulator, TerminalEvent};
 pub use renderer::TerminalRenderer;
 pub use selection::TextSelector;
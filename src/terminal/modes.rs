@@ -0,0 +1,74 @@
+//! 终端模式状态
+//! 记录远端程序通过 DECSET/DECRST 等序列切换的终端模式
+
+/// 终端模式集合
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalModes {
+    /// 光标键应用模式 (DECCKM, DECSET 1)
+    pub application_cursor: bool,
+    /// 原点模式 (DECOM, DECSET 6)：光标定位相对于滚动区域
+    pub origin: bool,
+    /// 自动换行 (DECAWM, DECSET 7)
+    pub autowrap: bool,
+    /// 光标可见 (DECTCEM, DECSET 25)
+    pub cursor_visible: bool,
+    /// 小键盘应用模式 (DECKPAM: ESC = / DECKPNM: ESC >)
+    pub application_keypad: bool,
+    /// 焦点变化报告 (DECSET 1004)：获得/失去焦点时发送 CSI I / CSI O
+    pub focus_reporting: bool,
+    /// 括号粘贴模式 (DECSET 2004)
+    pub bracketed_paste: bool,
+    /// 当前生效的 kitty 键盘协议标志
+    pub kitty_keyboard_flags: u8,
+    /// 同步输出 (DECSET 2026)：远端正在更新画面，界面继续显示更新前的完整画面
+    pub synchronized_output: bool,
+}
+
+impl Default for TerminalModes {
+    fn default() -> Self {
+        Self {
+            application_cursor: false,
+            origin: false,
+            autowrap: true,
+            cursor_visible: true,
+            application_keypad: false,
+            focus_reporting: false,
+            bracketed_paste: false,
+            kitty_keyboard_flags: 0,
+            synchronized_output: false,
+        }
+    }
+}
+
+impl TerminalModes {
+    /// 设置或清除DEC私有模式 (CSI ? Pm h / CSI ? Pm l)
+    ///
+    /// 返回该模式是否被识别
+    pub fn set_dec_private(&mut self, mode: u16, enabled: bool) -> bool {
+        match mode {
+            1 => self.application_cursor = enabled,
+            6 => self.origin = enabled,
+            7 => self.autowrap = enabled,
+            25 => self.cursor_visible = enabled,
+            1004 => self.focus_reporting = enabled,
+            2004 => self.bracketed_paste = enabled,
+            2026 => self.synchronized_output = enabled,
+            _ => return false,
+        }
+        true
+    }
+
+    /// 查询 DEC 私有模式的状态 (DECRQM)，未识别的模式返回 None
+    pub fn dec_private(&self, mode: u16) -> Option<bool> {
+        match mode {
+            1 => Some(self.application_cursor),
+            6 => Some(self.origin),
+            7 => Some(self.autowrap),
+            25 => Some(self.cursor_visible),
+            1004 => Some(self.focus_reporting),
+            2004 => Some(self.bracketed_paste),
+            2026 => Some(self.synchronized_output),
+            _ => None,
+        }
+    }
+}
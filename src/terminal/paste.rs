@@ -0,0 +1,108 @@
+//! 粘贴处理
+//! 清理剪贴板文本中的控制字符，并在括号粘贴模式下包裹粘贴内容
+
+/// 括号粘贴起始标记
+pub const BRACKETED_PASTE_START: &[u8] = b"\x1b[200~";
+/// 括号粘贴结束标记
+pub const BRACKETED_PASTE_END: &[u8] = b"\x1b[201~";
+
+/// 清理粘贴文本
+///
+/// 换行统一转换为回车（与xterm一致），保留制表符，
+/// 其余C0/C1控制字符和DEL全部移除，避免粘贴内容夹带转义序列提前结束括号粘贴。
+pub fn sanitize(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                // CRLF 只保留一个回车
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                result.push('\r');
+            }
+            '\n' => result.push('\r'),
+            '\t' => result.push('\t'),
+            c if c.is_control() => {}
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// 将粘贴文本编码为发送到远端的字节序列
+pub fn encode(text: &str, bracketed: bool) -> Vec<u8> {
+    let cleaned = sanitize(text);
+    let mut bytes = Vec::with_capacity(cleaned.len() + 12);
+
+    if bracketed {
+        bytes.extend_from_slice(BRACKETED_PASTE_START);
+        bytes.extend_from_slice(cleaned.as_bytes());
+        bytes.extend_from_slice(BRACKETED_PASTE_END);
+    } else {
+        bytes.extend_from_slice(cleaned.as_bytes());
+    }
+
+    bytes
+}
+
+/// 统计粘贴文本的行数（忽略末尾的换行）
+pub fn line_count(text: &str) -> usize {
+    let trimmed = text.trim_end_matches(['\r', '\n']);
+    if trimmed.is_empty() {
+        return 0;
+    }
+    trimmed.replace("\r\n", "\n").split(['\r', '\n']).count()
+}
+
+/// 判断粘贴前是否需要用户确认
+pub fn needs_confirmation(text: &str, confirm_multiline: bool, large_threshold: usize) -> bool {
+    (confirm_multiline && line_count(text) > 1)
+        || (large_threshold > 0 && text.len() > large_threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_strips_controls() {
+        assert_eq!(sanitize("ls\x1b[201~; rm -rf ~"), "ls[201~; rm -rf ~");
+        assert_eq!(sanitize("a\x07b\x7fc\u{9b}d"), "abcd");
+        assert_eq!(sanitize("a\tb"), "a\tb");
+    }
+
+    #[test]
+    fn test_sanitize_normalizes_newlines() {
+        assert_eq!(sanitize("a\r\nb\nc\rd"), "a\rb\rc\rd");
+    }
+
+    #[test]
+    fn test_encode_bracketed() {
+        assert_eq!(
+            encode("echo hi\n", true),
+            b"\x1b[200~echo hi\r\x1b[201~".to_vec()
+        );
+        assert_eq!(encode("echo hi\n", false), b"echo hi\r".to_vec());
+    }
+
+    #[test]
+    fn test_line_count() {
+        assert_eq!(line_count(""), 0);
+        assert_eq!(line_count("ls\n"), 1);
+        assert_eq!(line_count("a\r\nb\r\n"), 2);
+        assert_eq!(line_count("a\nb\nc"), 3);
+    }
+
+    #[test]
+    fn test_needs_confirmation() {
+        assert!(!needs_confirmation("ls -la\n", true, 1024));
+        assert!(needs_confirmation("ls\nrm x", true, 1024));
+        assert!(!needs_confirmation("ls\nrm x", false, 1024));
+        assert!(needs_confirmation(&"x".repeat(2048), true, 1024));
+        assert!(!needs_confirmation(&"x".repeat(2048), true, 0));
+    }
+}
@@ -1,192 +1,545 @@
 //! 终端渲染器
 //! 负责在egui中渲染终端内容
 
-use crate::terminal::buffer::TerminalBuffer;
-use crate::terminal::theme::TerminalTheme;
+use crate::terminal::buffer::{Link, Scroll, TerminalBuffer, TerminalCell};
+use crate::terminal::color::{CellColor, PaletteOverrides};
+use crate::terminal::cursor::{CursorShape, CursorStyle};
+use crate::terminal::graphics::{ImageCell, TerminalImage};
+use crate::terminal::theme::{TerminalTheme, ThemeStyle};
 use eframe::egui;
+use std::collections::HashMap;
+
+/// 左侧边栏宽度，用于显示提示符与命令退出码标记
+pub const GUTTER_WIDTH: f32 = 18.0;
+
+/// 右侧滚动条宽度
+pub const SCROLLBAR_WIDTH: f32 = 10.0;
+
+/// 滚动条滑块的最小高度
+const SCROLLBAR_MIN_THUMB: f32 = 16.0;
+
+/// 图片纹理连续多少帧未被绘制后释放
+const TEXTURE_IDLE_PASSES: u64 = 600;
+
+/// 内联图片的纹理缓存
+///
+/// 纹理保存在 egui 的临时存储中，按图片的全局键查找。
+#[derive(Clone, Default)]
+struct ImageTextures {
+    /// 图片键 -> (纹理, 最近一次绘制的帧号)
+    textures: HashMap<u64, (egui::TextureHandle, u64)>,
+}
+
+impl ImageTextures {
+    fn storage_id() -> egui::Id {
+        egui::Id::new("terminal_image_textures")
+    }
+
+    fn load(ctx: &egui::Context) -> Self {
+        ctx.data_mut(|data| data.get_temp(Self::storage_id()))
+            .unwrap_or_default()
+    }
+
+    /// 释放长时间未绘制的纹理后存回 egui
+    fn store(mut self, ctx: &egui::Context) {
+        let pass = ctx.cumulative_pass_nr();
+        self.textures
+            .retain(|_, (_, used)| pass.saturating_sub(*used) < TEXTURE_IDLE_PASSES);
+        ctx.data_mut(|data| data.insert_temp(Self::storage_id(), self));
+    }
+
+    /// 获取图片的纹理，第一次绘制时上传
+    fn texture(&mut self, ctx: &egui::Context, image: &TerminalImage) -> egui::TextureId {
+        let pass = ctx.cumulative_pass_nr();
+        let entry = self.textures.entry(image.key).or_insert_with(|| {
+            let max_side = ctx.input(|i| i.max_texture_side);
+            let color = fit_texture(image, max_side);
+            let texture = ctx.load_texture(
+                format!("terminal_image_{}", image.key),
+                color,
+                egui::TextureOptions::LINEAR,
+            );
+            (texture, pass)
+        });
+        entry.1 = pass;
+        entry.0.id()
+    }
+}
+
+/// 把图片转为纹理数据，边长超过显卡支持的上限时按比例缩小（最近邻采样）
+fn fit_texture(image: &TerminalImage, max_side: usize) -> egui::ColorImage {
+    let longest = image.width.max(image.height);
+    if longest <= max_side {
+        return egui::ColorImage::from_rgba_unmultiplied([image.width, image.height], &image.rgba);
+    }
+
+    let scale = |side: usize| (side * max_side / longest).max(1);
+    let (width, height) = (scale(image.width), scale(image.height));
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let src_y = y * image.height / height;
+        for x in 0..width {
+            let src = (src_y * image.width + x * image.width / width) * 4;
+            rgba.extend_from_slice(&image.rgba[src..src + 4]);
+        }
+    }
+    egui::ColorImage::from_rgba_unmultiplied([width, height], &rgba)
+}
+
+/// 缓存行图形时的布局参数，任一项变化后缓存整体失效
+///
+/// 单元格颜色在生成图形时才由主题解析，主题的配色变化后已有内容随之重绘。
+#[derive(Debug, Clone, PartialEq)]
+struct RowLayout {
+    buffer_id: u64,
+    char_size: egui::Vec2,
+    line_height: f32,
+    pixels_per_point: f32,
+    style: ThemeStyle,
+}
+
+/// 已生成图形的行
+struct CachedRow {
+    /// 生成时该行的修改计数
+    version: u64,
+    /// 背景与文字图形，坐标相对于行的左上角
+    shapes: Vec<egui::Shape>,
+    /// 行中的图片切片及其所在列（图片每帧单独绘制）
+    images: Vec<(usize, ImageCell)>,
+}
 
 /// 终端渲染器
+///
+/// 每个会话保留一个渲染器，可见行的图形按行缓存，内容未变化的行直接复用，
+/// 静止的画面每帧只需复制少量图形。
+///
+/// 只分配并绘制视口中的行，滚动位置由缓冲区的 display_offset 决定；
+/// 滚轮与滚动条产生的滚动放入 `scroll_request`，由调用方交给仿真器。
 pub struct TerminalRenderer {
-    pub buffer: TerminalBuffer,
     pub theme: TerminalTheme,
     pub font_id: egui::FontId,
-    /// 光标闪烁计时器
-    cursor_blink_timer: std::time::Instant,
-    /// 光标是否可见
-    cursor_visible: bool,
+    /// 按住 Ctrl（macOS 上为 Cmd）时鼠标悬停处的链接
+    pub hovered_link: Option<Link>,
+    /// 本帧滚轮或滚动条请求的视口滚动
+    pub scroll_request: Option<Scroll>,
+    /// 滚轮滚动不足一行的剩余距离
+    wheel_remainder: f32,
+    layout: Option<RowLayout>,
+    /// 行号（历史与屏幕统一编号）-> 该行的图形
+    rows: HashMap<usize, CachedRow>,
 }
 
 impl TerminalRenderer {
-    pub fn new(rows: usize, cols: usize, theme: TerminalTheme) -> Self {
-        let buffer = TerminalBuffer::new(rows, cols);
+    pub fn new(theme: TerminalTheme) -> Self {
         let font_id = egui::FontId::monospace(theme.font_size);
 
         Self {
-            buffer,
             theme,
             font_id,
-            cursor_blink_timer: std::time::Instant::now(),
-            cursor_visible: true,
+            hovered_link: None,
+            scroll_request: None,
+            wheel_remainder: 0.0,
+            layout: None,
+            rows: HashMap::new(),
+        }
+    }
+
+    /// 更新主题，字号变化时同步字体
+    pub fn set_theme(&mut self, theme: TerminalTheme) {
+        self.font_id = egui::FontId::monospace(theme.font_size);
+        self.theme = theme;
+    }
+
+    /// 终端背景色，远端通过 OSC 11 修改后以修改的为准
+    fn background(&self, buffer: &TerminalBuffer) -> egui::Color32 {
+        self.theme
+            .resolve(CellColor::Background, false, &buffer.palette)
+    }
+
+    /// 渲染左侧边栏、终端视口与滚动条，返回终端内容区域的响应
+    pub fn render(&mut self, ui: &mut egui::Ui, buffer: &TerminalBuffer) -> egui::Response {
+        ui.horizontal_top(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+
+            let char_size = self.measure_char_size(ui);
+            let height = buffer.rows as f32 * char_size.y * self.theme.line_height;
+            let (gutter_rect, _) =
+                ui.allocate_exact_size(egui::vec2(GUTTER_WIDTH, height), egui::Sense::hover());
+            self.render_gutter(ui, buffer, gutter_rect, char_size);
+
+            let response = self.render_content(ui, buffer);
+            self.handle_wheel(ui, &response, char_size);
+            self.render_scrollbar(ui, buffer, height);
+            response
+        })
+        .inner
+    }
+
+    /// 鼠标在终端上时把滚轮距离换算为行数，不足一行的部分留到下一帧
+    fn handle_wheel(&mut self, ui: &egui::Ui, response: &egui::Response, char_size: egui::Vec2) {
+        if !response.hovered() {
+            self.wheel_remainder = 0.0;
+            return;
+        }
+
+        let delta = ui.input(|i| i.smooth_scroll_delta.y);
+        if delta == 0.0 {
+            return;
+        }
+
+        let row_height = char_size.y * self.theme.line_height;
+        self.wheel_remainder += delta / row_height;
+        let lines = self.wheel_remainder.trunc();
+        if lines != 0.0 {
+            self.wheel_remainder -= lines;
+            self.scroll_request = Some(Scroll::Delta(lines as isize));
+        }
+    }
+
+    /// 绘制滚动条，拖动滑块或点击轨道时请求滚动到对应位置
+    fn render_scrollbar(&mut self, ui: &mut egui::Ui, buffer: &TerminalBuffer, height: f32) {
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(SCROLLBAR_WIDTH, height),
+            egui::Sense::click_and_drag(),
+        );
+        let history = buffer.history.len();
+        if history == 0 || !ui.is_rect_visible(rect) {
+            return;
         }
+
+        // 滑块按视口在全部行中的比例确定大小与位置
+        let total_rows = (history + buffer.rows) as f32;
+        let thumb_height = (height * buffer.rows as f32 / total_rows)
+            .max(SCROLLBAR_MIN_THUMB)
+            .min(height);
+        let track = height - thumb_height;
+
+        if let Some(pos) = response.interact_pointer_pos()
+            && (response.dragged() || response.clicked())
+        {
+            let fraction =
+                ((pos.y - rect.min.y - thumb_height / 2.0) / track.max(1.0)).clamp(0.0, 1.0);
+            let row = (fraction * history as f32).round() as usize;
+            self.scroll_request = Some(Scroll::ToRow(row));
+        }
+
+        let top = buffer.display_top() as f32 / history as f32 * track;
+        let thumb = egui::Rect::from_min_size(
+            egui::pos2(rect.min.x + 2.0, rect.min.y + top),
+            egui::vec2(SCROLLBAR_WIDTH - 4.0, thumb_height),
+        );
+        let color = if response.hovered() || response.dragged() {
+            self.theme.style.foreground.gamma_multiply(0.5)
+        } else {
+            self.theme.style.foreground.gamma_multiply(0.25)
+        };
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, self.background(buffer));
+        painter.rect_filled(thumb, 3.0, color);
     }
 
-    pub fn resize(&mut self, rows: usize, cols: usize) {
-        self.buffer.resize(rows, cols);
+    /// 在边栏中标记提示符行：命令成功为绿点，失败显示退出码，未结束为灰点
+    fn render_gutter(
+        &self,
+        ui: &egui::Ui,
+        buffer: &TerminalBuffer,
+        rect: egui::Rect,
+        char_size: egui::Vec2,
+    ) {
+        if !ui.is_rect_visible(rect) {
+            return;
+        }
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, self.background(buffer));
+
+        // 只处理视口中的行
+        let row_height = char_size.y * self.theme.line_height;
+        let top = buffer.display_top();
+        let (first_row, last_row) = visible_rows(ui.clip_rect(), rect, row_height, buffer.rows);
+
+        for row in first_row..last_row {
+            let Some(mark) = buffer.line_mark(top + row).filter(|mark| mark.prompt) else {
+                continue;
+            };
+
+            let center = egui::pos2(
+                rect.center().x,
+                rect.min.y + (row as f32 + 0.5) * row_height,
+            );
+            match mark.exit_code {
+                Some(0) => {
+                    painter.circle_filled(center, 3.0, self.theme.style.green);
+                }
+                Some(code) => {
+                    let label = if (-99..=999).contains(&code) {
+                        code.to_string()
+                    } else {
+                        "!".to_string()
+                    };
+                    painter.text(
+                        center,
+                        egui::Align2::CENTER_CENTER,
+                        label,
+                        egui::FontId::monospace(self.theme.font_size * 0.65),
+                        self.theme.style.red,
+                    );
+                }
+                None => {
+                    painter.circle_filled(center, 2.0, self.theme.style.bright_black);
+                }
+            }
+        }
     }
 
-    pub fn render(&mut self, ui: &mut egui::Ui) -> egui::Response {
+    fn render_content(&mut self, ui: &mut egui::Ui, buffer: &TerminalBuffer) -> egui::Response {
         let char_size = self.measure_char_size(ui);
+        let row_height = char_size.y * self.theme.line_height;
 
-        // 计算总高度（包括历史记录和当前可见行）
-        let total_rows = self.buffer.history.len() + self.buffer.rows;
-        let total_height = total_rows as f32 * char_size.y * self.theme.line_height;
-        let render_width = self.buffer.cols as f32 * char_size.x;
+        // 只分配视口的高度，历史记录通过 display_offset 滚入视口
+        let render_height = buffer.rows as f32 * row_height;
+        let render_width = buffer.cols as f32 * char_size.x;
+        let top = buffer.display_top();
 
         let (rect, response) = ui.allocate_exact_size(
-            egui::vec2(render_width, total_height),
+            egui::vec2(render_width, render_height),
             egui::Sense::click_and_drag(),
         );
 
         if ui.is_rect_visible(rect) {
             let painter = ui.painter_at(rect);
+            let mut textures = ImageTextures::load(ui.ctx());
+            let cell_size = egui::vec2(char_size.x, row_height);
 
             // 绘制整个背景
-            painter.rect_filled(rect, 0.0, self.theme.style.background);
-
-            // 1. 绘制历史记录
-            for (h_row, line) in self.buffer.history.iter().enumerate() {
-                let row_pos_y = rect.min.y + h_row as f32 * char_size.y * self.theme.line_height;
+            painter.rect_filled(rect, 0.0, self.background(buffer));
+
+            // 布局参数变化后缓存的图形全部失效
+            let layout = RowLayout {
+                buffer_id: buffer.id(),
+                char_size,
+                line_height: self.theme.line_height,
+                pixels_per_point: ui.ctx().pixels_per_point(),
+                style: self.theme.style.clone(),
+            };
+            if self.layout.as_ref() != Some(&layout) {
+                self.layout = Some(layout);
+                self.rows.clear();
+            }
 
-                // 检查行是否可见
-                let row_rect = egui::Rect::from_min_size(
-                    egui::pos2(rect.min.x, row_pos_y),
-                    egui::vec2(render_width, char_size.y * self.theme.line_height),
+            // 只绘制视口中的行（缓存按历史记录与当前屏幕统一的行号保存），只保留这些行的缓存
+            let (first_row, last_row) = visible_rows(ui.clip_rect(), rect, row_height, buffer.rows);
+            let mut cached_rows = std::mem::take(&mut self.rows);
+            for row in (first_row..last_row).map(|row| top + row) {
+                // 缓存有效时不读取行内容，历史行的解码只在行变化后进行
+                let version = buffer.line_version(row);
+                let cached = match cached_rows.remove(&row) {
+                    Some(cached) if cached.version == version => cached,
+                    _ => {
+                        let Some(line) = buffer.line(row) else {
+                            continue;
+                        };
+                        self.build_row(
+                            &painter,
+                            &line,
+                            &buffer.palette,
+                            buffer.cols,
+                            char_size,
+                            version,
+                        )
+                    }
+                };
+
+                let origin = egui::pos2(rect.min.x, rect.min.y + (row - top) as f32 * row_height);
+                painter.extend(
+                    cached
+                        .shapes
+                        .iter()
+                        .map(|shape| translated(shape, origin.to_vec2())),
                 );
 
-                if !ui.is_rect_visible(row_rect) {
-                    continue;
+                for (col, image) in &cached.images {
+                    let pos = origin + egui::vec2(*col as f32 * char_size.x, 0.0);
+                    self.paint_image_cell(
+                        ui.ctx(),
+                        &painter,
+                        &mut textures,
+                        buffer,
+                        image,
+                        egui::Rect::from_min_size(pos, cell_size),
+                    );
                 }
 
-                for col in 0..self.buffer.cols {
-                    if let Some(cell) = line.get(col) {
-                        if cell.is_continuation {
-                            continue;
-                        }
+                self.rows.insert(row, cached);
+            }
 
-                        if cell.character == ' ' && cell.bg_color == egui::Color32::TRANSPARENT {
-                            continue;
-                        }
-
-                        let width = crate::utils::helpers::get_char_width(cell.character);
-                        let char_pos = egui::pos2(rect.min.x + col as f32 * char_size.x, row_pos_y);
-
-                        // 绘制背景（包括选中状态）
-                        let bg_color = if cell.is_selected {
-                            // 选中状态的背景色
-                            self.theme.style.selection
-                        } else if cell.bg_color != egui::Color32::TRANSPARENT
-                            && cell.bg_color != self.theme.style.background
-                        {
-                            cell.bg_color
-                        } else {
-                            self.theme.style.background
-                        };
+            // 按住 Ctrl 悬停在链接上时绘制下划线
+            self.hovered_link = self.link_under_pointer(ui, buffer, &response, rect.min, char_size);
+            if let Some(ref link) = self.hovered_link {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+
+                let y = rect.min.y + (link.row - top) as f32 * row_height + char_size.y;
+                let x_start = rect.min.x + link.start_col as f32 * char_size.x;
+                let x_end = rect.min.x + link.end_col as f32 * char_size.x;
+                painter.line_segment(
+                    [egui::pos2(x_start, y), egui::pos2(x_end, y)],
+                    egui::Stroke::new(1.0, self.theme.style.foreground),
+                );
+            }
 
-                        if bg_color != self.theme.style.background {
-                            let bg_rect = egui::Rect::from_min_size(
-                                char_pos,
-                                egui::vec2(
-                                    char_size.x * width as f32,
-                                    char_size.y * self.theme.line_height,
-                                ),
-                            );
-                            painter.rect_filled(bg_rect, 0.0, bg_color);
-                        }
-
-                        // 绘制文字
-                        if cell.character != ' ' {
-                            painter.text(
-                                char_pos,
-                                egui::Align2::LEFT_TOP,
-                                cell.character.to_string(),
-                                self.font_id.clone(),
-                                cell.fg_color,
-                            );
-                        }
-                    }
+            // 绘制光标 (在其相对于当前屏幕的位置绘制)，终端或窗口失去焦点时绘制空心光标
+            let screen_start_y = rect.min.y + buffer.display_offset() as f32 * row_height;
+            let cursor_screen_pos = egui::pos2(rect.min.x, screen_start_y);
+            let focused = response.has_focus() && ui.input(|i| i.focused);
+            self.render_cursor(&painter, buffer, cursor_screen_pos, char_size, focused);
+
+            textures.store(ui.ctx());
+        }
+
+        response
+    }
+
+    /// 生成一行的背景与文字图形，坐标相对于行的左上角
+    ///
+    /// 相邻同色的背景合并为一个矩形，相邻同色的 ASCII 字符合并为一段文字
+    /// （等宽字体下 ASCII 字符的宽度与单元格一致），其余字符按单元格单独绘制。
+    fn build_row(
+        &self,
+        painter: &egui::Painter,
+        line: &[TerminalCell],
+        palette: &PaletteOverrides,
+        cols: usize,
+        char_size: egui::Vec2,
+        version: u64,
+    ) -> CachedRow {
+        let row_height = char_size.y * self.theme.line_height;
+        let background = self.theme.resolve(CellColor::Background, false, palette);
+        let mut backgrounds: Vec<(usize, usize, egui::Color32)> = Vec::new();
+        let mut texts: Vec<(usize, String, egui::Color32)> = Vec::new();
+        // 正在合并的文字段在 texts 中的位置及其结束列
+        let mut run: Option<(usize, usize)> = None;
+        let mut images = Vec::new();
+
+        for (col, cell) in line.iter().enumerate().take(cols) {
+            if let Some(ref image) = cell.image {
+                images.push((col, (**image).clone()));
+            }
+            if cell.is_continuation {
+                continue;
+            }
+
+            if cell.character == ' ' && cell.bg_color == CellColor::Background {
+                // 空白单元格只延长正在合并的文字段
+                if let Some((index, end)) = run.as_mut()
+                    && *end == col
+                {
+                    texts[*index].1.push(' ');
+                    *end += 1;
                 }
+                continue;
             }
 
-            // 2. 绘制当前可视屏幕
-            let screen_start_y = rect.min.y
-                + self.buffer.history.len() as f32 * char_size.y * self.theme.line_height;
-            for row in 0..self.buffer.rows {
-                let row_pos_y = screen_start_y + row as f32 * char_size.y * self.theme.line_height;
+            let width = cell_span(line, col);
 
-                let row_rect = egui::Rect::from_min_size(
-                    egui::pos2(rect.min.x, row_pos_y),
-                    egui::vec2(render_width, char_size.y * self.theme.line_height),
-                );
+            // 背景（包括选中状态）
+            let bg_color = if cell.is_selected {
+                // 选中状态的背景色
+                self.theme.style.selection
+            } else {
+                self.theme.resolve(cell.bg_color, false, palette)
+            };
+
+            if bg_color != background {
+                match backgrounds.last_mut() {
+                    Some((_, end, color)) if *end == col && *color == bg_color => *end += width,
+                    _ => backgrounds.push((col, col + width, bg_color)),
+                }
+            }
 
-                if !ui.is_rect_visible(row_rect) {
+            // 文字
+            let fg_color = self.theme.resolve(cell.fg_color, cell.bold, palette);
+            let single = width == 1 && cell.combining.is_none() && cell.character.is_ascii();
+            match run.as_mut() {
+                Some((index, end)) if single && *end == col && texts[*index].2 == fg_color => {
+                    texts[*index].1.push(cell.character);
+                    *end += 1;
                     continue;
                 }
+                _ => run = None,
+            }
+            if cell.character == ' ' && cell.combining.is_none() {
+                continue;
+            }
+            texts.push((col, cell.text(), fg_color));
+            if single {
+                run = Some((texts.len() - 1, col + 1));
+            }
+        }
 
-                for col in 0..self.buffer.cols {
-                    if let Some(cell) = self.buffer.get_cell(row, col) {
-                        if cell.is_continuation {
-                            continue;
-                        }
+        let mut shapes = Vec::with_capacity(backgrounds.len() + texts.len());
+        for (start, end, color) in backgrounds {
+            let rect = egui::Rect::from_min_max(
+                egui::pos2(start as f32 * char_size.x, 0.0),
+                egui::pos2(end as f32 * char_size.x, row_height),
+            );
+            shapes.push(egui::Shape::rect_filled(rect, 0.0, color));
+        }
+        for (col, text, color) in texts {
+            let text = text.trim_end();
+            if text.is_empty() {
+                continue;
+            }
+            let pos = egui::pos2(col as f32 * char_size.x, 0.0);
+            let galley = painter.layout_no_wrap(text.to_string(), self.font_id.clone(), color);
+            shapes.push(egui::Shape::galley(pos, galley, color));
+        }
 
-                        if cell.character == ' ' && cell.bg_color == egui::Color32::TRANSPARENT {
-                            continue;
-                        }
-
-                        let width = crate::utils::helpers::get_char_width(cell.character);
-                        let char_pos = egui::pos2(rect.min.x + col as f32 * char_size.x, row_pos_y);
-
-                        // 绘制背景（包括选中状态）
-                        let bg_color = if cell.is_selected {
-                            // 选中状态的背景色
-                            self.theme.style.selection
-                        } else if cell.bg_color != egui::Color32::TRANSPARENT
-                            && cell.bg_color != self.theme.style.background
-                        {
-                            cell.bg_color
-                        } else {
-                            self.theme.style.background
-                        };
+        CachedRow {
+            version,
+            shapes,
+            images,
+        }
+    }
 
-                        if bg_color != self.theme.style.background {
-                            let bg_rect = egui::Rect::from_min_size(
-                                char_pos,
-                                egui::vec2(
-                                    char_size.x * width as f32,
-                                    char_size.y * self.theme.line_height,
-                                ),
-                            );
-                            painter.rect_filled(bg_rect, 0.0, bg_color);
-                        }
-
-                        if cell.character != ' ' {
-                            painter.text(
-                                char_pos,
-                                egui::Align2::LEFT_TOP,
-                                cell.character.to_string(),
-                                self.font_id.clone(),
-                                cell.fg_color,
-                            );
-                        }
-                    }
-                }
-            }
+    /// 绘制单元格上的图片切片
+    fn paint_image_cell(
+        &self,
+        ctx: &egui::Context,
+        painter: &egui::Painter,
+        textures: &mut ImageTextures,
+        buffer: &TerminalBuffer,
+        image: &ImageCell,
+        rect: egui::Rect,
+    ) {
+        if let Some(data) = buffer.images.get(image.image) {
+            let texture = textures.texture(ctx, data);
+            let [left, top, right, bottom] = image.uv;
+            let uv = egui::Rect::from_min_max(egui::pos2(left, top), egui::pos2(right, bottom));
+            painter.image(texture, rect, uv, egui::Color32::WHITE);
+        }
+    }
 
-            // 绘制光标 (在其相对于当前屏幕的位置绘制)
-            let cursor_screen_pos = egui::pos2(rect.min.x, screen_start_y);
-            self.render_cursor(&painter, cursor_screen_pos, char_size);
+    /// 按住 Ctrl（macOS 上为 Cmd）时获取鼠标下的链接
+    fn link_under_pointer(
+        &self,
+        ui: &egui::Ui,
+        buffer: &TerminalBuffer,
+        response: &egui::Response,
+        origin: egui::Pos2,
+        char_size: egui::Vec2,
+    ) -> Option<Link> {
+        if !ui.input(|i| i.modifiers.command) {
+            return None;
         }
 
-        response
+        let pos = response.hover_pos()?;
+        let row = ((pos.y - origin.y) / (char_size.y * self.theme.line_height)).floor();
+        let col = ((pos.x - origin.x) / char_size.x).floor();
+        if row < 0.0 || col < 0.0 {
+            return None;
+        }
+
+        buffer.link_at(buffer.display_top() + row as usize, col as usize)
     }
 
     fn measure_char_size(&self, ui: &mut egui::Ui) -> egui::Vec2 {
@@ -203,107 +556,130 @@ impl TerminalRenderer {
     }
 
     fn render_cursor(
-        &mut self,
+        &self,
         painter: &egui::Painter,
+        buffer: &TerminalBuffer,
         screen_origin: egui::Pos2,
         char_size: egui::Vec2,
+        focused: bool,
     ) {
-        if self.buffer.cursor_row < self.buffer.rows && self.buffer.cursor_col < self.buffer.cols {
-            // 处理光标闪烁
-            if self.theme.cursor_blink {
-                let elapsed = self.cursor_blink_timer.elapsed().as_millis();
-                // 500ms切换一次可见性
-                if elapsed > 500 {
-                    self.cursor_visible = !self.cursor_visible;
-                    self.cursor_blink_timer = std::time::Instant::now();
-                }
+        // 远端程序隐藏了光标 (DECTCEM)
+        if !buffer.cursor_visible {
+            return;
+        }
 
-                // 如果光标不可见，直接返回
-                if !self.cursor_visible {
+        // 向上滚动查看历史时光标可能已移出视口
+        let row = buffer.cursor_row + buffer.display_offset();
+        if row < buffer.rows && buffer.cursor_col < buffer.cols {
+            let style = buffer.cursor_style.unwrap_or(CursorStyle {
+                shape: self.theme.cursor_shape,
+                blinking: self.theme.cursor_blink,
+            });
+
+            // 处理光标闪烁（失去焦点时不闪烁）
+            let interval = self.theme.cursor_blink_interval;
+            if focused && style.blinking && interval > 0 {
+                let elapsed = (painter.ctx().input(|i| i.time) * 1000.0) as u64;
+                let remaining = interval - elapsed % interval;
+                painter
+                    .ctx()
+                    .request_repaint_after(std::time::Duration::from_millis(remaining));
+
+                // 处于隐藏阶段，直接返回
+                if (elapsed / interval) % 2 == 1 {
                     return;
                 }
             }
 
             // 获取当前光标位置字符的宽度
-            let width = if let Some(cell) = self
-                .buffer
-                .get_cell(self.buffer.cursor_row, self.buffer.cursor_col)
-            {
+            let width = if let Some(cell) = buffer.get_cell(buffer.cursor_row, buffer.cursor_col) {
                 if cell.is_continuation {
                     1
                 } else {
-                    crate::utils::helpers::get_char_width(cell.character)
+                    cell_span(buffer.row_cells(buffer.cursor_row), buffer.cursor_col)
                 }
             } else {
                 1
             };
 
             let cursor_pos = egui::pos2(
-                screen_origin.x + self.buffer.cursor_col as f32 * char_size.x,
-                screen_origin.y
-                    + self.buffer.cursor_row as f32 * char_size.y * self.theme.line_height,
+                screen_origin.x + buffer.cursor_col as f32 * char_size.x,
+                screen_origin.y + buffer.cursor_row as f32 * char_size.y * self.theme.line_height,
             );
 
-            let cursor_rect = egui::Rect::from_min_size(
+            let cell_rect = egui::Rect::from_min_size(
                 cursor_pos,
                 egui::vec2(
                     char_size.x * width as f32,
                     char_size.y * self.theme.line_height,
                 ),
             );
+            let color = self.theme.style.cursor.linear_multiply(0.7);
+
+            if !focused {
+                painter.rect_stroke(
+                    cell_rect.shrink(0.5),
+                    0.0,
+                    egui::Stroke::new(1.0, color),
+                    egui::StrokeKind::Inside,
+                );
+                return;
+            }
 
-            painter.rect_filled(
-                cursor_rect,
-                0.0,
-                self.theme.style.cursor.linear_multiply(0.7),
-            );
+            // 下划线与竖线的粗细随字号变化
+            let thickness = (char_size.y * 0.12).max(2.0);
+            let cursor_rect = match style.shape {
+                CursorShape::Block => cell_rect,
+                CursorShape::Underline => egui::Rect::from_min_max(
+                    egui::pos2(cell_rect.min.x, cell_rect.max.y - thickness),
+                    cell_rect.max,
+                ),
+                CursorShape::Bar => egui::Rect::from_min_max(
+                    cell_rect.min,
+                    egui::pos2(cell_rect.min.x + thickness, cell_rect.max.y),
+                ),
+            };
+
+            painter.rect_filled(cursor_rect, 0.0, color);
         }
     }
+}
 
-    /// 更新缓冲区内容
-    pub fn update_buffer(&mut self, content: &str) {
-        let mut row = 0;
-        let mut col = 0;
-
-        for ch in content.chars() {
-            match ch {
-                '\n' => {
-                    row += 1;
-                    col = 0;
-                    if row >= self.buffer.rows {
-                        // 滚动逻辑可以在这里实现
-                        row = self.buffer.rows - 1;
-                    }
-                }
-                '\r' => {
-                    col = 0;
-                }
-                '\t' => {
-                    col = (col / 8 + 1) * 8;
-                    if col >= self.buffer.cols {
-                        col = self.buffer.cols - 1;
-                    }
-                }
-                ch => {
-                    if row < self.buffer.rows && col < self.buffer.cols {
-                        if let Some(cell) = self.buffer.get_cell_mut(row, col) {
-                            cell.character = ch;
-                            cell.fg_color = self.theme.style.foreground;
-                            cell.bg_color = self.theme.style.background;
-                        }
-                        col += 1;
-                        if col >= self.buffer.cols {
-                            col = 0;
-                            row += 1;
-                            if row >= self.buffer.rows {
-                                row = self.buffer.rows - 1;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+/// 单元格占用的列数（首列加上其后的延续位）
+fn cell_span(cells: &[TerminalCell], col: usize) -> usize {
+    1 + cells
+        .iter()
+        .skip(col + 1)
+        .take_while(|cell| cell.is_continuation)
+        .count()
+}
 
-        self.buffer.set_cursor(row, col);
+/// 裁剪区域内可见的行范围 [first, last)
+fn visible_rows(
+    clip: egui::Rect,
+    rect: egui::Rect,
+    row_height: f32,
+    total_rows: usize,
+) -> (usize, usize) {
+    let first = ((clip.min.y - rect.min.y) / row_height).floor().max(0.0) as usize;
+    let last = (((clip.max.y - rect.min.y) / row_height).ceil().max(0.0) as usize).min(total_rows);
+    (first.min(last), last)
+}
+
+/// 平移后的图形副本
+///
+/// 文字只移动位置：`Shape::translate` 会复制整个排版结果再逐个移动字形。
+fn translated(shape: &egui::Shape, offset: egui::Vec2) -> egui::Shape {
+    match shape {
+        egui::Shape::Text(text) => {
+            let mut text = text.clone();
+            text.pos += offset;
+            egui::Shape::Text(text)
+        }
+        _ => {
+            let mut shape = shape.clone();
+            shape.translate(offset);
+            shape
+        }
     }
 }
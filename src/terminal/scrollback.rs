@@ -0,0 +1,660 @@
+//! 回滚历史
+//! 以环形队列保存滚出屏幕的行：单元格按字符与属性分开存放，属性按连续段编码，
+//! 可选地对较早的行做压缩。行数与内存占用超过上限时淘汰最早的行。
+
+use crate::terminal::buffer::{HyperlinkId, LineMark, TerminalCell};
+use crate::terminal::color::CellColor;
+use crate::terminal::graphics::ImageCell;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+
+/// 最近的多少行始终保持未压缩，滚动浏览和选择最常访问这部分
+const UNCOMPRESSED_LINES: usize = 1000;
+
+/// 超出未压缩区的行积累到这么多时才成批压缩，避免每滚动一行都压缩一次
+const COMPRESS_BATCH: usize = 256;
+
+/// 每行除内容外的固定开销（队列中的条目本身）
+const LINE_OVERHEAD: usize = std::mem::size_of::<HistoryLine>();
+
+/// 回滚历史的限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrollbackConfig {
+    /// 最多保留的行数，0 表示不限制（仍受内存上限约束）
+    pub max_lines: usize,
+    /// 历史内容占用内存的上限（字节），0 表示不限制
+    pub max_bytes: usize,
+    /// 是否压缩较早的行
+    pub compress: bool,
+}
+
+impl Default for ScrollbackConfig {
+    fn default() -> Self {
+        Self {
+            max_lines: 10_000,
+            max_bytes: 64 * 1024 * 1024,
+            compress: false,
+        }
+    }
+}
+
+const FLAG_BOLD: u8 = 1 << 0;
+const FLAG_ITALIC: u8 = 1 << 1;
+const FLAG_UNDERLINE: u8 = 1 << 2;
+const FLAG_CONTINUATION: u8 = 1 << 3;
+
+/// 单元格除字符以外的属性，相邻单元格相同时合并为一段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CellAttrs {
+    fg: [u8; 4],
+    bg: [u8; 4],
+    flags: u8,
+    hyperlink: Option<HyperlinkId>,
+}
+
+impl CellAttrs {
+    fn of(cell: &TerminalCell) -> Self {
+        let mut flags = 0;
+        for (set, flag) in [
+            (cell.bold, FLAG_BOLD),
+            (cell.italic, FLAG_ITALIC),
+            (cell.underline, FLAG_UNDERLINE),
+            (cell.is_continuation, FLAG_CONTINUATION),
+        ] {
+            if set {
+                flags |= flag;
+            }
+        }
+        Self {
+            fg: cell.fg_color.to_bytes(),
+            bg: cell.bg_color.to_bytes(),
+            flags,
+            hyperlink: cell.hyperlink,
+        }
+    }
+
+    fn apply(&self, cell: &mut TerminalCell) {
+        cell.fg_color = CellColor::from_bytes(self.fg);
+        cell.bg_color = CellColor::from_bytes(self.bg);
+        cell.bold = self.flags & FLAG_BOLD != 0;
+        cell.italic = self.flags & FLAG_ITALIC != 0;
+        cell.underline = self.flags & FLAG_UNDERLINE != 0;
+        cell.is_continuation = self.flags & FLAG_CONTINUATION != 0;
+        cell.hyperlink = self.hyperlink;
+    }
+}
+
+/// 少数单元格才有的内容，按列号单独存放
+#[derive(Debug, Clone, PartialEq)]
+enum Extra {
+    Combining(Box<str>),
+    Image(ImageCell),
+}
+
+/// 紧凑格式的一行
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PackedLine {
+    /// 各单元格的首字符，一个单元格一个字符
+    text: Box<str>,
+    /// 属性段：（连续的单元格数，属性）
+    runs: Box<[(u16, CellAttrs)]>,
+    /// 组合字符与图片切片：（列号，内容）
+    extras: Box<[(u16, Extra)]>,
+}
+
+impl PackedLine {
+    fn pack(cells: &[TerminalCell]) -> Self {
+        let mut text = String::with_capacity(cells.len());
+        let mut runs: Vec<(u16, CellAttrs)> = Vec::new();
+        let mut extras = Vec::new();
+        for (col, cell) in cells.iter().enumerate() {
+            text.push(cell.character);
+
+            let attrs = CellAttrs::of(cell);
+            match runs.last_mut() {
+                Some((len, last)) if *last == attrs && *len < u16::MAX => *len += 1,
+                _ => runs.push((1, attrs)),
+            }
+
+            let col = col as u16;
+            if let Some(ref combining) = cell.combining {
+                extras.push((col, Extra::Combining(combining.clone())));
+            }
+            if let Some(ref image) = cell.image {
+                extras.push((col, Extra::Image((**image).clone())));
+            }
+        }
+        Self {
+            text: text.into_boxed_str(),
+            runs: runs.into_boxed_slice(),
+            extras: extras.into_boxed_slice(),
+        }
+    }
+
+    fn unpack(&self) -> Vec<TerminalCell> {
+        let mut cells: Vec<TerminalCell> = self
+            .text
+            .chars()
+            .map(|character| TerminalCell {
+                character,
+                ..Default::default()
+            })
+            .collect();
+
+        let mut col = 0;
+        for (len, attrs) in self.runs.iter() {
+            for cell in cells.iter_mut().skip(col).take(*len as usize) {
+                attrs.apply(cell);
+            }
+            col += *len as usize;
+        }
+
+        for (col, extra) in self.extras.iter() {
+            let Some(cell) = cells.get_mut(*col as usize) else {
+                continue;
+            };
+            match extra {
+                Extra::Combining(combining) => cell.combining = Some(combining.clone()),
+                Extra::Image(image) => cell.image = Some(Box::new(image.clone())),
+            }
+        }
+        cells
+    }
+
+    /// 该行引用的超链接（去重）
+    fn hyperlinks(&self) -> Box<[HyperlinkId]> {
+        let mut ids: Vec<HyperlinkId> = self
+            .runs
+            .iter()
+            .filter_map(|(_, attrs)| attrs.hyperlink)
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.into_boxed_slice()
+    }
+
+    fn has_images(&self) -> bool {
+        self.extras
+            .iter()
+            .any(|(_, extra)| matches!(extra, Extra::Image(_)))
+    }
+
+    /// 去掉不满足条件的图片切片
+    fn retain_images(&mut self, mut keep: impl FnMut(&ImageCell) -> bool) {
+        if self.has_images() {
+            let extras = std::mem::take(&mut self.extras).into_vec();
+            self.extras = extras
+                .into_iter()
+                .filter(|(_, extra)| match extra {
+                    Extra::Image(image) => keep(image),
+                    Extra::Combining(_) => true,
+                })
+                .collect();
+        }
+    }
+
+    /// 估算占用的堆内存
+    fn heap_bytes(&self) -> usize {
+        let extras: usize = self
+            .extras
+            .iter()
+            .map(|(_, extra)| match extra {
+                Extra::Combining(combining) => combining.len(),
+                Extra::Image(_) => 0,
+            })
+            .sum();
+        self.text.len()
+            + std::mem::size_of_val::<[(u16, CellAttrs)]>(&self.runs)
+            + std::mem::size_of_val::<[(u16, Extra)]>(&self.extras)
+            + extras
+    }
+
+    /// 序列化为字节，用于压缩
+    fn encode(&self, out: &mut Vec<u8>) {
+        put_bytes(out, self.text.as_bytes());
+        put_u32(out, self.runs.len() as u32);
+        for (len, attrs) in self.runs.iter() {
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(&attrs.fg);
+            out.extend_from_slice(&attrs.bg);
+            out.push(attrs.flags);
+            put_u32(out, attrs.hyperlink.map_or(u32::MAX, |id| id));
+        }
+        put_u32(out, self.extras.len() as u32);
+        for (col, extra) in self.extras.iter() {
+            out.extend_from_slice(&col.to_le_bytes());
+            match extra {
+                Extra::Combining(combining) => {
+                    out.push(0);
+                    put_bytes(out, combining.as_bytes());
+                }
+                Extra::Image(image) => {
+                    out.push(1);
+                    put_u32(out, image.image);
+                    for value in image.uv {
+                        out.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+        }
+    }
+
+    /// 从 encode 的结果还原，数据损坏时返回 None
+    fn decode(mut data: &[u8]) -> Option<Self> {
+        let data = &mut data;
+        let text = String::from_utf8(take_bytes(data)?.to_vec()).ok()?;
+
+        let run_count = take_u32(data)? as usize;
+        let mut runs = Vec::with_capacity(run_count.min(data.len()));
+        for _ in 0..run_count {
+            let len = u16::from_le_bytes(take(data)?);
+            let fg = take(data)?;
+            let bg = take(data)?;
+            let [flags] = take(data)?;
+            let hyperlink = Some(take_u32(data)?).filter(|&id| id != u32::MAX);
+            runs.push((
+                len,
+                CellAttrs {
+                    fg,
+                    bg,
+                    flags,
+                    hyperlink,
+                },
+            ));
+        }
+
+        let extra_count = take_u32(data)? as usize;
+        let mut extras = Vec::with_capacity(extra_count.min(data.len()));
+        for _ in 0..extra_count {
+            let col = u16::from_le_bytes(take(data)?);
+            let extra = match take::<1>(data)? {
+                [0] => Extra::Combining(
+                    String::from_utf8(take_bytes(data)?.to_vec())
+                        .ok()?
+                        .into_boxed_str(),
+                ),
+                [1] => {
+                    let image = take_u32(data)?;
+                    let mut uv = [0.0; 4];
+                    for value in &mut uv {
+                        *value = f32::from_le_bytes(take(data)?);
+                    }
+                    Extra::Image(ImageCell { image, uv })
+                }
+                _ => return None,
+            };
+            extras.push((col, extra));
+        }
+
+        Some(Self {
+            text: text.into_boxed_str(),
+            runs: runs.into_boxed_slice(),
+            extras: extras.into_boxed_slice(),
+        })
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_u32(out, bytes.len() as u32);
+    out.extend_from_slice(bytes);
+}
+
+fn take<const N: usize>(data: &mut &[u8]) -> Option<[u8; N]> {
+    let (head, rest) = data.split_first_chunk::<N>()?;
+    *data = rest;
+    Some(*head)
+}
+
+fn take_u32(data: &mut &[u8]) -> Option<u32> {
+    take(data).map(u32::from_le_bytes)
+}
+
+fn take_bytes<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = take_u32(data)? as usize;
+    if data.len() < len {
+        return None;
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Some(bytes)
+}
+
+/// 一行的存储形式
+#[derive(Debug, Clone)]
+enum LineData {
+    Packed(PackedLine),
+    /// deflate 压缩后的 PackedLine 序列化结果
+    Compressed(Box<[u8]>),
+}
+
+#[derive(Debug, Clone)]
+struct HistoryLine {
+    data: LineData,
+    /// 是否软换行
+    wrapped: bool,
+    /// shell 集成标记
+    mark: LineMark,
+    /// 该行引用的超链接，压缩后无需解码即可清理超链接表
+    hyperlinks: Box<[HyperlinkId]>,
+}
+
+impl HistoryLine {
+    fn heap_bytes(&self) -> usize {
+        let hyperlinks = std::mem::size_of_val::<[HyperlinkId]>(&self.hyperlinks);
+        hyperlinks
+            + match &self.data {
+                LineData::Packed(line) => line.heap_bytes(),
+                LineData::Compressed(data) => data.len(),
+            }
+    }
+
+    fn packed(&self) -> PackedLine {
+        match &self.data {
+            LineData::Packed(line) => line.clone(),
+            LineData::Compressed(data) => decompress(data).unwrap_or_default(),
+        }
+    }
+
+    /// 压缩该行，压缩后没有变小时保持原样
+    fn compress(&mut self) {
+        let LineData::Packed(ref line) = self.data else {
+            return;
+        };
+        // 带图片的行可能被删除图片，保持未压缩以便修改
+        if line.has_images() {
+            return;
+        }
+
+        let mut raw = Vec::new();
+        line.encode(&mut raw);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+        if encoder.write_all(&raw).is_err() {
+            return;
+        }
+        if let Ok(data) = encoder.finish()
+            && data.len() < line.heap_bytes()
+        {
+            self.data = LineData::Compressed(data.into_boxed_slice());
+        }
+    }
+}
+
+fn decompress(data: &[u8]) -> Option<PackedLine> {
+    let mut raw = Vec::new();
+    DeflateDecoder::new(data).read_to_end(&mut raw).ok()?;
+    PackedLine::decode(&raw)
+}
+
+/// 回滚历史：按从旧到新的顺序保存滚出屏幕的行
+#[derive(Debug, Clone, Default)]
+pub struct Scrollback {
+    lines: VecDeque<HistoryLine>,
+    config: ScrollbackConfig,
+    /// 所有行占用的内存估算（字节）
+    bytes: usize,
+    /// 从最早一行起已经处理过压缩的行数
+    compressed: usize,
+}
+
+impl Scrollback {
+    pub fn config(&self) -> ScrollbackConfig {
+        self.config
+    }
+
+    /// 修改限制，立即淘汰超出新限制的行
+    pub fn set_config(&mut self, config: ScrollbackConfig) {
+        self.config = config;
+        self.trim();
+        self.compress_old_lines();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// 历史内容占用内存的估算（字节）
+    #[cfg(test)]
+    pub fn memory_usage(&self) -> usize {
+        self.bytes
+    }
+
+    /// 追加一行到末尾（最新），超出限制时淘汰最早的行
+    pub fn push(&mut self, cells: &[TerminalCell], wrapped: bool, mark: LineMark) {
+        let packed = PackedLine::pack(cells);
+        let line = HistoryLine {
+            hyperlinks: packed.hyperlinks(),
+            data: LineData::Packed(packed),
+            wrapped,
+            mark,
+        };
+        self.bytes += line.heap_bytes() + LINE_OVERHEAD;
+        self.lines.push_back(line);
+        self.trim();
+        self.compress_old_lines();
+    }
+
+    /// 解码第 index 行（0 为最早）的单元格
+    pub fn cells(&self, index: usize) -> Option<Vec<TerminalCell>> {
+        self.lines.get(index).map(|line| line.packed().unpack())
+    }
+
+    /// 第 index 行是否软换行到下一行
+    pub fn is_wrapped(&self, index: usize) -> bool {
+        self.lines.get(index).is_some_and(|line| line.wrapped)
+    }
+
+    /// 所有行引用的超链接（可能重复）
+    pub fn hyperlinks(&self) -> impl Iterator<Item = HyperlinkId> + '_ {
+        self.lines
+            .iter()
+            .flat_map(|line| line.hyperlinks.iter().copied())
+    }
+
+    pub fn mark(&self, index: usize) -> Option<&LineMark> {
+        self.lines.get(index).map(|line| &line.mark)
+    }
+
+    pub fn mark_mut(&mut self, index: usize) -> Option<&mut LineMark> {
+        self.lines.get_mut(index).map(|line| &mut line.mark)
+    }
+
+    /// 取出最新的一行（单元格, 是否软换行, 标记）
+    pub fn pop(&mut self) -> Option<(Vec<TerminalCell>, bool, LineMark)> {
+        let line = self.lines.pop_back()?;
+        self.bytes -= line.heap_bytes() + LINE_OVERHEAD;
+        self.compressed = self.compressed.min(self.lines.len());
+        Some((line.packed().unpack(), line.wrapped, line.mark))
+    }
+
+    /// 取出所有行（单元格, 是否软换行, 标记），用于重排
+    pub fn take_all(&mut self) -> Vec<(Vec<TerminalCell>, bool, LineMark)> {
+        self.bytes = 0;
+        self.compressed = 0;
+        std::mem::take(&mut self.lines)
+            .into_iter()
+            .map(|line| (line.packed().unpack(), line.wrapped, line.mark))
+            .collect()
+    }
+
+    /// 去掉不满足条件的图片切片（图片被删除时）
+    pub fn retain_images(&mut self, mut keep: impl FnMut(&ImageCell) -> bool) {
+        // 带图片的行不会被压缩
+        for line in &mut self.lines {
+            if let LineData::Packed(ref mut packed) = line.data {
+                let before = packed.heap_bytes();
+                packed.retain_images(&mut keep);
+                self.bytes = self.bytes + packed.heap_bytes() - before;
+            }
+        }
+    }
+
+    /// 按行数与内存上限淘汰最早的行
+    fn trim(&mut self) {
+        let ScrollbackConfig {
+            max_lines,
+            max_bytes,
+            ..
+        } = self.config;
+        while (max_lines > 0 && self.lines.len() > max_lines)
+            || (max_bytes > 0 && self.bytes > max_bytes && !self.is_empty())
+        {
+            let Some(line) = self.lines.pop_front() else {
+                break;
+            };
+            self.bytes -= line.heap_bytes() + LINE_OVERHEAD;
+            self.compressed = self.compressed.saturating_sub(1);
+        }
+    }
+
+    /// 压缩未压缩区之前的行
+    fn compress_old_lines(&mut self) {
+        if !self.config.compress {
+            return;
+        }
+        let target = self.lines.len().saturating_sub(UNCOMPRESSED_LINES);
+        if target < self.compressed + COMPRESS_BATCH {
+            return;
+        }
+        for line in self.lines.range_mut(self.compressed..target) {
+            let before = line.heap_bytes();
+            line.compress();
+            self.bytes = self.bytes + line.heap_bytes() - before;
+        }
+        self.compressed = target;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str) -> Vec<TerminalCell> {
+        text.chars()
+            .map(|character| TerminalCell {
+                character,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn text(cells: &[TerminalCell]) -> String {
+        cells.iter().map(TerminalCell::text).collect()
+    }
+
+    #[test]
+    fn test_pack_roundtrip_keeps_attributes() {
+        let mut cells = line("ab中 e");
+        cells[0].bold = true;
+        cells[0].fg_color = CellColor::Indexed(1);
+        cells[1].bg_color = CellColor::Rgb(0, 0, 255);
+        cells[1].hyperlink = Some(3);
+        cells[3].is_continuation = true;
+        cells[4].push_combining('\u{301}');
+        cells[4].image = Some(Box::new(ImageCell {
+            image: 7,
+            uv: [0.0, 0.5, 0.25, 1.0],
+        }));
+
+        let packed = PackedLine::pack(&cells);
+        let unpacked = packed.unpack();
+        let mut encoded = Vec::new();
+        packed.encode(&mut encoded);
+        assert_eq!(PackedLine::decode(&encoded), Some(packed));
+
+        assert_eq!(text(&unpacked), text(&cells));
+        assert!(unpacked[0].bold && !unpacked[1].bold);
+        assert_eq!(unpacked[0].fg_color, CellColor::Indexed(1));
+        assert_eq!(unpacked[1].bg_color, CellColor::Rgb(0, 0, 255));
+        assert_eq!(unpacked[1].hyperlink, Some(3));
+        assert!(unpacked[3].is_continuation);
+        assert_eq!(unpacked[4].image, cells[4].image);
+    }
+
+    #[test]
+    fn test_identical_attributes_share_one_run() {
+        let packed = PackedLine::pack(&line(&"x".repeat(200)));
+        assert_eq!(packed.runs.len(), 1);
+        assert_eq!(packed.text.len(), 200);
+    }
+
+    #[test]
+    fn test_line_limit_evicts_oldest() {
+        let mut scrollback = Scrollback::default();
+        scrollback.set_config(ScrollbackConfig {
+            max_lines: 3,
+            ..Default::default()
+        });
+        for i in 0..5 {
+            scrollback.push(&line(&i.to_string()), false, LineMark::default());
+        }
+        assert_eq!(scrollback.len(), 3);
+        assert_eq!(text(&scrollback.cells(0).unwrap()), "2");
+
+        scrollback.set_config(ScrollbackConfig {
+            max_lines: 1,
+            ..Default::default()
+        });
+        assert_eq!(scrollback.len(), 1);
+        assert_eq!(text(&scrollback.cells(0).unwrap()), "4");
+    }
+
+    #[test]
+    fn test_unlimited_lines_respect_memory_cap() {
+        let mut scrollback = Scrollback::default();
+        scrollback.set_config(ScrollbackConfig {
+            max_lines: 0,
+            max_bytes: 64 * 1024,
+            compress: false,
+        });
+        for _ in 0..10_000 {
+            scrollback.push(&line(&"y".repeat(80)), false, LineMark::default());
+        }
+        assert!(scrollback.memory_usage() <= 64 * 1024);
+        assert!(scrollback.len() > 100 && scrollback.len() < 10_000);
+    }
+
+    #[test]
+    fn test_compressed_lines_decode_unchanged() {
+        let mut scrollback = Scrollback::default();
+        scrollback.set_config(ScrollbackConfig {
+            max_lines: 0,
+            max_bytes: 0,
+            compress: true,
+        });
+        let total = UNCOMPRESSED_LINES + COMPRESS_BATCH + 10;
+        for i in 0..total {
+            let mut cells = line(&format!("{i:>6} {}", "log line ".repeat(8)));
+            cells[0].fg_color = CellColor::Indexed(2);
+            scrollback.push(&cells, i % 2 == 0, LineMark::default());
+        }
+
+        assert!(matches!(scrollback.lines[0].data, LineData::Compressed(_)));
+        assert!(matches!(
+            scrollback.lines[total - 1].data,
+            LineData::Packed(_)
+        ));
+        let first = scrollback.cells(0).unwrap();
+        assert_eq!(text(&first), format!("{:>6} {}", 0, "log line ".repeat(8)));
+        assert_eq!(first[0].fg_color, CellColor::Indexed(2));
+        assert!(scrollback.lines[0].wrapped && !scrollback.lines[1].wrapped);
+
+        let usage = scrollback.memory_usage();
+        let recomputed: usize = scrollback
+            .lines
+            .iter()
+            .map(|line| line.heap_bytes() + LINE_OVERHEAD)
+            .sum();
+        assert_eq!(usage, recomputed);
+    }
+}
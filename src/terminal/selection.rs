@@ -43,14 +43,12 @@ impl TextSelector {
         self.state = SelectionState::Selecting;
         self.start_pos = Some((screen_x, screen_y));
         self.current_pos = Some((screen_x, screen_y));
-        println!("开始选择: ({}, {})", screen_x, screen_y);
     }
 
     /// 更新选择（鼠标移动）
     pub fn update_selection(&mut self, screen_x: f32, screen_y: f32) {
         if self.state == SelectionState::Selecting {
             self.current_pos = Some((screen_x, screen_y));
-            println!("更新选择: ({}, {})", screen_x, screen_y);
         }
     }
 
@@ -58,7 +56,6 @@ impl TextSelector {
     pub fn end_selection(&mut self) {
         if self.state == SelectionState::Selecting {
             self.state = SelectionState::Selected;
-            println!("结束选择");
         }
     }
 
@@ -75,7 +72,9 @@ impl TextSelector {
         &self.state
     }
 
-    /// 将屏幕坐标转换为缓冲区坐标
+    /// 将屏幕坐标转换为缓冲区坐标（行号包含历史记录）
+    ///
+    /// `rect` 只覆盖视口，`display_top` 为视口第一行的行号。
     pub fn screen_to_buffer_coords(
         &self,
         screen_x: f32,
@@ -85,7 +84,7 @@ impl TextSelector {
         line_height: f32,
         buffer_rows: usize,
         buffer_cols: usize,
-        history_size: usize,
+        display_top: usize,
     ) -> Option<(usize, usize)> {
         if !rect.contains(egui::pos2(screen_x, screen_y)) {
             return None;
@@ -95,13 +94,10 @@ impl TextSelector {
         let rel_x = screen_x - rect.min.x;
         let rel_y = screen_y - rect.min.y;
 
-        // 计算总行数（历史+当前屏幕）
-        let total_rows = history_size + buffer_rows;
-
-        // 计算行号（从0开始）
+        // 计算视口中的行号（从0开始）
         // 渲染时使用的是 char_size.y * line_height，所以这里也要对应
         let row = (rel_y / (char_size.y * line_height)).floor() as usize;
-        if row >= total_rows {
+        if row >= buffer_rows {
             return None;
         }
 
@@ -111,7 +107,7 @@ impl TextSelector {
             return None;
         }
 
-        Some((row, col))
+        Some((display_top + row, col))
     }
 
     /// 更新仿真器的选择状态
@@ -127,17 +123,18 @@ impl TextSelector {
         }
 
         if let (Some(start_pos), Some(current_pos)) = (self.start_pos, self.current_pos) {
-            // 先获取一次 buffer 只是为了读取行列信息
-            let buffer = emulator.buffer();
+            // 只读取行列信息
+            let (rows, cols, display_top) =
+                emulator.with_buffer(|buffer| (buffer.rows, buffer.cols, buffer.display_top()));
             if let Some((start_row, start_col)) = self.screen_to_buffer_coords(
                 start_pos.0,
                 start_pos.1,
                 rect,
                 char_size,
                 line_height,
-                buffer.rows,
-                buffer.cols,
-                buffer.history.len(),
+                rows,
+                cols,
+                display_top,
             ) {
                 if let Some((end_row, end_col)) = self.screen_to_buffer_coords(
                     current_pos.0,
@@ -145,15 +142,18 @@ impl TextSelector {
                     rect,
                     char_size,
                     line_height,
-                    buffer.rows,
-                    buffer.cols,
-                    buffer.history.len(),
+                    rows,
+                    cols,
+                    display_top,
                 ) {
                     // 清除之前的选择显示
                     emulator.clear_selection();
-                    println!(
+                    tracing::trace!(
                         "选择范围: 从 ({}, {}) 到 ({}, {})",
-                        start_row, start_col, end_row, end_col
+                        start_row,
+                        start_col,
+                        end_row,
+                        end_col
                     );
                     // 设置新的选择
                     emulator.start_selection(start_row, start_col);
@@ -174,36 +174,41 @@ impl TextSelector {
     /// 复制选中文本到剪贴板
     pub fn copy_selected_text(&self, emulator: &dyn TerminalEmulatorTrait) -> bool {
         if let Some(text) = self.get_selected_text_content(emulator) {
-            match ClipboardContext::new() {
-                Ok(mut ctx) => match ctx.set_contents(text) {
-                    Ok(_) => {
-                        println!("已通过 copypasta 复制到剪贴板");
-                        true
-                    }
-                    Err(_) => false,
-                },
-                Err(_) => false,
-            }
+            self.set_clipboard_text(text)
         } else {
             false
         }
     }
 
+    /// 写入文本到剪贴板
+    pub fn set_clipboard_text(&self, text: String) -> bool {
+        match ClipboardContext::new() {
+            Ok(mut ctx) => match ctx.set_contents(text) {
+                Ok(_) => {
+                    tracing::debug!("已复制到剪贴板");
+                    true
+                }
+                Err(_) => false,
+            },
+            Err(_) => false,
+        }
+    }
+
     /// 从剪贴板获取文本内容 (粘贴时使用)
     pub fn get_clipboard_text(&self) -> Option<String> {
         match ClipboardContext::new() {
             Ok(mut ctx) => match ctx.get_contents() {
                 Ok(text) => {
-                    println!("从 copypasta 获取到剪贴板文本, 长度: {}", text.len());
+                    tracing::debug!(bytes = text.len(), "已读取剪贴板");
                     Some(text)
                 }
                 Err(e) => {
-                    eprintln!("[TermLink] copypasta 读取剪贴板失败: {}", e);
+                    tracing::warn!("读取剪贴板失败: {}", e);
                     None
                 }
             },
             Err(e) => {
-                eprintln!("[TermLink] 初始化 copypasta 失败: {}", e);
+                tracing::warn!("初始化剪贴板失败: {}", e);
                 None
             }
         }
@@ -211,14 +216,25 @@ impl TextSelector {
 
     /// 全选
     pub fn select_all(&mut self, emulator: &mut dyn TerminalEmulatorTrait) {
-        // 先获取一次 buffer 只为了读取行列信息
-        let buffer = emulator.buffer();
         // 选择所有内容：从历史记录开始到当前屏幕结束
-        let total_rows = buffer.history.len() + buffer.rows;
-        if total_rows > 0 && buffer.cols > 0 {
+        let total_rows = emulator.with_buffer(|buffer| buffer.history.len() + buffer.rows);
+        if total_rows > 0 {
+            self.select_rows(emulator, 0, total_rows - 1);
+        }
+    }
+
+    /// 选中整行范围（行号包含历史记录）
+    pub fn select_rows(
+        &mut self,
+        emulator: &mut dyn TerminalEmulatorTrait,
+        start_row: usize,
+        end_row: usize,
+    ) {
+        let cols = emulator.with_buffer(|buffer| buffer.cols);
+        if cols > 0 {
             emulator.clear_selection();
-            emulator.start_selection(0, 0);
-            emulator.update_selection(total_rows - 1, buffer.cols - 1);
+            emulator.start_selection(start_row, 0);
+            emulator.update_selection(end_row, cols - 1);
             self.state = SelectionState::Selected;
         }
     }
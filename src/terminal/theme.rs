@@ -1,273 +1,12 @@
 //! 终端主题配置
 //! 管理终端的颜色主题和样式
 
+use crate::terminal::color::{COLOR_256, CellColor, Palette, PaletteOverrides};
+use crate::terminal::cursor::CursorShape;
 use egui::Color32;
 
-/// 256色调色板
-const COLOR_256: [(u8, u8, u8); 256] = [
-    // 标准颜色 (0-15)
-    (0, 0, 0),
-    (128, 0, 0),
-    (0, 128, 0),
-    (128, 128, 0),
-    (0, 0, 128),
-    (128, 0, 128),
-    (0, 128, 128),
-    (192, 192, 192),
-    (128, 128, 128),
-    (255, 0, 0),
-    (0, 255, 0),
-    (255, 255, 0),
-    (0, 0, 255),
-    (255, 0, 255),
-    (0, 255, 255),
-    (255, 255, 255),
-    // 6x6x6 立方体颜色 (16-231)
-    (0, 0, 0),
-    (0, 0, 95),
-    (0, 0, 135),
-    (0, 0, 175),
-    (0, 0, 215),
-    (0, 0, 255),
-    (0, 95, 0),
-    (0, 95, 95),
-    (0, 95, 135),
-    (0, 95, 175),
-    (0, 95, 215),
-    (0, 95, 255),
-    (0, 135, 0),
-    (0, 135, 95),
-    (0, 135, 135),
-    (0, 135, 175),
-    (0, 135, 215),
-    (0, 135, 255),
-    (0, 175, 0),
-    (0, 175, 95),
-    (0, 175, 135),
-    (0, 175, 175),
-    (0, 175, 215),
-    (0, 175, 255),
-    (0, 215, 0),
-    (0, 215, 95),
-    (0, 215, 135),
-    (0, 215, 175),
-    (0, 215, 215),
-    (0, 215, 255),
-    (0, 255, 0),
-    (0, 255, 95),
-    (0, 255, 135),
-    (0, 255, 175),
-    (0, 255, 215),
-    (0, 255, 255),
-    (95, 0, 0),
-    (95, 0, 95),
-    (95, 0, 135),
-    (95, 0, 175),
-    (95, 0, 215),
-    (95, 0, 255),
-    (95, 95, 0),
-    (95, 95, 95),
-    (95, 95, 135),
-    (95, 95, 175),
-    (95, 95, 215),
-    (95, 95, 255),
-    (95, 135, 0),
-    (95, 135, 95),
-    (95, 135, 135),
-    (95, 135, 175),
-    (95, 135, 215),
-    (95, 135, 255),
-    (95, 175, 0),
-    (95, 175, 95),
-    (95, 175, 135),
-    (95, 175, 175),
-    (95, 175, 215),
-    (95, 175, 255),
-    (95, 215, 0),
-    (95, 215, 95),
-    (95, 215, 135),
-    (95, 215, 175),
-    (95, 215, 215),
-    (95, 215, 255),
-    (95, 255, 0),
-    (95, 255, 95),
-    (95, 255, 135),
-    (95, 255, 175),
-    (95, 255, 215),
-    (95, 255, 255),
-    (135, 0, 0),
-    (135, 0, 95),
-    (135, 0, 135),
-    (135, 0, 175),
-    (135, 0, 215),
-    (135, 0, 255),
-    (135, 95, 0),
-    (135, 95, 95),
-    (135, 95, 135),
-    (135, 95, 175),
-    (135, 95, 215),
-    (135, 95, 255),
-    (135, 135, 0),
-    (135, 135, 95),
-    (135, 135, 135),
-    (135, 135, 175),
-    (135, 135, 215),
-    (135, 135, 255),
-    (135, 175, 0),
-    (135, 175, 95),
-    (135, 175, 135),
-    (135, 175, 175),
-    (135, 175, 215),
-    (135, 175, 255),
-    (135, 215, 0),
-    (135, 215, 95),
-    (135, 215, 135),
-    (135, 215, 175),
-    (135, 215, 215),
-    (135, 215, 255),
-    (135, 255, 0),
-    (135, 255, 95),
-    (135, 255, 135),
-    (135, 255, 175),
-    (135, 255, 215),
-    (135, 255, 255),
-    (175, 0, 0),
-    (175, 0, 95),
-    (175, 0, 135),
-    (175, 0, 175),
-    (175, 0, 215),
-    (175, 0, 255),
-    (175, 95, 0),
-    (175, 95, 95),
-    (175, 95, 135),
-    (175, 95, 175),
-    (175, 95, 215),
-    (175, 95, 255),
-    (175, 135, 0),
-    (175, 135, 95),
-    (175, 135, 135),
-    (175, 135, 175),
-    (175, 135, 215),
-    (175, 135, 255),
-    (175, 175, 0),
-    (175, 175, 95),
-    (175, 175, 135),
-    (175, 175, 175),
-    (175, 175, 215),
-    (175, 175, 255),
-    (175, 215, 0),
-    (175, 215, 95),
-    (175, 215, 135),
-    (175, 215, 175),
-    (175, 215, 215),
-    (175, 215, 255),
-    (175, 255, 0),
-    (175, 255, 95),
-    (175, 255, 135),
-    (175, 255, 175),
-    (175, 255, 215),
-    (175, 255, 255),
-    (215, 0, 0),
-    (215, 0, 95),
-    (215, 0, 135),
-    (215, 0, 175),
-    (215, 0, 215),
-    (215, 0, 255),
-    (215, 95, 0),
-    (215, 95, 95),
-    (215, 95, 135),
-    (215, 95, 175),
-    (215, 95, 215),
-    (215, 95, 255),
-    (215, 135, 0),
-    (215, 135, 95),
-    (215, 135, 135),
-    (215, 135, 175),
-    (215, 135, 215),
-    (215, 135, 255),
-    (215, 175, 0),
-    (215, 175, 95),
-    (215, 175, 135),
-    (215, 175, 175),
-    (215, 175, 215),
-    (215, 175, 255),
-    (215, 215, 0),
-    (215, 215, 95),
-    (215, 215, 135),
-    (215, 215, 175),
-    (215, 215, 215),
-    (215, 215, 255),
-    (215, 255, 0),
-    (215, 255, 95),
-    (215, 255, 135),
-    (215, 255, 175),
-    (215, 255, 215),
-    (215, 255, 255),
-    (255, 0, 0),
-    (255, 0, 95),
-    (255, 0, 135),
-    (255, 0, 175),
-    (255, 0, 215),
-    (255, 0, 255),
-    (255, 95, 0),
-    (255, 95, 95),
-    (255, 95, 135),
-    (255, 95, 175),
-    (255, 95, 215),
-    (255, 95, 255),
-    (255, 135, 0),
-    (255, 135, 95),
-    (255, 135, 135),
-    (255, 135, 175),
-    (255, 135, 215),
-    (255, 135, 255),
-    (255, 175, 0),
-    (255, 175, 95),
-    (255, 175, 135),
-    (255, 175, 175),
-    (255, 175, 215),
-    (255, 175, 255),
-    (255, 215, 0),
-    (255, 215, 95),
-    (255, 215, 135),
-    (255, 215, 175),
-    (255, 215, 215),
-    (255, 215, 255),
-    (255, 255, 0),
-    (255, 255, 95),
-    (255, 255, 135),
-    (255, 255, 175),
-    (255, 255, 215),
-    (255, 255, 255),
-    // 灰度颜色 (232-255)
-    (8, 8, 8),
-    (18, 18, 18),
-    (28, 28, 28),
-    (38, 38, 38),
-    (48, 48, 48),
-    (58, 58, 58),
-    (68, 68, 68),
-    (78, 78, 78),
-    (88, 88, 88),
-    (98, 98, 98),
-    (108, 108, 108),
-    (118, 118, 118),
-    (128, 128, 128),
-    (138, 138, 138),
-    (148, 148, 148),
-    (158, 158, 158),
-    (168, 168, 168),
-    (178, 178, 178),
-    (188, 188, 188),
-    (198, 198, 198),
-    (208, 208, 208),
-    (218, 218, 218),
-    (228, 228, 228),
-    (238, 238, 238),
-];
-
 /// 终端主题样式
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ThemeStyle {
     pub foreground: Color32,
     pub background: Color32,
@@ -371,12 +110,16 @@ impl ThemeStyle {
 }
 
 /// 终端主题
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TerminalTheme {
     pub style: ThemeStyle,
     pub font_size: f32,
     pub line_height: f32,
     pub cursor_blink: bool,
+    /// 默认光标形状（远端未通过 DECSCUSR 指定时使用）
+    pub cursor_shape: CursorShape,
+    /// 光标闪烁间隔（毫秒）
+    pub cursor_blink_interval: u64,
 }
 
 impl Default for TerminalTheme {
@@ -386,6 +129,8 @@ impl Default for TerminalTheme {
             font_size: 14.0,
             line_height: 1.2,
             cursor_blink: true,
+            cursor_shape: CursorShape::Block,
+            cursor_blink_interval: 500,
         }
     }
 }
@@ -397,6 +142,8 @@ impl TerminalTheme {
             font_size,
             line_height: 1.2,
             cursor_blink: true,
+            cursor_shape: CursorShape::Block,
+            cursor_blink_interval: 500,
         }
     }
 
@@ -433,6 +180,36 @@ impl TerminalTheme {
         }
     }
 
+    /// 把单元格颜色解析为具体颜色：远端修改过的颜色优先，其余取自主题；
+    /// 加粗时标准色 (0-7) 显示为对应的亮色
+    pub fn resolve(&self, color: CellColor, bold: bool, overrides: &PaletteOverrides) -> Color32 {
+        if let Some((r, g, b)) = overrides.get(color, bold) {
+            return Color32::from_rgb(r, g, b);
+        }
+        match color {
+            CellColor::Foreground => self.style.foreground,
+            CellColor::Background => self.style.background,
+            CellColor::Indexed(index) if index < 8 => self.get_color(index, bold),
+            CellColor::Indexed(index) if index < 16 => self.get_color(index - 8, true),
+            CellColor::Indexed(index) => self.get_color_256(index as u16),
+            CellColor::Rgb(r, g, b) => self.parse_rgb_color(r, g, b),
+        }
+    }
+
+    /// 转换为仿真器回答颜色查询用的调色板：默认色与 16 色取自主题，其余为标准 256 色
+    pub fn palette(&self) -> Palette {
+        let rgb = |color: Color32| (color.r(), color.g(), color.b());
+        let mut colors = COLOR_256;
+        for (index, color) in colors.iter_mut().take(16).enumerate() {
+            *color = rgb(self.get_color(index as u8 % 8, index >= 8));
+        }
+        Palette {
+            foreground: rgb(self.style.foreground),
+            background: rgb(self.style.background),
+            colors,
+        }
+    }
+
     /// 解析RGB颜色值
     pub fn parse_rgb_color(&self, r: u8, g: u8, b: u8) -> Color32 {
         Color32::from_rgb(r, g, b)
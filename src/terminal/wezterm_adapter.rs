@@ -1,459 +1,666 @@
 //! WezTerm终端仿真器适配器
-//! 将WezTerm的终端仿真能力集成到项目中
-
-use crate::terminal::buffer::TerminalBuffer;
-use crate::terminal::emulator::{TerminalEvent, TerminalState};
-use crate::terminal::theme::TerminalTheme;
+//! 由 wezterm-term 的终端状态机解析远端输出，再把它的屏幕与历史行映射为渲染用的 TerminalBuffer
+
+use crate::terminal::buffer::{
+    Hyperlink, HyperlinkTable, LineMark, Scroll, TerminalBuffer, TerminalCell,
+};
+use crate::terminal::color::{CellColor, Palette, PaletteOverrides, Rgb};
+use crate::terminal::cursor::CursorStyle;
+use crate::terminal::emulator::{
+    InputSink, TerminalEmulatorTrait, TerminalEvent, TerminalInput, TerminalState,
+};
+use crate::terminal::modes::TerminalModes;
+use crate::terminal::scrollback::ScrollbackConfig;
 use anyhow::Result;
-use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tattoy_wezterm_term::color::{ColorPalette, SrgbaTuple};
+use tattoy_wezterm_term::{
+    Alert, AlertHandler, Clipboard, ClipboardSelection, Intensity, Line, Terminal,
+    TerminalConfiguration, TerminalSize, Underline,
+};
+use termwiz::color::ColorAttribute;
+use termwiz::escape::csi::{DecPrivateMode, DecPrivateModeCode, Device, Edit, Mode};
+use termwiz::escape::parser::Parser;
+use termwiz::escape::{Action, CSI, ControlCode, Esc, EscCode, OperatingSystemCommand};
+use termwiz::input::KeyboardEncoding;
+use termwiz::surface::{CursorShape, CursorVisibility};
+
+/// 每次交给状态机的最大字节数
+///
+/// 每处理一块就把滚出屏幕的行搬进 TerminalBuffer 的历史，一块最多产生这么多新行，
+/// 因此 wezterm 自身只需保留同样多的回滚行。
+const SYNC_CHUNK_SIZE: usize = 4096;
+
+/// 界面尚未报告字体大小时使用的单元格像素大小
+const DEFAULT_CELL_SIZE: (f32, f32) = (8.0, 16.0);
+
+/// 事件回调
+type EventCallback = Arc<dyn Fn(TerminalEvent) + Send + Sync>;
+
+/// wezterm 状态机的配置
+#[derive(Debug)]
+struct WezTermConfig {
+    /// 主题配色，wezterm 用它回答颜色查询
+    palette: ColorPalette,
+}
 
-/// WezTerm终端仿真器适配器
-pub struct WezTermAdapter {
-    /// 终端缓冲区
+impl TerminalConfiguration for WezTermConfig {
+    fn scrollback_size(&self) -> usize {
+        SYNC_CHUNK_SIZE
+    }
+
+    fn color_palette(&self) -> ColorPalette {
+        self.palette.clone()
+    }
+
+    fn enable_kitty_keyboard(&self) -> bool {
+        true
+    }
+}
+
+/// 收集状态机回复给远端的数据（状态查询应答等）
+///
+/// wezterm 在自己的写线程中调用，数据由 I/O 线程取走。
+struct ReplyWriter(Arc<Mutex<Vec<u8>>>);
+
+impl Write for ReplyWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 把 wezterm 的通知与剪贴板写入转发为终端事件
+struct EventForwarder(EventCallback);
+
+impl AlertHandler for EventForwarder {
+    fn alert(&mut self, alert: Alert) {
+        match alert {
+            Alert::Bell => (self.0)(TerminalEvent::Bell),
+            Alert::WindowTitleChanged(title) => (self.0)(TerminalEvent::TitleChange(title)),
+            _ => {}
+        }
+    }
+}
+
+impl Clipboard for EventForwarder {
+    fn set_contents(
+        &self,
+        selection: ClipboardSelection,
+        data: Option<String>,
+    ) -> anyhow::Result<()> {
+        let selection = match selection {
+            ClipboardSelection::Clipboard => "c",
+            ClipboardSelection::PrimarySelection => "p",
+        };
+        if let Some(text) = data {
+            (self.0)(TerminalEvent::ClipboardWrite {
+                selection: selection.to_string(),
+                text,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// 与后台 I/O 线程共享的仿真状态
+struct WezTermState {
+    /// wezterm 终端状态机
+    terminal: Terminal,
+    /// 转义序列解析器，解析结果先用于记录模式再交给状态机
+    parser: Parser,
+    /// 渲染用的缓冲区
     buffer: TerminalBuffer,
-    /// 输出缓冲区
-    output_buffer: VecDeque<String>,
-    /// 终端状态
-    state: TerminalState,
-    /// 主题配置
-    theme: TerminalTheme,
-    /// 当前前景色
-    current_fg: Option<u8>,
-    /// 当前背景色
-    current_bg: Option<u8>,
-    /// 是否加粗
-    bold: bool,
+    /// 主题配色
+    palette: Palette,
     /// 事件回调
-    callback: Option<Box<dyn Fn(TerminalEvent) + Send + Sync>>,
+    callback: Option<EventCallback>,
+    /// 待回复给远端的数据
+    replies: Arc<Mutex<Vec<u8>>>,
+    /// 上次同步时屏幕各行的内容，相同的行不再重新转换
+    mirror: Vec<Option<Line>>,
+    /// wezterm 未公开的终端模式，由解析出的动作记录
+    modes: TerminalModes,
+    /// 单元格的像素大小（宽, 高）
+    cell_size: (f32, f32),
 }
 
-impl WezTermAdapter {
-    /// 创建新的WezTerm适配器
-    pub fn new(rows: usize, cols: usize, theme: TerminalTheme) -> Self {
-        let buffer = TerminalBuffer::new(rows, cols);
+impl WezTermState {
+    fn new(rows: usize, cols: usize) -> Self {
+        let replies = Arc::new(Mutex::new(Vec::new()));
+        let terminal = Terminal::new(
+            terminal_size(rows, cols, DEFAULT_CELL_SIZE),
+            Arc::new(WezTermConfig {
+                palette: color_palette(&Palette::default(), &PaletteOverrides::default()),
+            }),
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            Box::new(ReplyWriter(replies.clone())),
+        );
 
         Self {
-            buffer,
-            output_buffer: VecDeque::new(),
-            state: TerminalState::Disconnected,
-            theme,
-            current_fg: None,
-            current_bg: None,
-            bold: false,
+            terminal,
+            parser: Parser::new(),
+            buffer: TerminalBuffer::new(rows, cols),
+            palette: Palette::default(),
             callback: None,
+            replies,
+            mirror: vec![None; rows],
+            modes: TerminalModes::default(),
+            cell_size: DEFAULT_CELL_SIZE,
         }
     }
 
-    /// 设置事件回调
-    pub fn set_event_callback<F>(&mut self, callback: F)
-    where
-        F: Fn(TerminalEvent) + Send + Sync + 'static,
-    {
-        self.callback = Some(Box::new(callback));
-    }
-
-    /// 发送事件
     fn send_event(&self, event: TerminalEvent) {
         if let Some(ref callback) = self.callback {
             callback(event);
         }
     }
 
-    /// 处理ANSI转义序列
-    fn handle_ansi_escape(&mut self, sequence: &str) {
-        if sequence.starts_with("[") && sequence.ends_with("m") {
-            // 处理SGR (Select Graphic Rendition) 序列
-            let params_str = &sequence[1..sequence.len() - 1];
-            let params: Vec<&str> = params_str.split(';').collect();
+    /// 解析远端输出并同步到缓冲区
+    fn process(&mut self, data: &[u8]) {
+        for chunk in data.chunks(SYNC_CHUNK_SIZE) {
+            let mut actions = Vec::new();
+            let mut palette_changed = false;
+            for mut action in self.parser.parse_as_vec(chunk) {
+                clamp_edit_count(&mut action, self.buffer.rows, self.buffer.cols);
+                track_mode(&mut self.modes, &action);
+                palette_changed |= changes_palette(&action);
+                if matches!(
+                    action,
+                    Action::Esc(Esc::Code(EscCode::HorizontalTabSet))
+                        | Action::Control(ControlCode::HTS)
+                ) {
+                    // 光标停在最后一列之后时 wezterm 设置制表位会越界，先执行之前的动作再检查位置
+                    self.terminal.perform_actions(std::mem::take(&mut actions));
+                    if self.terminal.cursor_pos().x >= self.terminal.screen().physical_cols {
+                        continue;
+                    }
+                }
+                actions.push(action);
+            }
+            self.terminal.perform_actions(actions);
+            if palette_changed {
+                self.sync_palette();
+            }
+            self.sync();
+        }
+    }
 
-            if params.is_empty() || (params.len() == 1 && params[0].is_empty()) {
-                // 重置所有属性
-                self.current_fg = None;
-                self.current_bg = None;
-                self.bold = false;
+    /// 把状态机的屏幕、新滚出的行与光标同步到缓冲区
+    fn sync(&mut self) {
+        let screen = self.terminal.screen();
+        let rows = self.buffer.rows;
+        let cols = self.buffer.cols;
+        let total = screen.scrollback_rows();
+        let scrolled = total.saturating_sub(screen.physical_rows);
+
+        let mut history = Vec::with_capacity(scrolled);
+        let mut changed = Vec::new();
+        // with_phys_lines 在行队列首尾相接时会越界，这里逐行遍历
+        screen.for_each_phys_line(|index, line| {
+            if index < scrolled {
+                let cells = line_cells(line, cols, &mut self.buffer.hyperlinks);
+                history.push((cells, line.last_cell_was_wrapped()));
                 return;
             }
 
-            let mut i = 0;
-            while i < params.len() {
-                if let Ok(code) = params[i].parse::<u8>() {
-                    match code {
-                        0 => {
-                            // 重置所有属性
-                            self.current_fg = None;
-                            self.current_bg = None;
-                            self.bold = false;
-                        }
-                        1 => {
-                            // 加粗
-                            self.bold = true;
-                        }
-                        22 => {
-                            // 取消加粗
-                            self.bold = false;
-                        }
-                        30..=37 => {
-                            // 前景色
-                            self.current_fg = Some(code - 30);
-                        }
-                        39 => {
-                            // 默认前景色
-                            self.current_fg = None;
-                        }
-                        40..=47 => {
-                            // 背景色
-                            self.current_bg = Some(code - 40);
-                        }
-                        49 => {
-                            // 默认背景色
-                            self.current_bg = None;
-                        }
-                        90..=97 => {
-                            // 亮前景色
-                            self.current_fg = Some(code - 90 + 8);
-                        }
-                        100..=107 => {
-                            // 亮背景色
-                            self.current_bg = Some(code - 100 + 8);
-                        }
-                        _ => {}
-                    }
-                }
-                i += 1;
+            let row = index - scrolled;
+            if row >= rows || self.mirror[row].as_ref() == Some(line) {
+                return;
+            }
+            let cells = line_cells(line, cols, &mut self.buffer.hyperlinks);
+            changed.push((row, cells, line.last_cell_was_wrapped()));
+            self.mirror[row] = Some(line.clone());
+        });
+
+        if scrolled > 0 {
+            for (cells, wrapped) in history {
+                self.buffer
+                    .push_history(&cells, wrapped, LineMark::default());
             }
+            self.terminal.erase_scrollback();
         }
+        for (row, cells, wrapped) in changed {
+            self.buffer.replace_row(row, &cells, wrapped);
+        }
+        if self.buffer.hyperlinks.needs_prune() {
+            self.buffer.prune_hyperlinks(None);
+        }
+
+        let cursor = self.terminal.cursor_pos();
+        self.buffer.cursor_row = (cursor.y.max(0) as usize).min(rows.saturating_sub(1));
+        self.buffer.cursor_col = cursor.x.min(cols.saturating_sub(1));
+        self.buffer.cursor_visible = cursor.visibility == CursorVisibility::Visible;
+        self.buffer.cursor_style = cursor_style(cursor.shape);
     }
 
-    /// 使用文本更新终端缓冲区
-    fn update_buffer_with_text(&mut self, text: &str) {
-        for ch in text.chars() {
-            match ch {
-                '\n' => {
-                    // 换行处理
-                    self.buffer.newline();
-                }
-                '\r' => {
-                    // 回车处理
-                    self.buffer.carriage_return();
-                }
-                '\t' => {
-                    // 制表符处理
-                    let current_col = self.buffer.cursor_col;
-                    let next_tab_stop = ((current_col / 8) + 1) * 8;
-                    let spaces_needed = next_tab_stop.min(self.buffer.cols) - current_col;
-
-                    for _ in 0..spaces_needed {
-                        if self.buffer.cursor_col < self.buffer.cols {
-                            if let Some(cell) = self
-                                .buffer
-                                .get_cell_mut(self.buffer.cursor_row, self.buffer.cursor_col)
-                            {
-                                cell.character = ' ';
-                            }
-                            self.buffer.cursor_col += 1;
-                        }
-                    }
-                }
-                ch => {
-                    // 普通字符处理
-                    if self.buffer.cursor_row < self.buffer.rows
-                        && self.buffer.cursor_col < self.buffer.cols
-                    {
-                        if let Some(cell) = self
-                            .buffer
-                            .get_cell_mut(self.buffer.cursor_row, self.buffer.cursor_col)
-                        {
-                            cell.character = ch;
-                            // 设置前景色
-                            cell.fg_color = if let Some(fg_index) = self.current_fg {
-                                self.theme.get_color(fg_index, self.bold)
-                            } else {
-                                self.theme.style.foreground
-                            };
-                            // 设置背景色
-                            cell.bg_color = if let Some(bg_index) = self.current_bg {
-                                self.theme.get_color(bg_index, false)
-                            } else {
-                                self.theme.style.background
-                            };
-                            // 设置样式
-                            cell.bold = self.bold;
-                        }
-
-                        self.buffer.cursor_col += 1;
-
-                        // 如果到达行尾，自动换行
-                        if self.buffer.cursor_col >= self.buffer.cols {
-                            self.buffer.newline();
-                            self.buffer.cursor_col = 0;
-                        }
-                    }
-                }
-            }
+    /// 把远端修改的颜色（wezterm 当前调色板与配置中主题配色的差异）同步到缓冲区
+    fn sync_palette(&mut self) {
+        let current = self.terminal.palette();
+        let configured = self.terminal.get_config().color_palette();
+        let changed = |current: SrgbaTuple, configured: SrgbaTuple| {
+            (current != configured).then(|| rgb(current))
+        };
+
+        let overrides = PaletteOverrides {
+            foreground: changed(current.foreground, configured.foreground),
+            background: changed(current.background, configured.background),
+            colors: current
+                .colors
+                .0
+                .iter()
+                .zip(&configured.colors.0)
+                .enumerate()
+                .filter_map(|(index, (&current, &configured))| {
+                    changed(current, configured).map(|color| (index as u8, color))
+                })
+                .collect(),
+        };
+        self.buffer.set_palette(overrides);
+    }
+
+    /// 更换主题配色，远端修改过的颜色保留
+    fn set_palette(&mut self, palette: Palette) {
+        if self.palette == palette {
+            return;
+        }
+        let configured = color_palette(&palette, &PaletteOverrides::default());
+        self.terminal.set_config(Arc::new(WezTermConfig {
+            palette: configured,
+        }));
+        if !self.buffer.palette.is_empty() {
+            *self.terminal.palette_mut() = color_palette(&palette, &self.buffer.palette);
         }
+        self.palette = palette;
+    }
+
+    /// 丢弃缓存的行内容，整屏重新转换到缓冲区
+    fn resync(&mut self) {
+        self.mirror = vec![None; self.buffer.rows];
+        self.sync();
+    }
+
+    fn modes(&self) -> TerminalModes {
+        let mut modes = self.modes;
+        modes.bracketed_paste = self.terminal.bracketed_paste_enabled();
+        modes.cursor_visible = self.buffer.cursor_visible;
+        modes.kitty_keyboard_flags = match self.terminal.get_keyboard_encoding() {
+            KeyboardEncoding::Kitty(flags) => flags.bits() as u8,
+            _ => 0,
+        };
+        modes
     }
 }
 
-impl crate::terminal::emulator::TerminalEmulatorTrait for WezTermAdapter {
-    /// 处理输入数据
-    fn process_input(&mut self, data: &[u8]) -> Result<()> {
-        // 将输入数据转换为字符串
-        let input_text = String::from_utf8_lossy(data);
-
-        // 识别并处理ANSI转义序列
-        let mut chars = input_text.chars().peekable();
-        let mut normal_text = String::new();
-
-        while let Some(ch) = chars.next() {
-            if ch == '\x1b' {
-                // ESC字符
-                // 开始处理转义序列
-                if chars.peek() == Some(&'[') {
-                    // CSI序列
-                    chars.next(); // 跳过 '['
-                    let mut sequence = String::from("[");
-
-                    // 收集序列内容直到字母
-                    while let Some(seq_char) = chars.next() {
-                        sequence.push(seq_char);
-                        if seq_char.is_ascii_alphabetic() {
-                            break;
-                        }
-                    }
+/// 把编辑指令的次数限制在屏幕大小以内
+///
+/// wezterm 按次数逐个插入或重复字符，`CSI 4294967295 @` 之类的参数会让解析线程卡住很久；
+/// 超出屏幕的部分本来也没有效果。
+fn clamp_edit_count(action: &mut Action, rows: usize, cols: usize) {
+    let Action::CSI(CSI::Edit(edit)) = action else {
+        return;
+    };
+    let clamp = |n: &mut u32, max: usize| *n = (*n).min(max.max(1) as u32);
+    match edit {
+        Edit::InsertCharacter(n) | Edit::DeleteCharacter(n) | Edit::EraseCharacter(n) => {
+            clamp(n, cols)
+        }
+        Edit::InsertLine(n) | Edit::DeleteLine(n) | Edit::ScrollUp(n) | Edit::ScrollDown(n) => {
+            clamp(n, rows)
+        }
+        Edit::Repeat(n) => clamp(n, rows.saturating_mul(cols)),
+        _ => {}
+    }
+}
 
-                    // 处理转义序列
-                    if sequence.ends_with('m') {
-                        self.handle_ansi_escape(&sequence);
-                    }
+/// 记录 wezterm 不对外公开、但键盘编码需要的模式
+fn track_mode(modes: &mut TerminalModes, action: &Action) {
+    match action {
+        Action::CSI(CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(code)))) => {
+            set_dec_private(modes, code, true);
+        }
+        Action::CSI(CSI::Mode(Mode::ResetDecPrivateMode(DecPrivateMode::Code(code)))) => {
+            set_dec_private(modes, code, false);
+        }
+        Action::CSI(CSI::Device(device)) if matches!(**device, Device::SoftReset) => {
+            modes.application_cursor = false;
+            modes.application_keypad = false;
+        }
+        Action::Esc(Esc::Code(EscCode::DecApplicationKeyPad)) => modes.application_keypad = true,
+        Action::Esc(Esc::Code(EscCode::DecNormalKeyPad)) => modes.application_keypad = false,
+        Action::Esc(Esc::Code(EscCode::FullReset)) => *modes = TerminalModes::default(),
+        _ => {}
+    }
+}
 
-                    // 将之前积累的普通文本添加到缓冲区
-                    if !normal_text.is_empty() {
-                        self.add_text_to_buffer(&normal_text);
-                        normal_text.clear();
-                    }
-                } else {
-                    // 其他转义序列，暂时当作普通字符处理
-                    normal_text.push(ch);
-                }
-            } else {
-                normal_text.push(ch);
-            }
+/// 是否为修改或重置调色板的动作 (OSC 4/10/11/104/110/111、RIS)
+fn changes_palette(action: &Action) -> bool {
+    match action {
+        Action::OperatingSystemCommand(osc) => matches!(
+            **osc,
+            OperatingSystemCommand::ChangeColorNumber(_)
+                | OperatingSystemCommand::ChangeDynamicColors(..)
+                | OperatingSystemCommand::ResetColors(_)
+                | OperatingSystemCommand::ResetDynamicColor(_)
+        ),
+        Action::Esc(Esc::Code(EscCode::FullReset)) => true,
+        _ => false,
+    }
+}
+
+fn set_dec_private(modes: &mut TerminalModes, code: &DecPrivateModeCode, enabled: bool) {
+    match code {
+        DecPrivateModeCode::ApplicationCursorKeys => modes.application_cursor = enabled,
+        DecPrivateModeCode::FocusTracking => modes.focus_reporting = enabled,
+        _ => {}
+    }
+}
+
+/// 主题配色叠加远端修改的颜色后转换为 wezterm 的调色板
+fn color_palette(palette: &Palette, overrides: &PaletteOverrides) -> ColorPalette {
+    let mut colors = palette.colors;
+    for (&index, &color) in &overrides.colors {
+        colors[index as usize] = color;
+    }
+    let mut result = ColorPalette::default();
+    for (slot, color) in result.colors.0.iter_mut().zip(colors) {
+        *slot = color.into();
+    }
+    result.foreground = overrides.foreground.unwrap_or(palette.foreground).into();
+    result.background = overrides.background.unwrap_or(palette.background).into();
+    result
+}
+
+fn rgb(color: SrgbaTuple) -> Rgb {
+    let (r, g, b, _) = color.to_srgb_u8();
+    (r, g, b)
+}
+
+fn terminal_size(rows: usize, cols: usize, cell_size: (f32, f32)) -> TerminalSize {
+    TerminalSize {
+        rows,
+        cols,
+        pixel_width: (cols as f32 * cell_size.0) as usize,
+        pixel_height: (rows as f32 * cell_size.1) as usize,
+        dpi: 0,
+    }
+}
+
+/// wezterm 的光标形状转换为 DECSCUSR 样式，默认形状返回 None
+fn cursor_style(shape: CursorShape) -> Option<CursorStyle> {
+    let ps = match shape {
+        CursorShape::Default => return None,
+        CursorShape::BlinkingBlock => 1,
+        CursorShape::SteadyBlock => 2,
+        CursorShape::BlinkingUnderline => 3,
+        CursorShape::SteadyUnderline => 4,
+        CursorShape::BlinkingBar => 5,
+        CursorShape::SteadyBar => 6,
+    };
+    CursorStyle::from_decscusr(ps)
+}
+
+/// 转换 wezterm 的颜色属性，默认色由调用方指定是前景还是背景
+fn cell_color(color: ColorAttribute, default: CellColor) -> CellColor {
+    match color {
+        ColorAttribute::TrueColorWithPaletteFallback(rgb, _)
+        | ColorAttribute::TrueColorWithDefaultFallback(rgb) => {
+            let (r, g, b, _) = rgb.to_srgb_u8();
+            CellColor::Rgb(r, g, b)
+        }
+        ColorAttribute::PaletteIndex(index) => CellColor::Indexed(index),
+        ColorAttribute::Default => default,
+    }
+}
+
+/// 把 wezterm 的一行转换为 cols 个单元格
+fn line_cells(line: &Line, cols: usize, hyperlinks: &mut HyperlinkTable) -> Vec<TerminalCell> {
+    let mut cells = vec![TerminalCell::default(); cols];
+
+    for cell in line.visible_cells() {
+        let col = cell.cell_index();
+        if col >= cols {
+            break;
+        }
+
+        let attrs = cell.attrs();
+        let bold = attrs.intensity() == Intensity::Bold;
+        let mut fg = cell_color(attrs.foreground(), CellColor::Foreground);
+        let mut bg = cell_color(attrs.background(), CellColor::Background);
+        if attrs.reverse() {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        if attrs.invisible() {
+            fg = bg;
         }
 
-        // 处理剩余的普通文本
-        if !normal_text.is_empty() {
-            self.add_text_to_buffer(&normal_text);
+        let mut chars = cell.str().chars();
+        let character = chars.next().unwrap_or(' ');
+        let rest = chars.as_str();
+        let hyperlink = attrs.hyperlink().map(|link| {
+            hyperlinks.intern(Hyperlink {
+                id: link.params().get("id").cloned(),
+                uri: link.uri().to_string(),
+            })
+        });
+
+        cells[col] = TerminalCell {
+            character,
+            combining: (!rest.is_empty()).then(|| rest.into()),
+            fg_color: fg,
+            bg_color: bg,
+            bold,
+            italic: attrs.italic(),
+            underline: attrs.underline() != Underline::None,
+            hyperlink,
+            ..TerminalCell::default()
+        };
+        for continuation in cells.iter_mut().skip(col + 1).take(cell.width() - 1) {
+            *continuation = TerminalCell {
+                bg_color: bg,
+                is_continuation: true,
+                hyperlink,
+                ..TerminalCell::default()
+            };
         }
+    }
+
+    cells
+}
+
+/// 供后台 I/O 线程写入数据的句柄
+struct WezTermInput {
+    shared: Arc<Mutex<WezTermState>>,
+}
 
-        // 将输入添加到输出缓冲区
-        self.output_buffer.push_back(input_text.to_string());
+impl InputSink for WezTermInput {
+    fn process(&self, data: &[u8]) {
+        self.shared.lock().unwrap().process(data);
+    }
+
+    fn take_pending_output(&self) -> Vec<u8> {
+        let replies = self.shared.lock().unwrap().replies.clone();
+        std::mem::take(&mut *replies.lock().unwrap())
+    }
+}
 
-        // 限制缓冲区大小
-        if self.output_buffer.len() > 100 {
-            self.output_buffer.pop_front();
+/// WezTerm终端仿真器适配器
+pub struct WezTermAdapter {
+    /// 与后台 I/O 线程共享的仿真状态
+    shared: Arc<Mutex<WezTermState>>,
+    /// 终端状态
+    state: TerminalState,
+}
+
+impl WezTermAdapter {
+    /// 创建新的WezTerm适配器
+    ///
+    /// wezterm 状态机不支持 0 行或 0 列，尺寸至少为 1×1。
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let mut shared = WezTermState::new(rows.max(1), cols.max(1));
+        shared.sync();
+
+        Self {
+            shared: Arc::new(Mutex::new(shared)),
+            state: TerminalState::Disconnected,
         }
+    }
 
-        // 发送输出事件
-        self.send_event(TerminalEvent::Output(input_text.to_string()));
+    /// 设置事件回调
+    pub fn set_event_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(TerminalEvent) + Send + Sync + 'static,
+    {
+        let callback: EventCallback = Arc::new(callback);
+        let clipboard: Arc<dyn Clipboard> = Arc::new(EventForwarder(callback.clone()));
+
+        let mut shared = self.shared.lock().unwrap();
+        shared
+            .terminal
+            .set_notification_handler(Box::new(EventForwarder(callback.clone())));
+        shared.terminal.set_clipboard(&clipboard);
+        shared.callback = Some(callback);
+    }
 
-        Ok(())
+    /// 获取输入句柄
+    pub fn input(&self) -> TerminalInput {
+        TerminalInput::new(Arc::new(WezTermInput {
+            shared: self.shared.clone(),
+        }))
+    }
+
+    /// 获取当前光标位置
+    pub fn get_cursor_position(&self) -> (usize, usize) {
+        let shared = self.shared.lock().unwrap();
+        (shared.buffer.cursor_row, shared.buffer.cursor_col)
     }
 
-    /// 获取终端缓冲区
+    /// 获取终端缓冲区的完整拷贝（包括历史记录），测试中检查内容用
+    #[cfg(test)]
     fn buffer(&self) -> TerminalBuffer {
-        self.buffer.clone()
+        self.shared.lock().unwrap().buffer.clone()
     }
+}
 
-    /// 设置终端大小
-    fn resize(&mut self, rows: usize, cols: usize) {
-        self.buffer.resize(rows, cols);
-        self.send_event(TerminalEvent::Resize { rows, cols });
+impl TerminalEmulatorTrait for WezTermAdapter {
+    fn process_input(&mut self, data: &[u8]) -> Result<()> {
+        self.input().process(data);
+        Ok(())
+    }
+
+    fn visit_buffer(&self, visitor: &mut dyn FnMut(&TerminalBuffer)) {
+        visitor(&self.shared.lock().unwrap().buffer);
     }
 
-    /// 更新主题
-    fn update_theme(&self, theme: TerminalTheme) {
-        // 发送主题更新事件
-        self.send_event(TerminalEvent::Output("主题已更新".to_string()));
-        // 实际的主题更新逻辑可以在后续实现
+    fn set_palette(&self, palette: Palette) {
+        self.shared.lock().unwrap().set_palette(palette);
+    }
+
+    fn resize(&mut self, rows: usize, cols: usize) {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        let mut shared = self.shared.lock().unwrap();
+        // 历史由缓冲区自行重排，屏幕由 wezterm 重排后整体写回
+        shared.buffer.resize_history(rows, cols);
+        let size = terminal_size(rows, cols, shared.cell_size);
+        shared.terminal.resize(size);
+        shared.resync();
+        shared.send_event(TerminalEvent::Resize { rows, cols });
     }
 
-    /// 获取终端状态
     fn state(&self) -> &TerminalState {
         &self.state
     }
 
-    /// 设置终端状态
     fn set_state(&mut self, state: TerminalState) {
         self.state = state;
     }
 
-    /// 清空终端
     fn clear(&mut self) {
-        self.buffer.clear();
-        self.output_buffer.clear();
+        let mut shared = self.shared.lock().unwrap();
+        shared.terminal.erase_scrollback_and_viewport();
+        shared.buffer.clear();
+        shared.resync();
     }
 
     fn start_selection(&mut self, row: usize, col: usize) {
-        self.buffer.start_selection(row, col);
+        self.shared.lock().unwrap().buffer.start_selection(row, col);
     }
 
     fn update_selection(&mut self, row: usize, col: usize) {
-        self.buffer.update_selection(row, col);
+        self.shared
+            .lock()
+            .unwrap()
+            .buffer
+            .update_selection(row, col);
     }
 
     fn clear_selection(&mut self) {
-        self.buffer.clear_selection();
+        self.shared.lock().unwrap().buffer.clear_selection();
     }
 
     fn get_selected_text(&self) -> Option<String> {
-        self.buffer.get_selected_text()
+        self.shared.lock().unwrap().buffer.get_selected_text()
     }
-}
 
-impl WezTermAdapter {
-    /// 将文本添加到缓冲区
-    fn add_text_to_buffer(&mut self, text: &str) {
-        for ch in text.chars() {
-            match ch {
-                '\n' => {
-                    // 换行处理
-                    self.buffer.newline();
-                }
-                '\r' => {
-                    // 回车处理
-                    self.buffer.carriage_return();
-                }
-                '\t' => {
-                    // 制表符处理
-                    let current_col = self.buffer.cursor_col;
-                    let next_tab_stop = ((current_col / 8) + 1) * 8;
-                    let spaces_needed = next_tab_stop.min(self.buffer.cols) - current_col;
-
-                    for _ in 0..spaces_needed {
-                        if self.buffer.cursor_col < self.buffer.cols {
-                            if let Some(cell) = self
-                                .buffer
-                                .get_cell_mut(self.buffer.cursor_row, self.buffer.cursor_col)
-                            {
-                                cell.character = ' ';
-                                cell.fg_color = if let Some(fg_index) = self.current_fg {
-                                    self.theme.get_color(fg_index, self.bold)
-                                } else {
-                                    self.theme.style.foreground
-                                };
-                                cell.bg_color = if let Some(bg_index) = self.current_bg {
-                                    self.theme.get_color(bg_index, false)
-                                } else {
-                                    self.theme.style.background
-                                };
-                                cell.bold = self.bold;
-                            }
-                            self.buffer.cursor_col += 1;
-                        }
-                    }
-                }
-                ch => {
-                    // 普通字符处理
-                    if self.buffer.cursor_row < self.buffer.rows
-                        && self.buffer.cursor_col < self.buffer.cols
-                    {
-                        if let Some(cell) = self
-                            .buffer
-                            .get_cell_mut(self.buffer.cursor_row, self.buffer.cursor_col)
-                        {
-                            cell.character = ch;
-                            // 设置前景色
-                            cell.fg_color = if let Some(fg_index) = self.current_fg {
-                                self.theme.get_color(fg_index, self.bold)
-                            } else {
-                                self.theme.style.foreground
-                            };
-                            // 设置背景色
-                            cell.bg_color = if let Some(bg_index) = self.current_bg {
-                                self.theme.get_color(bg_index, false)
-                            } else {
-                                self.theme.style.background
-                            };
-                            // 设置样式
-                            cell.bold = self.bold;
-                        }
-
-                        self.buffer.cursor_col += 1;
-
-                        // 如果到达行尾，自动换行
-                        if self.buffer.cursor_col >= self.buffer.cols {
-                            self.buffer.newline();
-                            self.buffer.cursor_col = 0;
-                        }
-                    }
-                }
-            }
-        }
+    fn modes(&self) -> TerminalModes {
+        self.shared.lock().unwrap().modes()
     }
 
-    /// 获取终端缓冲区
-    fn buffer(&self) -> TerminalBuffer {
-        self.buffer.clone()
-    }
-
-    /// 设置终端大小
-    fn resize(&mut self, rows: usize, cols: usize) {
-        self.buffer.resize(rows, cols);
-        self.send_event(TerminalEvent::Resize { rows, cols });
+    fn take_pending_output(&mut self) -> Vec<u8> {
+        self.input().take_pending_output()
     }
 
-    /// 更新主题
-    fn update_theme(&self, theme: TerminalTheme) {
-        // 发送主题更新事件
-        self.send_event(TerminalEvent::Output("主题已更新".to_string()));
-        // 实际的主题更新逻辑可以在后续实现
-    }
-
-    /// 获取终端状态
-    fn state(&self) -> &TerminalState {
-        &self.state
-    }
-
-    /// 设置终端状态
-    fn set_state(&mut self, state: TerminalState) {
-        self.state = state;
+    fn set_cell_size(&mut self, width: f32, height: f32) {
+        if width > 0.0 && height > 0.0 {
+            self.shared.lock().unwrap().cell_size = (width, height);
+        }
     }
 
-    /// 清空终端
-    fn clear(&mut self) {
-        self.buffer.clear();
-        self.output_buffer.clear();
+    fn set_scrollback(&mut self, config: ScrollbackConfig) {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.buffer.history.config() != config {
+            shared.buffer.history.set_config(config);
+            shared.buffer.touch_all();
+        }
     }
-}
 
-impl WezTermAdapter {
-    /// 获取输出历史
-    pub fn get_output_history(&self) -> Vec<String> {
-        self.output_buffer.iter().cloned().collect()
+    fn scroll_display(&mut self, scroll: Scroll) {
+        self.shared.lock().unwrap().buffer.scroll_display(scroll);
     }
 
-    /// 获取当前光标位置
-    pub fn get_cursor_position(&self) -> (usize, usize) {
-        (self.buffer.cursor_row, self.buffer.cursor_col)
+    fn input_handle(&self) -> Option<TerminalInput> {
+        Some(self.input())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::terminal::emulator::TerminalEmulatorTrait;
+
+    fn row_text(buffer: &TerminalBuffer, row: usize) -> String {
+        let line = buffer.line(row).unwrap();
+        line.iter()
+            .map(TerminalCell::text)
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
 
     #[test]
     fn test_wezterm_adapter_creation() {
-        let theme = TerminalTheme::default();
-        let adapter = WezTermAdapter::new(24, 80, theme);
+        let adapter = WezTermAdapter::new(24, 80);
         assert_eq!(adapter.state(), &TerminalState::Disconnected);
     }
 
     #[test]
     fn test_buffer_conversion() {
-        let theme = TerminalTheme::default();
-        let mut adapter = WezTermAdapter::new(10, 20, theme);
+        let mut adapter = WezTermAdapter::new(10, 20);
 
         // 处理一些测试数据
         let test_data = b"Hello World\n";
@@ -462,29 +669,39 @@ mod tests {
         let buffer = adapter.buffer();
         assert_eq!(buffer.rows, 10);
         assert_eq!(buffer.cols, 20);
+        assert_eq!(row_text(&buffer, 0), "Hello World");
     }
 
     #[test]
-    fn test_output_history() {
-        let theme = TerminalTheme::default();
-        let mut adapter = WezTermAdapter::new(24, 80, theme);
-
-        // 处理多行输入
-        adapter.process_input(b"Line 1\n").unwrap();
-        adapter.process_input(b"Line 2\n").unwrap();
-        adapter.process_input(b"Line 3").unwrap();
-
-        let history = adapter.get_output_history();
-        assert_eq!(history.len(), 3);
-        assert_eq!(history[0], "Line 1\n");
-        assert_eq!(history[1], "Line 2\n");
-        assert_eq!(history[2], "Line 3");
+    fn test_history_eviction_updates_line_versions() {
+        let mut adapter = WezTermAdapter::new(2, 10);
+        adapter.set_scrollback(ScrollbackConfig {
+            max_lines: 3,
+            ..ScrollbackConfig::default()
+        });
+        for i in 0..5 {
+            adapter
+                .process_input(format!("line {i}\r\n").as_bytes())
+                .unwrap();
+        }
+        let version = {
+            let buffer = adapter.buffer();
+            assert_eq!(buffer.history.len(), 3);
+            assert_eq!(row_text(&buffer, 0), "line 1");
+            buffer.line_version(0)
+        };
+
+        // 历史已满，继续滚出的行淘汰最早的行，第 0 行的内容与版本都要变化
+        adapter.process_input(b"line 5\r\nline 6\r\n").unwrap();
+        let buffer = adapter.buffer();
+        assert_eq!(buffer.history.len(), 3);
+        assert_eq!(row_text(&buffer, 0), "line 3");
+        assert_ne!(buffer.line_version(0), version);
     }
 
     #[test]
     fn test_cursor_position() {
-        let theme = TerminalTheme::default();
-        let mut adapter = WezTermAdapter::new(24, 80, theme);
+        let mut adapter = WezTermAdapter::new(24, 80);
 
         // 初始位置应该是(0,0)
         let (row, col) = adapter.get_cursor_position();
@@ -497,8 +714,8 @@ mod tests {
         assert_eq!(row, 0);
         assert_eq!(col, 5);
 
-        // 换行测试
-        adapter.process_input(b"\n").unwrap();
+        // 换行测试（LF 只下移一行，回到行首需要 CR）
+        adapter.process_input(b"\r\n").unwrap();
         let (row, col) = adapter.get_cursor_position();
         assert_eq!(row, 1);
         assert_eq!(col, 0);
@@ -506,31 +723,129 @@ mod tests {
 
     #[test]
     fn test_ansi_color_processing() {
-        let theme = TerminalTheme::default();
-        let default_fg = theme.style.foreground;
-        let mut adapter = WezTermAdapter::new(24, 80, theme);
+        let mut adapter = WezTermAdapter::new(24, 80);
 
         // 测试红色前景色
         adapter.process_input(b"\x1b[31mRed Text").unwrap();
         let buffer = adapter.buffer();
 
-        // 检查第一个字符是否使用了红色
-        if let Some(cell) = buffer.get_cell(0, 0) {
-            assert_eq!(cell.character, 'R');
-            // 颜色应该不是默认的白色
-            assert_ne!(cell.fg_color, default_fg);
-        }
+        let cell = buffer.get_cell(0, 0).unwrap();
+        assert_eq!(cell.character, 'R');
+        assert_eq!(cell.fg_color, CellColor::Indexed(1));
 
         // 测试重置颜色
         adapter.process_input(b"\x1b[0mNormal Text").unwrap();
         let buffer = adapter.buffer();
 
-        // 检查重置后的字符是否使用默认颜色
-        let normal_start_col = 8;
-        if let Some(cell) = buffer.get_cell(0, normal_start_col) {
-            assert_eq!(cell.character, 'N');
-            // 颜色应该回到默认
-            assert_eq!(cell.fg_color, default_fg);
+        let cell = buffer.get_cell(0, 8).unwrap();
+        assert_eq!(cell.character, 'N');
+        assert_eq!(cell.fg_color, CellColor::Foreground);
+    }
+
+    #[test]
+    fn test_scrolled_lines_move_to_history() {
+        let mut adapter = WezTermAdapter::new(3, 20);
+
+        let output: String = (0..10).map(|i| format!("line {i}\r\n")).collect();
+        adapter.process_input(output.as_bytes()).unwrap();
+
+        let buffer = adapter.buffer();
+        assert_eq!(buffer.history.len(), 8);
+        assert_eq!(row_text(&buffer, 0), "line 0");
+        assert_eq!(row_text(&buffer, 7), "line 7");
+        assert_eq!(row_text(&buffer, 8), "line 8");
+        assert_eq!(row_text(&buffer, 9), "line 9");
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (2, 0));
+    }
+
+    #[test]
+    fn test_wide_chars_and_hyperlinks() {
+        let mut adapter = WezTermAdapter::new(5, 20);
+        adapter
+            .process_input("中a\x1b]8;id=x;https://example.com\x1b\\link\x1b]8;;\x1b\\".as_bytes())
+            .unwrap();
+
+        let buffer = adapter.buffer();
+        assert_eq!(buffer.get_cell(0, 0).unwrap().character, '中');
+        assert!(buffer.get_cell(0, 1).unwrap().is_continuation);
+        assert_eq!(buffer.get_cell(0, 2).unwrap().character, 'a');
+
+        let link = buffer.link_at(0, 4).unwrap();
+        assert_eq!(link.target, "https://example.com");
+        assert_eq!((link.start_col, link.end_col), (3, 7));
+    }
+
+    #[test]
+    fn test_modes_and_events() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut adapter = WezTermAdapter::new(5, 20);
+        let sink = events.clone();
+        adapter.set_event_callback(move |event| {
+            sink.lock().unwrap().push(format!("{event:?}"));
+        });
+
+        adapter
+            .process_input(b"\x1b[?1h\x1b=\x1b[?2004h\x1b[?25l\x1b]2;title\x07\x07")
+            .unwrap();
+        let modes = adapter.modes();
+        assert!(modes.application_cursor);
+        assert!(modes.application_keypad);
+        assert!(modes.bracketed_paste);
+        assert!(!modes.cursor_visible);
+
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|e| e.contains("TitleChange(\"title\")")));
+        assert!(events.iter().any(|e| e == "Bell"));
+    }
+
+    #[test]
+    fn test_resize_rewraps_screen() {
+        let mut adapter = WezTermAdapter::new(4, 10);
+        adapter.process_input(b"0123456789abc").unwrap();
+
+        adapter.resize(4, 20);
+        let buffer = adapter.buffer();
+        assert_eq!((buffer.rows, buffer.cols), (4, 20));
+        assert_eq!(row_text(&buffer, 0), "0123456789abc");
+    }
+
+    #[test]
+    fn test_palette_changes_survive_theme_switch() {
+        let mut adapter = WezTermAdapter::new(4, 20);
+        adapter.process_input(b"\x1b]4;1;#ff8000\x1b\\").unwrap();
+        assert_eq!(
+            adapter.buffer().palette.colors.get(&1),
+            Some(&(255, 128, 0))
+        );
+        assert_eq!(adapter.buffer().palette.colors.len(), 1);
+
+        // 更换主题后远端修改的颜色仍然有效，查询按新主题回答
+        adapter.set_palette(Palette {
+            background: (1, 2, 3),
+            ..Palette::default()
+        });
+        adapter
+            .process_input(b"\x1b]11;?\x1b\\\x1b]4;2;#00ff00\x1b\\")
+            .unwrap();
+        // wezterm 在自己的写线程中回复
+        let mut reply = Vec::new();
+        for _ in 0..100 {
+            reply.extend(adapter.take_pending_output());
+            if !reply.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
         }
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "\x1b]11;rgb:0101/0202/0303\x1b\\"
+        );
+        let buffer = adapter.buffer();
+        assert_eq!(buffer.palette.colors.get(&1), Some(&(255, 128, 0)));
+        assert_eq!(buffer.palette.colors.get(&2), Some(&(0, 255, 0)));
+        assert_eq!(buffer.palette.background, None);
+
+        adapter.process_input(b"\x1b]104\x1b\\").unwrap();
+        assert!(adapter.buffer().palette.is_empty());
     }
 }
@@ -92,23 +92,73 @@ pub fn join_paths(base: &str, relative: &str) -> String {
     }
 }
 
-/// 获取字符在终端中的显示宽度 (CJK)
+/// 获取字符在终端中的显示宽度
+///
+/// 基于 Unicode 东亚宽度与 emoji 表示属性，与远端 wcwidth 保持一致：
+/// 宽字符和 emoji 为2，组合音标、零宽连接符等为0，控制字符为0。
 pub fn get_char_width(c: char) -> usize {
-    if (c >= '\u{1100}' && c <= '\u{115f}') || // 谚文
-       (c >= '\u{2e80}' && c <= '\u{9fff}') || // 常用中日韩汉字
-       (c >= '\u{ac00}' && c <= '\u{d7a3}') || // 韩文音节
-       (c >= '\u{f900}' && c <= '\u{faff}') || // CJK 兼容汉字
-       (c >= '\u{fe10}' && c <= '\u{fe19}') || // 垂直形式
-       (c >= '\u{fe30}' && c <= '\u{fe6f}') || // CJK 兼容性形式
-       (c >= '\u{ff00}' && c <= '\u{ff60}') || // 全角 ASCII 变体
-       (c >= '\u{ffe0}' && c <= '\u{ffe6}') || // 全角标点
-       (c >= '\u{20000}' && c <= '\u{2fffd}') || // 扩展 B-F
-       (c >= '\u{30000}' && c <= '\u{3fffd}')
-    // 扩展 G
-    {
-        2
-    } else {
-        1
+    unicode_width::UnicodeWidthChar::width(c).unwrap_or(0)
+}
+
+/// 使用系统默认程序打开 URL
+pub fn open_with_system(target: &str) -> bool {
+    use std::process::Command;
+
+    #[cfg(windows)]
+    let result = Command::new("explorer").arg(target).spawn();
+
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg(target).spawn();
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let result = Command::new("xdg-open").arg(target).spawn();
+
+    match result {
+        Ok(mut child) => {
+            // 在后台回收子进程，避免留下僵尸进程
+            std::thread::spawn(move || child.wait());
+            true
+        }
+        Err(e) => {
+            tracing::warn!("打开链接失败: {}", e);
+            false
+        }
+    }
+}
+
+/// 播放系统提示音（响铃）
+pub fn play_bell_sound() {
+    use std::process::Command;
+
+    #[cfg(windows)]
+    let result = {
+        use std::os::windows::process::CommandExt;
+        // CREATE_NO_WINDOW：不弹出控制台窗口
+        Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "[System.Media.SystemSounds]::Beep.Play()",
+            ])
+            .creation_flags(0x0800_0000)
+            .spawn()
+    };
+
+    #[cfg(target_os = "macos")]
+    let result = Command::new("osascript").args(["-e", "beep"]).spawn();
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let result = Command::new("canberra-gtk-play")
+        .args(["-i", "bell"])
+        .spawn();
+
+    match result {
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => {
+            tracing::warn!("播放提示音失败: {}", e);
+        }
     }
 }
 